@@ -0,0 +1,95 @@
+//! Counts the allocations made delivering events with `listen_with`, through
+//! the mock backend and a `Settings::buffer` like a hook would. Events
+//! without a name (mouse moves, buttons, wheel) shouldn't allocate at all.
+//!
+//! cargo bench --bench allocations --features mock
+use rdev::mock::push_event;
+use rdev::{listen_with, Backend, Button, Event, EventType, Settings};
+use std::alloc::{GlobalAlloc, Layout, System};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::thread;
+use std::time::{Instant, SystemTime};
+
+struct Counting;
+
+static ALLOCATIONS: AtomicUsize = AtomicUsize::new(0);
+static RECEIVED: AtomicUsize = AtomicUsize::new(0);
+
+unsafe impl GlobalAlloc for Counting {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        ALLOCATIONS.fetch_add(1, Ordering::Relaxed);
+        System.alloc(layout)
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        System.dealloc(ptr, layout)
+    }
+
+    unsafe fn realloc(&self, ptr: *mut u8, layout: Layout, new_size: usize) -> *mut u8 {
+        ALLOCATIONS.fetch_add(1, Ordering::Relaxed);
+        System.realloc(ptr, layout, new_size)
+    }
+}
+
+#[global_allocator]
+static GLOBAL: Counting = Counting;
+
+const BATCH: usize = 1000;
+const BATCHES: usize = 100;
+
+/// Pushes every event and waits for the listener to get them.
+fn deliver(events: &mut Vec<Event>) {
+    let expected = RECEIVED.load(Ordering::SeqCst) + events.len();
+    for event in events.drain(..) {
+        push_event(event);
+    }
+    while RECEIVED.load(Ordering::SeqCst) < expected {
+        thread::yield_now();
+    }
+}
+
+fn main() {
+    let settings = Settings {
+        backend: Some(Backend::Mock),
+        buffer: Some(4 * BATCH),
+        ..Default::default()
+    };
+    thread::spawn(move || {
+        listen_with(settings, |event| {
+            // So the event isn't optimized away.
+            std::hint::black_box(&event);
+            RECEIVED.fetch_add(1, Ordering::SeqCst);
+        })
+    });
+    let kinds = [
+        EventType::MouseMove { x: 10.0, y: 20.0 },
+        EventType::ButtonPress(Button::Left),
+        EventType::Wheel {
+            delta_x: 0,
+            delta_y: 1,
+        },
+    ];
+    let mut events = Vec::with_capacity(BATCH);
+    for event_type in kinds.iter().copied() {
+        let fill = |events: &mut Vec<Event>| {
+            events.extend((0..BATCH).map(|_| Event::new(event_type, SystemTime::now(), None)))
+        };
+        // Lets the queues grow to their size first.
+        fill(&mut events);
+        deliver(&mut events);
+        let (mut allocations, start) = (0, Instant::now());
+        for _ in 0..BATCHES {
+            fill(&mut events);
+            let before = ALLOCATIONS.load(Ordering::SeqCst);
+            deliver(&mut events);
+            allocations += ALLOCATIONS.load(Ordering::SeqCst) - before;
+        }
+        let count = BATCH * BATCHES;
+        println!(
+            "{:?}: {} allocations per event, {:?} per event",
+            event_type,
+            allocations as f64 / count as f64,
+            start.elapsed() / count as u32
+        );
+    }
+}
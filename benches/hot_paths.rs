@@ -0,0 +1,119 @@
+//! The paths every event goes through, with the mock backends so they run
+//! without a display (CI): hook to callback latency through
+//! `Backend::Loopback`, `listen` throughput, `Dispatcher` filtering and
+//! `simulate` throughput. Allocations per event are counted by the
+//! `allocations` bench, which needs a global allocator of its own.
+//!
+//! cargo bench --bench hot_paths --features mock
+use criterion::{criterion_group, criterion_main, BatchSize, Criterion, Throughput};
+use rdev::mock::{push_event, take_simulated};
+use rdev::{
+    listen_with, simulate_with, Backend, Dispatcher, Event, EventFilter, EventType, Key, Settings,
+};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::mpsc;
+use std::thread;
+use std::time::{Duration, SystemTime};
+
+const BATCH: usize = 1000;
+
+static RECEIVED: AtomicUsize = AtomicUsize::new(0);
+
+fn settings(backend: Backend) -> Settings {
+    Settings {
+        backend: Some(backend),
+        ..Default::default()
+    }
+}
+
+fn mouse_move() -> EventType {
+    EventType::MouseMove { x: 10.0, y: 20.0 }
+}
+
+fn events(event_type: EventType) -> Vec<Event> {
+    (0..BATCH)
+        .map(|_| Event::new(event_type, SystemTime::now(), None))
+        .collect()
+}
+
+fn latency(c: &mut Criterion) {
+    let settings = settings(Backend::Loopback);
+    let (sender, receiver) = mpsc::channel();
+    thread::spawn(move || {
+        listen_with(settings, move |event| {
+            sender.send(event.event_type).ok();
+        })
+    });
+    // The listener registers from its own thread.
+    loop {
+        simulate_with(settings, &mouse_move()).unwrap();
+        if receiver.recv_timeout(Duration::from_millis(10)).is_ok() {
+            break;
+        }
+    }
+    while receiver.try_recv().is_ok() {}
+    c.bench_function("loopback_latency", |b| {
+        b.iter(|| {
+            simulate_with(settings, &EventType::KeyPress(Key::KeyA)).unwrap();
+            receiver.recv().unwrap()
+        })
+    });
+}
+
+fn throughput(c: &mut Criterion) {
+    thread::spawn(|| {
+        listen_with(settings(Backend::Mock), |event| {
+            criterion::black_box(&event);
+            RECEIVED.fetch_add(1, Ordering::SeqCst);
+        })
+    });
+    let mut group = c.benchmark_group("listen");
+    group.throughput(Throughput::Elements(BATCH as u64));
+    group.bench_function("mouse_moves", |b| {
+        b.iter_batched(
+            || events(mouse_move()),
+            |events| {
+                let expected = RECEIVED.load(Ordering::SeqCst) + events.len();
+                for event in events {
+                    push_event(event);
+                }
+                while RECEIVED.load(Ordering::SeqCst) < expected {
+                    thread::yield_now();
+                }
+            },
+            BatchSize::SmallInput,
+        )
+    });
+    group.finish();
+}
+
+fn dispatch(c: &mut Criterion) {
+    let dispatcher = Dispatcher::new();
+    for _ in 0..5 {
+        dispatcher.subscribe(EventFilter::KEYBOARD, |event| {
+            criterion::black_box(event);
+        });
+    }
+    let moved = events(mouse_move()).remove(0);
+    c.bench_function("dispatch_filtered_out", |b| {
+        b.iter(|| dispatcher.dispatch(criterion::black_box(&moved)))
+    });
+}
+
+fn simulate(c: &mut Criterion) {
+    let settings = settings(Backend::Mock);
+    let mut group = c.benchmark_group("simulate");
+    group.throughput(Throughput::Elements(BATCH as u64));
+    group.bench_function("mock", |b| {
+        b.iter(|| {
+            for _ in 0..BATCH {
+                simulate_with(settings, &mouse_move()).unwrap();
+            }
+            take_simulated()
+        })
+    });
+    group.finish();
+}
+
+criterion_group!(benches, latency, throughput, dispatch, simulate);
+criterion_main!(benches);
@@ -0,0 +1,115 @@
+/**
+ * Key names based on physical location on the device
+ * Merge Option(MacOS) and Alt(Windows, Linux) into Alt
+ * Merge Windows (Windows), Meta(Linux), Command(MacOS) into Meta
+ * Characters based on Qwerty layout, don't use this for characters as it WILL
+ * depend on the layout. Use Event.name instead. Key modifiers gives those keys
+ * a different value too.
+ * Careful, on Windows KpReturn does not exist, it' s strictly equivalent to Return, also Keypad keys
+ * get modified if NumLock is Off and ARE pagedown and so on.
+ */
+export type Key = "Alt" | "AltGr" | "Backspace" | "CapsLock" | "ControlLeft" | "ControlRight" | "Delete" | "DownArrow" | "End" | "Escape" | "F1" | "F10" | "F11" | "F12" | "F13" | "F14" | "F15" | "F16" | "F17" | "F18" | "F19" | "F20" | "F21" | "F22" | "F23" | "F24" | "F2" | "F3" | "F4" | "F5" | "F6" | "F7" | "F8" | "F9" | "Home" | "LeftArrow" | "MetaLeft" | "MetaRight" | "PageDown" | "PageUp" | "Return" | "RightArrow" | "ShiftLeft" | "ShiftRight" | "Space" | "Tab" | "UpArrow" | "PrintScreen" | "ScrollLock" | "Pause" | "NumLock" | "BackQuote" | "Num1" | "Num2" | "Num3" | "Num4" | "Num5" | "Num6" | "Num7" | "Num8" | "Num9" | "Num0" | "Minus" | "Equal" | "KeyQ" | "KeyW" | "KeyE" | "KeyR" | "KeyT" | "KeyY" | "KeyU" | "KeyI" | "KeyO" | "KeyP" | "LeftBracket" | "RightBracket" | "KeyA" | "KeyS" | "KeyD" | "KeyF" | "KeyG" | "KeyH" | "KeyJ" | "KeyK" | "KeyL" | "SemiColon" | "Quote" | "BackSlash" | "IntlBackslash" | "KeyZ" | "KeyX" | "KeyC" | "KeyV" | "KeyB" | "KeyN" | "KeyM" | "Comma" | "Dot" | "Slash" | "Insert" | "KpReturn" | "KpMinus" | "KpPlus" | "KpMultiply" | "KpDivide" | "Kp0" | "Kp1" | "Kp2" | "Kp3" | "Kp4" | "Kp5" | "Kp6" | "Kp7" | "Kp8" | "Kp9" | "KpDelete" | "Function" | "VolumeUp" | "VolumeDown" | "VolumeMute" | "MediaPlayPause" | "MediaNext" | "MediaPrev" | "BrightnessUp" | "BrightnessDown" | "Menu" | "Kana" | "Hangul" | "Henkan" | "Muhenkan" | "Yen" | "Ro" | "BrowserBack" | "BrowserForward" | "BrowserRefresh" | "BrowserHome" | "BrowserSearch" | "LaunchMail" | "Calculator" | "Sleep" | { Unknown: number }
+
+/**
+ * Standard mouse buttons
+ * `Back` and `Forward` are the thumb buttons (XButton1 and XButton2 on Windows).
+ * Some mice have even more buttons. These are not defined, and different
+ * OSs will give different `Button::Unknown` values.
+ */
+export type Button = "Left" | "Right" | "Middle" | "Back" | "Forward" | { Unknown: number }
+
+/**
+ * Where a continuous scroll is at.
+ */
+export type ScrollPhase = "Begin" | "Change" | "End"
+
+/**
+ * State of a pen or stylus. `x` and `y` are in pixels like `MouseMove`,
+ * `pressure` goes from 0.0 to 1.0 and `tilt_x`/`tilt_y` from -1.0 to 1.0,
+ * 0.0 being upright. `eraser` is true when the tablet reports the eraser
+ * end is used.
+ */
+export type Pen = { x: number; y: number; pressure: number; tilt_x: number; tilt_y: number; eraser: boolean }
+
+/**
+ * In order to manage different OSs, the current EventType choices are a mix and
+ * match to account for all possible events.
+ */
+export type EventType = { KeyPress: Key } | { KeyRelease: Key } | { ButtonPress: Button } | { ButtonRelease: Button } | { MouseMove: { x: number; y: number } } | { Wheel: { delta_x: number; delta_y: number } } | { Scroll: { delta_x: number; delta_y: number; phase: ScrollPhase; is_momentum: boolean } } | "LayoutChanged" | "ImeComposition" | "ImeCommit" | { SecureInput: boolean } | { TouchDown: { id: number; x: number; y: number; pressure: number } } | { TouchMove: { id: number; x: number; y: number; pressure: number } } | { TouchUp: { id: number; x: number; y: number; pressure: number } } | { PenDown: Pen } | { PenMove: Pen } | { PenUp: Pen }
+
+/**
+ * The characters of a key event, as code points, and whether a dead key is
+ * pending, waiting for the next key to compose with.
+ */
+export type UnicodeInfo = { code_points: number[]; is_dead: boolean }
+
+/**
+ * When events arrive from the OS they get some additional information added from
+ * EventType, which is the time when this event was received, and the name Option
+ * which contains what characters should be emmitted from that event. This relies
+ * on the OS layout and keyboard state machinery.
+ * Dead keys yield None (or an empty string), the accentuated letter comes
+ * with the key that follows.
+ * `unicode` holds the same characters along with the dead key state.
+ * `scan_code` is the platform code of the physical key, the evdev code on
+ * Linux, the scan code on Windows and the virtual key code on macOS (which
+ * names positions, not characters). It's 0 for other events.
+ * Only `name` and `unicode` allocate, events without text like mouse moves
+ * are delivered without any allocation (`benches/allocations.rs`).
+ * Mouse moves also get `display`, the index in `displays()` of the monitor
+ * under the cursor, and `delta`, the movement since the last
+ * event. On macOS and with the linux grab the delta comes from the device
+ * and keeps going at the screen edges, elsewhere it's the difference between
+ * positions. Both are None for other events and ignored when simulating.
+ * `numlock` is whether NumLock was on when a key event happened, sampled
+ * by the backend that listened to it, so a keypad key tells if it typed a
+ * digit or moved the caret. It's None for other events, on macOS which
+ * has no NumLock, and for events made up or read from formats not keeping
+ * it.
+ * More fields may be added, build events with `Event::new`.
+ */
+export type Event = { time: number; name: string | null; event_type: EventType; unicode?: UnicodeInfo | null; scan_code?: number; display?: number | null; delta?: [number, number] | null; numlock?: boolean | null }
+
+/**
+ * Keys with an on/off state, see `get_lock_state` and `set_lock_state`.
+ */
+export type LockKey = "CapsLock" | "NumLock" | "ScrollLock"
+
+/**
+ * Modifiers held along with a key. Only `shift` and `alt_gr` change the
+ * character a key types on usual layouts.
+ */
+export type Modifiers = { shift: boolean; ctrl: boolean; alt: boolean; meta: boolean; alt_gr: boolean }
+
+/**
+ * What an input device is used for, as far as the OS can tell.
+ */
+export type DeviceKind = "Keyboard" | "Mouse" | "Other"
+
+/**
+ * An input device reported by `listen_devices`. `id` is the OS handle of
+ * the device (its path on Linux and Windows), it's only stable while the
+ * device stays connected. Vendor and product ids are 0 when unknown.
+ * `serial` is the serial number the device reports, which tells apart two
+ * devices of the same model. Many keyboards and mice don't have one.
+ */
+export type InputDevice = { id: string; name: string; vendor_id: number; product_id: number; serial: string | null; kind: DeviceKind }
+
+/**
+ * An input device was plugged in or removed.
+ */
+export type DeviceEvent = { Connected: InputDevice } | { Disconnected: InputDevice }
+
+/**
+ * A monitor returned by `displays()`. The position and size are in the
+ * coordinates of `MouseMove` events (points on macOS, multiply by
+ * `scale_factor` for pixels). `refresh_rate` is in Hz, None when the OS
+ * doesn't know it.
+ */
+export type DisplayInfo = { x: number; y: number; width: number; height: number; scale_factor: number; refresh_rate: number | null; is_primary: boolean }
+
+/**
+ * The monitor configuration changed, see `listen_displays`. Monitors are
+ * told apart by their position, one that moved is `Removed` then `Added`.
+ */
+export type DisplayEvent = { Added: DisplayInfo } | { Removed: DisplayInfo } | { Changed: DisplayInfo } | { PrimaryChanged: DisplayInfo }
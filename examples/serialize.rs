@@ -2,11 +2,11 @@ use rdev::{Event, EventType, Key};
 use std::time::SystemTime;
 
 fn main() {
-    let event = Event {
-        event_type: EventType::KeyPress(Key::KeyS),
-        time: SystemTime::now(),
-        name: Some(String::from("S")),
-    };
+    let event = Event::new(
+        EventType::KeyPress(Key::KeyS),
+        SystemTime::now(),
+        Some(String::from("S")),
+    );
 
     let serialized = serde_json::to_string(&event).unwrap();
 
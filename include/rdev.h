@@ -0,0 +1,109 @@
+#ifndef RDEV_H
+#define RDEV_H
+
+/* Generated with cbindgen from src/ffi.rs, don't edit by hand. */
+
+#include <stdarg.h>
+#include <stdbool.h>
+#include <stdint.h>
+#include <stdlib.h>
+
+/**
+ * Changes when `RdevEvent` or a function signature does.
+ */
+#define RDEV_ABI_VERSION 1
+
+#define RDEV_OK 0
+
+/**
+ * The OS call failed, or rdev panicked.
+ */
+#define RDEV_ERROR -1
+
+#define RDEV_INVALID_ARGUMENT -2
+
+#define RDEV_KEY_PRESS 0
+
+#define RDEV_KEY_RELEASE 1
+
+#define RDEV_BUTTON_PRESS 2
+
+#define RDEV_BUTTON_RELEASE 3
+
+#define RDEV_MOUSE_MOVE 4
+
+#define RDEV_WHEEL 5
+
+/**
+ * Events without a C representation yet, only `time_ms` is set.
+ */
+#define RDEV_OTHER 255
+
+/**
+ * An event. `code` is the key or button of key and button events, `x` and
+ * `y` the position of mouse moves, `delta_x` and `delta_y` those of wheel
+ * events. `name` is UTF-8 and only valid during the callback, null when
+ * the key typed nothing.
+ */
+typedef struct RdevEvent {
+  uint32_t kind;
+  uint64_t code;
+  double x;
+  double y;
+  int64_t delta_x;
+  int64_t delta_y;
+  /**
+   * Milliseconds since the UNIX epoch.
+   */
+  uint64_t time_ms;
+  uint32_t scan_code;
+  const char *name;
+} RdevEvent;
+
+typedef void (*RdevCallback)(const struct RdevEvent *event, void *user_data);
+
+uint32_t rdev_abi_version(void);
+
+/**
+ * Calls `callback` with every event and `user_data`, see `listen`. Blocks,
+ * returns only on errors.
+ */
+int32_t rdev_listen(RdevCallback callback, void *user_data);
+
+/**
+ * Sends an event, see `simulate`. `name` and `time_ms` are ignored.
+ *
+ * # Safety
+ *
+ * `event` must be null or point to an `RdevEvent`.
+ */
+int32_t rdev_simulate(const struct RdevEvent *event);
+
+/**
+ * Types UTF-8 text, see `simulate_text`.
+ *
+ * # Safety
+ *
+ * `text` must be null or a NUL-terminated string.
+ */
+int32_t rdev_simulate_text(const char *text);
+
+/**
+ * The size of the main display, see `display_size`.
+ *
+ * # Safety
+ *
+ * `width` and `height` must be null or point to a `uint64_t`.
+ */
+int32_t rdev_display_size(uint64_t *width, uint64_t *height);
+
+/**
+ * The position of the pointer, see `get_mouse_position`.
+ *
+ * # Safety
+ *
+ * `x` and `y` must be null or point to a `double`.
+ */
+int32_t rdev_mouse_position(double *x, double *y);
+
+#endif /* RDEV_H */
@@ -0,0 +1,110 @@
+//! A Bevy plugin sending the events of `listen` to systems, requires the
+//! `bevy` feature. Events are listened to on a thread of their own and
+//! written as `GlobalEvent`s before `Update`, so systems read them with an
+//! `EventReader` like window events.
+//!
+//! ```no_run
+//! use bevy::prelude::*;
+//! use rdev::bevy_adapter::{GlobalEvent, RdevPlugin};
+//!
+//! fn print(mut events: EventReader<GlobalEvent>) {
+//!     for global in events.read() {
+//!         println!("{:?}", global.event.event_type);
+//!     }
+//! }
+//!
+//! App::new()
+//!     .add_plugins((MinimalPlugins, RdevPlugin))
+//!     .add_systems(Update, print)
+//!     .run();
+//! ```
+use crate::coordinates::point_to_physical;
+use crate::rdev::{DisplayInfo, Event, EventType, ListenError};
+use bevy::app::{App, Plugin, PreUpdate};
+use bevy::ecs::event::{Event as BevyEvent, EventWriter};
+use bevy::ecs::system::{Res, Resource};
+use bevy::math::Vec2;
+use bevy::window::{Window, WindowPosition};
+use std::sync::mpsc::{self, Receiver};
+use std::sync::{Mutex, PoisonError};
+use std::thread;
+
+/// An event of `listen`.
+#[derive(BevyEvent, Debug, Clone, PartialEq)]
+pub struct GlobalEvent {
+    pub event: Event,
+    /// Where mouse moves went on the screen, in physical pixels whatever the
+    /// `CoordinateMode`.
+    pub position: Option<(f64, f64)>,
+}
+
+impl GlobalEvent {
+    fn new(event: Event, displays: &[DisplayInfo]) -> GlobalEvent {
+        let position = match event.event_type {
+            EventType::MouseMove { x, y } => Some(point_to_physical(x, y, displays)),
+            _ => None,
+        };
+        GlobalEvent { event, position }
+    }
+
+    /// `position` relative to `window` in logical pixels, like
+    /// `Window::cursor_position`. None for other events, or when the window
+    /// position isn't known. Bevy positions windows with their decorations,
+    /// so this is off by the title bar on decorated windows.
+    pub fn window_position(&self, window: &Window) -> Option<Vec2> {
+        let (x, y) = self.position?;
+        let origin = match window.position {
+            WindowPosition::At(origin) => origin,
+            _ => return None,
+        };
+        let scale = window.resolution.scale_factor();
+        Some(Vec2::new(
+            ((x - origin.x as f64) / scale) as f32,
+            ((y - origin.y as f64) / scale) as f32,
+        ))
+    }
+}
+
+/// Sent once if listening fails, no `GlobalEvent` comes after.
+#[derive(BevyEvent, Debug)]
+pub struct ListenFailed(pub ListenError);
+
+#[derive(Resource)]
+struct Listened(Mutex<Receiver<Result<GlobalEvent, ListenError>>>);
+
+pub struct RdevPlugin;
+
+impl Plugin for RdevPlugin {
+    fn build(&self, app: &mut App) {
+        let (sender, receiver) = mpsc::channel();
+        thread::spawn(move || {
+            // Only used to scale mouse moves, monitors rarely change.
+            let displays = crate::_displays().unwrap_or_default();
+            let listening = sender.clone();
+            let listened = crate::listen(move |event| {
+                let _ = listening.send(Ok(GlobalEvent::new(event, &displays)));
+            });
+            if let Err(error) = listened {
+                let _ = sender.send(Err(error));
+            }
+        });
+        app.add_event::<GlobalEvent>()
+            .add_event::<ListenFailed>()
+            .insert_resource(Listened(Mutex::new(receiver)))
+            .add_systems(PreUpdate, write_events);
+    }
+}
+
+fn write_events(
+    listened: Res<Listened>,
+    mut events: EventWriter<GlobalEvent>,
+    mut failures: EventWriter<ListenFailed>,
+) {
+    let receiver = listened.0.lock().unwrap_or_else(PoisonError::into_inner);
+    for received in receiver.try_iter() {
+        match received {
+            Ok(event) => events.send(event),
+            Err(error) => failures.send(ListenFailed(error)),
+        }
+    }
+}
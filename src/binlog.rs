@@ -0,0 +1,259 @@
+//! A binary log of events, for long recordings: a mouse move takes about
+//! 20 bytes.
+//! Files start with `MAGIC`, then each event is the milliseconds since the
+//! previous one (since the UNIX epoch for the first one) as a zigzag varint,
+//! the length of the rest, and its `compact` representation with numbers as
+//! varints and floats little endian. Like `jsonl::EventReader`,
+//! `BinaryReader` skips the events it can't read and counts them.
+//! `jsonl_to_binary` and `binary_to_jsonl` convert between the two.
+//!
+//! ```no_run
+//! use rdev::binlog::{jsonl_to_binary, BinaryReader};
+//! use std::fs::File;
+//! use std::io::{BufReader, BufWriter};
+//!
+//! let jsonl = BufReader::new(File::open("events.jsonl").unwrap());
+//! let binary = BufWriter::new(File::create("events.bin").unwrap());
+//! jsonl_to_binary(jsonl, binary).unwrap();
+//! for event in BinaryReader::open("events.bin").unwrap() {
+//!     println!("{:?}", event.unwrap());
+//! }
+//! ```
+use crate::binary;
+use crate::compact::CompactEvent;
+use crate::jsonl::{EventReader, EventWriter};
+use crate::rdev::Event;
+use std::fs::File;
+use std::io::{self, BufRead, BufReader, BufWriter, Read, Write};
+use std::path::Path;
+use std::time::{Duration, UNIX_EPOCH};
+
+/// The first bytes of binary logs, the last one is the version.
+pub const MAGIC: &[u8; 8] = b"RDEVLOG\x01";
+
+fn millis(event: &Event) -> i64 {
+    event
+        .time
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_millis() as i64)
+        .unwrap_or(0)
+}
+
+pub struct BinaryWriter<W: Write> {
+    writer: W,
+    last: i64,
+    buffer: Vec<u8>,
+}
+
+impl<W: Write> BinaryWriter<W> {
+    /// Writes `MAGIC`.
+    pub fn new(mut writer: W) -> io::Result<BinaryWriter<W>> {
+        writer.write_all(MAGIC)?;
+        Ok(BinaryWriter {
+            writer,
+            last: 0,
+            buffer: vec![],
+        })
+    }
+
+    pub fn write(&mut self, event: &Event) -> io::Result<()> {
+        let time = millis(event);
+        binary::write_varint(&mut self.writer, binary::zigzag(time - self.last))?;
+        self.last = time;
+        // The time is already written, 0 takes a single byte.
+        let event = Event {
+            time: UNIX_EPOCH,
+            ..event.clone()
+        };
+        self.buffer.clear();
+        binary::to_writer(&mut self.buffer, &CompactEvent(event))?;
+        binary::write_varint(&mut self.writer, self.buffer.len() as u64)?;
+        self.writer.write_all(&self.buffer)
+    }
+
+    pub fn flush(&mut self) -> io::Result<()> {
+        self.writer.flush()
+    }
+
+    pub fn into_inner(self) -> W {
+        self.writer
+    }
+}
+
+impl BinaryWriter<BufWriter<File>> {
+    /// Creates the file, replacing an existing one.
+    pub fn create<P: AsRef<Path>>(path: P) -> io::Result<BinaryWriter<BufWriter<File>>> {
+        BinaryWriter::new(BufWriter::new(File::create(path)?))
+    }
+}
+
+/// Iterates over the events of a binary log.
+pub struct BinaryReader<R: Read> {
+    reader: R,
+    last: i64,
+    skipped: usize,
+}
+
+impl<R: Read> BinaryReader<R> {
+    /// Reads `MAGIC`, `InvalidData` if it isn't there or is of a newer
+    /// version.
+    pub fn new(mut reader: R) -> io::Result<BinaryReader<R>> {
+        let mut magic = [0; 8];
+        reader.read_exact(&mut magic)?;
+        if magic[..7] != MAGIC[..7] || magic[7] > MAGIC[7] {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                "not a binary event log, or of a newer version",
+            ));
+        }
+        Ok(BinaryReader {
+            reader,
+            last: 0,
+            skipped: 0,
+        })
+    }
+
+    /// How many events couldn't be read so far.
+    pub fn skipped(&self) -> usize {
+        self.skipped
+    }
+
+    /// None at the end of the log.
+    fn read_delta(&mut self) -> io::Result<Option<u64>> {
+        let mut first = [0];
+        loop {
+            match self.reader.read(&mut first) {
+                Ok(0) => return Ok(None),
+                Ok(_) => break,
+                Err(error) if error.kind() == io::ErrorKind::Interrupted => continue,
+                Err(error) => return Err(error),
+            }
+        }
+        let low = u64::from(first[0] & 0x7f);
+        if first[0] & 0x80 == 0 {
+            return Ok(Some(low));
+        }
+        Ok(Some(low | binary::read_varint(&mut self.reader)? << 7))
+    }
+}
+
+impl BinaryReader<BufReader<File>> {
+    pub fn open<P: AsRef<Path>>(path: P) -> io::Result<BinaryReader<BufReader<File>>> {
+        BinaryReader::new(BufReader::new(File::open(path)?))
+    }
+}
+
+impl<R: Read> Iterator for BinaryReader<R> {
+    type Item = io::Result<Event>;
+
+    fn next(&mut self) -> Option<io::Result<Event>> {
+        loop {
+            let delta = match self.read_delta() {
+                Ok(delta) => delta?,
+                Err(error) => return Some(Err(error)),
+            };
+            self.last += binary::unzigzag(delta);
+            let read = binary::read_varint(&mut self.reader).and_then(|len| {
+                let mut bytes = vec![];
+                (&mut self.reader).take(len).read_to_end(&mut bytes)?;
+                if bytes.len() as u64 != len {
+                    return Err(io::ErrorKind::UnexpectedEof.into());
+                }
+                Ok(bytes)
+            });
+            let bytes = match read {
+                Ok(bytes) => bytes,
+                // The log was cut short, we lost the last event.
+                Err(error) if error.kind() == io::ErrorKind::UnexpectedEof => {
+                    self.skipped += 1;
+                    return None;
+                }
+                Err(error) => return Some(Err(error)),
+            };
+            match binary::from_reader::<_, CompactEvent>(&bytes[..]) {
+                Ok(CompactEvent(mut event)) => {
+                    event.time = UNIX_EPOCH + Duration::from_millis(self.last.max(0) as u64);
+                    return Some(Ok(event));
+                }
+                // An event of a newer version.
+                Err(_) => self.skipped += 1,
+            }
+        }
+    }
+}
+
+/// Converts a `jsonl` stream to a binary log, returns the number of events.
+/// Lines that can't be read are left out.
+pub fn jsonl_to_binary<R: BufRead, W: Write>(jsonl: R, binary: W) -> io::Result<usize> {
+    let mut writer = BinaryWriter::new(binary)?;
+    let mut count = 0;
+    for event in EventReader::new(jsonl)? {
+        writer.write(&event?)?;
+        count += 1;
+    }
+    writer.flush()?;
+    Ok(count)
+}
+
+/// Converts a binary log to a `jsonl` stream, returns the number of events.
+/// Events that can't be read are left out.
+pub fn binary_to_jsonl<R: Read, W: Write>(binary: R, jsonl: W) -> io::Result<usize> {
+    let mut writer = EventWriter::new(jsonl)?;
+    let mut count = 0;
+    for event in BinaryReader::new(binary)? {
+        writer.write(&event?)?;
+        count += 1;
+    }
+    writer.flush()?;
+    Ok(count)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::rdev::EventType;
+
+    #[test]
+    fn test_binary_log() {
+        let start = UNIX_EPOCH + Duration::from_millis(1_700_000_000_000);
+        let events: Vec<Event> = [0, 8, 4, 20]
+            .iter()
+            .map(|millis| Event {
+                event_type: EventType::MouseMove {
+                    x: 100.0 + *millis as f64,
+                    y: 200.0,
+                },
+                time: start + Duration::from_millis(*millis),
+                name: None,
+                unicode: None,
+                scan_code: 0,
+                display: None,
+                delta: None,
+                numlock: None,
+            })
+            .collect();
+        let mut writer = BinaryWriter::new(vec![]).unwrap();
+        for event in &events {
+            writer.write(event).unwrap();
+        }
+        let mut bytes = writer.into_inner();
+        let jsonl_len = {
+            let mut jsonl = vec![];
+            assert_eq!(binary_to_jsonl(&bytes[..], &mut jsonl).unwrap(), 4);
+            let mut back = vec![];
+            assert_eq!(jsonl_to_binary(&jsonl[..], &mut back).unwrap(), 4);
+            assert_eq!(back, bytes);
+            jsonl.len()
+        };
+        assert!(bytes.len() < jsonl_len);
+
+        // A kind from the future, then a cut event.
+        bytes.extend_from_slice(&[2, 3, 2, 0, 100]);
+        bytes.extend_from_slice(&[2, 20, 1]);
+        let mut reader = BinaryReader::new(&bytes[..]).unwrap();
+        let read: Vec<Event> = (&mut reader).map(Result::unwrap).collect();
+        assert_eq!(read, events);
+        assert_eq!(reader.skipped(), 2);
+        assert!(BinaryReader::new(&b"RDEVREC\x01"[..]).is_err());
+    }
+}
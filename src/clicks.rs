@@ -0,0 +1,267 @@
+use crate::rdev::{Button, ClickSettings, Event, EventType, ListenError};
+use std::sync::{Arc, Mutex, PoisonError};
+use std::thread;
+use std::time::{Duration, SystemTime};
+
+/// How often `GestureDetector::listen` looks for long presses.
+const LONG_PRESS_POLL: Duration = Duration::from_millis(20);
+
+/// Sent by `GestureDetector`, at the position of the press except for
+/// `DragEnd`.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum ClickGesture {
+    /// On the second press.
+    DoubleClick { button: Button, x: f64, y: f64 },
+    /// On the third press, a fourth one starts over.
+    TripleClick { button: Button, x: f64, y: f64 },
+    /// The pointer moved past the drag threshold with the button down.
+    DragStart { button: Button, x: f64, y: f64 },
+    /// The button was released after `DragStart`.
+    DragEnd { button: Button, x: f64, y: f64 },
+    /// The button was held still for `ClickSettings::long_press`.
+    LongPress { button: Button, x: f64, y: f64 },
+}
+
+struct Press {
+    button: Button,
+    position: (f64, f64),
+    time: SystemTime,
+    dragging: bool,
+    long: bool,
+}
+
+/// A click that could become a double click.
+struct Click {
+    button: Button,
+    position: (f64, f64),
+    time: SystemTime,
+    count: u32,
+}
+
+fn distance(a: (f64, f64), b: (f64, f64)) -> f64 {
+    (a.0 - b.0).hypot(a.1 - b.1)
+}
+
+/// Turns button presses and mouse moves into double clicks, drags and long
+/// presses. Feed it events with `add`, and call `poll` now and then for long
+/// presses to come without waiting for the next event, or let `listen` do
+/// both.
+///
+/// ```no_run
+/// use rdev::GestureDetector;
+///
+/// // This will block.
+/// GestureDetector::new()
+///     .listen(|gesture| println!("{:?}", gesture))
+///     .unwrap();
+/// ```
+pub struct GestureDetector {
+    settings: ClickSettings,
+    position: (f64, f64),
+    press: Option<Press>,
+    click: Option<Click>,
+}
+
+impl GestureDetector {
+    /// With the settings of the OS, see `click_settings`.
+    pub fn new() -> GestureDetector {
+        GestureDetector::with_settings(crate::click_settings())
+    }
+
+    pub fn with_settings(settings: ClickSettings) -> GestureDetector {
+        GestureDetector {
+            settings,
+            position: (0.0, 0.0),
+            press: None,
+            click: None,
+        }
+    }
+
+    pub fn add(&mut self, event: &Event) -> Vec<ClickGesture> {
+        let mut gestures: Vec<ClickGesture> = self.poll(event.time).into_iter().collect();
+        let (x, y) = self.position;
+        match event.event_type {
+            EventType::ButtonPress(button) => {
+                let count = match &self.click {
+                    Some(click)
+                        if click.button == button
+                            && distance(click.position, self.position)
+                                <= self.settings.double_click_distance
+                            && event
+                                .time
+                                .duration_since(click.time)
+                                .map(|since| since <= self.settings.double_click_interval)
+                                .unwrap_or(false) =>
+                    {
+                        click.count % 3 + 1
+                    }
+                    _ => 1,
+                };
+                match count {
+                    2 => gestures.push(ClickGesture::DoubleClick { button, x, y }),
+                    3 => gestures.push(ClickGesture::TripleClick { button, x, y }),
+                    _ => (),
+                }
+                self.click = Some(Click {
+                    button,
+                    position: self.position,
+                    time: event.time,
+                    count,
+                });
+                self.press = Some(Press {
+                    button,
+                    position: self.position,
+                    time: event.time,
+                    dragging: false,
+                    long: false,
+                });
+            }
+            EventType::ButtonRelease(button) => {
+                if let Some(press) = self.press.take() {
+                    if press.button != button {
+                        self.press = Some(press);
+                    } else if press.dragging {
+                        gestures.push(ClickGesture::DragEnd { button, x, y });
+                    }
+                }
+            }
+            EventType::MouseMove { x, y, .. } => {
+                self.position = (x, y);
+                if let Some(press) = &mut self.press {
+                    if !press.dragging
+                        && distance(press.position, self.position) > self.settings.drag_threshold
+                    {
+                        press.dragging = true;
+                        // A drag isn't a click.
+                        self.click = None;
+                        gestures.push(ClickGesture::DragStart {
+                            button: press.button,
+                            x: press.position.0,
+                            y: press.position.1,
+                        });
+                    }
+                }
+            }
+            _ => (),
+        }
+        gestures
+    }
+
+    /// A `LongPress` if the button held reached the delay by `now`.
+    pub fn poll(&mut self, now: SystemTime) -> Option<ClickGesture> {
+        let press = self.press.as_mut()?;
+        let held = now.duration_since(press.time).ok()?;
+        if press.dragging || press.long || held < self.settings.long_press {
+            return None;
+        }
+        press.long = true;
+        self.click = None;
+        Some(ClickGesture::LongPress {
+            button: press.button,
+            x: press.position.0,
+            y: press.position.1,
+        })
+    }
+
+    /// Detects gestures in the events of `listen`, `callback` is called
+    /// from the thread polling for long presses too. Blocks.
+    pub fn listen<F>(self, callback: F) -> Result<(), ListenError>
+    where
+        F: FnMut(ClickGesture) + Send + 'static,
+    {
+        let detector = Arc::new(Mutex::new(self));
+        let callback = Arc::new(Mutex::new(callback));
+        let (polled, poll_callback) = (detector.clone(), callback.clone());
+        thread::spawn(move || loop {
+            thread::sleep(LONG_PRESS_POLL);
+            let gesture = polled
+                .lock()
+                .unwrap_or_else(PoisonError::into_inner)
+                .poll(SystemTime::now());
+            if let Some(gesture) = gesture {
+                let mut callback = poll_callback.lock().unwrap_or_else(PoisonError::into_inner);
+                (*callback)(gesture);
+            }
+        });
+        crate::listen(move |event| {
+            let gestures = detector
+                .lock()
+                .unwrap_or_else(PoisonError::into_inner)
+                .add(&event);
+            let mut callback = callback.lock().unwrap_or_else(PoisonError::into_inner);
+            for gesture in gestures {
+                (*callback)(gesture);
+            }
+        })
+    }
+}
+
+impl Default for GestureDetector {
+    fn default() -> GestureDetector {
+        GestureDetector::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::UNIX_EPOCH;
+
+    #[test]
+    fn test_gesture_detector() {
+        let mut detector = GestureDetector::with_settings(ClickSettings::default());
+        let mut add = |event_type, millis: u64| {
+            detector.add(&Event {
+                event_type,
+                time: UNIX_EPOCH + Duration::from_millis(millis),
+                name: None,
+                unicode: None,
+                scan_code: 0,
+                display: None,
+                delta: None,
+                numlock: None,
+            })
+        };
+        let move_to = |x, y| EventType::MouseMove { x, y };
+        let (press, release) = (
+            EventType::ButtonPress(Button::Left),
+            EventType::ButtonRelease(Button::Left),
+        );
+        add(move_to(10.0, 10.0), 0);
+        assert_eq!(add(press, 10), vec![]);
+        add(release, 50);
+        add(move_to(12.0, 10.0), 60);
+        let (button, x, y) = (Button::Left, 12.0, 10.0);
+        assert_eq!(
+            add(press, 100),
+            vec![ClickGesture::DoubleClick { button, x, y }]
+        );
+        add(release, 150);
+        assert_eq!(
+            add(press, 200),
+            vec![ClickGesture::TripleClick { button, x, y }]
+        );
+        add(release, 250);
+        // Too late for a double click.
+        assert_eq!(add(press, 1000), vec![]);
+        assert_eq!(
+            add(move_to(30.0, 10.0), 1100),
+            vec![ClickGesture::DragStart { button, x, y }]
+        );
+        assert_eq!(
+            add(release, 2000),
+            vec![ClickGesture::DragEnd {
+                button,
+                x: 30.0,
+                y: 10.0
+            }]
+        );
+        add(press, 3000);
+        let (x, y) = (30.0, 10.0);
+        assert_eq!(
+            add(move_to(31.0, 10.0), 3600),
+            vec![ClickGesture::LongPress { button, x, y }]
+        );
+        assert_eq!(add(release, 3700), vec![]);
+    }
+}
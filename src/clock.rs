@@ -0,0 +1,74 @@
+use std::sync::{Arc, Mutex, PoisonError};
+use std::thread;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+/// Where `Recorder`, `Player` and the mock backend get the time from, so
+/// tests and CI can replay without waiting.
+pub trait Clock: Send + Sync {
+    fn now(&self) -> SystemTime;
+
+    fn sleep(&self, duration: Duration);
+}
+
+/// The system time, and `thread::sleep`.
+#[derive(Debug, Copy, Clone, Default, PartialEq, Eq)]
+pub struct SystemClock;
+
+impl Clock for SystemClock {
+    fn now(&self) -> SystemTime {
+        SystemTime::now()
+    }
+
+    fn sleep(&self, duration: Duration) {
+        thread::sleep(duration)
+    }
+}
+
+/// A clock that only moves when told to, or when slept on, which returns
+/// right away. Clones share the same time.
+///
+/// ```
+/// use rdev::{Clock, VirtualClock};
+/// use std::time::{Duration, UNIX_EPOCH};
+///
+/// let clock = VirtualClock::new(UNIX_EPOCH);
+/// clock.sleep(Duration::from_secs(3600));
+/// assert_eq!(clock.now(), UNIX_EPOCH + Duration::from_secs(3600));
+/// ```
+#[derive(Debug, Clone)]
+pub struct VirtualClock {
+    now: Arc<Mutex<SystemTime>>,
+}
+
+impl VirtualClock {
+    pub fn new(start: SystemTime) -> VirtualClock {
+        VirtualClock {
+            now: Arc::new(Mutex::new(start)),
+        }
+    }
+
+    pub fn advance(&self, duration: Duration) {
+        *self.now.lock().unwrap_or_else(PoisonError::into_inner) += duration;
+    }
+
+    pub fn set(&self, time: SystemTime) {
+        *self.now.lock().unwrap_or_else(PoisonError::into_inner) = time;
+    }
+}
+
+/// Starts at the UNIX epoch.
+impl Default for VirtualClock {
+    fn default() -> VirtualClock {
+        VirtualClock::new(UNIX_EPOCH)
+    }
+}
+
+impl Clock for VirtualClock {
+    fn now(&self) -> SystemTime {
+        *self.now.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+
+    fn sleep(&self, duration: Duration) {
+        self.advance(duration)
+    }
+}
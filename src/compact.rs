@@ -0,0 +1,558 @@
+//! Compact serde representation of events, for high frequency recordings.
+//! An event becomes a flat sequence: the time in milliseconds since the
+//! UNIX epoch, a numeric kind, its payload, then the name, scan code and dead
+//! key state up to the last one that is set. `unicode` is rebuilt from the
+//! name.
+//! In JSON a mouse move is `[1700000000000,4,100.0,200.0]`, about 5x smaller
+//! than the default representation.
+//!
+//! ```no_run
+//! use rdev::compact::CompactEvent;
+//! use rdev::{Event, EventType};
+//! use std::time::SystemTime;
+//!
+//! let event = Event::new(
+//!     EventType::MouseMove { x: 100.0, y: 200.0 },
+//!     SystemTime::now(),
+//!     None,
+//! );
+//! let serialized = serde_json::to_string(&CompactEvent(event)).unwrap();
+//! let CompactEvent(event) = serde_json::from_str(&serialized).unwrap();
+//! ```
+//!
+//! The `serialize` and `deserialize` functions work with `#[serde(with = "rdev::compact")]`.
+//! Numeric values are stable, new keys only get new numbers.
+use crate::ids::{button_from_id, button_to_id, key_from_id, key_to_id};
+use crate::rdev::{Event, EventType, Pen, ScrollPhase, UnicodeInfo};
+#[cfg(feature = "gestures")]
+use crate::rdev::{Gesture, GesturePhase};
+use serde::de::{self, SeqAccess, Visitor};
+use serde::ser::SerializeSeq;
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+use std::fmt;
+use std::time::{Duration, UNIX_EPOCH};
+
+const KEY_PRESS: u8 = 0;
+const KEY_RELEASE: u8 = 1;
+const BUTTON_PRESS: u8 = 2;
+const BUTTON_RELEASE: u8 = 3;
+const MOUSE_MOVE: u8 = 4;
+const WHEEL: u8 = 5;
+const LAYOUT_CHANGED: u8 = 6;
+const IME_COMPOSITION: u8 = 7;
+const IME_COMMIT: u8 = 8;
+const TOUCH_DOWN: u8 = 9;
+const TOUCH_MOVE: u8 = 10;
+const TOUCH_UP: u8 = 11;
+#[cfg(feature = "gestures")]
+const MAGNIFY: u8 = 12;
+#[cfg(feature = "gestures")]
+const ROTATE: u8 = 13;
+#[cfg(feature = "gestures")]
+const SWIPE: u8 = 14;
+const PEN_DOWN: u8 = 15;
+const PEN_MOVE: u8 = 16;
+const PEN_UP: u8 = 17;
+const SCROLL: u8 = 18;
+/// A mouse move carrying its display and delta, either can be null.
+const MOUSE_MOTION: u8 = 19;
+const SECURE_INPUT: u8 = 20;
+
+fn scroll_phase_to_id(phase: &ScrollPhase) -> u8 {
+    match phase {
+        ScrollPhase::Begin => 0,
+        ScrollPhase::Change => 1,
+        ScrollPhase::End => 2,
+    }
+}
+
+fn scroll_phase_from_id(id: u8) -> Option<ScrollPhase> {
+    match id {
+        0 => Some(ScrollPhase::Begin),
+        1 => Some(ScrollPhase::Change),
+        2 => Some(ScrollPhase::End),
+        _ => None,
+    }
+}
+
+#[cfg(feature = "gestures")]
+fn phase_to_id(phase: &GesturePhase) -> u8 {
+    match phase {
+        GesturePhase::Begin => 0,
+        GesturePhase::Change => 1,
+        GesturePhase::End => 2,
+    }
+}
+
+#[cfg(feature = "gestures")]
+fn phase_from_id(id: u8) -> Option<GesturePhase> {
+    match id {
+        0 => Some(GesturePhase::Begin),
+        1 => Some(GesturePhase::Change),
+        2 => Some(GesturePhase::End),
+        _ => None,
+    }
+}
+
+/// Event with the compact serde representation.
+#[derive(Debug, Clone, PartialEq)]
+pub struct CompactEvent(pub Event);
+
+impl From<Event> for CompactEvent {
+    fn from(event: Event) -> CompactEvent {
+        CompactEvent(event)
+    }
+}
+
+impl From<CompactEvent> for Event {
+    fn from(event: CompactEvent) -> Event {
+        event.0
+    }
+}
+
+impl Serialize for CompactEvent {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serialize(&self.0, serializer)
+    }
+}
+
+impl<'de> Deserialize<'de> for CompactEvent {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<CompactEvent, D::Error> {
+        deserialize(deserializer).map(CompactEvent)
+    }
+}
+
+pub fn serialize<S: Serializer>(event: &Event, serializer: S) -> Result<S::Ok, S::Error> {
+    let millis = event
+        .time
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_millis() as u64)
+        .unwrap_or(0);
+    let motion = event.display.is_some() || event.delta.is_some();
+    let payload = match &event.event_type {
+        EventType::KeyPress(_)
+        | EventType::KeyRelease(_)
+        | EventType::ButtonPress(_)
+        | EventType::ButtonRelease(_) => 1,
+        EventType::MouseMove { .. } if motion => 5,
+        EventType::MouseMove { .. } | EventType::Wheel { .. } => 2,
+        EventType::Scroll { .. } => 4,
+        EventType::TouchDown { .. } | EventType::TouchMove { .. } | EventType::TouchUp { .. } => 4,
+        EventType::PenDown(_) | EventType::PenMove(_) | EventType::PenUp(_) => 6,
+        #[cfg(feature = "gestures")]
+        EventType::Gesture(Gesture::Swipe { .. }) => 3,
+        #[cfg(feature = "gestures")]
+        EventType::Gesture(_) => 2,
+        EventType::LayoutChanged | EventType::ImeComposition | EventType::ImeCommit => 0,
+        EventType::SecureInput(_) => 1,
+    };
+    let is_dead = event
+        .unicode
+        .as_ref()
+        .map(|unicode| unicode.is_dead)
+        .unwrap_or(false);
+    // Trailing values are only written up to the last one that isn't empty.
+    let trailing = if is_dead {
+        3
+    } else if event.scan_code != 0 {
+        2
+    } else {
+        event.name.is_some() as usize
+    };
+    let len = 2 + payload + trailing;
+    let mut seq = serializer.serialize_seq(Some(len))?;
+    seq.serialize_element(&millis)?;
+    match &event.event_type {
+        EventType::KeyPress(key) => {
+            seq.serialize_element(&KEY_PRESS)?;
+            seq.serialize_element(&key_to_id(key))?;
+        }
+        EventType::KeyRelease(key) => {
+            seq.serialize_element(&KEY_RELEASE)?;
+            seq.serialize_element(&key_to_id(key))?;
+        }
+        EventType::ButtonPress(button) => {
+            seq.serialize_element(&BUTTON_PRESS)?;
+            seq.serialize_element(&button_to_id(button))?;
+        }
+        EventType::ButtonRelease(button) => {
+            seq.serialize_element(&BUTTON_RELEASE)?;
+            seq.serialize_element(&button_to_id(button))?;
+        }
+        EventType::MouseMove { x, y } if motion => {
+            seq.serialize_element(&MOUSE_MOTION)?;
+            seq.serialize_element(x)?;
+            seq.serialize_element(y)?;
+            seq.serialize_element(&event.display)?;
+            seq.serialize_element(&event.delta.map(|(delta_x, _)| delta_x))?;
+            seq.serialize_element(&event.delta.map(|(_, delta_y)| delta_y))?;
+        }
+        EventType::MouseMove { x, y } => {
+            seq.serialize_element(&MOUSE_MOVE)?;
+            seq.serialize_element(x)?;
+            seq.serialize_element(y)?;
+        }
+        EventType::Wheel { delta_x, delta_y } => {
+            seq.serialize_element(&WHEEL)?;
+            seq.serialize_element(delta_x)?;
+            seq.serialize_element(delta_y)?;
+        }
+        EventType::Scroll {
+            delta_x,
+            delta_y,
+            phase,
+            is_momentum,
+        } => {
+            seq.serialize_element(&SCROLL)?;
+            seq.serialize_element(delta_x)?;
+            seq.serialize_element(delta_y)?;
+            seq.serialize_element(&scroll_phase_to_id(phase))?;
+            seq.serialize_element(is_momentum)?;
+        }
+        EventType::LayoutChanged => seq.serialize_element(&LAYOUT_CHANGED)?,
+        EventType::ImeComposition => seq.serialize_element(&IME_COMPOSITION)?,
+        EventType::ImeCommit => seq.serialize_element(&IME_COMMIT)?,
+        EventType::SecureInput(active) => {
+            seq.serialize_element(&SECURE_INPUT)?;
+            seq.serialize_element(active)?;
+        }
+        EventType::TouchDown { id, x, y, pressure }
+        | EventType::TouchMove { id, x, y, pressure }
+        | EventType::TouchUp { id, x, y, pressure } => {
+            let kind = match &event.event_type {
+                EventType::TouchDown { .. } => TOUCH_DOWN,
+                EventType::TouchMove { .. } => TOUCH_MOVE,
+                _ => TOUCH_UP,
+            };
+            seq.serialize_element(&kind)?;
+            seq.serialize_element(id)?;
+            seq.serialize_element(x)?;
+            seq.serialize_element(y)?;
+            seq.serialize_element(pressure)?;
+        }
+        EventType::PenDown(pen) | EventType::PenMove(pen) | EventType::PenUp(pen) => {
+            let kind = match &event.event_type {
+                EventType::PenDown(_) => PEN_DOWN,
+                EventType::PenMove(_) => PEN_MOVE,
+                _ => PEN_UP,
+            };
+            seq.serialize_element(&kind)?;
+            seq.serialize_element(&pen.x)?;
+            seq.serialize_element(&pen.y)?;
+            seq.serialize_element(&pen.pressure)?;
+            seq.serialize_element(&pen.tilt_x)?;
+            seq.serialize_element(&pen.tilt_y)?;
+            seq.serialize_element(&pen.eraser)?;
+        }
+        #[cfg(feature = "gestures")]
+        EventType::Gesture(gesture) => match gesture {
+            Gesture::Magnify { phase, delta } => {
+                seq.serialize_element(&MAGNIFY)?;
+                seq.serialize_element(&phase_to_id(phase))?;
+                seq.serialize_element(delta)?;
+            }
+            Gesture::Rotate { phase, degrees } => {
+                seq.serialize_element(&ROTATE)?;
+                seq.serialize_element(&phase_to_id(phase))?;
+                seq.serialize_element(degrees)?;
+            }
+            Gesture::Swipe {
+                phase,
+                delta_x,
+                delta_y,
+            } => {
+                seq.serialize_element(&SWIPE)?;
+                seq.serialize_element(&phase_to_id(phase))?;
+                seq.serialize_element(delta_x)?;
+                seq.serialize_element(delta_y)?;
+            }
+        },
+    }
+    if trailing > 0 {
+        seq.serialize_element(&event.name)?;
+    }
+    if trailing > 1 {
+        seq.serialize_element(&event.scan_code)?;
+    }
+    if trailing > 2 {
+        seq.serialize_element(&is_dead)?;
+    }
+    seq.end()
+}
+
+pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Event, D::Error> {
+    deserializer.deserialize_seq(CompactVisitor)
+}
+
+struct CompactVisitor;
+
+fn next<'de, A: SeqAccess<'de>, T: Deserialize<'de>>(seq: &mut A, i: usize) -> Result<T, A::Error> {
+    seq.next_element()?
+        .ok_or_else(|| de::Error::invalid_length(i, &"a compact event"))
+}
+
+impl<'de> Visitor<'de> for CompactVisitor {
+    type Value = Event;
+
+    fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        formatter.write_str("a compact event sequence")
+    }
+
+    fn visit_seq<A: SeqAccess<'de>>(self, mut seq: A) -> Result<Event, A::Error> {
+        let millis: u64 = next(&mut seq, 0)?;
+        let kind: u8 = next(&mut seq, 1)?;
+        let invalid = |what: &str, id: u64| -> A::Error {
+            de::Error::invalid_value(de::Unexpected::Unsigned(id), &what)
+        };
+        let mut display = None;
+        let mut delta = None;
+        let event_type = match kind {
+            KEY_PRESS | KEY_RELEASE => {
+                let id: u64 = next(&mut seq, 2)?;
+                let key = key_from_id(id).ok_or_else(|| invalid("a key id", id))?;
+                if kind == KEY_PRESS {
+                    EventType::KeyPress(key)
+                } else {
+                    EventType::KeyRelease(key)
+                }
+            }
+            BUTTON_PRESS | BUTTON_RELEASE => {
+                let id: u64 = next(&mut seq, 2)?;
+                let button = button_from_id(id).ok_or_else(|| invalid("a button id", id))?;
+                if kind == BUTTON_PRESS {
+                    EventType::ButtonPress(button)
+                } else {
+                    EventType::ButtonRelease(button)
+                }
+            }
+            MOUSE_MOVE => EventType::MouseMove {
+                x: next(&mut seq, 2)?,
+                y: next(&mut seq, 3)?,
+            },
+            MOUSE_MOTION => {
+                let x = next(&mut seq, 2)?;
+                let y = next(&mut seq, 3)?;
+                display = next(&mut seq, 4)?;
+                let delta_x: Option<f64> = next(&mut seq, 5)?;
+                let delta_y: Option<f64> = next(&mut seq, 6)?;
+                delta = delta_x.zip(delta_y);
+                EventType::MouseMove { x, y }
+            }
+            WHEEL => EventType::Wheel {
+                delta_x: next(&mut seq, 2)?,
+                delta_y: next(&mut seq, 3)?,
+            },
+            SCROLL => {
+                let delta_x = next(&mut seq, 2)?;
+                let delta_y = next(&mut seq, 3)?;
+                let id: u8 = next(&mut seq, 4)?;
+                let phase =
+                    scroll_phase_from_id(id).ok_or_else(|| invalid("a scroll phase", id.into()))?;
+                EventType::Scroll {
+                    delta_x,
+                    delta_y,
+                    phase,
+                    is_momentum: next(&mut seq, 5)?,
+                }
+            }
+            LAYOUT_CHANGED => EventType::LayoutChanged,
+            IME_COMPOSITION => EventType::ImeComposition,
+            IME_COMMIT => EventType::ImeCommit,
+            SECURE_INPUT => EventType::SecureInput(next(&mut seq, 2)?),
+            TOUCH_DOWN | TOUCH_MOVE | TOUCH_UP => {
+                let id = next(&mut seq, 2)?;
+                let x = next(&mut seq, 3)?;
+                let y = next(&mut seq, 4)?;
+                let pressure = next(&mut seq, 5)?;
+                match kind {
+                    TOUCH_DOWN => EventType::TouchDown { id, x, y, pressure },
+                    TOUCH_MOVE => EventType::TouchMove { id, x, y, pressure },
+                    _ => EventType::TouchUp { id, x, y, pressure },
+                }
+            }
+            PEN_DOWN | PEN_MOVE | PEN_UP => {
+                let pen = Pen {
+                    x: next(&mut seq, 2)?,
+                    y: next(&mut seq, 3)?,
+                    pressure: next(&mut seq, 4)?,
+                    tilt_x: next(&mut seq, 5)?,
+                    tilt_y: next(&mut seq, 6)?,
+                    eraser: next(&mut seq, 7)?,
+                };
+                match kind {
+                    PEN_DOWN => EventType::PenDown(pen),
+                    PEN_MOVE => EventType::PenMove(pen),
+                    _ => EventType::PenUp(pen),
+                }
+            }
+            #[cfg(feature = "gestures")]
+            MAGNIFY | ROTATE | SWIPE => {
+                let id: u8 = next(&mut seq, 2)?;
+                let phase =
+                    phase_from_id(id).ok_or_else(|| invalid("a gesture phase", id.into()))?;
+                let gesture = match kind {
+                    MAGNIFY => Gesture::Magnify {
+                        phase,
+                        delta: next(&mut seq, 3)?,
+                    },
+                    ROTATE => Gesture::Rotate {
+                        phase,
+                        degrees: next(&mut seq, 3)?,
+                    },
+                    _ => Gesture::Swipe {
+                        phase,
+                        delta_x: next(&mut seq, 3)?,
+                        delta_y: next(&mut seq, 4)?,
+                    },
+                };
+                EventType::Gesture(gesture)
+            }
+            kind => return Err(invalid("an event kind", kind.into())),
+        };
+        let name: Option<String> = seq.next_element::<Option<String>>()?.flatten();
+        let scan_code: u32 = seq.next_element()?.unwrap_or(0);
+        let is_dead: bool = seq.next_element()?.unwrap_or(false);
+        Ok(Event {
+            time: UNIX_EPOCH + Duration::from_millis(millis),
+            unicode: UnicodeInfo::new(name.as_deref(), is_dead),
+            name,
+            event_type,
+            scan_code,
+            display,
+            delta,
+            numlock: None,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::rdev::{Button, Key};
+
+    #[test]
+    fn test_compact_roundtrip() {
+        let time = UNIX_EPOCH + Duration::from_millis(1_600_000_000_123);
+        let events = vec![
+            Event {
+                time,
+                name: Some("s".to_string()),
+                event_type: EventType::KeyPress(Key::KeyS),
+                unicode: UnicodeInfo::new(Some("s"), false),
+                scan_code: 31,
+                display: None,
+                delta: None,
+                numlock: None,
+            },
+            Event {
+                time,
+                name: None,
+                event_type: EventType::KeyPress(Key::BackQuote),
+                unicode: UnicodeInfo::new(None, true),
+                scan_code: 41,
+                display: None,
+                delta: None,
+                numlock: None,
+            },
+            Event {
+                time,
+                name: None,
+                unicode: None,
+                scan_code: 0,
+                event_type: EventType::KeyRelease(Key::Unknown(42)),
+                display: None,
+                delta: None,
+                numlock: None,
+            },
+            Event {
+                time,
+                name: None,
+                unicode: None,
+                scan_code: 0,
+                event_type: EventType::MouseMove { x: 1.5, y: 2.0 },
+                display: None,
+                delta: None,
+                numlock: None,
+            },
+            Event {
+                time,
+                name: None,
+                unicode: None,
+                scan_code: 0,
+                event_type: EventType::SecureInput(true),
+                display: None,
+                delta: None,
+                numlock: None,
+            },
+            Event {
+                time,
+                name: None,
+                unicode: None,
+                scan_code: 0,
+                event_type: EventType::MouseMove { x: 1.5, y: 2.0 },
+                display: Some(1),
+                delta: Some((-3.0, 0.5)),
+                numlock: None,
+            },
+            Event {
+                time,
+                name: None,
+                unicode: None,
+                scan_code: 0,
+                event_type: EventType::ButtonPress(Button::Unknown(2)),
+                display: None,
+                delta: None,
+                numlock: None,
+            },
+            Event {
+                time,
+                name: None,
+                unicode: None,
+                scan_code: 0,
+                event_type: EventType::TouchMove {
+                    id: 3,
+                    x: 10.0,
+                    y: 20.5,
+                    pressure: 0.5,
+                },
+                display: None,
+                delta: None,
+                numlock: None,
+            },
+            Event {
+                time,
+                name: None,
+                unicode: None,
+                scan_code: 0,
+                event_type: EventType::PenDown(Pen {
+                    x: 1.0,
+                    y: 2.0,
+                    pressure: 0.25,
+                    tilt_x: -0.5,
+                    tilt_y: 0.0,
+                    eraser: true,
+                }),
+                display: None,
+                delta: None,
+                numlock: None,
+            },
+        ];
+        for event in events {
+            let serialized = serde_json::to_string(&CompactEvent(event.clone())).unwrap();
+            let CompactEvent(deserialized) = serde_json::from_str(&serialized).unwrap();
+            assert_eq!(deserialized, event);
+        }
+        let serialized = serde_json::to_string(&CompactEvent(Event {
+            time,
+            name: None,
+            unicode: None,
+            scan_code: 0,
+            event_type: EventType::MouseMove { x: 1.5, y: 2.0 },
+            display: None,
+            delta: None,
+            numlock: None,
+        }))
+        .unwrap();
+        assert_eq!(serialized, "[1600000000123,4,1.5,2.0]");
+    }
+}
@@ -0,0 +1,86 @@
+use crate::rdev::InputDevice;
+
+/// Another program taking input where rdev does, found by `find_conflicts`.
+/// Another remapper eating keys looks like rdev missing them.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum Conflict {
+    /// MacOS, an event tap of process `pid`. `hid` taps are at the same
+    /// location as ours, the others (session taps) only get events after
+    /// them.
+    EventTap {
+        pid: i32,
+        listen_only: bool,
+        enabled: bool,
+        hid: bool,
+    },
+    /// Linux, another process grabbed the device (EVIOCGRAB), like keyd or
+    /// kmonad do: its events only reach that process, and what it sends
+    /// on through uinput.
+    DeviceGrab(InputDevice),
+}
+
+impl Conflict {
+    /// Whether rdev may never see some events because of it. Taps at the
+    /// same location are called in an order we can't tell, an active one
+    /// called before ours can drop events or change them.
+    pub fn may_hide_events(&self) -> bool {
+        match self {
+            Conflict::EventTap {
+                listen_only,
+                enabled,
+                hid,
+                ..
+            } => !listen_only && *enabled && *hid,
+            Conflict::DeviceGrab(_) => true,
+        }
+    }
+}
+
+/// Other programs hooking input, None where the OS can't tell: Windows
+/// doesn't list low level hooks (the last one installed is called first,
+/// so a hook installed after `listen` or `grab` can swallow events before
+/// ours), and Linux needs read access to /dev/input. Linux briefly grabs
+/// each device to find out.
+///
+/// ```no_run
+/// use rdev::find_conflicts;
+///
+/// for conflict in find_conflicts().unwrap_or_default() {
+///     if conflict.may_hide_events() {
+///         println!("Events may not reach rdev because of {:?}", conflict);
+///     }
+/// }
+/// ```
+pub fn find_conflicts() -> Option<Vec<Conflict>> {
+    #[cfg(target_os = "macos")]
+    {
+        crate::macos::conflicts()
+    }
+    #[cfg(target_os = "linux")]
+    {
+        crate::linux::conflicts()
+    }
+    #[cfg(not(any(target_os = "macos", target_os = "linux")))]
+    {
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_may_hide_events() {
+        let tap = |listen_only, hid| Conflict::EventTap {
+            pid: 1,
+            listen_only,
+            enabled: true,
+            hid,
+        };
+        assert!(tap(false, true).may_hide_events());
+        assert!(!tap(true, true).may_hide_events());
+        assert!(!tap(false, false).may_hide_events());
+    }
+}
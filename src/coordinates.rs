@@ -0,0 +1,222 @@
+use crate::rdev::{DisplayError, DisplayInfo, EventType};
+use lazy_static::lazy_static;
+use std::sync::Mutex;
+
+/// The unit of every position rdev gives or takes (events, `simulate`,
+/// `display_size`, `displays` and `get_mouse_position`), see
+/// `set_coordinate_mode`.
+///
+/// ```no_run
+/// use rdev::{set_coordinate_mode, CoordinateMode};
+///
+/// set_coordinate_mode(CoordinateMode::Physical);
+/// ```
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum CoordinateMode {
+    /// Actual pixels of the monitor.
+    Physical,
+    /// Pixels divided by the scale factor of the monitor, points on macOS.
+    Logical,
+}
+
+/// What the OS gives us. Windows is made DPI aware when a mode is set, X has
+/// no scaling at all.
+#[cfg(target_os = "macos")]
+pub(crate) const NATIVE: CoordinateMode = CoordinateMode::Logical;
+#[cfg(not(target_os = "macos"))]
+pub(crate) const NATIVE: CoordinateMode = CoordinateMode::Physical;
+
+lazy_static! {
+    static ref MODE: Mutex<Option<CoordinateMode>> = Mutex::new(None);
+}
+
+pub(crate) fn set_mode(mode: CoordinateMode) {
+    if let Ok(mut current) = MODE.lock() {
+        *current = Some(mode);
+    }
+}
+
+/// None until a mode is set, positions are then left as the OS gives them.
+pub(crate) fn current_mode() -> Option<CoordinateMode> {
+    MODE.lock().map(|mode| *mode).unwrap_or(None)
+}
+
+/// The factor from native to `mode` for a monitor of scale `scale`.
+pub(crate) fn factor(mode: CoordinateMode, scale: f64) -> f64 {
+    if mode == NATIVE || scale <= 0.0 {
+        1.0
+    } else if mode == CoordinateMode::Logical {
+        1.0 / scale
+    } else {
+        scale
+    }
+}
+
+/// Monitors keep their origin, only their size changes.
+pub(crate) fn display_to_mode(display: &DisplayInfo, mode: CoordinateMode) -> DisplayInfo {
+    let factor = factor(mode, display.scale_factor);
+    DisplayInfo {
+        width: (display.width as f64 * factor).round() as u64,
+        height: (display.height as f64 * factor).round() as u64,
+        ..display.clone()
+    }
+}
+
+fn contains(display: &DisplayInfo, x: f64, y: f64) -> bool {
+    x >= display.x as f64
+        && x < (display.x + display.width as i64) as f64
+        && y >= display.y as f64
+        && y < (display.y + display.height as i64) as f64
+}
+
+/// The monitor of `displays` containing the point, both in the same space.
+pub(crate) fn display_at(displays: &[DisplayInfo], x: f64, y: f64) -> Option<&DisplayInfo> {
+    displays.iter().find(|display| contains(display, x, y))
+}
+
+/// Scales a point around the origin of the monitor it's on, `displays` are
+/// in the space of the point. Points outside of every monitor are left as is.
+fn scale_point(
+    x: f64,
+    y: f64,
+    displays: &[DisplayInfo],
+    factor_of: impl Fn(&DisplayInfo) -> f64,
+) -> (f64, f64) {
+    match display_at(displays, x, y) {
+        Some(display) => {
+            let factor = factor_of(display);
+            let (origin_x, origin_y) = (display.x as f64, display.y as f64);
+            (
+                origin_x + (x - origin_x) * factor,
+                origin_y + (y - origin_y) * factor,
+            )
+        }
+        None => (x, y),
+    }
+}
+
+/// From what the OS gives to `mode`, `displays` are the native ones.
+pub(crate) fn point_to_mode(
+    x: f64,
+    y: f64,
+    displays: &[DisplayInfo],
+    mode: CoordinateMode,
+) -> (f64, f64) {
+    scale_point(x, y, displays, |display| factor(mode, display.scale_factor))
+}
+
+/// From `mode` to what the OS takes, `displays` are the native ones.
+pub(crate) fn point_from_mode(
+    x: f64,
+    y: f64,
+    displays: &[DisplayInfo],
+    mode: CoordinateMode,
+) -> (f64, f64) {
+    let converted: Vec<DisplayInfo> = displays
+        .iter()
+        .map(|display| display_to_mode(display, mode))
+        .collect();
+    scale_point(x, y, &converted, |display| {
+        1.0 / factor(mode, display.scale_factor)
+    })
+}
+
+/// The mouse moves to simulate, as the OS takes them. None when there is
+/// nothing to convert.
+pub(crate) fn native_event(event_type: &EventType) -> Option<EventType> {
+    let mode = current_mode()?;
+    if let EventType::MouseMove { x, y } = event_type {
+        let displays = crate::_displays().ok()?;
+        let (x, y) = point_from_mode(*x, *y, &displays, mode);
+        return Some(EventType::MouseMove { x, y });
+    }
+    None
+}
+
+pub(crate) fn displays() -> Result<Vec<DisplayInfo>, DisplayError> {
+    let displays = crate::_displays()?;
+    Ok(match current_mode() {
+        Some(mode) => displays
+            .iter()
+            .map(|display| display_to_mode(display, mode))
+            .collect(),
+        None => displays,
+    })
+}
+
+pub(crate) fn display_size() -> Result<(u64, u64), DisplayError> {
+    if current_mode().is_none() {
+        return crate::_display_size();
+    }
+    let displays = displays()?;
+    let main = displays
+        .iter()
+        .find(|display| display.is_primary)
+        .or_else(|| displays.first())
+        .ok_or(DisplayError::NoDisplay)?;
+    Ok((main.width, main.height))
+}
+
+pub(crate) fn mouse_position() -> Result<(f64, f64), DisplayError> {
+    let (x, y) = crate::_get_mouse_position()?;
+    match current_mode() {
+        Some(mode) => Ok(point_to_mode(x, y, &crate::_displays()?, mode)),
+        None => Ok((x, y)),
+    }
+}
+
+/// A point of an event, in the current mode, in physical pixels like
+/// windowing libraries use. `displays` are the native ones.
+#[cfg(any(feature = "winit", feature = "bevy"))]
+pub(crate) fn point_to_physical(x: f64, y: f64, displays: &[DisplayInfo]) -> (f64, f64) {
+    let (x, y) = match current_mode() {
+        Some(mode) => point_from_mode(x, y, displays, mode),
+        None => (x, y),
+    };
+    point_to_mode(x, y, displays, CoordinateMode::Physical)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_point_roundtrip() {
+        let displays = vec![
+            DisplayInfo {
+                x: 0,
+                y: 0,
+                width: 1000,
+                height: 1000,
+                scale_factor: 1.0,
+                refresh_rate: None,
+                is_primary: true,
+            },
+            DisplayInfo {
+                x: 1000,
+                y: 0,
+                width: 1000,
+                height: 1000,
+                scale_factor: 2.0,
+                refresh_rate: None,
+                is_primary: false,
+            },
+        ];
+        let other = if NATIVE == CoordinateMode::Logical {
+            CoordinateMode::Physical
+        } else {
+            CoordinateMode::Logical
+        };
+        assert_eq!(display_at(&displays, 1500.0, 10.0), Some(&displays[1]));
+        assert_eq!(display_at(&displays, 2000.0, 10.0), None);
+        // Unscaled monitor.
+        assert_eq!(point_to_mode(10.0, 20.0, &displays, other), (10.0, 20.0));
+        let (x, y) = point_to_mode(1100.0, 100.0, &displays, other);
+        assert_ne!((x, y), (1100.0, 100.0));
+        assert_eq!(point_from_mode(x, y, &displays, other), (1100.0, 100.0));
+        assert_eq!(
+            point_to_mode(1100.0, 100.0, &displays, NATIVE),
+            (1100.0, 100.0)
+        );
+    }
+}
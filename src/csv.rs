@@ -0,0 +1,383 @@
+//! Events as CSV, one row per event, for loading a recording into pandas
+//! or a spreadsheet as is. The columns are
+//! `timestamp_ms,type,key,x,y,dx,dy,name,scan_code,event`: milliseconds
+//! since the epoch, the `EventType` variant, the key or button of key and
+//! button events, the position of mouse moves, touches and pens, the delta
+//! of mouse moves, wheels, scrolls and swipes, then `event` is the whole
+//! `EventType` in its `Display` form, which `EventReader` reads back with
+//! the delta of mouse moves. Empty cells are values the event doesn't have.
+//! `unicode` and `display` aren't written, and read back as None.
+//!
+//! ```no_run
+//! use rdev::csv::{EventReader, EventWriter};
+//! use rdev::listen;
+//!
+//! let mut reader = EventReader::open("events.csv").unwrap();
+//! for event in &mut reader {
+//!     println!("{:?}", event.unwrap());
+//! }
+//! println!("{} rows could not be read", reader.skipped());
+//!
+//! let mut writer = EventWriter::create("events.csv").unwrap();
+//! // This will block.
+//! listen(move |event| writer.write(&event).unwrap()).unwrap();
+//! ```
+#[cfg(feature = "gestures")]
+use crate::rdev::Gesture;
+use crate::rdev::{Event, EventType};
+use std::fs::File;
+use std::io::{self, BufRead, BufReader, BufWriter, Write};
+use std::path::Path;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+const COLUMNS: [&str; 10] = [
+    "timestamp_ms",
+    "type",
+    "key",
+    "x",
+    "y",
+    "dx",
+    "dy",
+    "name",
+    "scan_code",
+    "event",
+];
+
+type Columns = (String, Option<(f64, f64)>, Option<(f64, f64)>);
+
+/// The key or button, position and delta columns of an event.
+fn columns(event: &Event) -> Columns {
+    match &event.event_type {
+        EventType::KeyPress(key) | EventType::KeyRelease(key) => (key.to_string(), None, None),
+        EventType::ButtonPress(button) | EventType::ButtonRelease(button) => {
+            (button.to_string(), None, None)
+        }
+        EventType::MouseMove { x, y } => (String::new(), Some((*x, *y)), event.delta),
+        EventType::Wheel { delta_x, delta_y } => (
+            String::new(),
+            None,
+            Some((*delta_x as f64, *delta_y as f64)),
+        ),
+        EventType::Scroll {
+            delta_x, delta_y, ..
+        } => (String::new(), None, Some((*delta_x, *delta_y))),
+        EventType::TouchDown { x, y, .. }
+        | EventType::TouchMove { x, y, .. }
+        | EventType::TouchUp { x, y, .. } => (String::new(), Some((*x, *y)), None),
+        EventType::PenDown(pen) | EventType::PenMove(pen) | EventType::PenUp(pen) => {
+            (String::new(), Some((pen.x, pen.y)), None)
+        }
+        #[cfg(feature = "gestures")]
+        EventType::Gesture(Gesture::Swipe {
+            delta_x, delta_y, ..
+        }) => (String::new(), None, Some((*delta_x, *delta_y))),
+        _ => (String::new(), None, None),
+    }
+}
+
+/// Quoted when it holds a comma, a quote or a line break, quotes doubled.
+fn field(value: &str) -> String {
+    if value.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", value.replace('"', "\"\""))
+    } else {
+        value.to_string()
+    }
+}
+
+/// Exact, a double of the whole time in milliseconds can't hold nanoseconds.
+fn timestamp(time: SystemTime) -> String {
+    let since = time.duration_since(UNIX_EPOCH).unwrap_or_default();
+    format!(
+        "{}.{:06}",
+        since.as_millis(),
+        since.subsec_nanos() % 1_000_000
+    )
+}
+
+fn parse_timestamp(value: &str) -> Option<SystemTime> {
+    let mut parts = value.splitn(2, '.');
+    let millis: u64 = parts.next()?.parse().ok()?;
+    let nanos = match parts.next() {
+        Some(fraction) if fraction.bytes().all(|b| b.is_ascii_digit()) => {
+            // Nanoseconds are the first 6 digits.
+            let digits = format!("{:0<6}", &fraction[..fraction.len().min(6)]);
+            digits.parse().ok()?
+        }
+        Some(_) => return None,
+        None => 0,
+    };
+    Some(UNIX_EPOCH + Duration::from_millis(millis) + Duration::from_nanos(nanos))
+}
+
+pub struct EventWriter<W: Write> {
+    writer: W,
+}
+
+impl<W: Write> EventWriter<W> {
+    /// Writes the header row.
+    pub fn new(mut writer: W) -> io::Result<EventWriter<W>> {
+        writeln!(writer, "{}", COLUMNS.join(","))?;
+        Ok(EventWriter { writer })
+    }
+
+    pub fn write(&mut self, event: &Event) -> io::Result<()> {
+        let full = event.event_type.to_string();
+        let kind = full.split('(').next().unwrap_or_default();
+        let (key, position, delta) = columns(event);
+        let pair = |pair: Option<(f64, f64)>| match pair {
+            Some((a, b)) => (a.to_string(), b.to_string()),
+            None => (String::new(), String::new()),
+        };
+        let (x, y) = pair(position);
+        let (dx, dy) = pair(delta);
+        let row = [
+            timestamp(event.time),
+            field(kind),
+            field(&key),
+            x,
+            y,
+            dx,
+            dy,
+            field(event.name.as_deref().unwrap_or_default()),
+            event.scan_code.to_string(),
+            field(&full),
+        ];
+        writeln!(self.writer, "{}", row.join(","))
+    }
+
+    pub fn flush(&mut self) -> io::Result<()> {
+        self.writer.flush()
+    }
+
+    pub fn into_inner(self) -> W {
+        self.writer
+    }
+}
+
+impl EventWriter<BufWriter<File>> {
+    /// Creates the file, replacing an existing one.
+    pub fn create<P: AsRef<Path>>(path: P) -> io::Result<EventWriter<BufWriter<File>>> {
+        EventWriter::new(BufWriter::new(File::create(path)?))
+    }
+}
+
+/// Splits a row in fields, None while a quoted field goes on past the end.
+fn split_row(row: &str) -> Option<Vec<String>> {
+    let mut fields = vec![];
+    let mut current = String::new();
+    let mut quoted = false;
+    let mut chars = row.chars().peekable();
+    while let Some(c) = chars.next() {
+        match c {
+            '"' if quoted && chars.peek() == Some(&'"') => {
+                chars.next();
+                current.push('"');
+            }
+            '"' => quoted = !quoted,
+            ',' if !quoted => fields.push(std::mem::take(&mut current)),
+            c => current.push(c),
+        }
+    }
+    if quoted {
+        return None;
+    }
+    fields.push(current);
+    Some(fields)
+}
+
+/// Where the columns `EventReader` needs are, spreadsheets may reorder them.
+struct Header {
+    timestamp: usize,
+    event: usize,
+    name: Option<usize>,
+    scan_code: Option<usize>,
+    delta: Option<(usize, usize)>,
+}
+
+/// Iterates over the events of a CSV file, see the module documentation.
+pub struct EventReader<R> {
+    reader: R,
+    header: Header,
+    skipped: usize,
+}
+
+impl<R: BufRead> EventReader<R> {
+    /// Reads the header row, `InvalidData` without a `timestamp_ms` and an
+    /// `event` column.
+    pub fn new(mut reader: R) -> io::Result<EventReader<R>> {
+        let columns = read_row(&mut reader)?.unwrap_or_default();
+        // Excel starts UTF-8 files it saves with a byte order mark.
+        let position = |name: &str| {
+            columns
+                .iter()
+                .position(|column| column.trim_start_matches('\u{feff}').trim() == name)
+        };
+        let header = match (position("timestamp_ms"), position("event")) {
+            (Some(timestamp), Some(event)) => Header {
+                timestamp,
+                event,
+                name: position("name"),
+                scan_code: position("scan_code"),
+                delta: position("dx").zip(position("dy")),
+            },
+            _ => {
+                return Err(io::Error::new(
+                    io::ErrorKind::InvalidData,
+                    "no timestamp_ms and event columns",
+                ))
+            }
+        };
+        Ok(EventReader {
+            reader,
+            header,
+            skipped: 0,
+        })
+    }
+
+    /// How many rows couldn't be read so far.
+    pub fn skipped(&self) -> usize {
+        self.skipped
+    }
+
+    fn event(&self, fields: &[String]) -> Option<Event> {
+        let cell = |index: Option<usize>| index.and_then(|index| fields.get(index));
+        let time = parse_timestamp(cell(Some(self.header.timestamp))?.trim())?;
+        let event_type: EventType = cell(Some(self.header.event))?.trim().parse().ok()?;
+        let name = cell(self.header.name)
+            .filter(|name| !name.is_empty())
+            .cloned();
+        let scan_code = match cell(self.header.scan_code).map(|code| code.trim()) {
+            Some(code) if !code.is_empty() => code.parse().ok()?,
+            _ => 0,
+        };
+        let number = |index| cell(Some(index)).and_then(|value| value.trim().parse().ok());
+        let delta = match (event_type, self.header.delta) {
+            (EventType::MouseMove { .. }, Some((dx, dy))) => number(dx).zip(number(dy)),
+            _ => None,
+        };
+        Some(Event {
+            time,
+            name,
+            event_type,
+            unicode: None,
+            scan_code,
+            display: None,
+            delta,
+            numlock: None,
+        })
+    }
+}
+
+impl EventReader<BufReader<File>> {
+    pub fn open<P: AsRef<Path>>(path: P) -> io::Result<EventReader<BufReader<File>>> {
+        EventReader::new(BufReader::new(File::open(path)?))
+    }
+}
+
+/// The fields of the next row, which spans lines when a quoted field holds
+/// a line break, like the name of Enter. None at the end.
+fn read_row<R: BufRead>(reader: &mut R) -> io::Result<Option<Vec<String>>> {
+    let mut row = String::new();
+    loop {
+        if reader.read_line(&mut row)? == 0 {
+            // A quote left open by a file cut short.
+            return Ok(if row.is_empty() {
+                None
+            } else {
+                Some(split_row(&row).unwrap_or_default())
+            });
+        }
+        if let Some(mut fields) = split_row(&row) {
+            if let Some(last) = fields.last_mut() {
+                let end = last.trim_end_matches(['\n', '\r']).len();
+                last.truncate(end);
+            }
+            return Ok(Some(fields));
+        }
+    }
+}
+
+impl<R: BufRead> Iterator for EventReader<R> {
+    type Item = io::Result<Event>;
+
+    fn next(&mut self) -> Option<io::Result<Event>> {
+        loop {
+            let fields = match read_row(&mut self.reader) {
+                Ok(fields) => fields?,
+                Err(error) => return Some(Err(error)),
+            };
+            if fields.iter().all(|field| field.trim().is_empty()) {
+                continue;
+            }
+            match self.event(&fields) {
+                Some(event) => return Some(Ok(event)),
+                // Events added by a newer version, rows edited by hand.
+                None => self.skipped += 1,
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::rdev::{Button, Key};
+
+    #[test]
+    fn test_csv() {
+        let time = UNIX_EPOCH + Duration::from_nanos(1_700_000_000_123_456_789);
+        let event =
+            |event_type, name: Option<&str>| Event::new(event_type, time, name.map(String::from));
+        let events = vec![
+            Event {
+                scan_code: 28,
+                ..event(EventType::KeyPress(Key::Return), Some("\r"))
+            },
+            event(EventType::KeyPress(Key::Comma), Some(",")),
+            event(EventType::ButtonRelease(Button::Unknown(4)), None),
+            Event {
+                delta: Some((1.5, -2.0)),
+                ..event(EventType::MouseMove { x: 10.5, y: 20.0 }, None)
+            },
+            event(
+                EventType::Wheel {
+                    delta_x: 0,
+                    delta_y: -1,
+                },
+                None,
+            ),
+        ];
+        let mut writer = EventWriter::new(vec![]).unwrap();
+        for event in &events {
+            writer.write(event).unwrap();
+        }
+        let mut bytes = writer.into_inner();
+        let text = String::from_utf8(bytes.clone()).unwrap();
+        let mut lines = text.lines();
+        assert_eq!(lines.next(), Some(COLUMNS.join(",").as_str()));
+        assert_eq!(
+            lines.nth(3),
+            Some("1700000000123.456789,MouseMove,,10.5,20,1.5,-2,,0,\"MouseMove(10.5,20.0)\"")
+        );
+        bytes.extend_from_slice(b"\n1700000000123,Future,,,,,,,0,Future(1)\n");
+
+        let mut reader = EventReader::new(&bytes[..]).unwrap();
+        let read: Vec<Event> = (&mut reader).map(Result::unwrap).collect();
+        assert_eq!(read, events);
+        assert_eq!(reader.skipped(), 1);
+
+        // Reordered, and without the columns it doesn't need.
+        let reordered = &b"event,timestamp_ms\nKeyRelease(KeyA),1700000000123.5\n"[..];
+        let read = EventReader::new(reordered)
+            .unwrap()
+            .next()
+            .unwrap()
+            .unwrap();
+        assert_eq!(read.event_type, EventType::KeyRelease(Key::KeyA));
+        assert_eq!(
+            read.time,
+            UNIX_EPOCH + Duration::from_micros(1_700_000_000_123_500)
+        );
+        assert!(EventReader::new(&b"time,type\n"[..]).is_err());
+    }
+}
@@ -0,0 +1,235 @@
+use crate::rdev::{Event, EventType, ListenError};
+use std::sync::{Arc, Mutex, PoisonError};
+
+/// The kinds of events a `Dispatcher` subscriber gets. It's checked on
+/// the event before the subscriber is called, so events it doesn't want
+/// cost nothing more than that.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Default)]
+pub struct EventFilter {
+    /// `KeyPress`, `KeyRelease`, `ImeComposition` and `ImeCommit`.
+    pub keys: bool,
+    /// `ButtonPress` and `ButtonRelease`.
+    pub buttons: bool,
+    /// `MouseMove`.
+    pub moves: bool,
+    /// `Wheel` and `Scroll`.
+    pub wheel: bool,
+    /// Every other event: layout changes, touches, pens...
+    pub other: bool,
+}
+
+impl EventFilter {
+    pub const ALL: EventFilter = EventFilter {
+        keys: true,
+        buttons: true,
+        moves: true,
+        wheel: true,
+        other: true,
+    };
+    pub const KEYBOARD: EventFilter = EventFilter {
+        keys: true,
+        buttons: false,
+        moves: false,
+        wheel: false,
+        other: false,
+    };
+    /// Buttons, moves and the wheel.
+    pub const MOUSE: EventFilter = EventFilter {
+        keys: false,
+        buttons: true,
+        moves: true,
+        wheel: true,
+        other: false,
+    };
+
+    pub fn matches(&self, event_type: &EventType) -> bool {
+        match event_type {
+            EventType::KeyPress(_)
+            | EventType::KeyRelease(_)
+            | EventType::ImeComposition
+            | EventType::ImeCommit => self.keys,
+            EventType::ButtonPress(_) | EventType::ButtonRelease(_) => self.buttons,
+            EventType::MouseMove { .. } => self.moves,
+            EventType::Wheel { .. } | EventType::Scroll { .. } => self.wheel,
+            _ => self.other,
+        }
+    }
+}
+
+/// Returned by `Dispatcher::subscribe`, to unsubscribe.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub struct SubscriptionId(u64);
+
+type Callback = Arc<Mutex<dyn FnMut(&Event) + Send>>;
+
+#[derive(Clone)]
+struct Subscriber {
+    id: SubscriptionId,
+    filter: EventFilter,
+    callback: Callback,
+}
+
+#[derive(Default)]
+struct State {
+    next_id: u64,
+    /// Replaced on every change rather than modified, `dispatch` keeps the
+    /// one it started with without holding the lock.
+    subscribers: Arc<Vec<Subscriber>>,
+}
+
+/// Sends the events of one `listen` to several subscribers, each with an
+/// `EventFilter`. Subscribers get the event by reference, it's never
+/// cloned, and only those whose filter matches are called. Clones share
+/// the same subscribers, so they can subscribe and unsubscribe from any
+/// thread while `listen` runs, callbacks included.
+///
+/// ```no_run
+/// use rdev::{Dispatcher, EventFilter};
+///
+/// let dispatcher = Dispatcher::new();
+/// dispatcher.subscribe(EventFilter::KEYBOARD, |event| println!("{:?}", event.name));
+/// dispatcher.subscribe(EventFilter::MOUSE, |event| println!("{:?}", event.event_type));
+/// // This will block.
+/// if let Err(error) = dispatcher.listen() {
+///     println!("Error: {:?}", error)
+/// }
+/// ```
+#[derive(Clone, Default)]
+pub struct Dispatcher {
+    state: Arc<Mutex<State>>,
+}
+
+impl Dispatcher {
+    pub fn new() -> Dispatcher {
+        Dispatcher::default()
+    }
+
+    pub fn subscribe<F>(&self, filter: EventFilter, callback: F) -> SubscriptionId
+    where
+        F: FnMut(&Event) + Send + 'static,
+    {
+        let mut state = self.state.lock().unwrap_or_else(PoisonError::into_inner);
+        let id = SubscriptionId(state.next_id);
+        state.next_id += 1;
+        let mut subscribers = Vec::clone(&state.subscribers);
+        subscribers.push(Subscriber {
+            id,
+            filter,
+            callback: Arc::new(Mutex::new(callback)),
+        });
+        state.subscribers = Arc::new(subscribers);
+        id
+    }
+
+    /// Returns false if `id` was unsubscribed already.
+    pub fn unsubscribe(&self, id: SubscriptionId) -> bool {
+        let mut state = self.state.lock().unwrap_or_else(PoisonError::into_inner);
+        if !state
+            .subscribers
+            .iter()
+            .any(|subscriber| subscriber.id == id)
+        {
+            return false;
+        }
+        let subscribers = state
+            .subscribers
+            .iter()
+            .filter(|subscriber| subscriber.id != id)
+            .cloned()
+            .collect();
+        state.subscribers = Arc::new(subscribers);
+        true
+    }
+
+    /// Calls the subscribers whose filter matches `event`, in the order
+    /// they subscribed.
+    pub fn dispatch(&self, event: &Event) {
+        let subscribers = self
+            .state
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .subscribers
+            .clone();
+        for subscriber in subscribers.iter() {
+            if !subscriber.filter.matches(&event.event_type) {
+                continue;
+            }
+            if let Ok(mut callback) = subscriber.callback.lock() {
+                callback(event);
+            }
+        }
+    }
+
+    /// Dispatches the events of `listen`. Blocks.
+    pub fn listen(&self) -> Result<(), ListenError> {
+        let dispatcher = self.clone();
+        crate::listen(move |event| dispatcher.dispatch(&event))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::rdev::{Button, Key};
+    use std::time::SystemTime;
+
+    fn event(event_type: EventType) -> Event {
+        Event {
+            event_type,
+            time: SystemTime::now(),
+            name: None,
+            unicode: None,
+            scan_code: 0,
+            display: None,
+            delta: None,
+            numlock: None,
+        }
+    }
+
+    #[test]
+    fn test_dispatch() {
+        let dispatcher = Dispatcher::new();
+        let seen = Arc::new(Mutex::new(vec![]));
+        let keys = seen.clone();
+        let keyboard = dispatcher.subscribe(EventFilter::KEYBOARD, move |event| {
+            keys.lock().unwrap().push(("keyboard", event.event_type))
+        });
+        let mouse = seen.clone();
+        dispatcher.subscribe(EventFilter::MOUSE, move |event| {
+            mouse.lock().unwrap().push(("mouse", event.event_type))
+        });
+        // Subscribing from a callback doesn't deadlock, and only counts
+        // from the next event.
+        let inner = dispatcher.clone();
+        let seen_late = seen.clone();
+        let other = EventFilter {
+            other: true,
+            ..Default::default()
+        };
+        dispatcher.subscribe(other, move |_| {
+            let late = seen_late.clone();
+            inner.subscribe(EventFilter::ALL, move |event| {
+                late.lock().unwrap().push(("late", event.event_type))
+            });
+        });
+
+        let moved = EventType::MouseMove { x: 1.0, y: 2.0 };
+        dispatcher.dispatch(&event(EventType::KeyPress(Key::KeyA)));
+        dispatcher.dispatch(&event(moved));
+        dispatcher.dispatch(&event(EventType::LayoutChanged));
+        assert!(dispatcher.unsubscribe(keyboard));
+        assert!(!dispatcher.unsubscribe(keyboard));
+        dispatcher.dispatch(&event(EventType::KeyPress(Key::KeyB)));
+        dispatcher.dispatch(&event(EventType::ButtonPress(Button::Left)));
+        assert_eq!(
+            *seen.lock().unwrap(),
+            vec![
+                ("keyboard", EventType::KeyPress(Key::KeyA)),
+                ("mouse", moved),
+                ("late", EventType::KeyPress(Key::KeyB)),
+                ("mouse", EventType::ButtonPress(Button::Left)),
+                ("late", EventType::ButtonPress(Button::Left)),
+            ]
+        );
+    }
+}
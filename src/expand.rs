@@ -0,0 +1,325 @@
+use crate::rdev::{Event, EventType, Key, ListenError, SimulateError};
+use std::sync::{Arc, Mutex, MutexGuard, PoisonError};
+use std::thread;
+use std::time::Duration;
+
+/// Characters kept of what was typed.
+const BUFFER_LEN: usize = 256;
+/// Our own typing comes back to `listen`, ignored until this long after it
+/// ends.
+const TYPING_GRACE: Duration = Duration::from_millis(50);
+
+/// Word boundaries are anything but letters and digits.
+fn is_boundary(c: char) -> bool {
+    !c.is_alphanumeric()
+}
+
+struct Abbreviation {
+    trigger: String,
+    replacement: String,
+    /// Expands once the word ends instead of right away.
+    word: bool,
+}
+
+/// What to type for an expansion.
+#[derive(Debug, PartialEq, Eq)]
+struct Expansion {
+    backspaces: usize,
+    text: String,
+}
+
+/// The last expansion, Backspace right after it undoes it.
+struct Undo {
+    trigger: String,
+    /// What's left to delete after the Backspace.
+    typed: usize,
+}
+
+#[derive(Default)]
+struct State {
+    abbreviations: Vec<Abbreviation>,
+    typed: String,
+    undo: Option<Undo>,
+    typing: bool,
+}
+
+impl State {
+    /// Whether `trigger` ends `typed` at the start of a word, if it starts
+    /// with a letter or digit.
+    fn ends_with(typed: &str, trigger: &str) -> bool {
+        if trigger.is_empty() || !typed.ends_with(trigger) {
+            return false;
+        }
+        let before = typed[..typed.len() - trigger.len()].chars().next_back();
+        let starts_word = trigger.chars().next().map(is_boundary) == Some(false);
+        !starts_word || before.map(is_boundary).unwrap_or(true)
+    }
+
+    fn expand(&mut self, c: char) -> Option<Expansion> {
+        let (before, _) = self.typed.split_at(self.typed.len() - c.len_utf8());
+        let abbreviation = self.abbreviations.iter().find(|abbreviation| {
+            if abbreviation.word {
+                is_boundary(c) && State::ends_with(before, &abbreviation.trigger)
+            } else {
+                State::ends_with(&self.typed, &abbreviation.trigger)
+            }
+        })?;
+        let mut backspaces = abbreviation.trigger.chars().count();
+        let mut text = abbreviation.replacement.clone();
+        let mut typed = text.chars().count();
+        if abbreviation.word {
+            // The boundary was typed already, it's typed again after.
+            backspaces += 1;
+            text.push(c);
+        } else {
+            typed -= 1;
+        }
+        self.undo = Some(Undo {
+            trigger: abbreviation.trigger.clone(),
+            typed,
+        });
+        let kept = self.typed.len() - backspaces_len(&self.typed, backspaces);
+        self.typed.truncate(kept);
+        self.typed.push_str(&text);
+        Some(Expansion { backspaces, text })
+    }
+
+    fn handle(&mut self, event: &Event) -> Option<Expansion> {
+        if self.typing {
+            return None;
+        }
+        let key = match event.event_type {
+            EventType::KeyPress(key) => key,
+            EventType::ButtonPress(_) => {
+                // The caret probably moved.
+                self.typed.clear();
+                self.undo = None;
+                return None;
+            }
+            _ => return None,
+        };
+        match key {
+            Key::Backspace => {
+                self.typed.pop();
+                let undo = self.undo.take()?;
+                self.typed.clear();
+                return Some(Expansion {
+                    backspaces: undo.typed,
+                    text: undo.trigger,
+                });
+            }
+            Key::LeftArrow
+            | Key::RightArrow
+            | Key::UpArrow
+            | Key::DownArrow
+            | Key::Home
+            | Key::End
+            | Key::PageUp
+            | Key::PageDown
+            | Key::Delete
+            | Key::Escape => {
+                self.typed.clear();
+                self.undo = None;
+                return None;
+            }
+            _ => (),
+        }
+        // Modifiers and such type nothing.
+        let name = event.name.as_ref()?;
+        self.undo = None;
+        let mut expansion = None;
+        for c in name.chars() {
+            if c.is_control() && c != '\r' && c != '\n' && c != '\t' {
+                // Shortcuts like Ctrl+V, who knows what happened.
+                self.typed.clear();
+                return None;
+            }
+            self.typed.push(c);
+            expansion = self.expand(c);
+        }
+        let extra = self.typed.chars().count().saturating_sub(BUFFER_LEN);
+        if extra > 0 {
+            let start = self.typed.char_indices().nth(extra).map(|(i, _)| i);
+            self.typed.drain(..start.unwrap_or(0));
+        }
+        expansion
+    }
+}
+
+/// Bytes of the last `count` characters of `s`.
+fn backspaces_len(s: &str, count: usize) -> usize {
+    s.chars().rev().take(count).map(char::len_utf8).sum()
+}
+
+/// Replaces abbreviations as they are typed, like `";addr"` by an address.
+/// It watches the text of key presses, deletes the abbreviation with
+/// Backspace and types the replacement with `simulate_text`. Abbreviations
+/// starting with a letter or digit only expand at the start of a word, and
+/// Backspace right after an expansion types the abbreviation back. Clicks
+/// and arrow keys start over, the caret could be anywhere.
+///
+/// ```no_run
+/// use rdev::Expander;
+///
+/// let expander = Expander::new();
+/// expander.add(";addr", "221B Baker Street");
+/// // Corrected once the word ends.
+/// expander.add_word("teh", "the");
+/// // This will block.
+/// expander.listen().unwrap();
+/// ```
+#[derive(Clone, Default)]
+pub struct Expander {
+    state: Arc<Mutex<State>>,
+}
+
+impl Expander {
+    pub fn new() -> Expander {
+        Expander::default()
+    }
+
+    fn state(&self) -> MutexGuard<'_, State> {
+        self.state.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+
+    fn insert(&self, trigger: &str, replacement: &str, word: bool) {
+        let mut state = self.state();
+        state
+            .abbreviations
+            .retain(|abbreviation| abbreviation.trigger != trigger);
+        state.abbreviations.push(Abbreviation {
+            trigger: trigger.to_string(),
+            replacement: replacement.to_string(),
+            word,
+        });
+    }
+
+    /// Expands `trigger` as soon as it's typed, replacing an abbreviation
+    /// with the same trigger.
+    pub fn add(&self, trigger: &str, replacement: &str) {
+        self.insert(trigger, replacement, false)
+    }
+
+    /// Expands `word` once it's followed by a word boundary, like a space
+    /// or punctuation, kept after the replacement.
+    pub fn add_word(&self, word: &str, replacement: &str) {
+        self.insert(word, replacement, true)
+    }
+
+    pub fn remove(&self, trigger: &str) {
+        self.state()
+            .abbreviations
+            .retain(|abbreviation| abbreviation.trigger != trigger);
+    }
+
+    /// Types an expansion, from another thread as OSes deliver our events
+    /// after the callback returns.
+    fn type_expansion(&self, expansion: Expansion) {
+        self.state().typing = true;
+        let expander = self.clone();
+        thread::spawn(move || {
+            let typed = (|| -> Result<(), SimulateError> {
+                for _ in 0..expansion.backspaces {
+                    crate::simulate_all(&[
+                        EventType::KeyPress(Key::Backspace),
+                        EventType::KeyRelease(Key::Backspace),
+                    ])?;
+                }
+                crate::simulate_text(&expansion.text)
+            })();
+            thread::sleep(TYPING_GRACE);
+            let mut state = expander.state();
+            state.typing = false;
+            if typed.is_err() {
+                state.typed.clear();
+                state.undo = None;
+            }
+        });
+    }
+
+    /// Expands with `listen`. Blocks.
+    pub fn listen(&self) -> Result<(), ListenError> {
+        let expander = self.clone();
+        crate::listen(move |event| {
+            let expansion = expander.state().handle(&event);
+            if let Some(expansion) = expansion {
+                expander.type_expansion(expansion);
+            }
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::SystemTime;
+
+    #[test]
+    fn test_expander() {
+        let expander = Expander::new();
+        expander.add(";addr", "221B Baker St");
+        expander.add("sig", "Regards");
+        expander.add_word("teh", "the");
+        let mut state = expander.state();
+        let mut type_text = |text: &str| -> Vec<Expansion> {
+            let mut expansions = vec![];
+            for c in text.chars() {
+                let key = if c == '\u{8}' {
+                    Key::Backspace
+                } else {
+                    Key::KeyA
+                };
+                let event = Event {
+                    event_type: EventType::KeyPress(key),
+                    time: SystemTime::now(),
+                    name: Some(c.to_string()).filter(|_| key != Key::Backspace),
+                    unicode: None,
+                    scan_code: 0,
+                    display: None,
+                    delta: None,
+                    numlock: None,
+                };
+                expansions.extend(state.handle(&event));
+            }
+            expansions
+        };
+        assert_eq!(
+            type_text("at;addr"),
+            vec![Expansion {
+                backspaces: 5,
+                text: "221B Baker St".to_string()
+            }]
+        );
+        // Not at the start of a word.
+        assert_eq!(type_text(" design"), vec![]);
+        assert_eq!(
+            type_text(" teh."),
+            vec![Expansion {
+                backspaces: 4,
+                text: "the.".to_string()
+            }]
+        );
+        // Undo, the Backspace deleted the dot.
+        assert_eq!(
+            type_text("\u{8}"),
+            vec![Expansion {
+                backspaces: 3,
+                text: "teh".to_string()
+            }]
+        );
+        assert_eq!(type_text(" "), vec![]);
+        assert_eq!(
+            type_text("sig\u{8}"),
+            vec![
+                Expansion {
+                    backspaces: 3,
+                    text: "Regards".to_string()
+                },
+                Expansion {
+                    backspaces: 6,
+                    text: "sig".to_string()
+                }
+            ]
+        );
+    }
+}
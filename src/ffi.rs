@@ -0,0 +1,274 @@
+//! A C API, for C, C++, C#, Swift and the like. Requires the `ffi` feature,
+//! build a shared library with
+//! `cargo rustc --release --features ffi --crate-type cdylib`. The header
+//! is `include/rdev.h`, generated from this module with
+//! `cbindgen --config cbindgen.toml --output include/rdev.h`.
+//!
+//! The ABI only grows: new functions and kinds get new names and numbers,
+//! `RdevEvent` keeps its layout within an `RDEV_ABI_VERSION`. Keys and
+//! buttons have the same numbers as in `compact`. Functions return `RDEV_OK`
+//! or a negative error and never unwind into the caller.
+use crate::ids::{button_from_id, button_to_id, key_from_id, key_to_id};
+use crate::rdev::{Event, EventType};
+use std::ffi::{CStr, CString};
+use std::os::raw::{c_char, c_void};
+use std::panic::{self, UnwindSafe};
+use std::ptr;
+use std::time::UNIX_EPOCH;
+
+/// Changes when `RdevEvent` or a function signature does.
+pub const RDEV_ABI_VERSION: u32 = 1;
+
+pub const RDEV_OK: i32 = 0;
+/// The OS call failed, or rdev panicked.
+pub const RDEV_ERROR: i32 = -1;
+pub const RDEV_INVALID_ARGUMENT: i32 = -2;
+
+pub const RDEV_KEY_PRESS: u32 = 0;
+pub const RDEV_KEY_RELEASE: u32 = 1;
+pub const RDEV_BUTTON_PRESS: u32 = 2;
+pub const RDEV_BUTTON_RELEASE: u32 = 3;
+pub const RDEV_MOUSE_MOVE: u32 = 4;
+pub const RDEV_WHEEL: u32 = 5;
+/// Events without a C representation yet, only `time_ms` is set.
+pub const RDEV_OTHER: u32 = 255;
+
+/// An event. `code` is the key or button of key and button events, `x` and
+/// `y` the position of mouse moves, `delta_x` and `delta_y` those of wheel
+/// events. `name` is UTF-8 and only valid during the callback, null when
+/// the key typed nothing.
+#[repr(C)]
+#[derive(Debug, Copy, Clone)]
+pub struct RdevEvent {
+    pub kind: u32,
+    pub code: u64,
+    pub x: f64,
+    pub y: f64,
+    pub delta_x: i64,
+    pub delta_y: i64,
+    /// Milliseconds since the UNIX epoch.
+    pub time_ms: u64,
+    pub scan_code: u32,
+    pub name: *const c_char,
+}
+
+pub type RdevCallback = extern "C" fn(event: *const RdevEvent, user_data: *mut c_void);
+
+fn to_c(event: &Event, name: Option<&CString>) -> RdevEvent {
+    let mut c_event = RdevEvent {
+        kind: RDEV_OTHER,
+        code: 0,
+        x: 0.0,
+        y: 0.0,
+        delta_x: 0,
+        delta_y: 0,
+        time_ms: event
+            .time
+            .duration_since(UNIX_EPOCH)
+            .map(|time| time.as_millis() as u64)
+            .unwrap_or(0),
+        scan_code: event.scan_code,
+        name: name.map_or(ptr::null(), |name| name.as_ptr()),
+    };
+    match event.event_type {
+        EventType::KeyPress(key) => {
+            c_event.kind = RDEV_KEY_PRESS;
+            c_event.code = key_to_id(&key);
+        }
+        EventType::KeyRelease(key) => {
+            c_event.kind = RDEV_KEY_RELEASE;
+            c_event.code = key_to_id(&key);
+        }
+        EventType::ButtonPress(button) => {
+            c_event.kind = RDEV_BUTTON_PRESS;
+            c_event.code = button_to_id(&button);
+        }
+        EventType::ButtonRelease(button) => {
+            c_event.kind = RDEV_BUTTON_RELEASE;
+            c_event.code = button_to_id(&button);
+        }
+        EventType::MouseMove { x, y, .. } => {
+            c_event.kind = RDEV_MOUSE_MOVE;
+            c_event.x = x;
+            c_event.y = y;
+        }
+        EventType::Wheel { delta_x, delta_y } => {
+            c_event.kind = RDEV_WHEEL;
+            c_event.delta_x = delta_x;
+            c_event.delta_y = delta_y;
+        }
+        _ => (),
+    }
+    c_event
+}
+
+fn from_c(event: &RdevEvent) -> Option<EventType> {
+    let event_type = match event.kind {
+        RDEV_KEY_PRESS => EventType::KeyPress(key_from_id(event.code)?),
+        RDEV_KEY_RELEASE => EventType::KeyRelease(key_from_id(event.code)?),
+        RDEV_BUTTON_PRESS => EventType::ButtonPress(button_from_id(event.code)?),
+        RDEV_BUTTON_RELEASE => EventType::ButtonRelease(button_from_id(event.code)?),
+        RDEV_MOUSE_MOVE => EventType::MouseMove {
+            x: event.x,
+            y: event.y,
+        },
+        RDEV_WHEEL => EventType::Wheel {
+            delta_x: event.delta_x,
+            delta_y: event.delta_y,
+        },
+        _ => return None,
+    };
+    Some(event_type)
+}
+
+/// Runs `f`, a panic is `RDEV_ERROR`.
+fn guard<F: FnOnce() -> i32 + UnwindSafe>(f: F) -> i32 {
+    panic::catch_unwind(f).unwrap_or(RDEV_ERROR)
+}
+
+#[no_mangle]
+pub extern "C" fn rdev_abi_version() -> u32 {
+    RDEV_ABI_VERSION
+}
+
+/// Calls `callback` with every event and `user_data`, see `listen`. Blocks,
+/// returns only on errors.
+#[no_mangle]
+pub extern "C" fn rdev_listen(callback: Option<RdevCallback>, user_data: *mut c_void) -> i32 {
+    let callback = match callback {
+        Some(callback) => callback,
+        None => return RDEV_INVALID_ARGUMENT,
+    };
+    guard(move || {
+        let listened = crate::listen(move |event| {
+            let name = event
+                .name
+                .as_deref()
+                .and_then(|name| CString::new(name).ok());
+            let c_event = to_c(&event, name.as_ref());
+            callback(&c_event, user_data);
+        });
+        match listened {
+            Ok(()) => RDEV_OK,
+            Err(_) => RDEV_ERROR,
+        }
+    })
+}
+
+/// Sends an event, see `simulate`. `name` and `time_ms` are ignored.
+///
+/// # Safety
+///
+/// `event` must be null or point to an `RdevEvent`.
+#[no_mangle]
+pub unsafe extern "C" fn rdev_simulate(event: *const RdevEvent) -> i32 {
+    let event_type = match event.as_ref().and_then(from_c) {
+        Some(event_type) => event_type,
+        None => return RDEV_INVALID_ARGUMENT,
+    };
+    guard(move || match crate::simulate(&event_type) {
+        Ok(()) => RDEV_OK,
+        Err(_) => RDEV_ERROR,
+    })
+}
+
+/// Types UTF-8 text, see `simulate_text`.
+///
+/// # Safety
+///
+/// `text` must be null or a NUL-terminated string.
+#[no_mangle]
+pub unsafe extern "C" fn rdev_simulate_text(text: *const c_char) -> i32 {
+    if text.is_null() {
+        return RDEV_INVALID_ARGUMENT;
+    }
+    let text = match CStr::from_ptr(text).to_str() {
+        Ok(text) => text,
+        Err(_) => return RDEV_INVALID_ARGUMENT,
+    };
+    guard(move || match crate::simulate_text(text) {
+        Ok(()) => RDEV_OK,
+        Err(_) => RDEV_ERROR,
+    })
+}
+
+/// The size of the main display, see `display_size`.
+///
+/// # Safety
+///
+/// `width` and `height` must be null or point to a `uint64_t`.
+#[no_mangle]
+pub unsafe extern "C" fn rdev_display_size(width: *mut u64, height: *mut u64) -> i32 {
+    if width.is_null() || height.is_null() {
+        return RDEV_INVALID_ARGUMENT;
+    }
+    match panic::catch_unwind(crate::display_size) {
+        Ok(Ok((w, h))) => {
+            *width = w;
+            *height = h;
+            RDEV_OK
+        }
+        _ => RDEV_ERROR,
+    }
+}
+
+/// The position of the pointer, see `get_mouse_position`.
+///
+/// # Safety
+///
+/// `x` and `y` must be null or point to a `double`.
+#[no_mangle]
+pub unsafe extern "C" fn rdev_mouse_position(x: *mut f64, y: *mut f64) -> i32 {
+    if x.is_null() || y.is_null() {
+        return RDEV_INVALID_ARGUMENT;
+    }
+    match panic::catch_unwind(crate::get_mouse_position) {
+        Ok(Ok((px, py))) => {
+            *x = px;
+            *y = py;
+            RDEV_OK
+        }
+        _ => RDEV_ERROR,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::rdev::{Button, Key};
+    use std::time::Duration;
+
+    #[test]
+    fn test_ffi_events() {
+        let event = |event_type| Event {
+            event_type,
+            time: UNIX_EPOCH + Duration::from_millis(1_700_000_000_000),
+            name: None,
+            unicode: None,
+            scan_code: 30,
+            display: None,
+            delta: None,
+            numlock: None,
+        };
+        for event_type in [
+            EventType::KeyPress(Key::KeyA),
+            EventType::KeyRelease(Key::Unknown(500)),
+            EventType::ButtonPress(Button::Right),
+            EventType::MouseMove { x: 10.5, y: 20.0 },
+            EventType::Wheel {
+                delta_x: 0,
+                delta_y: -1,
+            },
+        ] {
+            let c_event = to_c(&event(event_type), None);
+            assert_eq!(c_event.time_ms, 1_700_000_000_000);
+            assert_eq!(from_c(&c_event), Some(event_type));
+        }
+        let name = CString::new("a").unwrap();
+        let c_event = to_c(&event(EventType::LayoutChanged), Some(&name));
+        assert_eq!(c_event.kind, RDEV_OTHER);
+        assert_eq!(unsafe { CStr::from_ptr(c_event.name) }, name.as_c_str());
+        assert_eq!(from_c(&c_event), None);
+        assert_eq!(unsafe { rdev_simulate(ptr::null()) }, RDEV_INVALID_ARGUMENT);
+    }
+}
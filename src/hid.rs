@@ -0,0 +1,176 @@
+use crate::rdev::Key;
+
+macro_rules! decl_usages {
+    ($($key:ident, $usage:literal),*) => {
+        impl Key {
+            /// USB HID usage of the key on the Keyboard/Keypad page (0x07), as
+            /// used by QMK, evdev's HID layer or BLE keyboards. Keys living on
+            /// another page (media keys, brightness) and `Function` give None.
+            pub fn to_hid_usage(&self) -> Option<u16> {
+                match self {
+                    $(
+                        Key::$key => Some($usage),
+                    )*
+                    _ => None,
+                }
+            }
+
+            /// Key with the USB HID usage `usage` on the Keyboard/Keypad page (0x07).
+            pub fn from_hid_usage(usage: u16) -> Option<Key> {
+                match usage {
+                    $(
+                        $usage => Some(Key::$key),
+                    )*
+                    _ => None,
+                }
+            }
+        }
+    };
+}
+
+#[rustfmt::skip]
+decl_usages!(
+    KeyA, 0x04,
+    KeyB, 0x05,
+    KeyC, 0x06,
+    KeyD, 0x07,
+    KeyE, 0x08,
+    KeyF, 0x09,
+    KeyG, 0x0A,
+    KeyH, 0x0B,
+    KeyI, 0x0C,
+    KeyJ, 0x0D,
+    KeyK, 0x0E,
+    KeyL, 0x0F,
+    KeyM, 0x10,
+    KeyN, 0x11,
+    KeyO, 0x12,
+    KeyP, 0x13,
+    KeyQ, 0x14,
+    KeyR, 0x15,
+    KeyS, 0x16,
+    KeyT, 0x17,
+    KeyU, 0x18,
+    KeyV, 0x19,
+    KeyW, 0x1A,
+    KeyX, 0x1B,
+    KeyY, 0x1C,
+    KeyZ, 0x1D,
+    Num1, 0x1E,
+    Num2, 0x1F,
+    Num3, 0x20,
+    Num4, 0x21,
+    Num5, 0x22,
+    Num6, 0x23,
+    Num7, 0x24,
+    Num8, 0x25,
+    Num9, 0x26,
+    Num0, 0x27,
+    Return, 0x28,
+    Escape, 0x29,
+    Backspace, 0x2A,
+    Tab, 0x2B,
+    Space, 0x2C,
+    Minus, 0x2D,
+    Equal, 0x2E,
+    LeftBracket, 0x2F,
+    RightBracket, 0x30,
+    BackSlash, 0x31,
+    SemiColon, 0x33,
+    Quote, 0x34,
+    BackQuote, 0x35,
+    Comma, 0x36,
+    Dot, 0x37,
+    Slash, 0x38,
+    CapsLock, 0x39,
+    F1, 0x3A,
+    F2, 0x3B,
+    F3, 0x3C,
+    F4, 0x3D,
+    F5, 0x3E,
+    F6, 0x3F,
+    F7, 0x40,
+    F8, 0x41,
+    F9, 0x42,
+    F10, 0x43,
+    F11, 0x44,
+    F12, 0x45,
+    PrintScreen, 0x46,
+    ScrollLock, 0x47,
+    Pause, 0x48,
+    Insert, 0x49,
+    Home, 0x4A,
+    PageUp, 0x4B,
+    Delete, 0x4C,
+    End, 0x4D,
+    PageDown, 0x4E,
+    RightArrow, 0x4F,
+    LeftArrow, 0x50,
+    DownArrow, 0x51,
+    UpArrow, 0x52,
+    NumLock, 0x53,
+    KpDivide, 0x54,
+    KpMultiply, 0x55,
+    KpMinus, 0x56,
+    KpPlus, 0x57,
+    KpReturn, 0x58,
+    Kp1, 0x59,
+    Kp2, 0x5A,
+    Kp3, 0x5B,
+    Kp4, 0x5C,
+    Kp5, 0x5D,
+    Kp6, 0x5E,
+    Kp7, 0x5F,
+    Kp8, 0x60,
+    Kp9, 0x61,
+    Kp0, 0x62,
+    KpDelete, 0x63,
+    IntlBackslash, 0x64,
+    Menu, 0x65,
+    F13, 0x68,
+    F14, 0x69,
+    F15, 0x6A,
+    F16, 0x6B,
+    F17, 0x6C,
+    F18, 0x6D,
+    F19, 0x6E,
+    F20, 0x6F,
+    F21, 0x70,
+    F22, 0x71,
+    F23, 0x72,
+    F24, 0x73,
+    VolumeMute, 0x7F,
+    VolumeUp, 0x80,
+    VolumeDown, 0x81,
+    Ro, 0x87,
+    Kana, 0x88,
+    Yen, 0x89,
+    Henkan, 0x8A,
+    Muhenkan, 0x8B,
+    Hangul, 0x90,
+    ControlLeft, 0xE0,
+    ShiftLeft, 0xE1,
+    Alt, 0xE2,
+    MetaLeft, 0xE3,
+    ControlRight, 0xE4,
+    ShiftRight, 0xE5,
+    AltGr, 0xE6,
+    MetaRight, 0xE7
+);
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_hid_usage() {
+        assert_eq!(Key::KeyA.to_hid_usage(), Some(0x04));
+        assert_eq!(Key::from_hid_usage(0x28), Some(Key::Return));
+        assert_eq!(Key::MediaNext.to_hid_usage(), None);
+        for usage in 0..=u16::MAX {
+            if let Some(key) = Key::from_hid_usage(usage) {
+                assert_eq!(key.to_hid_usage(), Some(usage));
+            }
+        }
+    }
+}
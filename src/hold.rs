@@ -0,0 +1,275 @@
+use crate::rdev::{Event, EventType, Key};
+use std::sync::{Arc, Mutex, MutexGuard, PoisonError};
+use std::time::{Duration, Instant};
+
+type Callback = Arc<dyn Fn() + Send + Sync>;
+
+/// What a `HoldKey` event starts or ends.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+enum Change {
+    Press,
+    Release,
+}
+
+#[derive(Debug, Default)]
+struct State {
+    /// When we last saw the key go down or repeat, while held.
+    held: Option<Instant>,
+    /// We suppressed the press, apps mustn't see the release either even
+    /// if the hold ended already.
+    suppressed: bool,
+}
+
+impl State {
+    /// Whether `event_type` goes through, and what it changed.
+    fn handle(
+        &mut self,
+        key: Key,
+        release_after: Option<Duration>,
+        event_type: &EventType,
+        now: Instant,
+    ) -> (bool, Vec<Change>) {
+        let mut changes: Vec<Change> = self.expire(release_after, now).into_iter().collect();
+        match event_type {
+            EventType::KeyPress(pressed) if *pressed == key => {
+                // Autorepeat only keeps it held.
+                if self.held.replace(now).is_none() {
+                    changes.push(Change::Press);
+                }
+                self.suppressed = true;
+                (false, changes)
+            }
+            EventType::KeyRelease(released) if *released == key => {
+                if self.held.take().is_some() {
+                    changes.push(Change::Release);
+                }
+                // Otherwise pressed before we started, apps saw the press
+                // and want the release.
+                (!std::mem::replace(&mut self.suppressed, false), changes)
+            }
+            _ => (true, changes),
+        }
+    }
+
+    /// Forgets the key if it neither repeated nor got released for
+    /// `release_after`, the release got lost.
+    fn expire(&mut self, release_after: Option<Duration>, now: Instant) -> Option<Change> {
+        match (self.held, release_after) {
+            (Some(since), Some(after)) if now.saturating_duration_since(since) >= after => {
+                self.held = None;
+                Some(Change::Release)
+            }
+            _ => None,
+        }
+    }
+}
+
+/// Suppresses `key` system-wide and calls `on_press` when it goes down,
+/// `on_release` when it goes up, once each per hold however many times the
+/// key repeats: push to talk, or a key held for a quick overlay. Other
+/// events go through.
+///
+/// Releases get lost when the session locks, another desktop (UAC, a login
+/// screen) takes the input or the hook stops, and the key would stay held.
+/// `release` ends the hold on demand, `release_after` when the key didn't
+/// repeat for a while, and `grab` ends it when grabbing stops. Callbacks
+/// run on the thread handling events, keep them short.
+///
+/// ```no_run
+/// use rdev::{HoldKey, Key};
+///
+/// let push_to_talk = HoldKey::new(Key::F13)
+///     .on_press(|| println!("Talking"))
+///     .on_release(|| println!("Muted"));
+/// ```
+#[derive(Clone)]
+pub struct HoldKey {
+    key: Key,
+    release_after: Option<Duration>,
+    on_press: Option<Callback>,
+    on_release: Option<Callback>,
+    state: Arc<Mutex<State>>,
+}
+
+impl HoldKey {
+    pub fn new(key: Key) -> HoldKey {
+        HoldKey {
+            key,
+            release_after: None,
+            on_press: None,
+            on_release: None,
+            state: Arc::default(),
+        }
+    }
+
+    pub fn on_press<F>(mut self, callback: F) -> HoldKey
+    where
+        F: Fn() + Send + Sync + 'static,
+    {
+        self.on_press = Some(Arc::new(callback));
+        self
+    }
+
+    pub fn on_release<F>(mut self, callback: F) -> HoldKey
+    where
+        F: Fn() + Send + Sync + 'static,
+    {
+        self.on_release = Some(Arc::new(callback));
+        self
+    }
+
+    /// Ends the hold when the key didn't repeat for `after`, for when its
+    /// release got lost. Only for keys that autorepeat, made longer than
+    /// `keyboard_repeat_delay`: this is checked on each event and every
+    /// `after` while grabbing.
+    pub fn release_after(mut self, after: Duration) -> HoldKey {
+        self.release_after = Some(after);
+        self
+    }
+
+    pub fn key(&self) -> Key {
+        self.key
+    }
+
+    fn state(&self) -> MutexGuard<'_, State> {
+        self.state.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+
+    pub fn is_held(&self) -> bool {
+        self.state().held.is_some()
+    }
+
+    /// Calls the callbacks for `changes`, outside the lock so that it can use
+    /// `self`.
+    fn changed(&self, changes: &[Change]) {
+        for change in changes {
+            let callback = match change {
+                Change::Press => &self.on_press,
+                Change::Release => &self.on_release,
+            };
+            if let Some(callback) = callback {
+                callback();
+            }
+        }
+    }
+
+    /// Calls the callbacks `event` asks for, returns whether it should go
+    /// through.
+    pub fn handle(&self, event: &Event) -> bool {
+        let (pass, changes) = self.state().handle(
+            self.key,
+            self.release_after,
+            &event.event_type,
+            Instant::now(),
+        );
+        self.changed(&changes);
+        pass
+    }
+
+    /// Ends the hold if the key is held, calling `on_release`.
+    pub fn release(&self) {
+        let held = self.state().held.take();
+        if held.is_some() {
+            self.changed(&[Change::Release]);
+        }
+    }
+
+    /// Handles events from `grab`, ending the hold when grabbing stops.
+    ///
+    /// ```no_run
+    /// use rdev::{HoldKey, Key};
+    /// use std::time::Duration;
+    ///
+    /// let push_to_talk = HoldKey::new(Key::KeyV)
+    ///     .on_press(|| println!("Talking"))
+    ///     .on_release(|| println!("Muted"))
+    ///     .release_after(Duration::from_secs(2));
+    /// // This will block.
+    /// if let Err(error) = push_to_talk.grab() {
+    ///     println!("Error: {:?}", error)
+    /// }
+    /// ```
+    #[cfg(feature = "unstable_grab")]
+    #[cfg(not(target_os = "freebsd"))]
+    pub fn grab(&self) -> Result<(), crate::rdev::GrabError> {
+        use std::sync::atomic::{AtomicBool, Ordering};
+        use std::thread;
+
+        /// Ends the hold and the timer when grabbing stops, even by a
+        /// panic.
+        struct Release(HoldKey, Arc<AtomicBool>);
+
+        impl Drop for Release {
+            fn drop(&mut self) {
+                self.1.store(true, Ordering::Relaxed);
+                self.0.release();
+            }
+        }
+
+        let stopped = Arc::new(AtomicBool::new(false));
+        let _release = Release(self.clone(), stopped.clone());
+        if let Some(after) = self.release_after {
+            // No event may come after a lost release.
+            let hold = self.clone();
+            thread::spawn(move || {
+                while !stopped.load(Ordering::Relaxed) {
+                    thread::sleep(after);
+                    let change = hold.state().expire(Some(after), Instant::now());
+                    hold.changed(change.as_slice());
+                }
+            });
+        }
+        let hold = self.clone();
+        crate::grab(move |event| {
+            if hold.handle(&event) {
+                Some(event)
+            } else {
+                None
+            }
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_hold_key() {
+        let mut state = State::default();
+        let start = Instant::now();
+        let after = Some(Duration::from_secs(1));
+        let mut handle = |event_type, at| {
+            state.handle(
+                Key::F13,
+                after,
+                &event_type,
+                start + Duration::from_millis(at),
+            )
+        };
+        let press = EventType::KeyPress(Key::F13);
+        let release = EventType::KeyRelease(Key::F13);
+        // Pressed before we started.
+        assert_eq!(handle(release, 0), (true, vec![]));
+        assert_eq!(handle(press, 10), (false, vec![Change::Press]));
+        for at in 500..520 {
+            assert_eq!(handle(press, at), (false, vec![]));
+        }
+        assert_eq!(handle(EventType::KeyPress(Key::KeyA), 600), (true, vec![]));
+        assert_eq!(handle(release, 700), (false, vec![Change::Release]));
+        assert_eq!(handle(release, 710), (true, vec![]));
+
+        // Lost releases
+        assert_eq!(handle(press, 2000), (false, vec![Change::Press]));
+        assert_eq!(
+            handle(EventType::KeyPress(Key::KeyA), 3000),
+            (true, vec![Change::Release])
+        );
+        assert_eq!(handle(release, 3010), (false, vec![]));
+        assert_eq!(handle(press, 4000), (false, vec![Change::Press]));
+        assert_eq!(
+            handle(press, 5000),
+            (false, vec![Change::Release, Change::Press])
+        );
+    }
+}
@@ -0,0 +1,624 @@
+use crate::rdev::{Event, EventType, Key, ListenError, Modifiers, ParseKeyError};
+use std::collections::{HashMap, HashSet};
+use std::fmt;
+use std::str::FromStr;
+use std::sync::{Arc, Mutex, PoisonError};
+use std::time::{Duration, SystemTime};
+
+/// A key pressed while exactly `modifiers` are held, left and right
+/// modifiers are the same. Parses from strings like `"Ctrl+Shift+P"`: the
+/// modifiers are `Ctrl`, `Shift`, `Alt`, `AltGr` and `Meta`, case
+/// insensitive, with the aliases `Control`, `Option` for Alt, and `Cmd`,
+/// `Command`, `Super` and `Win` for Meta. The key is a `Key` name, a letter,
+/// a digit, `Enter` or `Esc`. Displays in the canonical form, modifiers in
+/// that order followed by the `Key` name: `"Ctrl+Shift+KeyP"`.
+///
+/// ```
+/// use rdev::{Hotkey, Key, Modifiers};
+///
+/// let hotkey: Hotkey = "Cmd+shift+p".parse().unwrap();
+/// let modifiers = Modifiers {
+///     meta: true,
+///     shift: true,
+///     ..Default::default()
+/// };
+/// assert_eq!(hotkey, Hotkey::new(modifiers, Key::KeyP));
+/// assert_eq!(hotkey.to_string(), "Shift+Meta+KeyP");
+/// ```
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub struct Hotkey {
+    pub modifiers: Modifiers,
+    pub key: Key,
+}
+
+impl Hotkey {
+    pub fn new(modifiers: Modifiers, key: Key) -> Hotkey {
+        Hotkey { modifiers, key }
+    }
+}
+
+/// The name config files usually give to a `Hotkey`.
+pub type Shortcut = Hotkey;
+
+impl fmt::Display for Hotkey {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let names = [
+            (self.modifiers.ctrl, "Ctrl"),
+            (self.modifiers.shift, "Shift"),
+            (self.modifiers.alt, "Alt"),
+            (self.modifiers.alt_gr, "AltGr"),
+            (self.modifiers.meta, "Meta"),
+        ];
+        for (_, name) in names.iter().filter(|(held, _)| *held) {
+            write!(f, "{}+", name)?;
+        }
+        write!(f, "{}", self.key.to_string())
+    }
+}
+
+impl FromStr for Hotkey {
+    type Err = ParseKeyError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut parts: Vec<&str> = s.split('+').map(str::trim).collect();
+        let key = parts.pop().ok_or(ParseKeyError)?;
+        let mut modifiers = Modifiers::default();
+        for part in parts {
+            let modifier = match part.to_lowercase().as_str() {
+                "ctrl" | "control" => &mut modifiers.ctrl,
+                "shift" => &mut modifiers.shift,
+                "alt" | "option" => &mut modifiers.alt,
+                "altgr" => &mut modifiers.alt_gr,
+                "meta" | "cmd" | "command" | "super" | "win" => &mut modifiers.meta,
+                _ => return Err(ParseKeyError),
+            };
+            *modifier = true;
+        }
+        let key = match key {
+            "Enter" => Key::Return,
+            "Esc" => Key::Escape,
+            _ => match key.parse() {
+                Ok(key) => key,
+                Err(_) => {
+                    let mut chars = key.chars();
+                    match (chars.next(), chars.next()) {
+                        (Some(c), None) if c.is_ascii_alphabetic() => {
+                            format!("Key{}", c.to_ascii_uppercase()).parse()?
+                        }
+                        (Some(c), None) if c.is_ascii_digit() => format!("Num{}", c).parse()?,
+                        _ => return Err(ParseKeyError),
+                    }
+                }
+            },
+        };
+        Ok(Hotkey { modifiers, key })
+    }
+}
+
+/// How long `KeySequence`s parsed from strings wait for each step.
+pub const DEFAULT_STEP_TIMEOUT: Duration = Duration::from_secs(1);
+
+/// A step of a `KeySequence`, `timeout` is how long after the previous step
+/// it can be pressed (ignored for the first one).
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub struct SequenceStep {
+    pub hotkey: Hotkey,
+    pub timeout: Duration,
+}
+
+/// Hotkeys pressed one after the other, like Ctrl+K then Ctrl+C, or Shift
+/// twice (`ShiftLeft, ShiftLeft`). Modifiers pressed between steps are
+/// ignored, any other key starts over. So do the `abort` keys, which are
+/// also suppressed when the sequence is, while it's in progress.
+/// Parses from hotkeys separated by commas, like `"Ctrl+K, Ctrl+C"`, with
+/// `DEFAULT_STEP_TIMEOUT` and Escape as the abort key.
+///
+/// ```
+/// use rdev::{Hotkey, Key, KeySequence, Modifiers};
+/// use std::time::Duration;
+///
+/// let shift = Hotkey::new(Modifiers::default(), Key::ShiftLeft);
+/// let double_shift = KeySequence::new(&[shift, shift], Duration::from_millis(300));
+/// assert_eq!(double_shift.steps.len(), 2);
+/// let sequence: KeySequence = "Ctrl+K, Ctrl+C".parse().unwrap();
+/// assert_eq!(sequence.abort, vec![Key::Escape]);
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct KeySequence {
+    pub steps: Vec<SequenceStep>,
+    pub abort: Vec<Key>,
+}
+
+impl KeySequence {
+    /// Every step with the same timeout, and Escape as the abort key.
+    pub fn new(hotkeys: &[Hotkey], timeout: Duration) -> KeySequence {
+        KeySequence {
+            steps: hotkeys
+                .iter()
+                .map(|hotkey| SequenceStep {
+                    hotkey: *hotkey,
+                    timeout,
+                })
+                .collect(),
+            abort: vec![Key::Escape],
+        }
+    }
+
+    fn hotkeys(&self) -> impl Iterator<Item = &Hotkey> {
+        self.steps.iter().map(|step| &step.hotkey)
+    }
+
+    /// One of them would fire whenever the other does, or prevent it from
+    /// firing.
+    fn conflicts_with(&self, other: &KeySequence) -> bool {
+        self.hotkeys().zip(other.hotkeys()).all(|(a, b)| a == b)
+    }
+}
+
+impl From<Hotkey> for KeySequence {
+    fn from(hotkey: Hotkey) -> KeySequence {
+        KeySequence {
+            steps: vec![SequenceStep {
+                hotkey,
+                timeout: DEFAULT_STEP_TIMEOUT,
+            }],
+            abort: vec![],
+        }
+    }
+}
+
+/// Hotkeys separated by `", "`, the timeouts and abort keys are left out.
+impl fmt::Display for KeySequence {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        for (index, step) in self.steps.iter().enumerate() {
+            if index > 0 {
+                write!(f, ", ")?;
+            }
+            write!(f, "{}", step.hotkey)?;
+        }
+        Ok(())
+    }
+}
+
+impl FromStr for KeySequence {
+    type Err = ParseKeyError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let hotkeys = s
+            .split(',')
+            .map(str::parse)
+            .collect::<Result<Vec<Hotkey>, _>>()?;
+        Ok(KeySequence::new(&hotkeys, DEFAULT_STEP_TIMEOUT))
+    }
+}
+
+/// Returned by `HotkeyManager::register`, to unregister the hotkey.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub struct HotkeyId(pub(crate) u64);
+
+/// Errors of `HotkeyManager::register` and `HotkeyManager::unregister`.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum HotkeyError {
+    /// The hotkey is already registered, with this id. For sequences, one is
+    /// the start of the other.
+    Conflict(HotkeyId),
+    /// No hotkey has this id, it was unregistered already.
+    NotRegistered,
+    /// A `KeySequence` without steps.
+    EmptySequence,
+}
+
+type Callback = Arc<Mutex<dyn FnMut() + Send>>;
+
+const MODIFIER_KEYS: [Key; 8] = [
+    Key::ShiftLeft,
+    Key::ShiftRight,
+    Key::ControlLeft,
+    Key::ControlRight,
+    Key::Alt,
+    Key::AltGr,
+    Key::MetaLeft,
+    Key::MetaRight,
+];
+
+struct Registration {
+    sequence: KeySequence,
+    suppress: bool,
+    callback: Callback,
+    /// Steps already pressed, and when the last one was.
+    progress: usize,
+    last_step: SystemTime,
+}
+
+impl Registration {
+    /// Starts over if the next step came too late.
+    fn expire(&mut self, time: SystemTime) {
+        if self.progress > 0 {
+            let elapsed = time.duration_since(self.last_step).unwrap_or_default();
+            if elapsed > self.sequence.steps[self.progress].timeout {
+                self.progress = 0;
+            }
+        }
+    }
+
+    /// Whether `hotkey` pressed at `time` is the next step, moving to it.
+    fn advance(&mut self, hotkey: &Hotkey, time: SystemTime) -> bool {
+        if self.sequence.steps[self.progress].hotkey == *hotkey {
+            self.progress += 1;
+            self.last_step = time;
+            return true;
+        }
+        if !MODIFIER_KEYS.contains(&hotkey.key) && self.progress > 0 {
+            self.progress = 0;
+            return self.advance(hotkey, time);
+        }
+        false
+    }
+}
+
+#[derive(Default)]
+struct State {
+    next_id: u64,
+    hotkeys: HashMap<HotkeyId, Registration>,
+    /// Every key held down, modifiers included.
+    held: HashSet<Key>,
+    /// Keys whose press was suppressed, their repeats and release are too.
+    suppressed: HashSet<Key>,
+}
+
+fn held_modifiers(held: &HashSet<Key>, except: Key) -> Modifiers {
+    let any = |keys: &[Key]| keys.iter().any(|key| *key != except && held.contains(key));
+    Modifiers {
+        shift: any(&[Key::ShiftLeft, Key::ShiftRight]),
+        ctrl: any(&[Key::ControlLeft, Key::ControlRight]),
+        alt: any(&[Key::Alt]),
+        meta: any(&[Key::MetaLeft, Key::MetaRight]),
+        alt_gr: any(&[Key::AltGr]),
+    }
+}
+
+impl State {
+    /// The callbacks to call, and whether the event is suppressed.
+    fn handle(&mut self, event: &Event) -> (Vec<Callback>, bool) {
+        let mut callbacks = vec![];
+        match event.event_type {
+            EventType::KeyPress(key) => {
+                // Autorepeat, steps are only pressed once.
+                if !self.held.insert(key) {
+                    return (callbacks, self.suppressed.contains(&key));
+                }
+                let hotkey = Hotkey::new(held_modifiers(&self.held, key), key);
+                let mut suppress = false;
+                for registration in self.hotkeys.values_mut() {
+                    registration.expire(event.time);
+                    if registration.progress > 0 && registration.sequence.abort.contains(&key) {
+                        registration.progress = 0;
+                        suppress |= registration.suppress;
+                    } else if registration.advance(&hotkey, event.time) {
+                        suppress |= registration.suppress;
+                        if registration.progress == registration.sequence.steps.len() {
+                            registration.progress = 0;
+                            callbacks.push(registration.callback.clone());
+                        }
+                    }
+                }
+                if suppress {
+                    self.suppressed.insert(key);
+                }
+                (callbacks, suppress)
+            }
+            EventType::KeyRelease(key) => {
+                self.held.remove(&key);
+                (callbacks, self.suppressed.remove(&key))
+            }
+            _ => (callbacks, false),
+        }
+    }
+}
+
+/// Calls a function when one of its hotkeys is pressed, tracking the
+/// modifiers held from the events it's given. Clones share the same
+/// hotkeys, so they can be registered and unregistered from any thread
+/// while `listen` or `grab` runs. Use `handle` to feed it events from your
+/// own `listen` or `grab` callback instead.
+///
+/// A hotkey fires once when its key is pressed, not on autorepeat, and only
+/// if exactly its modifiers are held. With `suppress`, its key press,
+/// repeats and release don't reach other applications when grabbing. The
+/// modifiers always do, so they aren't stuck for other applications.
+/// Sequences suppress each of their steps, even if the sequence isn't
+/// completed afterwards.
+///
+/// ```no_run
+/// use rdev::HotkeyManager;
+///
+/// let manager = HotkeyManager::new();
+/// let hotkey = "Ctrl+Shift+P".parse().unwrap();
+/// manager
+///     .register(hotkey, false, || println!("Ctrl+Shift+P was pressed"))
+///     .unwrap();
+/// // This will block.
+/// if let Err(error) = manager.listen() {
+///     println!("Error: {:?}", error)
+/// }
+/// ```
+#[derive(Clone, Default)]
+pub struct HotkeyManager {
+    state: Arc<Mutex<State>>,
+}
+
+impl HotkeyManager {
+    pub fn new() -> HotkeyManager {
+        HotkeyManager::default()
+    }
+
+    /// `HotkeyError::Conflict` if `hotkey` is already registered.
+    pub fn register<F>(
+        &self,
+        hotkey: Hotkey,
+        suppress: bool,
+        callback: F,
+    ) -> Result<HotkeyId, HotkeyError>
+    where
+        F: FnMut() + Send + 'static,
+    {
+        self.register_sequence(hotkey.into(), suppress, callback)
+    }
+
+    /// `HotkeyError::Conflict` if a registered sequence or hotkey starts
+    /// like `sequence`, or is its start.
+    pub fn register_sequence<F>(
+        &self,
+        sequence: KeySequence,
+        suppress: bool,
+        callback: F,
+    ) -> Result<HotkeyId, HotkeyError>
+    where
+        F: FnMut() + Send + 'static,
+    {
+        if sequence.steps.is_empty() {
+            return Err(HotkeyError::EmptySequence);
+        }
+        let mut state = self.state.lock().unwrap_or_else(PoisonError::into_inner);
+        let conflict = state
+            .hotkeys
+            .iter()
+            .find(|(_, registration)| registration.sequence.conflicts_with(&sequence));
+        if let Some((id, _)) = conflict {
+            return Err(HotkeyError::Conflict(*id));
+        }
+        let id = HotkeyId(state.next_id);
+        state.next_id += 1;
+        state.hotkeys.insert(
+            id,
+            Registration {
+                sequence,
+                suppress,
+                callback: Arc::new(Mutex::new(callback)),
+                progress: 0,
+                last_step: SystemTime::UNIX_EPOCH,
+            },
+        );
+        Ok(id)
+    }
+
+    /// Unregisters a hotkey or a sequence.
+    pub fn unregister(&self, id: HotkeyId) -> Result<(), HotkeyError> {
+        let mut state = self.state.lock().unwrap_or_else(PoisonError::into_inner);
+        state
+            .hotkeys
+            .remove(&id)
+            .map(|_| ())
+            .ok_or(HotkeyError::NotRegistered)
+    }
+
+    /// The hotkeys and sequences registered, with their id. Hotkeys are
+    /// sequences of one step.
+    pub fn sequences(&self) -> Vec<(HotkeyId, KeySequence)> {
+        let state = self.state.lock().unwrap_or_else(PoisonError::into_inner);
+        state
+            .hotkeys
+            .iter()
+            .map(|(id, registration)| (*id, registration.sequence.clone()))
+            .collect()
+    }
+
+    /// Updates the modifiers held and calls the callbacks of the hotkeys
+    /// and sequences completed, if any. Returns true if the event should be
+    /// suppressed.
+    pub fn handle(&self, event: &Event) -> bool {
+        let (callbacks, suppress) = self
+            .state
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .handle(event);
+        // Not holding the state, the callbacks may register or unregister.
+        for callback in callbacks {
+            if let Ok(mut callback) = callback.lock() {
+                callback();
+            }
+        }
+        suppress
+    }
+
+    /// Runs the hotkeys with `listen`, nothing is suppressed. Blocks.
+    pub fn listen(&self) -> Result<(), ListenError> {
+        let manager = self.clone();
+        crate::listen(move |event| {
+            manager.handle(&event);
+        })
+    }
+
+    /// Runs the hotkeys with `grab`, suppressing the keys of hotkeys
+    /// registered with `suppress`. Blocks.
+    #[cfg(feature = "unstable_grab")]
+    #[cfg(not(target_os = "freebsd"))]
+    pub fn grab(&self) -> Result<(), crate::rdev::GrabError> {
+        let manager = self.clone();
+        crate::grab(move |event| {
+            if manager.handle(&event) {
+                None
+            } else {
+                Some(event)
+            }
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::time::SystemTime;
+
+    fn event(event_type: EventType) -> Event {
+        event_at(event_type, SystemTime::now())
+    }
+
+    fn event_at(event_type: EventType, time: SystemTime) -> Event {
+        Event {
+            event_type,
+            time,
+            name: None,
+            unicode: None,
+            scan_code: 0,
+            display: None,
+            delta: None,
+            numlock: None,
+        }
+    }
+
+    #[test]
+    fn test_parse() {
+        let hotkey: Hotkey = "Cmd+Alt+F5".parse().unwrap();
+        assert!(hotkey.modifiers.meta && hotkey.modifiers.alt && !hotkey.modifiers.ctrl);
+        assert_eq!(hotkey.key, Key::F5);
+        assert_eq!("shift + 1".parse::<Hotkey>().unwrap().key, Key::Num1);
+        assert_eq!("Ctrl+".parse::<Hotkey>(), Err(ParseKeyError));
+        assert_eq!("Hyper+P".parse::<Hotkey>(), Err(ParseKeyError));
+        assert_eq!("Win+Esc".parse::<Hotkey>().unwrap().key, Key::Escape);
+        for canonical in [
+            "Ctrl+Shift+F5",
+            "Alt+Meta+Space",
+            "Meta+KeyK",
+            "Unknown(42)",
+        ] {
+            let hotkey: Shortcut = canonical.parse().unwrap();
+            assert_eq!(hotkey.to_string(), canonical);
+        }
+        assert_eq!(
+            "super+k".parse::<Hotkey>().unwrap().to_string(),
+            "Meta+KeyK"
+        );
+        let sequence: KeySequence = "Ctrl+K,Ctrl+C".parse().unwrap();
+        assert_eq!(sequence.to_string(), "Ctrl+KeyK, Ctrl+KeyC");
+    }
+
+    #[test]
+    fn test_hotkey_manager() {
+        let manager = HotkeyManager::new();
+        let count = Arc::new(AtomicUsize::new(0));
+        let fired = count.clone();
+        let hotkey: Hotkey = "Ctrl+Shift+P".parse().unwrap();
+        let id = manager
+            .register(hotkey, true, move || {
+                fired.fetch_add(1, Ordering::SeqCst);
+            })
+            .unwrap();
+        assert_eq!(
+            manager.register(hotkey, false, || ()),
+            Err(HotkeyError::Conflict(id))
+        );
+
+        let press = |key| manager.handle(&event(EventType::KeyPress(key)));
+        let release = |key| manager.handle(&event(EventType::KeyRelease(key)));
+        assert!(!press(Key::ControlRight));
+        assert!(!press(Key::ShiftLeft));
+        assert!(press(Key::KeyP));
+        // Autorepeat
+        assert!(press(Key::KeyP));
+        assert!(release(Key::KeyP));
+        assert_eq!(count.load(Ordering::SeqCst), 1);
+        // Extra modifier
+        assert!(!press(Key::Alt));
+        assert!(!press(Key::KeyP));
+        assert!(!release(Key::KeyP));
+        assert!(!release(Key::Alt));
+        assert_eq!(count.load(Ordering::SeqCst), 1);
+
+        assert_eq!(manager.unregister(id), Ok(()));
+        assert_eq!(manager.unregister(id), Err(HotkeyError::NotRegistered));
+        assert!(!press(Key::KeyP));
+        assert_eq!(count.load(Ordering::SeqCst), 1);
+    }
+
+    #[test]
+    fn test_sequence() {
+        let manager = HotkeyManager::new();
+        let count = Arc::new(AtomicUsize::new(0));
+        let fired = count.clone();
+        let sequence: KeySequence = "Ctrl+K, Ctrl+C".parse().unwrap();
+        let id = manager
+            .register_sequence(sequence, true, move || {
+                fired.fetch_add(1, Ordering::SeqCst);
+            })
+            .unwrap();
+        let start = "Ctrl+K".parse::<Hotkey>().unwrap();
+        assert_eq!(
+            manager.register(start, false, || ()),
+            Err(HotkeyError::Conflict(id))
+        );
+        assert_eq!(
+            manager.register_sequence(KeySequence::new(&[], DEFAULT_STEP_TIMEOUT), false, || ()),
+            Err(HotkeyError::EmptySequence)
+        );
+
+        let start = SystemTime::now();
+        let at = |millis| start + Duration::from_millis(millis);
+        let press = |key, millis| manager.handle(&event_at(EventType::KeyPress(key), at(millis)));
+        let release =
+            |key, millis| manager.handle(&event_at(EventType::KeyRelease(key), at(millis)));
+        let ctrl = |key, millis| {
+            let suppressed = (
+                press(Key::ControlLeft, millis),
+                press(key, millis),
+                release(key, millis),
+            );
+            release(Key::ControlLeft, millis);
+            suppressed
+        };
+        assert_eq!(ctrl(Key::KeyK, 0), (false, true, true));
+        assert_eq!(ctrl(Key::KeyC, 500), (false, true, true));
+        assert_eq!(count.load(Ordering::SeqCst), 1);
+        // Too late
+        ctrl(Key::KeyK, 1000);
+        assert_eq!(ctrl(Key::KeyC, 2500), (false, false, false));
+        // Another key in between
+        ctrl(Key::KeyK, 3000);
+        assert!(!press(Key::KeyX, 3100));
+        ctrl(Key::KeyC, 3200);
+        // Aborted
+        ctrl(Key::KeyK, 4000);
+        assert!(press(Key::Escape, 4100));
+        assert!(release(Key::Escape, 4100));
+        assert!(!press(Key::Escape, 4200));
+        ctrl(Key::KeyC, 4300);
+        assert_eq!(count.load(Ordering::SeqCst), 1);
+
+        // Double tap
+        let shift = Hotkey::new(Modifiers::default(), Key::ShiftLeft);
+        let double = KeySequence::new(&[shift, shift], Duration::from_millis(300));
+        let fired = count.clone();
+        manager
+            .register_sequence(double, false, move || {
+                fired.fetch_add(1, Ordering::SeqCst);
+            })
+            .unwrap();
+        for millis in [5000, 5200] {
+            press(Key::ShiftLeft, millis);
+            release(Key::ShiftLeft, millis);
+        }
+        assert_eq!(count.load(Ordering::SeqCst), 2);
+    }
+}
@@ -0,0 +1,92 @@
+use crate::rdev::DisplayError;
+use std::thread;
+use std::time::Duration;
+
+/// How often we look for input while idle, resuming is noticed this late at
+/// most.
+const RESUME_POLL: Duration = Duration::from_millis(250);
+
+/// Sent by `on_idle` when the user goes idle and when they come back.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum IdleEvent {
+    /// No input was seen for the threshold given to `on_idle`.
+    Idle,
+    /// Input was seen again after `Idle`.
+    Resumed,
+}
+
+/// Turns idle times into `IdleEvent`s, and says how long we can wait
+/// before asking again.
+struct IdleWatcher {
+    threshold: Duration,
+    idle: bool,
+}
+
+impl IdleWatcher {
+    fn update(&mut self, idle_time: Duration) -> (Option<IdleEvent>, Duration) {
+        if idle_time >= self.threshold {
+            let event = if self.idle {
+                None
+            } else {
+                Some(IdleEvent::Idle)
+            };
+            self.idle = true;
+            (event, RESUME_POLL)
+        } else {
+            let event = if self.idle {
+                Some(IdleEvent::Resumed)
+            } else {
+                None
+            };
+            self.idle = false;
+            // Nothing can happen before the threshold is reached.
+            (event, self.threshold - idle_time)
+        }
+    }
+}
+
+pub(crate) fn watch<F, T>(
+    threshold: Duration,
+    idle_time: F,
+    mut callback: T,
+) -> Result<(), DisplayError>
+where
+    F: Fn() -> Result<Duration, DisplayError>,
+    T: FnMut(IdleEvent),
+{
+    let mut watcher = IdleWatcher {
+        threshold,
+        idle: false,
+    };
+    loop {
+        let (event, wait) = watcher.update(idle_time()?);
+        if let Some(event) = event {
+            callback(event);
+        }
+        thread::sleep(wait);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_idle_watcher() {
+        let second = Duration::from_secs(1);
+        let mut watcher = IdleWatcher {
+            threshold: 10 * second,
+            idle: false,
+        };
+        assert_eq!(watcher.update(4 * second), (None, 6 * second));
+        assert_eq!(
+            watcher.update(10 * second),
+            (Some(IdleEvent::Idle), RESUME_POLL)
+        );
+        assert_eq!(watcher.update(30 * second), (None, RESUME_POLL));
+        assert_eq!(
+            watcher.update(Duration::from_millis(0)),
+            (Some(IdleEvent::Resumed), 10 * second)
+        );
+    }
+}
@@ -0,0 +1,210 @@
+//! Stable numbers of keys and buttons, shared by `compact` and `ffi`. New
+//! keys only get new numbers.
+use crate::rdev::{Button, Key};
+
+/// `Unknown(code)` keys and buttons are stored as UNKNOWN_OFFSET + code.
+pub(crate) const UNKNOWN_OFFSET: u64 = 1000;
+
+macro_rules! decl_key_ids {
+    ($($key:ident, $id:literal),*) => {
+        pub(crate) fn key_to_id(key: &Key) -> u64 {
+            match key {
+                $(
+                    Key::$key => $id,
+                )*
+                Key::Unknown(code) => UNKNOWN_OFFSET + u64::from(*code),
+            }
+        }
+
+        pub(crate) fn key_from_id(id: u64) -> Option<Key> {
+            match id {
+                $(
+                    $id => Some(Key::$key),
+                )*
+                id if id >= UNKNOWN_OFFSET => Some(Key::Unknown((id - UNKNOWN_OFFSET) as u32)),
+                _ => None,
+            }
+        }
+    };
+}
+
+#[rustfmt::skip]
+decl_key_ids!(
+    Alt, 1,
+    AltGr, 2,
+    Backspace, 3,
+    CapsLock, 4,
+    ControlLeft, 5,
+    ControlRight, 6,
+    Delete, 7,
+    DownArrow, 8,
+    End, 9,
+    Escape, 10,
+    F1, 11,
+    F10, 12,
+    F11, 13,
+    F12, 14,
+    F13, 15,
+    F14, 16,
+    F15, 17,
+    F16, 18,
+    F17, 19,
+    F18, 20,
+    F19, 21,
+    F20, 22,
+    F21, 23,
+    F22, 24,
+    F23, 25,
+    F24, 26,
+    F2, 27,
+    F3, 28,
+    F4, 29,
+    F5, 30,
+    F6, 31,
+    F7, 32,
+    F8, 33,
+    F9, 34,
+    Home, 35,
+    LeftArrow, 36,
+    MetaLeft, 37,
+    MetaRight, 38,
+    PageDown, 39,
+    PageUp, 40,
+    Return, 41,
+    RightArrow, 42,
+    ShiftLeft, 43,
+    ShiftRight, 44,
+    Space, 45,
+    Tab, 46,
+    UpArrow, 47,
+    PrintScreen, 48,
+    ScrollLock, 49,
+    Pause, 50,
+    NumLock, 51,
+    BackQuote, 52,
+    Num1, 53,
+    Num2, 54,
+    Num3, 55,
+    Num4, 56,
+    Num5, 57,
+    Num6, 58,
+    Num7, 59,
+    Num8, 60,
+    Num9, 61,
+    Num0, 62,
+    Minus, 63,
+    Equal, 64,
+    KeyQ, 65,
+    KeyW, 66,
+    KeyE, 67,
+    KeyR, 68,
+    KeyT, 69,
+    KeyY, 70,
+    KeyU, 71,
+    KeyI, 72,
+    KeyO, 73,
+    KeyP, 74,
+    LeftBracket, 75,
+    RightBracket, 76,
+    KeyA, 77,
+    KeyS, 78,
+    KeyD, 79,
+    KeyF, 80,
+    KeyG, 81,
+    KeyH, 82,
+    KeyJ, 83,
+    KeyK, 84,
+    KeyL, 85,
+    SemiColon, 86,
+    Quote, 87,
+    BackSlash, 88,
+    IntlBackslash, 89,
+    KeyZ, 90,
+    KeyX, 91,
+    KeyC, 92,
+    KeyV, 93,
+    KeyB, 94,
+    KeyN, 95,
+    KeyM, 96,
+    Comma, 97,
+    Dot, 98,
+    Slash, 99,
+    Insert, 100,
+    KpReturn, 101,
+    KpMinus, 102,
+    KpPlus, 103,
+    KpMultiply, 104,
+    KpDivide, 105,
+    Kp0, 106,
+    Kp1, 107,
+    Kp2, 108,
+    Kp3, 109,
+    Kp4, 110,
+    Kp5, 111,
+    Kp6, 112,
+    Kp7, 113,
+    Kp8, 114,
+    Kp9, 115,
+    KpDelete, 116,
+    Function, 117,
+    VolumeUp, 118,
+    VolumeDown, 119,
+    VolumeMute, 120,
+    MediaPlayPause, 121,
+    MediaNext, 122,
+    MediaPrev, 123,
+    BrightnessUp, 124,
+    BrightnessDown, 125,
+    Menu, 126,
+    Kana, 127,
+    Hangul, 128,
+    Henkan, 129,
+    Muhenkan, 130,
+    Yen, 131,
+    Ro, 132,
+    BrowserBack, 133,
+    BrowserForward, 134,
+    BrowserRefresh, 135,
+    BrowserHome, 136,
+    BrowserSearch, 137,
+    LaunchMail, 138,
+    Calculator, 139,
+    Sleep, 140
+);
+
+pub(crate) fn button_to_id(button: &Button) -> u64 {
+    match button {
+        Button::Left => 1,
+        Button::Right => 2,
+        Button::Middle => 3,
+        Button::Back => 4,
+        Button::Forward => 5,
+        Button::Unknown(code) => UNKNOWN_OFFSET + u64::from(*code),
+    }
+}
+
+pub(crate) fn button_from_id(id: u64) -> Option<Button> {
+    match id {
+        1 => Some(Button::Left),
+        2 => Some(Button::Right),
+        3 => Some(Button::Middle),
+        4 => Some(Button::Back),
+        5 => Some(Button::Forward),
+        id if id >= UNKNOWN_OFFSET => Some(Button::Unknown((id - UNKNOWN_OFFSET) as u8)),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_key_ids() {
+        for id in 0..UNKNOWN_OFFSET + 300 {
+            if let Some(key) = key_from_id(id) {
+                assert_eq!(key_to_id(&key), id);
+            }
+        }
+    }
+}
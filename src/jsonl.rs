@@ -0,0 +1,231 @@
+//! Streaming events as newline delimited JSON that later versions of rdev
+//! can still read. The first line is a header with the schema version,
+//! `{"rdev":"events","version":1}`, then each line is an event in the
+//! `compact` representation, whose numbers are stable.
+//! `EventReader` skips the lines it can't read, like events added by a
+//! newer version, and counts them in `skipped`. Lines of the default serde
+//! representation are read too, for streams written by hand with serde.
+//! With the `gzip` feature, `EventWriter::create_gzip` compresses the file
+//! and `EventReader::open` reads either.
+//!
+//! ```no_run
+//! use rdev::jsonl::{EventReader, EventWriter};
+//! use rdev::listen;
+//!
+//! let mut reader = EventReader::open("events.jsonl").unwrap();
+//! for event in &mut reader {
+//!     println!("{:?}", event.unwrap());
+//! }
+//! println!("{} events could not be read", reader.skipped());
+//!
+//! let mut writer = EventWriter::create("events.jsonl").unwrap();
+//! // This will block.
+//! listen(move |event| writer.write(&event).unwrap()).unwrap();
+//! ```
+use crate::compact::{self, CompactEvent};
+use crate::rdev::Event;
+#[cfg(feature = "gzip")]
+use flate2::{read::GzDecoder, write::GzEncoder, Compression};
+use serde::{Deserialize, Serialize};
+use std::fs::File;
+use std::io::{self, BufRead, BufReader, BufWriter, Write};
+use std::path::Path;
+
+/// Written in the header, only changes if old readers can't skip what's new.
+pub const SCHEMA_VERSION: u32 = 1;
+const HEADER: &str = "events";
+
+#[derive(Serialize, Deserialize)]
+struct Header {
+    rdev: String,
+    version: u32,
+}
+
+pub struct EventWriter<W: Write> {
+    writer: W,
+}
+
+impl<W: Write> EventWriter<W> {
+    /// Writes the header.
+    pub fn new(mut writer: W) -> io::Result<EventWriter<W>> {
+        let header = Header {
+            rdev: HEADER.to_string(),
+            version: SCHEMA_VERSION,
+        };
+        serde_json::to_writer(&mut writer, &header)?;
+        writer.write_all(b"\n")?;
+        Ok(EventWriter { writer })
+    }
+
+    pub fn write(&mut self, event: &Event) -> io::Result<()> {
+        compact::serialize(event, &mut serde_json::Serializer::new(&mut self.writer))?;
+        self.writer.write_all(b"\n")
+    }
+
+    pub fn flush(&mut self) -> io::Result<()> {
+        self.writer.flush()
+    }
+
+    pub fn into_inner(self) -> W {
+        self.writer
+    }
+}
+
+impl EventWriter<BufWriter<File>> {
+    /// Creates the file, replacing an existing one.
+    pub fn create<P: AsRef<Path>>(path: P) -> io::Result<EventWriter<BufWriter<File>>> {
+        EventWriter::new(BufWriter::new(File::create(path)?))
+    }
+}
+
+#[cfg(feature = "gzip")]
+impl EventWriter<GzEncoder<BufWriter<File>>> {
+    /// Creates a gzip compressed file, replacing an existing one. Call
+    /// `finish` when done, dropping the writer finishes it too but ignores
+    /// errors.
+    pub fn create_gzip<P: AsRef<Path>>(
+        path: P,
+    ) -> io::Result<EventWriter<GzEncoder<BufWriter<File>>>> {
+        let file = BufWriter::new(File::create(path)?);
+        EventWriter::new(GzEncoder::new(file, Compression::default()))
+    }
+
+    pub fn finish(self) -> io::Result<()> {
+        self.writer.finish()?.flush()
+    }
+}
+
+/// Iterates over the events of a stream, see the module documentation.
+pub struct EventReader<R> {
+    lines: io::Lines<R>,
+    /// The first line when it isn't a header.
+    pending: Option<String>,
+    version: u32,
+    skipped: usize,
+}
+
+impl<R: BufRead> EventReader<R> {
+    /// Reads the header, `InvalidData` if the stream was written with a
+    /// schema newer than `SCHEMA_VERSION`.
+    pub fn new(reader: R) -> io::Result<EventReader<R>> {
+        let mut lines = reader.lines();
+        let mut pending = None;
+        let mut version = 0;
+        if let Some(line) = lines.next() {
+            let line = line?;
+            match serde_json::from_str::<Header>(&line) {
+                Ok(header) if header.rdev == HEADER => {
+                    if header.version > SCHEMA_VERSION {
+                        return Err(io::Error::new(
+                            io::ErrorKind::InvalidData,
+                            format!(
+                                "events written with schema {}, we read up to {}",
+                                header.version, SCHEMA_VERSION
+                            ),
+                        ));
+                    }
+                    version = header.version;
+                }
+                _ => pending = Some(line),
+            }
+        }
+        Ok(EventReader {
+            lines,
+            pending,
+            version,
+            skipped: 0,
+        })
+    }
+
+    /// The schema version of the stream, 0 if it had no header.
+    pub fn version(&self) -> u32 {
+        self.version
+    }
+
+    /// How many lines couldn't be read so far.
+    pub fn skipped(&self) -> usize {
+        self.skipped
+    }
+}
+
+impl EventReader<Box<dyn BufRead>> {
+    /// Opens a file, gzip compressed ones too with the `gzip` feature.
+    pub fn open<P: AsRef<Path>>(path: P) -> io::Result<EventReader<Box<dyn BufRead>>> {
+        #[allow(unused_mut)]
+        let mut reader = BufReader::new(File::open(path)?);
+        #[cfg(feature = "gzip")]
+        {
+            if reader.fill_buf()?.starts_with(&[0x1f, 0x8b]) {
+                return EventReader::new(Box::new(BufReader::new(GzDecoder::new(reader))));
+            }
+        }
+        EventReader::new(Box::new(reader))
+    }
+}
+
+impl<R: BufRead> Iterator for EventReader<R> {
+    type Item = io::Result<Event>;
+
+    fn next(&mut self) -> Option<io::Result<Event>> {
+        loop {
+            let line = match self.pending.take() {
+                Some(line) => line,
+                None => match self.lines.next()? {
+                    Ok(line) => line,
+                    Err(error) => return Some(Err(error)),
+                },
+            };
+            if line.trim().is_empty() {
+                continue;
+            }
+            if let Ok(CompactEvent(event)) = serde_json::from_str(&line) {
+                return Some(Ok(event));
+            }
+            if let Ok(event) = serde_json::from_str(&line) {
+                return Some(Ok(event));
+            }
+            // Unknown events, or the last line of a stream cut short.
+            self.skipped += 1;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::rdev::{EventType, Key};
+    use std::time::{Duration, UNIX_EPOCH};
+
+    #[test]
+    fn test_event_stream() {
+        let event = Event {
+            event_type: EventType::KeyPress(Key::KeyA),
+            time: UNIX_EPOCH + Duration::from_millis(1_700_000_000_000),
+            name: None,
+            unicode: None,
+            scan_code: 30,
+            display: None,
+            delta: None,
+            numlock: None,
+        };
+        let mut writer = EventWriter::new(vec![]).unwrap();
+        writer.write(&event).unwrap();
+        let mut bytes = writer.into_inner();
+        // An event kind from the future, a default serde event, a cut line.
+        bytes.extend_from_slice(b"[1700000000000,250]\n");
+        serde_json::to_writer(&mut bytes, &event).unwrap();
+        bytes.extend_from_slice(b"\n[1700000000000,0,");
+
+        let mut reader = EventReader::new(&bytes[..]).unwrap();
+        assert_eq!(reader.version(), SCHEMA_VERSION);
+        let events: Vec<Event> = (&mut reader).map(Result::unwrap).collect();
+        assert_eq!(events, vec![event.clone(), event.clone()]);
+        assert_eq!(reader.skipped(), 2);
+
+        let headerless = serde_json::to_string(&event).unwrap();
+        let mut reader = EventReader::new(headerless.as_bytes()).unwrap();
+        assert_eq!(reader.version(), 0);
+        assert_eq!(reader.next().unwrap().unwrap(), event);
+        assert!(EventReader::new(&b"{\"rdev\":\"events\",\"version\":99}\n"[..]).is_err());
+    }
+}
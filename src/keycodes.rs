@@ -0,0 +1,52 @@
+//! Conversions between `Key` and the native key codes of the current OS:
+//! X11 keycodes on Linux (evdev codes are available too), virtual-key codes
+//! on Windows and CGKeyCodes on macOS.
+//!
+//! ```no_run
+//! use rdev::keycodes::{code_from_key, key_from_code};
+//! use rdev::Key;
+//!
+//! let code = code_from_key(Key::KeyA).unwrap();
+//! assert_eq!(key_from_code(code), Key::KeyA);
+//! ```
+//!
+//! Codes without a matching variant are converted to and from `Key::Unknown`.
+
+#[cfg(any(target_os = "linux", target_os = "freebsd"))]
+pub use crate::linux::{code_from_key, key_from_code};
+#[cfg(target_os = "macos")]
+pub use crate::macos::{code_from_key, key_from_code};
+#[cfg(target_os = "windows")]
+pub use crate::windows::{code_from_key, key_from_code};
+
+#[cfg(any(target_os = "linux", target_os = "freebsd"))]
+use crate::rdev::Key;
+
+/// X11 keycodes are evdev codes shifted by 8.
+#[cfg(any(target_os = "linux", target_os = "freebsd"))]
+const EVDEV_OFFSET: u32 = 8;
+
+/// Returns the evdev code (`KEY_*` in linux/input-event-codes.h) of `key`.
+#[cfg(any(target_os = "linux", target_os = "freebsd"))]
+pub fn evdev_code_from_key(key: Key) -> Option<u32> {
+    code_from_key(key)?.checked_sub(EVDEV_OFFSET)
+}
+
+/// Returns the key with evdev code `code` (`KEY_*` in linux/input-event-codes.h).
+#[cfg(any(target_os = "linux", target_os = "freebsd"))]
+pub fn key_from_evdev_code(code: u32) -> Key {
+    key_from_code(code.saturating_add(EVDEV_OFFSET))
+}
+
+#[cfg(test)]
+#[cfg(any(target_os = "linux", target_os = "freebsd"))]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_evdev_codes() {
+        // KEY_ESC and KEY_A
+        assert_eq!(evdev_code_from_key(Key::Escape), Some(1));
+        assert_eq!(key_from_evdev_code(30), Key::KeyA);
+    }
+}
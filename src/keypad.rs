@@ -0,0 +1,86 @@
+use crate::rdev::Key;
+use std::cell::Cell;
+
+thread_local! {
+    static PHYSICAL: Cell<bool> = const { Cell::new(false) };
+}
+
+/// Whether the hooks running on this thread report keypad keys as such
+/// whatever NumLock says, see `Settings::physical_keypad`.
+#[cfg_attr(not(target_os = "windows"), allow(dead_code))]
+pub(crate) fn physical() -> bool {
+    PHYSICAL.with(Cell::get)
+}
+
+/// Puts the previous value back when dropped.
+pub(crate) struct Physical(bool);
+
+impl Drop for Physical {
+    fn drop(&mut self) {
+        PHYSICAL.with(|physical| physical.set(self.0));
+    }
+}
+
+pub(crate) fn set_physical(physical: bool) -> Physical {
+    Physical(PHYSICAL.with(|current| current.replace(physical)))
+}
+
+/// The key a keypad key types with NumLock off, for replaying what a
+/// recording did: `Kp7` is `Home`, `KpDelete` is `Delete`. None for the
+/// other keys and for `Kp5`, which does nothing then.
+///
+/// ```
+/// use rdev::{keypad_navigation_key, Key};
+///
+/// assert_eq!(keypad_navigation_key(Key::Kp8), Some(Key::UpArrow));
+/// ```
+///
+/// With `Event::numlock`:
+///
+/// ```no_run
+/// use rdev::{keypad_navigation_key, listen_with, EventType, Settings};
+///
+/// let settings = Settings {
+///     physical_keypad: true,
+///     ..Default::default()
+/// };
+/// listen_with(settings, |event| {
+///     if let (EventType::KeyPress(key), Some(false)) = (event.event_type, event.numlock) {
+///         println!("{:?} moved the caret, {:?}", key, keypad_navigation_key(key));
+///     }
+/// })
+/// .unwrap();
+/// ```
+pub fn keypad_navigation_key(key: Key) -> Option<Key> {
+    Some(match key {
+        Key::Kp0 => Key::Insert,
+        Key::Kp1 => Key::End,
+        Key::Kp2 => Key::DownArrow,
+        Key::Kp3 => Key::PageDown,
+        Key::Kp4 => Key::LeftArrow,
+        Key::Kp6 => Key::RightArrow,
+        Key::Kp7 => Key::Home,
+        Key::Kp8 => Key::UpArrow,
+        Key::Kp9 => Key::PageUp,
+        Key::KpDelete => Key::Delete,
+        _ => return None,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_keypad() {
+        assert_eq!(keypad_navigation_key(Key::Kp7), Some(Key::Home));
+        assert_eq!(keypad_navigation_key(Key::KpDelete), Some(Key::Delete));
+        assert_eq!(keypad_navigation_key(Key::Kp5), None);
+        assert_eq!(keypad_navigation_key(Key::KeyA), None);
+        {
+            let _physical = set_physical(true);
+            assert!(physical());
+        }
+        assert!(!physical());
+    }
+}
@@ -0,0 +1,143 @@
+use crate::layout;
+use crate::rdev::{Key, KeyboardState};
+
+#[cfg(target_os = "macos")]
+fn modifier_label(key: Key) -> Option<&'static str> {
+    Some(match key {
+        Key::MetaLeft => "⌘",
+        Key::MetaRight => "Right ⌘",
+        Key::Alt => "⌥",
+        Key::AltGr => "Right ⌥",
+        Key::ControlLeft => "⌃",
+        Key::ControlRight => "Right ⌃",
+        Key::ShiftLeft => "⇧",
+        Key::ShiftRight => "Right ⇧",
+        Key::CapsLock => "⇪",
+        Key::Function => "fn",
+        Key::Backspace => "⌫",
+        Key::Delete => "⌦",
+        Key::Return => "↩",
+        Key::Tab => "⇥",
+        Key::Escape => "⎋",
+        Key::LeftArrow => "←",
+        Key::RightArrow => "→",
+        Key::UpArrow => "↑",
+        Key::DownArrow => "↓",
+        Key::PageUp => "⇞",
+        Key::PageDown => "⇟",
+        Key::Home => "↖",
+        Key::End => "↘",
+        _ => return None,
+    })
+}
+
+#[cfg(not(target_os = "macos"))]
+fn modifier_label(key: Key) -> Option<&'static str> {
+    Some(match key {
+        #[cfg(target_os = "windows")]
+        Key::MetaLeft => "Win",
+        #[cfg(target_os = "windows")]
+        Key::MetaRight => "Right Win",
+        #[cfg(not(target_os = "windows"))]
+        Key::MetaLeft => "Super",
+        #[cfg(not(target_os = "windows"))]
+        Key::MetaRight => "Right Super",
+        Key::Alt => "Alt",
+        Key::AltGr => "AltGr",
+        Key::ControlLeft => "Ctrl",
+        Key::ControlRight => "Right Ctrl",
+        Key::ShiftLeft => "Shift",
+        Key::ShiftRight => "Right Shift",
+        Key::CapsLock => "Caps Lock",
+        Key::Function => "Fn",
+        Key::Backspace => "Backspace",
+        Key::Delete => "Delete",
+        Key::Return => "Enter",
+        Key::Tab => "Tab",
+        Key::Escape => "Esc",
+        Key::LeftArrow => "Left",
+        Key::RightArrow => "Right",
+        Key::UpArrow => "Up",
+        Key::DownArrow => "Down",
+        Key::PageUp => "Page Up",
+        Key::PageDown => "Page Down",
+        Key::Home => "Home",
+        Key::End => "End",
+        _ => return None,
+    })
+}
+
+/// Keys whose label is the same everywhere.
+fn fixed_label(key: Key) -> Option<&'static str> {
+    Some(match key {
+        Key::Space => "Space",
+        Key::Insert => "Insert",
+        Key::PrintScreen => "Print Screen",
+        Key::ScrollLock => "Scroll Lock",
+        Key::Pause => "Pause",
+        Key::NumLock => "Num Lock",
+        Key::Menu => "Menu",
+        Key::KpReturn => "Num Enter",
+        Key::KpMinus => "Num -",
+        Key::KpPlus => "Num +",
+        Key::KpMultiply => "Num *",
+        Key::KpDivide => "Num /",
+        Key::KpDelete => "Num .",
+        Key::Kp0 => "Num 0",
+        Key::Kp1 => "Num 1",
+        Key::Kp2 => "Num 2",
+        Key::Kp3 => "Num 3",
+        Key::Kp4 => "Num 4",
+        Key::Kp5 => "Num 5",
+        Key::Kp6 => "Num 6",
+        Key::Kp7 => "Num 7",
+        Key::Kp8 => "Num 8",
+        Key::Kp9 => "Num 9",
+        Key::VolumeUp => "Volume Up",
+        Key::VolumeDown => "Volume Down",
+        Key::VolumeMute => "Mute",
+        Key::MediaPlayPause => "Play/Pause",
+        Key::MediaNext => "Next Track",
+        Key::MediaPrev => "Previous Track",
+        Key::BrightnessUp => "Brightness Up",
+        Key::BrightnessDown => "Brightness Down",
+        Key::BrowserBack => "Browser Back",
+        Key::BrowserForward => "Browser Forward",
+        Key::BrowserRefresh => "Browser Refresh",
+        Key::BrowserHome => "Browser Home",
+        Key::BrowserSearch => "Browser Search",
+        Key::LaunchMail => "Mail",
+        Key::Calculator => "Calculator",
+        Key::Sleep => "Sleep",
+        _ => return None,
+    })
+}
+
+impl Key {
+    /// What a user calls the key on their platform and on the layout
+    /// behind `keyboard`, for showing hotkeys: "⌘" or "Right Ctrl", "Ö" for
+    /// `SemiColon` on a German layout, "´" for a dead acute accent. Not
+    /// stable across layouts and versions, unlike `to_string`. The
+    /// keyboard is probed like `char_from_key` does, then reset.
+    ///
+    /// ```no_run
+    /// use rdev::{Key, Keyboard};
+    ///
+    /// let mut keyboard = Keyboard::new().unwrap();
+    /// // On a qwerty layout
+    /// assert_eq!(Key::SemiColon.label(&mut keyboard), ";");
+    /// assert_eq!(Key::KeyA.label(&mut keyboard), "A");
+    /// ```
+    pub fn label<K: KeyboardState>(&self, keyboard: &mut K) -> String {
+        if let Some(label) = modifier_label(*self).or_else(|| fixed_label(*self)) {
+            return label.to_string();
+        }
+        if let Some(label) = layout::label_for_key(keyboard, *self) {
+            return label;
+        }
+        match self {
+            Key::Unknown(code) => format!("Key {}", code),
+            key => key.to_string(),
+        }
+    }
+}
@@ -0,0 +1,422 @@
+use crate::rdev::{EventType, Key, KeyboardState, Modifiers};
+use std::collections::HashMap;
+
+/// Keys that can produce a character on a regular layout. Modifiers,
+/// navigation and function keys never do so we don't bother probing them.
+const PRINTABLE_KEYS: [Key; 48] = [
+    Key::BackQuote,
+    Key::Num1,
+    Key::Num2,
+    Key::Num3,
+    Key::Num4,
+    Key::Num5,
+    Key::Num6,
+    Key::Num7,
+    Key::Num8,
+    Key::Num9,
+    Key::Num0,
+    Key::Minus,
+    Key::Equal,
+    Key::KeyQ,
+    Key::KeyW,
+    Key::KeyE,
+    Key::KeyR,
+    Key::KeyT,
+    Key::KeyY,
+    Key::KeyU,
+    Key::KeyI,
+    Key::KeyO,
+    Key::KeyP,
+    Key::LeftBracket,
+    Key::RightBracket,
+    Key::KeyA,
+    Key::KeyS,
+    Key::KeyD,
+    Key::KeyF,
+    Key::KeyG,
+    Key::KeyH,
+    Key::KeyJ,
+    Key::KeyK,
+    Key::KeyL,
+    Key::SemiColon,
+    Key::Quote,
+    Key::BackSlash,
+    Key::IntlBackslash,
+    Key::KeyZ,
+    Key::KeyX,
+    Key::KeyC,
+    Key::KeyV,
+    Key::KeyB,
+    Key::KeyN,
+    Key::KeyM,
+    Key::Comma,
+    Key::Dot,
+    Key::Slash,
+];
+
+/// Modifier combinations we try for every key, in order of preference.
+const LEVELS: [&[Key]; 4] = [
+    &[],
+    &[Key::ShiftLeft],
+    &[Key::AltGr],
+    &[Key::ShiftLeft, Key::AltGr],
+];
+
+/// Modifiers matching each of `LEVELS`.
+const LEVEL_MODIFIERS: [Modifiers; 4] = [
+    Modifiers {
+        shift: false,
+        ctrl: false,
+        alt: false,
+        meta: false,
+        alt_gr: false,
+    },
+    Modifiers {
+        shift: true,
+        ctrl: false,
+        alt: false,
+        meta: false,
+        alt_gr: false,
+    },
+    Modifiers {
+        shift: false,
+        ctrl: false,
+        alt: false,
+        meta: false,
+        alt_gr: true,
+    },
+    Modifiers {
+        shift: true,
+        ctrl: false,
+        alt: false,
+        meta: false,
+        alt_gr: true,
+    },
+];
+
+fn modifier_keys(modifiers: &Modifiers) -> Vec<Key> {
+    let mut keys = vec![];
+    if modifiers.shift {
+        keys.push(Key::ShiftLeft);
+    }
+    if modifiers.ctrl {
+        keys.push(Key::ControlLeft);
+    }
+    if modifiers.alt {
+        keys.push(Key::Alt);
+    }
+    if modifiers.meta {
+        keys.push(Key::MetaLeft);
+    }
+    if modifiers.alt_gr {
+        keys.push(Key::AltGr);
+    }
+    keys
+}
+
+/// Returns the string the keyboard state emits for `key` pressed with `modifiers`
+/// held. The state is left as is afterwards so the caller can keep on typing
+/// (dead keys).
+fn probe<K: KeyboardState>(keyboard: &mut K, modifiers: &[Key], key: Key) -> Option<String> {
+    for modifier in modifiers {
+        keyboard.add(&EventType::KeyPress(*modifier));
+    }
+    let name = keyboard.add(&EventType::KeyPress(key));
+    keyboard.add(&EventType::KeyRelease(key));
+    for modifier in modifiers.iter().rev() {
+        keyboard.add(&EventType::KeyRelease(*modifier));
+    }
+    name
+}
+
+fn press(sequence: &mut Vec<EventType>, modifiers: &[Key], key: Key) {
+    for modifier in modifiers {
+        sequence.push(EventType::KeyPress(*modifier));
+    }
+    sequence.push(EventType::KeyPress(key));
+    sequence.push(EventType::KeyRelease(key));
+    for modifier in modifiers.iter().rev() {
+        sequence.push(EventType::KeyRelease(*modifier));
+    }
+}
+
+/// Dead keys don't emit anything on their own, and emit the bare accent
+/// when followed by a space.
+fn is_dead_key<K: KeyboardState>(keyboard: &mut K, modifiers: &[Key], key: Key) -> bool {
+    keyboard.reset();
+    let is_dead = match probe(keyboard, modifiers, key) {
+        Some(ref s) if !s.is_empty() => false,
+        _ => match probe(keyboard, &[], Key::Space) {
+            Some(ref s) => !s.is_empty() && s != " ",
+            None => false,
+        },
+    };
+    keyboard.reset();
+    is_dead
+}
+
+/// Finds the sequence of events that types `c` with the layout behind `keyboard`.
+/// Direct keys (with Shift and/or AltGr) are preferred, then dead key + key
+/// combinations. Returns None if the layout can't produce that character.
+pub(crate) fn sequence_for_char<K: KeyboardState>(
+    keyboard: &mut K,
+    c: char,
+) -> Option<Vec<EventType>> {
+    let target = c.to_string();
+    for modifiers in LEVELS.iter() {
+        for key in PRINTABLE_KEYS.iter() {
+            keyboard.reset();
+            if probe(keyboard, modifiers, *key).as_ref() == Some(&target) {
+                let mut sequence = vec![];
+                press(&mut sequence, modifiers, *key);
+                return Some(sequence);
+            }
+        }
+    }
+
+    for dead_modifiers in LEVELS.iter() {
+        for dead_key in PRINTABLE_KEYS.iter() {
+            if !is_dead_key(keyboard, dead_modifiers, *dead_key) {
+                continue;
+            }
+            for modifiers in LEVELS.iter() {
+                for key in PRINTABLE_KEYS.iter() {
+                    keyboard.reset();
+                    probe(keyboard, dead_modifiers, *dead_key);
+                    if probe(keyboard, modifiers, *key).as_ref() == Some(&target) {
+                        let mut sequence = vec![];
+                        press(&mut sequence, dead_modifiers, *dead_key);
+                        press(&mut sequence, modifiers, *key);
+                        keyboard.reset();
+                        return Some(sequence);
+                    }
+                }
+            }
+        }
+    }
+    keyboard.reset();
+    None
+}
+
+/// Finds the key typing `c` directly (no dead key) with the layout behind
+/// `keyboard`, along with the modifiers it needs.
+pub(crate) fn key_for_char<K: KeyboardState>(
+    keyboard: &mut K,
+    c: char,
+) -> Option<(Key, Modifiers)> {
+    let target = c.to_string();
+    let mut found = None;
+    'levels: for (modifiers, level) in LEVELS.iter().zip(LEVEL_MODIFIERS.iter()) {
+        for key in PRINTABLE_KEYS.iter() {
+            keyboard.reset();
+            if probe(keyboard, modifiers, *key).as_ref() == Some(&target) {
+                found = Some((*key, *level));
+                break 'levels;
+            }
+        }
+    }
+    keyboard.reset();
+    found
+}
+
+/// Returns what `key` types with `modifiers` held on the layout behind
+/// `keyboard`, from a clean state. Dead keys give None.
+pub(crate) fn char_for_key<K: KeyboardState>(
+    keyboard: &mut K,
+    key: Key,
+    modifiers: &Modifiers,
+) -> Option<String> {
+    keyboard.reset();
+    let name = probe(keyboard, &modifier_keys(modifiers), key);
+    keyboard.reset();
+    name.filter(|s| !s.is_empty())
+}
+
+/// What `key` types without modifiers, in uppercase like keycaps show it
+/// (unless that changes its length, like ß), or the accent of a dead key.
+/// None for keys that don't type anything printable.
+pub(crate) fn label_for_key<K: KeyboardState>(keyboard: &mut K, key: Key) -> Option<String> {
+    keyboard.reset();
+    let mut name = probe(keyboard, &[], key).filter(|s| !s.is_empty());
+    if name.is_none() && is_dead_key(keyboard, &[], key) {
+        probe(keyboard, &[], key);
+        name = probe(keyboard, &[], Key::Space);
+    }
+    keyboard.reset();
+    let name = name.filter(|s| !s.trim().is_empty() && !s.chars().any(char::is_control))?;
+    let upper = name.to_uppercase();
+    if upper.chars().count() == name.chars().count() {
+        Some(upper)
+    } else {
+        Some(name)
+    }
+}
+
+/// What the current layout types for the keys already translated, by key
+/// code and modifier state, so a key only goes through the OS the first
+/// time it's typed. Keyboards clear it when the layout changes. Only keys
+/// typed without a pending dead key, and that aren't dead keys themselves,
+/// belong here: the others depend on what was typed before.
+#[derive(Debug, Default)]
+pub(crate) struct NameTable {
+    names: HashMap<(u32, u32), Option<String>>,
+}
+
+impl NameTable {
+    /// None if the key wasn't translated yet on this layout.
+    pub(crate) fn get(&self, code: u32, state: u32) -> Option<Option<String>> {
+        self.names.get(&(code, state)).cloned()
+    }
+
+    pub(crate) fn insert(&mut self, code: u32, state: u32, name: Option<String>) {
+        self.names.insert((code, state), name);
+    }
+
+    pub(crate) fn clear(&mut self) {
+        self.names.clear();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Tiny fake layout: qwerty letters, Quote is a dead acute accent.
+    #[derive(Default)]
+    struct FakeKeyboard {
+        shift: bool,
+        dead: bool,
+    }
+
+    impl KeyboardState for FakeKeyboard {
+        fn add(&mut self, event_type: &EventType) -> Option<String> {
+            match event_type {
+                EventType::KeyPress(Key::ShiftLeft) => {
+                    self.shift = true;
+                    None
+                }
+                EventType::KeyRelease(Key::ShiftLeft) => {
+                    self.shift = false;
+                    None
+                }
+                EventType::KeyPress(Key::Quote) => {
+                    self.dead = true;
+                    Some("".to_string())
+                }
+                EventType::KeyPress(key) => {
+                    let base = match key {
+                        Key::KeyA => "a",
+                        Key::KeyE => "e",
+                        Key::Space => " ",
+                        _ => return None,
+                    };
+                    let dead = std::mem::replace(&mut self.dead, false);
+                    let s = match (dead, base) {
+                        (true, "e") => "é".to_string(),
+                        (true, " ") => "´".to_string(),
+                        _ => base.to_string(),
+                    };
+                    if self.shift {
+                        Some(s.to_uppercase())
+                    } else {
+                        Some(s)
+                    }
+                }
+                _ => None,
+            }
+        }
+
+        fn reset(&mut self) {
+            self.shift = false;
+            self.dead = false;
+        }
+    }
+
+    #[test]
+    fn test_sequence_for_char() {
+        let mut keyboard = FakeKeyboard::default();
+        assert_eq!(
+            sequence_for_char(&mut keyboard, 'a'),
+            Some(vec![
+                EventType::KeyPress(Key::KeyA),
+                EventType::KeyRelease(Key::KeyA)
+            ])
+        );
+        assert_eq!(
+            sequence_for_char(&mut keyboard, 'A'),
+            Some(vec![
+                EventType::KeyPress(Key::ShiftLeft),
+                EventType::KeyPress(Key::KeyA),
+                EventType::KeyRelease(Key::KeyA),
+                EventType::KeyRelease(Key::ShiftLeft)
+            ])
+        );
+        assert_eq!(
+            sequence_for_char(&mut keyboard, 'é'),
+            Some(vec![
+                EventType::KeyPress(Key::Quote),
+                EventType::KeyRelease(Key::Quote),
+                EventType::KeyPress(Key::KeyE),
+                EventType::KeyRelease(Key::KeyE)
+            ])
+        );
+        assert_eq!(sequence_for_char(&mut keyboard, '€'), None);
+    }
+
+    #[test]
+    fn test_key_char_mapping() {
+        let mut keyboard = FakeKeyboard::default();
+        let shift = Modifiers {
+            shift: true,
+            ..Default::default()
+        };
+        assert_eq!(
+            key_for_char(&mut keyboard, 'a'),
+            Some((Key::KeyA, Modifiers::default()))
+        );
+        assert_eq!(key_for_char(&mut keyboard, 'E'), Some((Key::KeyE, shift)));
+        // Only reachable through a dead key.
+        assert_eq!(key_for_char(&mut keyboard, 'é'), None);
+
+        assert_eq!(
+            char_for_key(&mut keyboard, Key::KeyE, &shift),
+            Some("E".to_string())
+        );
+        assert_eq!(
+            char_for_key(&mut keyboard, Key::KeyA, &Modifiers::default()),
+            Some("a".to_string())
+        );
+        assert_eq!(
+            char_for_key(&mut keyboard, Key::Quote, &Modifiers::default()),
+            None
+        );
+    }
+
+    #[test]
+    fn test_key_labels() {
+        let mut keyboard = FakeKeyboard::default();
+        assert_eq!(Key::KeyA.label(&mut keyboard), "A");
+        assert_eq!(Key::Quote.label(&mut keyboard), "´");
+        assert_eq!(Key::Space.label(&mut keyboard), "Space");
+        // Types nothing on this layout.
+        assert_eq!(Key::KeyZ.label(&mut keyboard), "KeyZ");
+        assert_eq!(Key::Unknown(300).label(&mut keyboard), "Key 300");
+        #[cfg(target_os = "linux")]
+        assert_eq!(Key::ControlRight.label(&mut keyboard), "Right Ctrl");
+    }
+
+    #[test]
+    fn test_name_table() {
+        let mut table = NameTable::default();
+        assert_eq!(table.get(38, 0), None);
+        table.insert(38, 0, Some("a".to_string()));
+        table.insert(38, 1, Some("A".to_string()));
+        table.insert(37, 0, None);
+        assert_eq!(table.get(38, 0), Some(Some("a".to_string())));
+        assert_eq!(table.get(38, 1), Some(Some("A".to_string())));
+        // Known to type nothing, unlike not translated yet.
+        assert_eq!(table.get(37, 0), Some(None));
+        table.clear();
+        assert_eq!(table.get(38, 0), None);
+    }
+}
@@ -28,6 +28,9 @@
 //! ## OS Caveats:
 //! When using the `listen` function, the following caveats apply:
 //!
+//! `permissions::can_listen`, `can_grab` and `can_simulate` check the ones below
+//! without blocking, and say what's missing.
+//!
 //! ## Mac OS
 //! The process running the blocking `listen` function (loop) needs to be the parent process (no fork before).
 //! The process needs to be granted access to the Accessibility API (ie. if you're running your process
@@ -35,10 +38,20 @@
 //! System Preferences > Security & Privacy > Privacy > Accessibility)
 //! If the process is not granted access to the Accessibility API, MacOS will silently ignore rdev's
 //! `listen` calleback and will not trigger it with events. No error will be generated.
+//! `permissions::query` tells whether access was granted, and `permissions::request` prompts for it.
 //!
 //! ## Linux
 //! The `listen` function uses X11 APIs, and so will not work in Wayland or in the linux kernel virtual console
 //!
+//! With the `evdev` feature (enabled by `wayland`), `listen` reads `/dev/input` directly when
+//! there is no X server at all or when `WAYLAND_DISPLAY` is set and X has no RECORD extension.
+//! This needs to be root or in the `input` group. Without an X server `simulate` then goes
+//! through a virtual `/dev/uinput` device.
+//!
+//! ## FreeBSD
+//! FreeBSD uses the same X11 code as Linux. The `evdev` feature, `grab`, `grab_device` and
+//! `listen_devices` are Linux only (the latter returns `DeviceMonitorError`).
+//!
 //! # Sending some events
 //!
 //! ```no_run
@@ -80,7 +93,7 @@
 //! what key was interpreted by the OS at that time, it will respect the layout.
 //!
 //! ```no_run
-//! # use crate::rdev::EventType;
+//! # use crate::rdev::{EventType, UnicodeInfo};
 //! # use std::time::SystemTime;
 //! /// When events arrive from the system we can add some information
 //! /// time is when the event was received.
@@ -89,13 +102,24 @@
 //!     pub time: SystemTime,
 //!     pub name: Option<String>,
 //!     pub event_type: EventType,
+//!     /// The characters of `name` as code points, and the dead key state.
+//!     pub unicode: Option<UnicodeInfo>,
+//!     /// The platform code of the physical key, 0 for other events.
+//!     pub scan_code: u32,
+//!     /// Mouse moves only, the monitor under the cursor and the movement.
+//!     pub display: Option<usize>,
+//!     pub delta: Option<(f64, f64)>,
+//!     /// Whether NumLock was on, for key events.
+//!     pub numlock: Option<bool>,
 //! }
 //! ```
 //!
+//! `Event` is `#[non_exhaustive]`, build one with `Event::new` so new fields
+//! don't break your code.
+//!
 //! Be careful, Event::name, might be None, but also String::from(""), and might contain
 //! not displayable unicode characters. We send exactly what the OS sends us so do some sanity checking
 //! before using it.
-//! Caveat: Dead keys don't function yet on Linux
 //!
 //! ## EventType
 //!
@@ -151,11 +175,12 @@
 //! We can define a dummy Keyboard, that we will use to detect
 //! what kind of EventType trigger some String. We get the currently used
 //! layout for now !
-//! Caveat : This is layout dependent. If your app needs to support
-//! layout switching don't use this !
-//! Caveat: On Linux, the dead keys mechanism is not implemented.
-//! Caveat: Only shift and dead keys are implemented, Alt+unicode code on windows
-//! won't work.
+//! The layout is refreshed automatically when the user switches layouts,
+//! `listen` and `grab` emit an `EventType::LayoutChanged` when that happens.
+//! Caveat: Only shift, AltGr and dead keys are implemented.
+//! On Windows, Alt+numpad codes give their character when Alt is released.
+//! `Key::label` gives what users call a key on their layout and OS ("⌘", "Right Ctrl", "Ö"),
+//! for showing hotkeys, `to_string` stays the same everywhere.
 //!
 //! ```no_run
 //! use rdev::{Keyboard, EventType, Key, KeyboardState};
@@ -217,32 +242,162 @@
 //!
 //! Event data returned by the `listen` and `grab` functions can be serialized and de-serialized with
 //! Serde if you install this library with the `serialize` feature.
+//! The default representation follows the structs and changes with them, the `schema` module
+//! gives a versioned one that later versions of rdev and other OSs read the same.
+//! For long recordings, the `compact` module gives a much smaller representation.
+//! The `jsonl` module streams events to and from files that later versions of rdev can read,
+//! `binlog` to and from binary files several times smaller.
+//! With the `schemars` feature `Event`, `EventType`, `Key` and `Button` implement `JsonSchema`,
+//! describing the default representation.
+//! The `record` feature adds the `record` module, writing events and the time between them to
+//! a file.
+#[cfg(feature = "bevy")]
+pub mod bevy_adapter;
+#[cfg(feature = "serialize")]
+mod binary;
+#[cfg(feature = "serialize")]
+pub mod binlog;
+mod clicks;
+mod clock;
+#[cfg(feature = "serialize")]
+pub mod compact;
+mod conflicts;
+mod coordinates;
+#[cfg(feature = "csv")]
+pub mod csv;
+mod dispatch;
+mod expand;
+#[cfg(feature = "ffi")]
+pub mod ffi;
+mod hid;
+mod hold;
+mod hotkey;
+mod idle;
+#[cfg(any(feature = "serialize", feature = "ffi", feature = "proto"))]
+mod ids;
+#[cfg(feature = "serialize")]
+pub mod jsonl;
+pub mod keycodes;
+mod keypad;
+mod labels;
+mod layout;
+#[cfg(feature = "mock")]
+pub mod mock;
+mod motion;
+mod names;
+#[cfg(feature = "node")]
+mod node;
+mod pacing;
+pub mod permissions;
+mod pressed;
+mod priority;
+#[cfg(feature = "proto")]
+pub mod proto;
+#[cfg(feature = "python")]
+mod python;
 mod rdev;
+#[cfg(feature = "record")]
+pub mod record;
+mod remap;
+#[cfg(feature = "remote")]
+pub mod remote;
+mod ring;
+#[cfg(feature = "serialize")]
+pub mod schema;
+mod scroll;
+#[cfg(feature = "stats")]
+pub mod stats;
+mod strokes;
+#[cfg(feature = "tauri")]
+pub mod tauri_plugin;
+mod topology;
+mod trace;
+#[cfg(feature = "typescript")]
+mod typescript;
+mod w3c;
+mod watchdog;
+#[cfg(feature = "winit")]
+pub mod winit_adapter;
+pub use crate::clicks::{ClickGesture, GestureDetector};
+pub use crate::clock::{Clock, SystemClock, VirtualClock};
+pub use crate::conflicts::{find_conflicts, Conflict};
+pub use crate::coordinates::CoordinateMode;
+pub use crate::dispatch::{Dispatcher, EventFilter, SubscriptionId};
+pub use crate::expand::Expander;
+pub use crate::hold::HoldKey;
+pub use crate::hotkey::{
+    Hotkey, HotkeyError, HotkeyId, HotkeyManager, KeySequence, SequenceStep, Shortcut,
+    DEFAULT_STEP_TIMEOUT,
+};
+pub use crate::idle::IdleEvent;
+pub use crate::keypad::keypad_navigation_key;
+pub use crate::pacing::Pacing;
+pub use crate::pressed::{release_all_pressed, ReleasePressed};
+pub use crate::priority::HookPriority;
 pub use crate::rdev::{
-    Button, DisplayError, Event, EventType, GrabCallback, GrabError, Key, KeyboardState,
-    ListenError, SimulateError,
+    Backend, Button, ClickSettings, DeviceEvent, DeviceKind, DisplayError, DisplayEvent,
+    DisplayInfo, Event, EventType, GrabCallback, GrabError, InputDevice, Key, KeyboardState,
+    ListenError, LockKey, Modifiers, OsError, ParseKeyError, Pen, ScrollPhase, Settings,
+    SimulateError, UnicodeInfo,
 };
+#[cfg(feature = "gestures")]
+pub use crate::rdev::{Gesture, GesturePhase};
+pub use crate::remap::{Mapping, Remap, Remapper};
+pub use crate::scroll::{simulate_smooth_scroll, smooth_scroll_events, SmoothScroll};
+pub use crate::strokes::{Direction, StrokeGesture, StrokeRecognizer, StrokeSettings};
+#[cfg(feature = "typescript")]
+pub use crate::typescript::typescript_bindings;
+pub use crate::watchdog::{ListenHandle, Watchdog};
+use std::collections::HashSet;
+use std::time::Duration;
 
 #[cfg(target_os = "macos")]
 mod macos;
 #[cfg(target_os = "macos")]
 pub use crate::macos::Keyboard;
 #[cfg(target_os = "macos")]
-use crate::macos::{display_size as _display_size, listen as _listen, simulate as _simulate};
+use crate::macos::{
+    available_layouts as _available_layouts, click_settings as _click_settings,
+    display_size as _display_size, displays as _displays, get_lock_state as _get_lock_state,
+    get_mouse_position as _get_mouse_position, idle_time as _idle_time,
+    keyboard_repeat as _keyboard_repeat, keyboard_state as _keyboard_state, listen as _listen,
+    listen_devices as _listen_devices, listen_display_changes as _listen_display_changes,
+    mouse_state as _mouse_state, raise_thread_priority as _raise_thread_priority,
+    set_lock_state as _set_lock_state, simulate as _simulate,
+    simulate_unicode as _simulate_unicode,
+};
 
-#[cfg(target_os = "linux")]
+#[cfg(any(target_os = "linux", target_os = "freebsd"))]
 mod linux;
-#[cfg(target_os = "linux")]
+#[cfg(any(target_os = "linux", target_os = "freebsd"))]
 pub use crate::linux::Keyboard;
-#[cfg(target_os = "linux")]
-use crate::linux::{display_size as _display_size, listen as _listen, simulate as _simulate};
+#[cfg(any(target_os = "linux", target_os = "freebsd"))]
+use crate::linux::{
+    available_layouts as _available_layouts, click_settings as _click_settings,
+    display_size as _display_size, displays as _displays, get_lock_state as _get_lock_state,
+    get_mouse_position as _get_mouse_position, idle_time as _idle_time,
+    keyboard_repeat as _keyboard_repeat, keyboard_state as _keyboard_state, listen as _listen,
+    listen_devices as _listen_devices, listen_display_changes as _listen_display_changes,
+    mouse_state as _mouse_state, raise_thread_priority as _raise_thread_priority,
+    set_lock_state as _set_lock_state, simulate as _simulate,
+    simulate_unicode as _simulate_unicode,
+};
 
 #[cfg(target_os = "windows")]
 mod windows;
 #[cfg(target_os = "windows")]
 pub use crate::windows::Keyboard;
 #[cfg(target_os = "windows")]
-use crate::windows::{display_size as _display_size, listen as _listen, simulate as _simulate};
+use crate::windows::{
+    available_layouts as _available_layouts, click_settings as _click_settings,
+    display_size as _display_size, displays as _displays, get_lock_state as _get_lock_state,
+    get_mouse_position as _get_mouse_position, idle_time as _idle_time,
+    keyboard_repeat as _keyboard_repeat, keyboard_state as _keyboard_state, listen as _listen,
+    listen_devices as _listen_devices, listen_display_changes as _listen_display_changes,
+    mouse_state as _mouse_state, raise_thread_priority as _raise_thread_priority,
+    set_lock_state as _set_lock_state, simulate as _simulate,
+    simulate_unicode as _simulate_unicode,
+};
 
 /// Listening to global events. Caveat: On MacOS, you require the listen
 /// loop needs to be the primary app (no fork before) and need to have accessibility
@@ -269,9 +424,155 @@ pub fn listen<T>(callback: T) -> Result<(), ListenError>
 where
     T: FnMut(Event) + 'static,
 {
+    let _priority = priority::raise();
     _listen(callback)
 }
 
+/// Listening to input devices being plugged in and removed. Devices already
+/// connected are reported as `Connected` first. Like `listen` this blocks.
+///
+/// ```no_run
+/// use rdev::{listen_devices, DeviceEvent, DeviceKind};
+///
+/// fn main() {
+///     let result = listen_devices(|event| match event {
+///         DeviceEvent::Connected(device) if device.kind == DeviceKind::Keyboard => {
+///             println!("Keyboard plugged in: {}", device.name)
+///         }
+///         DeviceEvent::Disconnected(device) => println!("{} removed", device.name),
+///         _ => (),
+///     });
+///     if let Err(error) = result {
+///         println!("Error: {:?}", error)
+///     }
+/// }
+/// ```
+pub fn listen_devices<T>(callback: T) -> Result<(), ListenError>
+where
+    T: FnMut(DeviceEvent) + 'static,
+{
+    _listen_devices(callback)
+}
+
+/// Like `listen`, with a chosen way of capturing events. Returns
+/// `ListenError::UnsupportedBackend` if the platform doesn't have the
+/// backend of `settings`.
+///
+/// ```no_run
+/// use rdev::{listen_with, Backend, Settings};
+///
+/// fn main() {
+///     let settings = Settings {
+///         backend: Some(Backend::RawInput),
+///         ..Default::default()
+///     };
+///     if let Err(error) = listen_with(settings, |event| println!("{:?}", event)) {
+///         println!("Error: {:?}", error)
+///     }
+/// }
+/// ```
+pub fn listen_with<T>(settings: Settings, callback: T) -> Result<(), ListenError>
+where
+    T: FnMut(Event) + 'static,
+{
+    if settings.buffer.is_some() || settings.mouse_move_max_hz.is_some() {
+        let capacity = settings.buffer.unwrap_or(crate::ring::DEFAULT_CAPACITY);
+        let max_hz = settings.mouse_move_max_hz;
+        let settings = Settings {
+            buffer: None,
+            mouse_move_max_hz: None,
+            hook_thread: false,
+            ..settings
+        };
+        return crate::ring::listen_buffered(settings, capacity, max_hz, callback);
+    }
+    let _priority = priority::raise();
+    let _names = names::set_wanted(settings.names);
+    let _keypad = keypad::set_physical(settings.physical_keypad);
+    let _span = trace::listening(settings.backend);
+    #[cfg(feature = "tracing")]
+    let callback = trace::timed(callback);
+    let result = match settings.backend {
+        #[cfg(target_os = "windows")]
+        None | Some(Backend::Hooks) if settings.hook_thread => {
+            crate::windows::listen_on_hook_thread(callback)
+        }
+        #[cfg(not(target_os = "macos"))]
+        None => _listen(callback),
+        #[cfg(any(target_os = "linux", target_os = "freebsd"))]
+        Some(Backend::X11) => crate::linux::listen_x11(callback),
+        #[cfg(any(target_os = "linux", target_os = "freebsd"))]
+        Some(Backend::XInput2) => crate::linux::listen_xinput(callback),
+        #[cfg(all(target_os = "linux", feature = "evdev"))]
+        Some(Backend::Evdev) => crate::linux::listen_evdev(callback),
+        #[cfg(target_os = "windows")]
+        Some(Backend::Hooks) => _listen(callback),
+        #[cfg(target_os = "windows")]
+        Some(Backend::RawInput) => crate::windows::listen_raw_input(callback),
+        #[cfg(target_os = "macos")]
+        None | Some(Backend::EventTap) => crate::macos::listen_tap(settings.listen_only, callback),
+        #[cfg(target_os = "macos")]
+        Some(Backend::IOHid) => crate::macos::listen_iohid(callback),
+        #[cfg(feature = "mock")]
+        Some(Backend::Mock) => mock::listen(callback),
+        #[cfg(feature = "mock")]
+        Some(Backend::Loopback) => mock::listen_loopback(callback),
+        _ => {
+            drop(callback);
+            Err(ListenError::UnsupportedBackend)
+        }
+    };
+    trace::finished(&result);
+    result
+}
+
+/// MacOS, whether an application turned Secure Input on (password fields,
+/// Terminal's Secure Keyboard Entry). Keyboard events don't reach `listen`
+/// while it is, `EventType::SecureInput` tells when it changes. Always false
+/// on other platforms.
+///
+/// ```no_run
+/// use rdev::is_secure_input_active;
+///
+/// if is_secure_input_active() {
+///     println!("Another application is hiding the keyboard, close its password field");
+/// }
+/// ```
+pub fn is_secure_input_active() -> bool {
+    #[cfg(target_os = "macos")]
+    {
+        crate::macos::is_secure_input_active()
+    }
+    #[cfg(not(target_os = "macos"))]
+    {
+        false
+    }
+}
+
+/// The backend `listen` and `simulate` use. On Linux it depends on the
+/// session: `Evdev` without X or without RECORD under Wayland (with the
+/// `evdev` feature), `X11` otherwise.
+///
+/// ```no_run
+/// use rdev::default_backend;
+///
+/// println!("Listening with {:?}", default_backend());
+/// ```
+pub fn default_backend() -> Backend {
+    #[cfg(any(target_os = "linux", target_os = "freebsd"))]
+    {
+        crate::linux::default_backend()
+    }
+    #[cfg(target_os = "windows")]
+    {
+        Backend::Hooks
+    }
+    #[cfg(target_os = "macos")]
+    {
+        Backend::EventTap
+    }
+}
+
 /// Sending some events
 ///
 /// ```no_run
@@ -305,7 +606,232 @@ where
 /// }
 /// ```
 pub fn simulate(event_type: &EventType) -> Result<(), SimulateError> {
-    _simulate(event_type)
+    let sent = match coordinates::native_event(event_type) {
+        Some(native) => _simulate(&native),
+        None => _simulate(event_type),
+    };
+    sent.map(|()| pressed::sent(event_type, None))
+}
+
+/// Like `simulate`, with a chosen way of sending events. Windows backends
+/// both send with SendInput, and macOS only simulates with `EventTap`.
+///
+/// ```no_run
+/// use rdev::{simulate_with, Backend, EventType, Key, Settings};
+///
+/// let settings = Settings {
+///     backend: Some(Backend::X11),
+///     ..Default::default()
+/// };
+/// simulate_with(settings, &EventType::KeyPress(Key::KeyS)).ok();
+/// ```
+pub fn simulate_with(settings: Settings, event_type: &EventType) -> Result<(), SimulateError> {
+    // What `release_all_pressed` releases with, None for `_simulate`.
+    let (send, release): (pressed::Sender, Option<pressed::Sender>) = match settings.backend {
+        None => (_simulate, None),
+        #[cfg(any(target_os = "linux", target_os = "freebsd"))]
+        Some(Backend::X11) | Some(Backend::XInput2) => {
+            (crate::linux::simulate_x11, Some(crate::linux::simulate_x11))
+        }
+        #[cfg(all(target_os = "linux", feature = "evdev"))]
+        Some(Backend::Evdev) => (
+            crate::linux::simulate_uinput,
+            Some(crate::linux::simulate_uinput),
+        ),
+        #[cfg(target_os = "windows")]
+        Some(Backend::Hooks) | Some(Backend::RawInput) => (_simulate, None),
+        #[cfg(target_os = "macos")]
+        Some(Backend::EventTap) => (_simulate, None),
+        // Nothing reaches the system, there is nothing to release there.
+        #[cfg(feature = "mock")]
+        Some(Backend::Mock) => return mock::simulate(event_type),
+        #[cfg(feature = "mock")]
+        Some(Backend::Loopback) => return mock::simulate_loopback(event_type),
+        _ => return Err(SimulateError),
+    };
+    let sent = match coordinates::native_event(event_type) {
+        Some(native) => send(&native),
+        None => send(event_type),
+    };
+    sent.map(|()| pressed::sent(event_type, release))
+}
+
+/// Types a character the way a user of the current layout would: we look
+/// for the key (plus Shift/AltGr, or a dead key first) producing it and send
+/// those events. Characters that the layout can't produce are injected
+/// directly as unicode.
+///
+/// ```no_run
+/// use rdev::simulate_char_via_layout;
+///
+/// simulate_char_via_layout('é').unwrap();
+/// ```
+pub fn simulate_char_via_layout(c: char) -> Result<(), SimulateError> {
+    let mut keyboard = Keyboard::new();
+    let mut pacer = pacing::Pacer::new(Pacing::default());
+    send_char(keyboard.as_mut(), c, &mut pacer)
+}
+
+fn send_char(
+    keyboard: Option<&mut Keyboard>,
+    c: char,
+    pacer: &mut pacing::Pacer,
+) -> Result<(), SimulateError> {
+    match keyboard.and_then(|keyboard| layout::sequence_for_char(keyboard, c)) {
+        Some(sequence) => {
+            for event_type in sequence.iter() {
+                pacer.wait();
+                _simulate(event_type)?;
+                pressed::sent(event_type, None);
+            }
+            Ok(())
+        }
+        None => {
+            pacer.wait();
+            _simulate_unicode(c)
+        }
+    }
+}
+
+/// Finds the key (and the Shift/AltGr modifiers) that types `c` on the
+/// current layout. Characters only reachable through a dead key give None.
+///
+/// ```no_run
+/// use rdev::{key_from_char, Key, Modifiers};
+///
+/// // On a qwerty layout
+/// let (key, modifiers) = key_from_char(':').unwrap();
+/// assert_eq!(key, Key::SemiColon);
+/// assert!(modifiers.shift);
+/// ```
+pub fn key_from_char(c: char) -> Option<(Key, Modifiers)> {
+    let mut keyboard = Keyboard::new()?;
+    layout::key_for_char(&mut keyboard, c)
+}
+
+/// Returns what `key` types on the current layout with `modifiers` held,
+/// None for dead keys and keys that don't type anything.
+///
+/// ```no_run
+/// use rdev::{char_from_key, Key, Modifiers};
+///
+/// let shift = Modifiers {
+///     shift: true,
+///     ..Default::default()
+/// };
+/// // On a qwerty layout
+/// assert_eq!(char_from_key(Key::SemiColon, shift), Some(":".to_string()));
+/// ```
+pub fn char_from_key(key: Key, modifiers: Modifiers) -> Option<String> {
+    let mut keyboard = Keyboard::new()?;
+    layout::char_for_key(&mut keyboard, key, &modifiers)
+}
+
+/// Sends a whole sequence of events, respecting the pacing set with
+/// `set_simulate_pacing`. Without pacing, Windows sends them all with one
+/// SendInput call so the input of other processes can't come in between.
+///
+/// ```no_run
+/// use rdev::{simulate_all, EventType, Key};
+///
+/// simulate_all(&[
+///     EventType::KeyPress(Key::KeyS),
+///     EventType::KeyRelease(Key::KeyS),
+/// ])
+/// .unwrap();
+/// ```
+pub fn simulate_all(event_types: &[EventType]) -> Result<(), SimulateError> {
+    let pacing = pacing::current_pacing();
+    if pacing.gap() == Duration::from_secs(0) {
+        return simulate_batch(event_types);
+    }
+    let mut pacer = pacing::Pacer::new(pacing);
+    for event_type in event_types {
+        pacer.wait();
+        simulate(event_type)?;
+    }
+    Ok(())
+}
+
+/// `simulate` for each event without pacing, in one go on Windows.
+#[cfg(target_os = "windows")]
+pub(crate) fn simulate_batch(event_types: &[EventType]) -> Result<(), SimulateError> {
+    let mut batch = crate::windows::Batch::default();
+    for event_type in event_types {
+        batch.push(&coordinates::native_event(event_type).unwrap_or(*event_type))?;
+    }
+    batch.send().map(|()| {
+        event_types
+            .iter()
+            .for_each(|event_type| pressed::sent(event_type, None))
+    })
+}
+
+#[cfg(not(target_os = "windows"))]
+pub(crate) fn simulate_batch(event_types: &[EventType]) -> Result<(), SimulateError> {
+    event_types.iter().try_for_each(simulate)
+}
+
+/// Types some text with the current layout (see `simulate_char_via_layout`),
+/// respecting the pacing set with `set_simulate_pacing`. Like with
+/// `simulate_all`, unpaced text is sent in one go on Windows.
+///
+/// ```no_run
+/// use rdev::simulate_text;
+///
+/// simulate_text("Hello world !").unwrap();
+/// ```
+pub fn simulate_text(text: &str) -> Result<(), SimulateError> {
+    let mut keyboard = Keyboard::new();
+    let pacing = pacing::current_pacing();
+    #[cfg(target_os = "windows")]
+    if pacing.gap() == Duration::from_secs(0) {
+        let mut batch = crate::windows::Batch::default();
+        let mut sent = vec![];
+        for c in text.chars() {
+            match keyboard
+                .as_mut()
+                .and_then(|keyboard| layout::sequence_for_char(keyboard, c))
+            {
+                Some(sequence) => {
+                    for event_type in sequence.iter() {
+                        batch.push(event_type)?;
+                        sent.push(*event_type);
+                    }
+                }
+                None => batch.push_unicode(c),
+            }
+        }
+        return batch.send().map(|()| {
+            sent.iter()
+                .for_each(|event_type| pressed::sent(event_type, None))
+        });
+    }
+    let mut pacer = pacing::Pacer::new(pacing);
+    for c in text.chars() {
+        send_char(keyboard.as_mut(), c, &mut pacer)?;
+    }
+    Ok(())
+}
+
+/// Sets how fast `simulate_all` and `simulate_text` send events, for every
+/// call that follows.
+pub fn set_simulate_pacing(pacing: Pacing) {
+    pacing::set_pacing(pacing)
+}
+
+/// Sets the priority of the threads receiving events from the OS, for the
+/// `listen`, `listen_with` and `grab` calls that follow. They keep it until
+/// they return. Raising it keeps a remapper grabbing events responsive
+/// while the system is busy, at the expense of everything else.
+///
+/// ```no_run
+/// use rdev::{set_hook_priority, HookPriority};
+///
+/// set_hook_priority(HookPriority::TimeCritical);
+/// ```
+pub fn set_hook_priority(priority: HookPriority) {
+    priority::set_priority(priority)
 }
 
 /// Returns the size in pixels of the main screen.
@@ -318,7 +844,314 @@ pub fn simulate(event_type: &EventType) -> Result<(), SimulateError> {
 /// println!("My screen size : {:?}x{:?}", w, h);
 /// ```
 pub fn display_size() -> Result<(u64, u64), DisplayError> {
-    _display_size()
+    coordinates::display_size()
+}
+
+/// Returns every monitor, in the order used by the `display` of
+/// `MouseMove` events.
+///
+/// ```no_run
+/// use rdev::displays;
+///
+/// for display in displays().unwrap() {
+///     println!(
+///         "{}x{} at ({}, {}), primary: {}",
+///         display.width, display.height, display.x, display.y, display.is_primary
+///     );
+/// }
+/// ```
+pub fn displays() -> Result<Vec<DisplayInfo>, DisplayError> {
+    coordinates::displays()
+}
+
+/// Sets the unit of positions in events, `simulate`, `display_size`,
+/// `displays` and `get_mouse_position`. Without it positions are what the
+/// OS gives: points on macOS, and on Windows pixels scaled or not depending
+/// on the DPI awareness of the process. Monitors keep their origin, positions
+/// are scaled around it with the `scale_factor` of the monitor.
+/// Caveat: On Windows this makes the whole process DPI aware, which changes
+/// how its own windows are scaled, call it before creating any.
+///
+/// ```no_run
+/// use rdev::{display_size, set_coordinate_mode, CoordinateMode};
+///
+/// set_coordinate_mode(CoordinateMode::Physical);
+/// let (w, h) = display_size().unwrap();
+/// println!("My screen has {}x{} pixels", w, h);
+/// ```
+pub fn set_coordinate_mode(mode: CoordinateMode) {
+    #[cfg(target_os = "windows")]
+    crate::windows::enable_dpi_awareness();
+    coordinates::set_mode(mode);
+}
+
+/// Linux, the X server every function opens from this thread: `listen`,
+/// `grab`, `simulate` and the display queries. None goes back to $DISPLAY.
+/// It's per thread, so listening to several servers (multi-seat, forwarded
+/// displays) is a thread for each. `DisplayError::ConversionError` if the
+/// name has a NUL byte.
+///
+/// ```no_run
+/// use rdev::{listen, set_x11_display};
+/// use std::thread;
+///
+/// for name in [":0", ":1"].iter() {
+///     thread::spawn(move || {
+///         set_x11_display(Some(name)).unwrap();
+///         listen(move |event| println!("{}: {:?}", name, event)).ok();
+///     });
+/// }
+/// ```
+#[cfg(any(target_os = "linux", target_os = "freebsd"))]
+pub fn set_x11_display(name: Option<&str>) -> Result<(), DisplayError> {
+    crate::linux::set_display_name(name)
+}
+
+/// Returns the keys currently held down, as reported by the OS (not
+/// reconstructed from events, so it works without `listen`).
+///
+/// ```no_run
+/// use rdev::{keyboard_state, Key};
+///
+/// let keys = keyboard_state().unwrap();
+/// if keys.contains(&Key::ShiftLeft) {
+///     println!("Shift is down");
+/// }
+/// ```
+pub fn keyboard_state() -> Result<HashSet<Key>, DisplayError> {
+    _keyboard_state()
+}
+
+/// Returns the mouse buttons currently held down, as reported by the OS.
+///
+/// ```no_run
+/// use rdev::{mouse_state, Button};
+///
+/// let buttons = mouse_state().unwrap();
+/// println!("Left button down: {}", buttons.contains(&Button::Left));
+/// ```
+pub fn mouse_state() -> Result<HashSet<Button>, DisplayError> {
+    _mouse_state()
+}
+
+/// Returns the current position of the mouse, in the coordinates of
+/// `MouseMove` events, without having to listen.
+///
+/// ```no_run
+/// use rdev::get_mouse_position;
+///
+/// let (x, y) = get_mouse_position().unwrap();
+/// println!("Mouse at {}, {}", x, y);
+/// ```
+pub fn get_mouse_position() -> Result<(f64, f64), DisplayError> {
+    coordinates::mouse_position()
+}
+
+/// Returns the monitor containing the point, in the coordinates of
+/// `MouseMove` events. None when the point is outside every monitor
+/// or they can't be queried.
+///
+/// ```no_run
+/// use rdev::display_at;
+///
+/// if let Some(display) = display_at(1920.0, 10.0) {
+///     println!("{}x{}", display.width, display.height);
+/// }
+/// ```
+pub fn display_at(x: f64, y: f64) -> Option<DisplayInfo> {
+    let displays = displays().ok()?;
+    coordinates::display_at(&displays, x, y).cloned()
+}
+
+/// Returns the monitor the mouse is on, see `display_at`.
+///
+/// ```no_run
+/// use rdev::display_under_cursor;
+///
+/// if let Some(display) = display_under_cursor() {
+///     println!("Mouse on a {}x{} monitor", display.width, display.height);
+/// }
+/// ```
+pub fn display_under_cursor() -> Option<DisplayInfo> {
+    let (x, y) = get_mouse_position().ok()?;
+    display_at(x, y)
+}
+
+/// Calls `callback` when a monitor is added, removed or changes resolution,
+/// and when the primary one changes, so cached geometry can be refreshed
+/// instead of polling `displays()`. Like `listen` this blocks.
+///
+/// ```no_run
+/// use rdev::{listen_displays, DisplayEvent};
+///
+/// fn main() {
+///     let result = listen_displays(|event| match event {
+///         DisplayEvent::Added(display) => println!("New {}x{}", display.width, display.height),
+///         DisplayEvent::Removed(_) => println!("Monitor removed"),
+///         _ => (),
+///     });
+///     if let Err(error) = result {
+///         println!("Error: {:?}", error)
+///     }
+/// }
+/// ```
+pub fn listen_displays<T>(mut callback: T) -> Result<(), ListenError>
+where
+    T: FnMut(DisplayEvent) + 'static,
+{
+    let mut topology = topology::Topology::new(displays().unwrap_or_default());
+    _listen_display_changes(move || {
+        if let Ok(displays) = displays() {
+            for event in topology.update(displays) {
+                callback(event);
+            }
+        }
+    })
+}
+
+/// Creates the keyboard state of the current platform behind a trait
+/// object, handy to store it in structs that are sent across threads.
+///
+/// ```no_run
+/// use rdev::{new_keyboard_state, EventType, Key};
+/// use std::thread;
+///
+/// let mut keyboard = new_keyboard_state().unwrap();
+/// thread::spawn(move || {
+///     let name = keyboard.add(&EventType::KeyPress(Key::KeyS));
+///     println!("{:?}", name);
+/// });
+/// ```
+pub fn new_keyboard_state() -> Option<Box<dyn KeyboardState + Send>> {
+    Some(Box::new(Keyboard::new()?))
+}
+
+/// Lists the layouts that can be given to `Keyboard::with_layout`: HKLs in
+/// hexadecimal on Windows, input source ids on macOS and the layouts
+/// configured in X on Linux.
+///
+/// ```no_run
+/// use rdev::{available_layouts, EventType, Key, Keyboard, KeyboardState};
+///
+/// for layout in available_layouts().unwrap() {
+///     let mut keyboard = Keyboard::with_layout(&layout).unwrap();
+///     let name = keyboard.add(&EventType::KeyPress(Key::KeyQ));
+///     println!("{}: {:?}", layout, name);
+/// }
+/// ```
+pub fn available_layouts() -> Result<Vec<String>, DisplayError> {
+    _available_layouts()
+}
+
+/// Returns the time since the last keyboard or mouse input, in the whole
+/// session (not only since `listen` started).
+///
+/// ```no_run
+/// use rdev::idle_time;
+///
+/// println!("Idle for {:?}", idle_time().unwrap());
+/// ```
+pub fn idle_time() -> Result<Duration, DisplayError> {
+    _idle_time()
+}
+
+/// Calls `callback` with `IdleEvent::Idle` once no input was seen for
+/// `threshold`, and with `IdleEvent::Resumed` when input comes back.
+/// This blocks, polling the OS idle time (no hook needed), and only returns
+/// if the idle time can't be read.
+///
+/// ```no_run
+/// use rdev::{on_idle, IdleEvent};
+/// use std::time::Duration;
+///
+/// on_idle(Duration::from_secs(300), |event| match event {
+///     IdleEvent::Idle => println!("Away"),
+///     IdleEvent::Resumed => println!("Back"),
+/// })
+/// .unwrap();
+/// ```
+pub fn on_idle<T>(threshold: Duration, callback: T) -> Result<(), DisplayError>
+where
+    T: FnMut(IdleEvent),
+{
+    idle::watch(threshold, _idle_time, callback)
+}
+
+/// Returns the double click and drag settings of the OS, used by
+/// `GestureDetector::new`. On Linux they're the XSETTINGS of the desktop,
+/// what GTK and Qt use. `ClickSettings::default()` fills in those missing:
+/// all of them without a desktop or a display, the drag threshold on macOS.
+///
+/// ```no_run
+/// use rdev::click_settings;
+///
+/// println!("Double click within {:?}", click_settings().double_click_interval);
+/// ```
+pub fn click_settings() -> ClickSettings {
+    _click_settings().unwrap_or_default()
+}
+
+/// Returns the longest time between the presses of a double click the user
+/// set, `click_settings().double_click_interval`. On Linux the default when
+/// the desktop doesn't say, `DisplayError::NoDisplay` without a display.
+///
+/// ```no_run
+/// use rdev::system_double_click_time;
+///
+/// println!("Double click within {:?}", system_double_click_time().unwrap());
+/// ```
+pub fn system_double_click_time() -> Result<Duration, DisplayError> {
+    _click_settings().map(|settings| settings.double_click_interval)
+}
+
+/// Returns how long a key is held before it repeats, as the user set it.
+/// On Linux this is the X server's, which desktops set from theirs.
+///
+/// ```no_run
+/// use rdev::keyboard_repeat_delay;
+///
+/// println!("Keys repeat after {:?}", keyboard_repeat_delay().unwrap());
+/// ```
+pub fn keyboard_repeat_delay() -> Result<Duration, DisplayError> {
+    _keyboard_repeat().map(|(delay, _)| delay)
+}
+
+/// Returns how many times per second a held key repeats after
+/// `keyboard_repeat_delay`, as the user set it, 0 when keys don't repeat.
+///
+/// ```no_run
+/// use rdev::keyboard_repeat_rate;
+///
+/// println!("Keys repeat {} times per second", keyboard_repeat_rate().unwrap());
+/// ```
+pub fn keyboard_repeat_rate() -> Result<f64, DisplayError> {
+    _keyboard_repeat().map(|(_, rate)| rate)
+}
+
+/// Returns whether CapsLock, NumLock or ScrollLock is currently on.
+///
+/// ```no_run
+/// use rdev::{get_lock_state, LockKey};
+///
+/// if get_lock_state(LockKey::CapsLock).unwrap() {
+///     println!("Caps lock is on");
+/// }
+/// ```
+pub fn get_lock_state(lock: LockKey) -> Result<bool, DisplayError> {
+    _get_lock_state(lock)
+}
+
+/// Turns a lock on or off. Nothing is sent if it's already in the requested
+/// state, so calling it twice is harmless (unlike simulating the key).
+/// Caveat: macOS has no NumLock and ScrollLock, turning them on fails.
+///
+/// ```no_run
+/// use rdev::{set_lock_state, LockKey};
+///
+/// set_lock_state(LockKey::NumLock, true).unwrap();
+/// ```
+pub fn set_lock_state(lock: LockKey, on: bool) -> Result<(), SimulateError> {
+    _set_lock_state(lock, on)
 }
 
 #[cfg(feature = "unstable_grab")]
@@ -338,6 +1171,7 @@ pub use crate::windows::grab as _grab;
 /// loop needs to be the primary app (no fork before) and need to have accessibility
 /// settings enabled.
 /// On Linux, you need rw access to evdev devices in /etc/input/ (usually group membership in `input` group is enough)
+/// Not available on FreeBSD.
 ///
 /// ```no_run
 /// use rdev::{grab, Event, EventType, Key};
@@ -357,17 +1191,172 @@ pub use crate::windows::grab as _grab;
 /// }
 /// ```
 #[cfg(feature = "unstable_grab")]
+#[cfg(not(target_os = "freebsd"))]
 pub fn grab<T>(callback: T) -> Result<(), GrabError>
 where
     T: Fn(Event) -> Option<Event> + 'static,
 {
-    _grab(callback)
+    let _priority = priority::raise();
+    let _release = ReleasePressed;
+    let _span = trace::grabbing();
+    let result = _grab(callback);
+    trace::finished(&result);
+    result
+}
+
+/// Linux with the `evdev` feature, grabs the devices matching `filter`
+/// (EVIOCGRAB): their events only reach `callback`, and what it returns is
+/// sent through a uinput device. Every other keyboard and mouse is left
+/// alone. Keys held down are waited for before grabbing, autorepeat isn't
+/// reported since the system repeats the forwarded keys. Moves are
+/// forwarded by their `delta`. Returns when the devices are all unplugged,
+/// `GrabError::EvdevError` if none could be grabbed.
+/// Needs read access to /dev/input and write access to /dev/uinput.
+///
+/// ```no_run
+/// use rdev::{grab_device, EventType, Key};
+///
+/// // Caps Lock as Escape, on one keyboard only.
+/// let result = grab_device(
+///     |device| device.vendor_id == 0x046d && device.product_id == 0xc31c,
+///     |mut event| {
+///         event.event_type = match event.event_type {
+///             EventType::KeyPress(Key::CapsLock) => EventType::KeyPress(Key::Escape),
+///             EventType::KeyRelease(Key::CapsLock) => EventType::KeyRelease(Key::Escape),
+///             other => other,
+///         };
+///         Some(event)
+///     },
+/// );
+/// if let Err(error) = result {
+///     println!("Error: {:?}", error)
+/// }
+/// ```
+#[cfg(all(target_os = "linux", feature = "evdev"))]
+pub fn grab_device<F, T>(filter: F, callback: T) -> Result<(), GrabError>
+where
+    F: Fn(&InputDevice) -> bool,
+    T: FnMut(Event) -> Option<Event> + 'static,
+{
+    let _priority = priority::raise();
+    let _release = ReleasePressed;
+    crate::linux::grab_device(filter, callback)
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
 
+    #[test]
+    fn test_keyboard_is_send() {
+        fn assert_send<T: Send>() {}
+        assert_send::<Keyboard>();
+        assert_send::<Box<dyn KeyboardState + Send>>();
+    }
+
+    #[test]
+    fn test_errors() {
+        use std::error::Error;
+        let error: Box<dyn Error> = Box::new(ListenError::KeyHookError(5));
+        assert_eq!(
+            error.to_string(),
+            "Could not install the keyboard hook (error 5)"
+        );
+        let io = std::io::Error::from(std::io::ErrorKind::PermissionDenied);
+        let error = GrabError::IoError(io);
+        let source = error.source().unwrap();
+        assert_eq!(
+            source.to_string(),
+            std::io::Error::from(std::io::ErrorKind::PermissionDenied).to_string()
+        );
+        let error = ListenError::EvdevError(OsError::new("open", 13));
+        assert_eq!(
+            error.to_string(),
+            "Could not read any device in /dev/input (open failed with error 13), add the user \
+             to the `input` group and log in again"
+        );
+        let source = error.source().unwrap();
+        assert_eq!(source.to_string(), "open failed with error 13");
+    }
+
+    #[test]
+    fn test_event_type_strings() {
+        assert_eq!(
+            EventType::MouseMove { x: 100.5, y: 200.0 }.to_string(),
+            "MouseMove(100.5,200.0)"
+        );
+        assert_eq!(
+            "ButtonPress(Unknown(8))".parse(),
+            Ok(EventType::ButtonPress(Button::Unknown(8)))
+        );
+        let event_types = [
+            EventType::KeyPress(Key::KeyA),
+            EventType::KeyRelease(Key::Unknown(300)),
+            EventType::ButtonRelease(Button::Left),
+            EventType::MouseMove { x: 1.0, y: -2.25 },
+            EventType::Wheel {
+                delta_x: 0,
+                delta_y: -1,
+            },
+            EventType::Scroll {
+                delta_x: 0.5,
+                delta_y: 2.0,
+                phase: ScrollPhase::End,
+                is_momentum: true,
+            },
+            EventType::LayoutChanged,
+            EventType::SecureInput(false),
+            EventType::TouchUp {
+                id: 7,
+                x: 0.25,
+                y: 0.75,
+                pressure: 1.0,
+            },
+            EventType::PenMove(Pen {
+                eraser: true,
+                ..Default::default()
+            }),
+        ];
+        for event_type in &event_types {
+            assert_eq!(event_type.to_string().parse(), Ok(*event_type));
+        }
+        assert_eq!("MouseMove(1.0)".parse::<EventType>(), Err(ParseKeyError));
+        assert_eq!("KeyPress(KeyA".parse::<EventType>(), Err(ParseKeyError));
+    }
+
+    #[test]
+    #[cfg(feature = "serialize")]
+    fn test_event_time() {
+        let event = Event {
+            time: std::time::UNIX_EPOCH + Duration::from_millis(1_700_000_000_123),
+            name: None,
+            event_type: EventType::KeyPress(Key::KeyA),
+            unicode: None,
+            scan_code: 0,
+            display: None,
+            delta: None,
+            numlock: None,
+        };
+        let serialized = serde_json::to_string(&event).unwrap();
+        assert!(serialized.starts_with(r#"{"time":1700000000123.0,"#));
+        let read: Event = serde_json::from_str(&serialized).unwrap();
+        assert_eq!(read.time, event.time);
+        // As SystemTime serializes.
+        let older = serialized.replace(
+            "1700000000123.0",
+            r#"{"secs_since_epoch":1700000000,"nanos_since_epoch":123000000}"#,
+        );
+        assert_eq!(serde_json::from_str::<Event>(&older).unwrap(), event);
+    }
+
+    #[test]
+    #[cfg(feature = "schemars")]
+    fn test_json_schema() {
+        let schema = serde_json::to_value(schemars::schema_for!(Event)).unwrap();
+        assert_eq!(schema["properties"]["time"]["type"], "number");
+        assert!(schema["definitions"]["Key"].is_object());
+    }
+
     #[test]
     fn test_keyboard_state() {
         // S
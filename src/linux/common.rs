@@ -1,17 +1,86 @@
 use crate::linux::keyboard::Keyboard;
 use crate::linux::keycodes::key_from_code;
-use crate::rdev::{Button, Event, EventType, KeyboardState};
+use crate::motion::Motion;
+use crate::rdev::{
+    Button, DisplayError, DisplayInfo, Event, EventType, KeyboardState, UnicodeInfo,
+};
+use std::cell::RefCell;
+use std::collections::HashMap;
 use std::convert::TryInto;
-use std::os::raw::{c_int, c_uchar, c_uint};
-use std::ptr::null;
-use std::time::SystemTime;
-use x11::xlib;
+use std::ffi::CString;
+use std::mem::zeroed;
+use std::os::raw::{c_char, c_int, c_uchar, c_uint, c_void};
+use std::ptr::{null, null_mut};
+use std::sync::atomic::{AtomicU8, Ordering};
+use std::time::{Duration, SystemTime};
+use x11::{xlib, xrandr, xss};
 
 pub const TRUE: c_int = 1;
 pub const FALSE: c_int = 0;
 
+thread_local! {
+    static DISPLAY_NAME: RefCell<Option<CString>> = const { RefCell::new(None) };
+}
+
+pub fn set_display_name(name: Option<&str>) -> Result<(), DisplayError> {
+    let name = match name {
+        Some(name) => Some(CString::new(name).map_err(|_| DisplayError::ConversionError)?),
+        None => None,
+    };
+    DISPLAY_NAME.with(|current| *current.borrow_mut() = name);
+    Ok(())
+}
+
+/// Opens the display set for this thread, $DISPLAY otherwise.
+pub fn open_display() -> *mut xlib::Display {
+    DISPLAY_NAME.with(|name| match &*name.borrow() {
+        Some(name) => unsafe { xlib::XOpenDisplay(name.as_ptr()) },
+        None => unsafe { xlib::XOpenDisplay(null()) },
+    })
+}
+
+/// XkbUseCoreKbd, the x11 crate lacks it.
+const XKB_USE_CORE_KBD: c_uint = 0x0100;
+
+/// The code of the last X error since `catch_x_errors`.
+static X_ERROR: AtomicU8 = AtomicU8::new(0);
+
+pub type XErrorHandler =
+    Option<unsafe extern "C" fn(*mut xlib::Display, *mut xlib::XErrorEvent) -> c_int>;
+
+unsafe extern "C" fn keep_x_error(
+    _display: *mut xlib::Display,
+    event: *mut xlib::XErrorEvent,
+) -> c_int {
+    X_ERROR.store((*event).error_code, Ordering::SeqCst);
+    0
+}
+
+/// Sets the previous X error handler back on drop.
+pub struct CaughtXErrors(pub XErrorHandler);
+
+impl Drop for CaughtXErrors {
+    fn drop(&mut self) {
+        unsafe { xlib::XSetErrorHandler(self.0) };
+    }
+}
+
+/// Keeps the code of X errors for `last_x_error` rather than exiting, the
+/// default, until the guard is dropped. The handler is for the whole
+/// process, so only around calls we need the error of.
+pub unsafe fn catch_x_errors() -> CaughtXErrors {
+    X_ERROR.store(0, Ordering::SeqCst);
+    CaughtXErrors(xlib::XSetErrorHandler(Some(keep_x_error)))
+}
+
+/// 0 without any error.
+pub fn last_x_error() -> i64 {
+    X_ERROR.load(Ordering::SeqCst).into()
+}
+
 // A global for the callbacks.
 pub static mut KEYBOARD: Option<Keyboard> = None;
+pub static mut MOTION: Motion = Motion::new();
 
 pub fn convert_event(code: c_uchar, type_: c_int, x: f64, y: f64) -> Option<EventType> {
     match type_ {
@@ -43,6 +112,8 @@ pub fn convert_event(code: c_uchar, type_: c_int, x: f64, y: f64) -> Option<Even
                 delta_y: 0,
                 delta_x: 1,
             }),
+            8 => Some(EventType::ButtonPress(Button::Back)),
+            9 => Some(EventType::ButtonPress(Button::Forward)),
             code => Some(EventType::ButtonPress(Button::Unknown(code))),
         },
         xlib::ButtonRelease => match code {
@@ -50,6 +121,8 @@ pub fn convert_event(code: c_uchar, type_: c_int, x: f64, y: f64) -> Option<Even
             2 => Some(EventType::ButtonRelease(Button::Middle)),
             3 => Some(EventType::ButtonRelease(Button::Right)),
             4 | 5 => None,
+            8 => Some(EventType::ButtonRelease(Button::Back)),
+            9 => Some(EventType::ButtonRelease(Button::Forward)),
             _ => Some(EventType::ButtonRelease(Button::Unknown(code))),
         },
         xlib::MotionNotify => Some(EventType::MouseMove { x, y }),
@@ -57,6 +130,26 @@ pub fn convert_event(code: c_uchar, type_: c_int, x: f64, y: f64) -> Option<Even
     }
 }
 
+/// State of the keyboard led called `name` on `display`, None when the
+/// layout has no such indicator.
+pub unsafe fn named_indicator(display: *mut xlib::Display, name: &str) -> Option<bool> {
+    let name = CString::new(name).ok()?;
+    let atom = xlib::XInternAtom(display, name.as_ptr(), FALSE);
+    let mut state = FALSE;
+    let found = xlib::XkbGetNamedIndicator(
+        display,
+        atom,
+        null_mut(),
+        &mut state,
+        null_mut(),
+        null_mut(),
+    );
+    if found == FALSE {
+        return None;
+    }
+    Some(state != FALSE)
+}
+
 pub fn convert(
     keyboard: &mut Option<Keyboard>,
     code: c_uint,
@@ -66,26 +159,276 @@ pub fn convert(
 ) -> Option<Event> {
     let event_type = convert_event(code as c_uchar, type_, x, y)?;
     let kb: &mut Keyboard = (*keyboard).as_mut()?;
-    let name = kb.add(&event_type);
+    let names = crate::names::wanted();
+    let name = if names { kb.add(&event_type) } else { None };
+    let (is_dead, scan_code) = match event_type {
+        // X keycodes are evdev codes shifted by 8.
+        EventType::KeyPress(_) => (names && kb.is_dead(), code.saturating_sub(8)),
+        EventType::KeyRelease(_) => (false, code.saturating_sub(8)),
+        _ => (false, 0),
+    };
+    let numlock = match event_type {
+        EventType::KeyPress(_) | EventType::KeyRelease(_) => kb.numlock(),
+        _ => None,
+    };
     Some(Event {
         event_type,
         time: SystemTime::now(),
+        unicode: UnicodeInfo::new(name.as_deref(), is_dead),
         name,
+        scan_code,
+        display: None,
+        delta: None,
+        numlock,
     })
 }
 
+/// The event to emit before the last converted one, if looking its name up
+/// made the keyboard pick up a new layout.
+pub fn layout_event(keyboard: &mut Option<Keyboard>) -> Option<Event> {
+    let kb: &mut Keyboard = (*keyboard).as_mut()?;
+    if kb.take_layout_changed() {
+        Some(Event {
+            event_type: EventType::LayoutChanged,
+            time: SystemTime::now(),
+            name: None,
+            unicode: None,
+            scan_code: 0,
+            display: None,
+            delta: None,
+            numlock: None,
+        })
+    } else {
+        None
+    }
+}
+
+/// Layouts configured in X, in group order ("us", "fr(azerty)"...), read
+/// from the _XKB_RULES_NAMES property of the root window.
+pub unsafe fn xkb_layouts(display: *mut xlib::Display) -> Option<Vec<String>> {
+    let name = CString::new("_XKB_RULES_NAMES").ok()?;
+    let atom = xlib::XInternAtom(display, name.as_ptr(), TRUE);
+    if atom == 0 {
+        return None;
+    }
+    let mut actual_type = 0;
+    let mut actual_format = 0;
+    let mut n_items = 0;
+    let mut bytes_after = 0;
+    let mut prop: *mut c_uchar = null_mut();
+    let status = xlib::XGetWindowProperty(
+        display,
+        xlib::XDefaultRootWindow(display),
+        atom,
+        0,
+        1024,
+        FALSE,
+        xlib::XA_STRING,
+        &mut actual_type,
+        &mut actual_format,
+        &mut n_items,
+        &mut bytes_after,
+        &mut prop,
+    );
+    if status != 0 || prop.is_null() {
+        return None;
+    }
+    let bytes = std::slice::from_raw_parts(prop, n_items as usize).to_vec();
+    xlib::XFree(prop as *mut c_void);
+    parse_rules_names(&bytes)
+}
+
+fn parse_rules_names(bytes: &[u8]) -> Option<Vec<String>> {
+    // rules, model, layouts, variants, options
+    let fields: Vec<String> = bytes
+        .split(|b| *b == 0)
+        .map(|field| String::from_utf8_lossy(field).into_owned())
+        .collect();
+    let layouts = fields.get(2)?;
+    let variants: Vec<&str> = fields
+        .get(3)
+        .map(|v| v.split(',').collect())
+        .unwrap_or_default();
+    Some(
+        layouts
+            .split(',')
+            .enumerate()
+            .map(|(i, layout)| match variants.get(i) {
+                Some(variant) if !variant.is_empty() => format!("{}({})", layout, variant),
+                _ => layout.to_string(),
+            })
+            .collect(),
+    )
+}
+
+/// The integer settings of an _XSETTINGS_SETTINGS property, strings and
+/// colors are skipped.
+fn parse_xsettings(bytes: &[u8]) -> Option<HashMap<String, i32>> {
+    // The byte order of the manager, MSBFirst or LSBFirst.
+    let big_endian = *bytes.first()? == 1;
+    let card16 = |at: usize| {
+        let field = bytes.get(at..at + 2)?.try_into().ok()?;
+        Some(if big_endian {
+            u16::from_be_bytes(field)
+        } else {
+            u16::from_le_bytes(field)
+        })
+    };
+    let card32 = |at: usize| {
+        let field = bytes.get(at..at + 4)?.try_into().ok()?;
+        Some(if big_endian {
+            u32::from_be_bytes(field)
+        } else {
+            u32::from_le_bytes(field)
+        })
+    };
+    let padded = |len: usize| (len + 3) & !3;
+    let mut settings = HashMap::new();
+    // After the byte order, padding and the serial.
+    let mut at = 12;
+    for _ in 0..card32(8)? {
+        let kind = *bytes.get(at)?;
+        let name_len = card16(at + 2)? as usize;
+        let name = bytes.get(at + 4..at + 4 + name_len)?;
+        // Then the serial of the last change.
+        at += 4 + padded(name_len) + 4;
+        match kind {
+            0 => {
+                let value = card32(at)? as i32;
+                settings.insert(String::from_utf8_lossy(name).into_owned(), value);
+                at += 4;
+            }
+            1 => at += 4 + padded(card32(at)? as usize),
+            2 => at += 8,
+            _ => return None,
+        }
+    }
+    Some(settings)
+}
+
 pub struct Display {
     display: *mut xlib::Display,
 }
 
 impl Display {
     pub fn new() -> Option<Display> {
+        let display = open_display();
+        if display.is_null() {
+            return None;
+        }
+        Some(Display { display })
+    }
+
+    /// Only valid while `self` is, it closes the connection on drop.
+    pub fn as_ptr(&self) -> *mut xlib::Display {
+        self.display
+    }
+
+    /// Refresh rate of the mode used by the first output of a monitor.
+    unsafe fn refresh_rate(
+        &self,
+        resources: *mut xrandr::XRRScreenResources,
+        monitor: &xrandr::XRRMonitorInfo,
+    ) -> Option<f64> {
+        if resources.is_null() || monitor.noutput < 1 || monitor.outputs.is_null() {
+            return None;
+        }
+        let output = xrandr::XRRGetOutputInfo(self.display, resources, *monitor.outputs);
+        if output.is_null() {
+            return None;
+        }
+        let crtc = xrandr::XRRGetCrtcInfo(self.display, resources, (*output).crtc);
+        xrandr::XRRFreeOutputInfo(output);
+        if crtc.is_null() {
+            return None;
+        }
+        let mode_id = (*crtc).mode;
+        xrandr::XRRFreeCrtcInfo(crtc);
+        let modes = std::slice::from_raw_parts(
+            (*resources).modes,
+            (*resources).nmode.try_into().unwrap_or(0),
+        );
+        let mode = modes.iter().find(|mode| mode.id == mode_id)?;
+        let pixels = f64::from(mode.hTotal) * f64::from(mode.vTotal);
+        if pixels == 0.0 {
+            return None;
+        }
+        Some(mode.dotClock as f64 / pixels)
+    }
+
+    /// Monitors from Xrandr, the root window only gives their union. X has
+    /// no per monitor scaling, the scale factor is always 1.
+    pub fn get_displays(&self) -> Option<Vec<DisplayInfo>> {
+        unsafe {
+            let root = xlib::XDefaultRootWindow(self.display);
+            let mut count: c_int = 0;
+            let monitors = xrandr::XRRGetMonitors(self.display, root, TRUE, &mut count);
+            if monitors.is_null() {
+                return None;
+            }
+            let resources = xrandr::XRRGetScreenResourcesCurrent(self.display, root);
+            let displays = std::slice::from_raw_parts(monitors, count.try_into().unwrap_or(0))
+                .iter()
+                .map(|monitor| DisplayInfo {
+                    x: monitor.x.into(),
+                    y: monitor.y.into(),
+                    width: monitor.width.try_into().unwrap_or(0),
+                    height: monitor.height.try_into().unwrap_or(0),
+                    scale_factor: 1.0,
+                    refresh_rate: self.refresh_rate(resources, monitor),
+                    is_primary: monitor.primary == TRUE,
+                })
+                .collect();
+            if !resources.is_null() {
+                xrandr::XRRFreeScreenResources(resources);
+            }
+            xrandr::XRRFreeMonitors(monitors);
+            Some(displays)
+        }
+    }
+
+    /// Blocks, calling `callback` after Xrandr reports screen, crtc or
+    /// output changes. A single change sends a burst of those, we wait for
+    /// the queue to be empty so the callback sees the final configuration.
+    pub fn watch_displays<F>(&self, mut callback: F) -> Option<()>
+    where
+        F: FnMut(),
+    {
         unsafe {
-            let display = xlib::XOpenDisplay(null());
-            if display.is_null() {
+            let mut event_base = 0;
+            let mut error_base = 0;
+            if xrandr::XRRQueryExtension(self.display, &mut event_base, &mut error_base) == FALSE {
                 return None;
             }
-            Some(Display { display })
+            let root = xlib::XDefaultRootWindow(self.display);
+            xrandr::XRRSelectInput(
+                self.display,
+                root,
+                xrandr::RRScreenChangeNotifyMask
+                    | xrandr::RRCrtcChangeNotifyMask
+                    | xrandr::RROutputChangeNotifyMask,
+            );
+            let mut event: xlib::XEvent = zeroed();
+            loop {
+                let mut changed = false;
+                xlib::XNextEvent(self.display, &mut event);
+                loop {
+                    let kind = event.get_type() - event_base;
+                    if kind == xrandr::RRScreenChangeNotify {
+                        xrandr::XRRUpdateConfiguration(&mut event);
+                        changed = true;
+                    } else if kind == xrandr::RRNotify {
+                        changed = true;
+                    }
+                    if xlib::XPending(self.display) == 0 {
+                        break;
+                    }
+                    xlib::XNextEvent(self.display, &mut event);
+                }
+                if changed {
+                    callback();
+                }
+            }
         }
     }
 
@@ -103,7 +446,6 @@ impl Display {
         }
     }
 
-    #[cfg(feature = "unstable_grab")]
     pub fn get_mouse_pos(&self) -> Option<(u64, u64)> {
         unsafe {
             let root_window = xlib::XRootWindow(self.display, 0);
@@ -128,6 +470,145 @@ impl Display {
             Some((root_x.try_into().ok()?, root_y.try_into().ok()?))
         }
     }
+
+    pub fn get_layouts(&self) -> Option<Vec<String>> {
+        unsafe { xkb_layouts(self.display) }
+    }
+
+    /// One bit per keycode, set when the key is down.
+    pub fn get_keymap(&self) -> [c_char; 32] {
+        let mut keymap = [0; 32];
+        unsafe {
+            xlib::XQueryKeymap(self.display, keymap.as_mut_ptr());
+        }
+        keymap
+    }
+
+    /// State of the keyboard led called `name` ("Caps Lock", "Num Lock"...)
+    pub fn get_named_indicator(&self, name: &str) -> Option<bool> {
+        unsafe { named_indicator(self.display, name) }
+    }
+
+    /// Time since the last input, from the MIT-SCREEN-SAVER extension.
+    pub fn get_idle_time(&self) -> Option<Duration> {
+        unsafe {
+            let info = xss::XScreenSaverAllocInfo();
+            if info.is_null() {
+                return None;
+            }
+            let root = xlib::XDefaultRootWindow(self.display);
+            let status = xss::XScreenSaverQueryInfo(self.display, root, info);
+            let idle = (*info).idle;
+            xlib::XFree(info as *mut c_void);
+            if status == 0 {
+                return None;
+            }
+            Some(Duration::from_millis(idle as u64))
+        }
+    }
+
+    /// Delay before a held key repeats and time between repeats, from XKB.
+    pub fn get_repeat_rate(&self) -> Option<(Duration, Duration)> {
+        let mut delay = 0;
+        let mut interval = 0;
+        unsafe {
+            if xlib::XkbGetAutoRepeatRate(self.display, XKB_USE_CORE_KBD, &mut delay, &mut interval)
+                == FALSE
+            {
+                return None;
+            }
+        }
+        Some((
+            Duration::from_millis(delay.into()),
+            Duration::from_millis(interval.into()),
+        ))
+    }
+
+    /// Whether held keys repeat at all (`xset r off`).
+    pub fn get_auto_repeat(&self) -> bool {
+        unsafe {
+            let mut state: xlib::XKeyboardState = zeroed();
+            xlib::XGetKeyboardControl(self.display, &mut state);
+            state.global_auto_repeat != xlib::AutoRepeatModeOff
+        }
+    }
+
+    /// Integer settings ("Net/DoubleClickTime"...) of the XSETTINGS manager,
+    /// the settings daemon desktops run, None without one.
+    pub fn get_xsettings(&self) -> Option<HashMap<String, i32>> {
+        unsafe {
+            let screen = xlib::XDefaultScreen(self.display);
+            let selection = CString::new(format!("_XSETTINGS_S{}", screen)).ok()?;
+            let selection = xlib::XInternAtom(self.display, selection.as_ptr(), FALSE);
+            let owner = xlib::XGetSelectionOwner(self.display, selection);
+            let name = CString::new("_XSETTINGS_SETTINGS").ok()?;
+            let atom = xlib::XInternAtom(self.display, name.as_ptr(), TRUE);
+            if owner == 0 || atom == 0 {
+                return None;
+            }
+            let mut actual_type = 0;
+            let mut actual_format = 0;
+            let mut n_items = 0;
+            let mut bytes_after = 0;
+            let mut prop: *mut c_uchar = null_mut();
+            // The manager may exit meanwhile, its window with it.
+            let caught = catch_x_errors();
+            let status = xlib::XGetWindowProperty(
+                self.display,
+                owner,
+                atom,
+                0,
+                // In 32 bit units.
+                65536,
+                FALSE,
+                atom,
+                &mut actual_type,
+                &mut actual_format,
+                &mut n_items,
+                &mut bytes_after,
+                &mut prop,
+            );
+            drop(caught);
+            if status != 0 || prop.is_null() {
+                return None;
+            }
+            let bytes = std::slice::from_raw_parts(prop, n_items as usize).to_vec();
+            xlib::XFree(prop as *mut c_void);
+            if actual_format != 8 {
+                return None;
+            }
+            parse_xsettings(&bytes)
+        }
+    }
+
+    /// Modifiers and pointer buttons mask (Button1Mask...)
+    pub fn get_pointer_mask(&self) -> Option<c_uint> {
+        unsafe {
+            let root_window = xlib::XRootWindow(self.display, 0);
+            let mut root_x = 0;
+            let mut root_y = 0;
+            let mut x = 0;
+            let mut y = 0;
+            let mut root = 0;
+            let mut child = 0;
+            let mut mask = 0;
+            let status = xlib::XQueryPointer(
+                self.display,
+                root_window,
+                &mut root,
+                &mut child,
+                &mut root_x,
+                &mut root_y,
+                &mut x,
+                &mut y,
+                &mut mask,
+            );
+            if status == FALSE {
+                return None;
+            }
+            Some(mask)
+        }
+    }
 }
 impl Drop for Display {
     fn drop(&mut self) {
@@ -136,3 +617,31 @@ impl Drop for Display {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_rules_names() {
+        let names = b"evdev\0pc105\0us,fr\0,azerty\0grp:alt_shift_toggle\0";
+        assert_eq!(
+            parse_rules_names(names),
+            Some(vec!["us".to_string(), "fr(azerty)".to_string()])
+        );
+        assert_eq!(parse_rules_names(b"evdev\0pc105"), None);
+    }
+
+    #[test]
+    fn test_parse_xsettings() {
+        let mut settings = vec![0, 0, 0, 0, 7, 0, 0, 0, 3, 0, 0, 0];
+        // A string, a color, then an integer, names padded to 4 bytes.
+        settings.extend(b"\x01\0\x0d\0Net/ThemeName\0\0\0\0\0\0\0\x07\0\0\0Adwaita\0");
+        settings.extend(b"\x02\0\x09\0Gtk/Color\0\0\0\0\0\0\0\0\0\0\0\0\0\0\0");
+        settings.extend(b"\0\0\x13\0Net/DoubleClickTime\0\0\0\0\0\xf4\x01\0\0");
+        let parsed = parse_xsettings(&settings).unwrap();
+        assert_eq!(parsed.len(), 1);
+        assert_eq!(parsed.get("Net/DoubleClickTime"), Some(&500));
+        assert_eq!(parse_xsettings(&settings[..settings.len() - 2]), None);
+    }
+}
@@ -0,0 +1,55 @@
+use crate::conflicts::Conflict;
+use crate::linux::devices::input_device;
+use std::ffi::CString;
+use std::fs::read_dir;
+
+const DEV_INPUT: &str = "/dev/input";
+// From linux/input.h, _IOW('E', 0x90, int)
+const EVIOCGRAB: u64 = 0x4004_4590;
+
+/// Whether another process has an EVIOCGRAB on the device, found by trying
+/// one: the kernel refuses a second grab with EBUSY. Ours are released
+/// right away, events coming in between reach only us. None when the
+/// device can't be opened.
+fn grabbed_elsewhere(path: &str) -> Option<bool> {
+    let path = CString::new(path).ok()?;
+    unsafe {
+        let fd = libc::open(path.as_ptr(), libc::O_RDONLY | libc::O_CLOEXEC);
+        if fd < 0 {
+            return None;
+        }
+        let busy = if libc::ioctl(fd, EVIOCGRAB as _, 1) < 0 {
+            std::io::Error::last_os_error().raw_os_error() == Some(libc::EBUSY)
+        } else {
+            libc::ioctl(fd, EVIOCGRAB as _, 0);
+            false
+        };
+        libc::close(fd);
+        Some(busy)
+    }
+}
+
+/// The devices another process grabbed, None without read access to
+/// /dev/input.
+pub fn conflicts() -> Option<Vec<Conflict>> {
+    let mut readable = false;
+    let mut conflicts = vec![];
+    for entry in read_dir(DEV_INPUT).ok()?.filter_map(Result::ok) {
+        let node = entry.file_name().to_string_lossy().into_owned();
+        let device = match input_device(&node) {
+            Some(device) => device,
+            None => continue,
+        };
+        match grabbed_elsewhere(&device.id) {
+            Some(true) => conflicts.push(Conflict::DeviceGrab(device)),
+            Some(false) => (),
+            None => continue,
+        }
+        readable = true;
+    }
+    if readable {
+        Some(conflicts)
+    } else {
+        None
+    }
+}
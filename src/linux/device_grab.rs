@@ -0,0 +1,218 @@
+use crate::linux::devices::input_device;
+use crate::linux::display::display_size;
+use crate::linux::evdev::{numlock_led, Converter};
+use crate::linux::uinput;
+use crate::linux::xkb::XkbKeyboard;
+use crate::motion::{display_rects, Motion};
+use crate::rdev::{Event, EventType, GrabError, InputDevice, OsError, SimulateError, UnicodeInfo};
+use std::ffi::CString;
+use std::fs;
+use std::mem::{size_of, zeroed};
+use std::os::raw::c_void;
+use std::os::unix::io::RawFd;
+use std::thread;
+use std::time::{Duration, SystemTime};
+
+const DEV_INPUT: &str = "/dev/input";
+// From linux/input.h, _IOW('E', 0x90, int) and _IOC(_IOC_READ, 'E', 0x18, KEYS_LEN)
+const EVIOCGRAB: u64 = 0x4004_4590;
+const EVIOCGKEY: u64 = 0x8060_4518;
+// Enough bits for KEY_MAX.
+const KEYS_LEN: usize = 0x60;
+// From linux/input-event-codes.h
+const EV_KEY: u16 = 0x01;
+const AUTOREPEAT: i32 = 2;
+/// Keys held when the device is grabbed would never be released for the
+/// system, we wait this long at most for them to be.
+const RELEASE_TIMEOUT: Duration = Duration::from_secs(2);
+const RELEASE_POLL: Duration = Duration::from_millis(10);
+
+fn keys_down(fd: RawFd) -> bool {
+    let mut keys = [0_u8; KEYS_LEN];
+    let read = unsafe { libc::ioctl(fd, EVIOCGKEY as _, keys.as_mut_ptr()) };
+    read > 0 && keys.iter().any(|byte| *byte != 0)
+}
+
+/// The devices we grabbed, nothing else sees their events until they are
+/// released on drop.
+#[derive(Default)]
+struct Grabbed {
+    fds: Vec<RawFd>,
+    /// Why the last device matching couldn't be grabbed.
+    error: Option<OsError>,
+}
+
+impl Grabbed {
+    fn grab<F>(filter: F) -> Grabbed
+    where
+        F: Fn(&InputDevice) -> bool,
+    {
+        let mut grabbed = Grabbed::default();
+        let entries = match fs::read_dir(DEV_INPUT) {
+            Ok(entries) => entries,
+            Err(_) => return grabbed,
+        };
+        for entry in entries.flatten() {
+            let node = entry.file_name().to_string_lossy().into_owned();
+            match input_device(&node) {
+                Some(device) if filter(&device) => {}
+                _ => continue,
+            }
+            let path = match CString::new(format!("{}/{}", DEV_INPUT, node)) {
+                Ok(path) => path,
+                Err(_) => continue,
+            };
+            let fd = unsafe {
+                libc::open(
+                    path.as_ptr(),
+                    libc::O_RDONLY | libc::O_NONBLOCK | libc::O_CLOEXEC,
+                )
+            };
+            if fd < 0 {
+                grabbed.error = Some(OsError::last("open"));
+                continue;
+            }
+            let mut waited = Duration::from_millis(0);
+            while keys_down(fd) && waited < RELEASE_TIMEOUT {
+                thread::sleep(RELEASE_POLL);
+                waited += RELEASE_POLL;
+            }
+            if unsafe { libc::ioctl(fd, EVIOCGRAB as _, 1) } < 0 {
+                grabbed.error = Some(OsError::last("EVIOCGRAB"));
+                unsafe { libc::close(fd) };
+                continue;
+            }
+            grabbed.fds.push(fd);
+        }
+        grabbed
+    }
+
+    fn release(&mut self, fd: RawFd) {
+        self.fds.retain(|grabbed| *grabbed != fd);
+        unsafe {
+            libc::ioctl(fd, EVIOCGRAB as _, 0);
+            libc::close(fd);
+        }
+    }
+}
+
+impl Drop for Grabbed {
+    fn drop(&mut self) {
+        for fd in self.fds.drain(..) {
+            unsafe {
+                libc::ioctl(fd, EVIOCGRAB as _, 0);
+                libc::close(fd);
+            }
+        }
+    }
+}
+
+/// Moves are forwarded by their delta, the pointer is wherever the other
+/// devices put it. A move without delta goes to its position.
+fn forward(event: &Event) -> Result<(), SimulateError> {
+    match (&event.event_type, event.delta) {
+        (EventType::MouseMove { .. }, Some((dx, dy))) => uinput::move_by(dx, dy),
+        (event_type, _) => uinput::simulate(event_type),
+    }
+}
+
+/// Grabs the devices matching `filter` so only we get their events, and
+/// sends what `callback` returns through uinput. Returns when they are
+/// all unplugged.
+pub fn grab_device<F, T>(filter: F, mut callback: T) -> Result<(), GrabError>
+where
+    F: Fn(&InputDevice) -> bool,
+    T: FnMut(Event) -> Option<Event> + 'static,
+{
+    // Without somewhere to forward to, grabbing would take the devices away.
+    uinput::open().map_err(|_| GrabError::SimulateError)?;
+    let mut grabbed = Grabbed::grab(filter);
+    if grabbed.fds.is_empty() {
+        return Err(GrabError::EvdevError(grabbed.error));
+    }
+    let mut keyboard = XkbKeyboard::new();
+    let mut motion = Motion::new();
+    let bounds = display_size()
+        .ok()
+        .map(|(width, height)| (width as f64, height as f64));
+    let mut converter = Converter::new(bounds);
+
+    let mut events: [libc::input_event; 64] = unsafe { zeroed() };
+    while !grabbed.fds.is_empty() {
+        let mut fds: Vec<libc::pollfd> = grabbed
+            .fds
+            .iter()
+            .map(|fd| libc::pollfd {
+                fd: *fd,
+                events: libc::POLLIN,
+                revents: 0,
+            })
+            .collect();
+        let ready = unsafe { libc::poll(fds.as_mut_ptr(), fds.len() as libc::nfds_t, -1) };
+        if ready < 0 {
+            if std::io::Error::last_os_error().kind() == std::io::ErrorKind::Interrupted {
+                continue;
+            }
+            return Err(GrabError::IoError(std::io::Error::last_os_error()));
+        }
+        for pollfd in &fds {
+            if pollfd.revents == 0 {
+                continue;
+            }
+            let len = unsafe {
+                libc::read(
+                    pollfd.fd,
+                    events.as_mut_ptr() as *mut c_void,
+                    size_of::<[libc::input_event; 64]>(),
+                )
+            };
+            if len < 0 {
+                if std::io::Error::last_os_error().kind() != std::io::ErrorKind::WouldBlock {
+                    grabbed.release(pollfd.fd);
+                }
+                continue;
+            }
+            let count = len as usize / size_of::<libc::input_event>();
+            for raw in &events[..count] {
+                // The system repeats the keys we forward itself.
+                if raw.type_ == EV_KEY && raw.value == AUTOREPEAT {
+                    continue;
+                }
+                let event_type = match converter.convert(raw.type_, raw.code, raw.value) {
+                    Some(event_type) => event_type,
+                    None => continue,
+                };
+                let (name, is_dead) = match (&event_type, &mut keyboard) {
+                    (EventType::KeyPress(_), Some(keyboard)) => keyboard.press(raw.code.into()),
+                    (EventType::KeyRelease(_), Some(keyboard)) => {
+                        keyboard.release(raw.code.into());
+                        (None, false)
+                    }
+                    _ => (None, false),
+                };
+                let (scan_code, numlock) = match event_type {
+                    EventType::KeyPress(_) | EventType::KeyRelease(_) => {
+                        (raw.code.into(), numlock_led(pollfd.fd))
+                    }
+                    _ => (0, None),
+                };
+                let mut event = Event {
+                    event_type,
+                    time: SystemTime::now(),
+                    unicode: UnicodeInfo::new(name.as_deref(), is_dead),
+                    name,
+                    scan_code,
+                    display: None,
+                    delta: converter.delta.take(),
+                    numlock,
+                };
+                motion.annotate(&mut event, display_rects);
+                if let Some(event) = callback(event) {
+                    // Keys uinput can't send are dropped, like the event.
+                    forward(&event).ok();
+                }
+            }
+        }
+    }
+    Ok(())
+}
@@ -0,0 +1,150 @@
+use crate::rdev::{DeviceEvent, DeviceKind, InputDevice, ListenError, OsError};
+use std::collections::HashMap;
+use std::ffi::CString;
+use std::fs;
+use std::mem::size_of;
+use std::os::raw::c_void;
+use std::ptr;
+
+const DEV_INPUT: &str = "/dev/input";
+const SYS_INPUT: &str = "/sys/class/input";
+// Bits from linux/input-event-codes.h
+const KEY_A: usize = 30;
+const REL_X: usize = 0;
+
+fn read_sys(node: &str, file: &str) -> Option<String> {
+    fs::read_to_string(format!("{}/{}/device/{}", SYS_INPUT, node, file))
+        .ok()
+        .map(|content| content.trim().to_string())
+}
+
+/// Capabilities in sysfs are hexadecimal longs, most significant first.
+fn has_bit(bitmap: &str, bit: usize) -> bool {
+    let word_bits = usize::BITS as usize;
+    bitmap
+        .split_whitespace()
+        .rev()
+        .nth(bit / word_bits)
+        .and_then(|word| usize::from_str_radix(word, 16).ok())
+        .map(|word| word & (1 << (bit % word_bits)) != 0)
+        .unwrap_or(false)
+}
+
+fn device_kind(node: &str) -> DeviceKind {
+    let has = |file, bit| {
+        read_sys(node, file)
+            .map(|bitmap| has_bit(&bitmap, bit))
+            .unwrap_or(false)
+    };
+    if has("capabilities/key", KEY_A) {
+        DeviceKind::Keyboard
+    } else if has("capabilities/rel", REL_X) {
+        DeviceKind::Mouse
+    } else {
+        DeviceKind::Other
+    }
+}
+
+/// Reads the device behind /dev/input/`node` from sysfs, which unlike the
+/// device itself doesn't need any permission.
+pub fn input_device(node: &str) -> Option<InputDevice> {
+    if !node.starts_with("event") {
+        return None;
+    }
+    let id = |file| {
+        read_sys(node, file)
+            .and_then(|id| u16::from_str_radix(&id, 16).ok())
+            .unwrap_or(0)
+    };
+    Some(InputDevice {
+        id: format!("{}/{}", DEV_INPUT, node),
+        name: read_sys(node, "name")?,
+        vendor_id: id("id/vendor"),
+        product_id: id("id/product"),
+        serial: read_sys(node, "uniq").filter(|serial| !serial.is_empty()),
+        kind: device_kind(node),
+    })
+}
+
+pub fn listen_devices<T>(mut callback: T) -> Result<(), ListenError>
+where
+    T: FnMut(DeviceEvent) + 'static,
+{
+    let mut devices = HashMap::new();
+    let path = CString::new(DEV_INPUT)
+        .map_err(|_| ListenError::DeviceMonitorError(OsError::new("CString::new", 0)))?;
+    let fd = unsafe { libc::inotify_init1(libc::IN_CLOEXEC) };
+    if fd < 0 {
+        return Err(ListenError::DeviceMonitorError(OsError::last(
+            "inotify_init1",
+        )));
+    }
+    // Watch before listing so we don't miss a device plugged in between.
+    if unsafe { libc::inotify_add_watch(fd, path.as_ptr(), libc::IN_CREATE | libc::IN_DELETE) } < 0
+    {
+        let error = OsError::last("inotify_add_watch");
+        unsafe { libc::close(fd) };
+        return Err(ListenError::DeviceMonitorError(error));
+    }
+    if let Ok(entries) = fs::read_dir(DEV_INPUT) {
+        for entry in entries.flatten() {
+            let node = entry.file_name().to_string_lossy().into_owned();
+            if let Some(device) = input_device(&node) {
+                devices.insert(node, device.clone());
+                callback(DeviceEvent::Connected(device));
+            }
+        }
+    }
+
+    let mut buffer = [0_u8; 4096];
+    let header = size_of::<libc::inotify_event>();
+    loop {
+        let len = unsafe { libc::read(fd, buffer.as_mut_ptr() as *mut c_void, buffer.len()) };
+        if len < 0 {
+            if std::io::Error::last_os_error().kind() == std::io::ErrorKind::Interrupted {
+                continue;
+            }
+            let error = OsError::last("read");
+            unsafe { libc::close(fd) };
+            return Err(ListenError::DeviceMonitorError(error));
+        }
+        let len = len as usize;
+        let mut offset = 0;
+        while offset + header <= len {
+            let event: libc::inotify_event =
+                unsafe { ptr::read_unaligned(buffer.as_ptr().add(offset) as *const _) };
+            let name_end = (offset + header + event.len as usize).min(len);
+            let name = &buffer[offset + header..name_end];
+            let name = name.split(|byte| *byte == 0).next().unwrap_or(&[]);
+            let node = String::from_utf8_lossy(name).into_owned();
+            offset = name_end;
+
+            if event.mask & libc::IN_CREATE != 0 {
+                if let Some(device) = input_device(&node) {
+                    devices.insert(node, device.clone());
+                    callback(DeviceEvent::Connected(device));
+                }
+            } else if event.mask & libc::IN_DELETE != 0 {
+                // sysfs is already gone, we report what we knew.
+                if let Some(device) = devices.remove(&node) {
+                    callback(DeviceEvent::Disconnected(device));
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_has_bit() {
+        let word_bits = usize::BITS as usize;
+        assert!(has_bit("40000000", 30));
+        assert!(!has_bit("40000000", 31));
+        assert!(has_bit("1 0", word_bits));
+        assert!(!has_bit("1 0", 0));
+        assert!(!has_bit("", 0));
+    }
+}
@@ -1,7 +1,37 @@
 use crate::linux::common::Display;
-use crate::rdev::DisplayError;
+#[cfg(feature = "wayland")]
+use crate::linux::wayland;
+use crate::rdev::{DisplayError, DisplayInfo, ListenError, OsError};
 
 pub fn display_size() -> Result<(u64, u64), DisplayError> {
-    let display = Display::new().ok_or(DisplayError::NoDisplay)?;
-    display.get_size().ok_or(DisplayError::NoDisplay)
+    match Display::new() {
+        Some(display) => display.get_size().ok_or(DisplayError::NoDisplay),
+        #[cfg(feature = "wayland")]
+        None => wayland::display_size(),
+        #[cfg(not(feature = "wayland"))]
+        None => Err(DisplayError::NoDisplay),
+    }
+}
+
+pub fn displays() -> Result<Vec<DisplayInfo>, DisplayError> {
+    match Display::new() {
+        Some(display) => display.get_displays().ok_or(DisplayError::NoDisplay),
+        #[cfg(feature = "wayland")]
+        None => wayland::displays(),
+        #[cfg(not(feature = "wayland"))]
+        None => Err(DisplayError::NoDisplay),
+    }
+}
+
+pub fn listen_display_changes<T>(callback: T) -> Result<(), ListenError>
+where
+    T: FnMut() + 'static,
+{
+    let display = Display::new().ok_or(ListenError::MissingDisplayError)?;
+    display
+        .watch_displays(callback)
+        .ok_or(ListenError::DisplayMonitorError(OsError::new(
+            "XRRQueryExtension",
+            0,
+        )))
 }
@@ -0,0 +1,386 @@
+use crate::linux::display::display_size;
+use crate::linux::keycodes::key_from_code;
+use crate::linux::xkb::XkbKeyboard;
+use crate::motion::{display_rects, Motion};
+use crate::rdev::{Button, Event, EventType, ListenError, OsError, UnicodeInfo};
+use std::collections::HashMap;
+use std::ffi::CString;
+use std::fs;
+use std::mem::{size_of, zeroed};
+use std::os::raw::c_void;
+use std::os::unix::io::RawFd;
+use std::ptr;
+use std::time::SystemTime;
+
+const DEV_INPUT: &str = "/dev/input";
+// From linux/input-event-codes.h
+const EV_SYN: u16 = 0x00;
+const EV_KEY: u16 = 0x01;
+const EV_REL: u16 = 0x02;
+const SYN_REPORT: u16 = 0;
+const REL_X: u16 = 0x00;
+const REL_Y: u16 = 0x01;
+const REL_HWHEEL: u16 = 0x06;
+const REL_WHEEL: u16 = 0x08;
+const BTN_LEFT: u16 = 0x110;
+const BTN_RIGHT: u16 = 0x111;
+const BTN_MIDDLE: u16 = 0x112;
+const BTN_SIDE: u16 = 0x113;
+const BTN_EXTRA: u16 = 0x114;
+const BTN_FORWARD: u16 = 0x115;
+const BTN_BACK: u16 = 0x116;
+// Joysticks, tablets, touchpads... between the keyboard keys and KEY_OK.
+const BTN_FIRST: u16 = 0x100;
+const BTN_LAST: u16 = 0x15f;
+const LED_NUML: u8 = 0x00;
+// _IOC(_IOC_READ, 'E', 0x20 + EV_LED, 8) and _IOC(_IOC_READ, 'E', 0x19, 8)
+const EVIOCGBIT_LED: u64 = 0x8008_4531;
+const EVIOCGLED: u64 = 0x8008_4519;
+
+/// Whether the NumLock led of the device behind `fd` is on, the compositor
+/// keeps the leds of every keyboard in sync with the lock. None when the
+/// device has no such led.
+pub(crate) fn numlock_led(fd: RawFd) -> Option<bool> {
+    let mut leds = [0_u8; 8];
+    let numl = 1 << LED_NUML;
+    unsafe {
+        if libc::ioctl(fd, EVIOCGBIT_LED as _, leds.as_mut_ptr()) < 0 || leds[0] & numl == 0 {
+            return None;
+        }
+        if libc::ioctl(fd, EVIOCGLED as _, leds.as_mut_ptr()) < 0 {
+            return None;
+        }
+    }
+    Some(leds[0] & numl != 0)
+}
+
+fn button(code: u16) -> Option<Button> {
+    match code {
+        BTN_LEFT => Some(Button::Left),
+        BTN_RIGHT => Some(Button::Right),
+        BTN_MIDDLE => Some(Button::Middle),
+        BTN_SIDE | BTN_BACK => Some(Button::Back),
+        BTN_EXTRA | BTN_FORWARD => Some(Button::Forward),
+        _ => None,
+    }
+}
+
+/// Turns raw evdev events into event types. No display server tells us
+/// where the pointer is, so its position starts at 0, 0 and follows
+/// relative moves, clamped to the screen when its size is known. Moves are
+/// added up until the end of the frame (SYN_REPORT) so a diagonal move is
+/// one event, its unclamped delta is kept in `delta`.
+pub struct Converter {
+    x: f64,
+    y: f64,
+    dx: f64,
+    dy: f64,
+    bounds: Option<(f64, f64)>,
+    pub delta: Option<(f64, f64)>,
+}
+
+impl Converter {
+    pub fn new(bounds: Option<(f64, f64)>) -> Converter {
+        Converter {
+            x: 0.0,
+            y: 0.0,
+            dx: 0.0,
+            dy: 0.0,
+            bounds,
+            delta: None,
+        }
+    }
+
+    pub fn convert(&mut self, type_: u16, code: u16, value: i32) -> Option<EventType> {
+        match (type_, code) {
+            (EV_KEY, code) if (BTN_FIRST..=BTN_LAST).contains(&code) => {
+                let button = button(code)?;
+                match value {
+                    0 => Some(EventType::ButtonRelease(button)),
+                    1 => Some(EventType::ButtonPress(button)),
+                    _ => None,
+                }
+            }
+            (EV_KEY, code) => {
+                // X keycodes are evdev codes shifted by 8.
+                let key = key_from_code(u32::from(code) + 8);
+                match value {
+                    0 => Some(EventType::KeyRelease(key)),
+                    // 2 is autorepeat, sent as presses like X does.
+                    _ => Some(EventType::KeyPress(key)),
+                }
+            }
+            (EV_REL, REL_X) => {
+                self.dx += f64::from(value);
+                None
+            }
+            (EV_REL, REL_Y) => {
+                self.dy += f64::from(value);
+                None
+            }
+            (EV_REL, REL_WHEEL) => Some(EventType::Wheel {
+                delta_x: 0,
+                delta_y: value.into(),
+            }),
+            (EV_REL, REL_HWHEEL) => Some(EventType::Wheel {
+                delta_x: value.into(),
+                delta_y: 0,
+            }),
+            (EV_SYN, SYN_REPORT) if self.dx != 0.0 || self.dy != 0.0 => {
+                self.delta = Some((self.dx, self.dy));
+                self.x += self.dx;
+                self.y += self.dy;
+                self.dx = 0.0;
+                self.dy = 0.0;
+                if let Some((width, height)) = self.bounds {
+                    self.x = self.x.max(0.0).min(width - 1.0);
+                    self.y = self.y.max(0.0).min(height - 1.0);
+                }
+                Some(EventType::MouseMove {
+                    x: self.x,
+                    y: self.y,
+                })
+            }
+            _ => None,
+        }
+    }
+}
+
+/// Input nodes we could open, by name in /dev/input.
+#[derive(Default)]
+struct Devices {
+    fds: HashMap<String, RawFd>,
+    /// A node was opened or closed since `listen` last looked.
+    changed: bool,
+    /// The errno of the last node that couldn't be opened.
+    error: i64,
+}
+
+impl Devices {
+    fn open(&mut self, node: &str) {
+        if !node.starts_with("event") || self.fds.contains_key(node) {
+            return;
+        }
+        let path = match CString::new(format!("{}/{}", DEV_INPUT, node)) {
+            Ok(path) => path,
+            Err(_) => return,
+        };
+        let fd = unsafe {
+            libc::open(
+                path.as_ptr(),
+                libc::O_RDONLY | libc::O_NONBLOCK | libc::O_CLOEXEC,
+            )
+        };
+        if fd >= 0 {
+            self.fds.insert(node.to_string(), fd);
+            self.changed = true;
+        } else {
+            self.error = std::io::Error::last_os_error()
+                .raw_os_error()
+                .unwrap_or(0)
+                .into();
+        }
+    }
+
+    fn close(&mut self, node: &str) {
+        if let Some(fd) = self.fds.remove(node) {
+            unsafe { libc::close(fd) };
+            self.changed = true;
+        }
+    }
+}
+
+impl Drop for Devices {
+    fn drop(&mut self) {
+        for fd in self.fds.values() {
+            unsafe { libc::close(*fd) };
+        }
+    }
+}
+
+/// Reads the pending inotify events, opening nodes as they appear. udev
+/// gives us access to new nodes a bit after creating them, hence IN_ATTRIB.
+fn read_inotify(fd: RawFd, devices: &mut Devices) {
+    let mut buffer = [0_u8; 4096];
+    let header = size_of::<libc::inotify_event>();
+    let len = unsafe { libc::read(fd, buffer.as_mut_ptr() as *mut c_void, buffer.len()) };
+    if len <= 0 {
+        return;
+    }
+    let len = len as usize;
+    let mut offset = 0;
+    while offset + header <= len {
+        let event: libc::inotify_event =
+            unsafe { ptr::read_unaligned(buffer.as_ptr().add(offset) as *const _) };
+        let name_end = (offset + header + event.len as usize).min(len);
+        let name = &buffer[offset + header..name_end];
+        let name = name.split(|byte| *byte == 0).next().unwrap_or(&[]);
+        let node = String::from_utf8_lossy(name).into_owned();
+        offset = name_end;
+        if event.mask & libc::IN_DELETE != 0 {
+            devices.close(&node);
+        } else {
+            devices.open(&node);
+        }
+    }
+}
+
+/// Listens to every readable device in /dev/input, for sessions where X
+/// can't tell us about input (Wayland, or no display server at all).
+/// Reading devices needs to be root or in the `input` group.
+pub fn listen<T>(mut callback: T) -> Result<(), ListenError>
+where
+    T: FnMut(Event) + 'static,
+{
+    let mut keyboard = if crate::names::wanted() {
+        XkbKeyboard::new()
+    } else {
+        None
+    };
+    let mut motion = Motion::new();
+    let bounds = display_size()
+        .ok()
+        .map(|(width, height)| (width as f64, height as f64));
+    let mut converter = Converter::new(bounds);
+
+    let path = CString::new(DEV_INPUT)
+        .map_err(|_| ListenError::EvdevError(OsError::new("CString::new", 0)))?;
+    let inotify = unsafe { libc::inotify_init1(libc::IN_CLOEXEC | libc::IN_NONBLOCK) };
+    if inotify < 0 {
+        return Err(ListenError::EvdevError(OsError::last("inotify_init1")));
+    }
+    let mask = libc::IN_CREATE | libc::IN_ATTRIB | libc::IN_DELETE;
+    // Watch before listing so we don't miss a device plugged in between.
+    if unsafe { libc::inotify_add_watch(inotify, path.as_ptr(), mask) } < 0 {
+        let error = OsError::last("inotify_add_watch");
+        unsafe { libc::close(inotify) };
+        return Err(ListenError::EvdevError(error));
+    }
+    let mut devices = Devices::default();
+    if let Ok(entries) = fs::read_dir(DEV_INPUT) {
+        for entry in entries.flatten() {
+            devices.open(&entry.file_name().to_string_lossy());
+        }
+    }
+    if devices.fds.is_empty() {
+        unsafe { libc::close(inotify) };
+        return Err(ListenError::EvdevError(OsError::new("open", devices.error)));
+    }
+    crate::trace::installed("evdev devices");
+
+    let mut events: [libc::input_event; 64] = unsafe { zeroed() };
+    // Reused, so only plugging devices allocates and events don't.
+    let mut nodes: Vec<(String, RawFd)> = Vec::new();
+    let mut fds: Vec<libc::pollfd> = Vec::new();
+    devices.changed = true;
+    loop {
+        if devices.changed {
+            devices.changed = false;
+            nodes.clear();
+            nodes.extend(devices.fds.iter().map(|(node, fd)| (node.clone(), *fd)));
+        }
+        fds.clear();
+        fds.extend(
+            Some(inotify)
+                .into_iter()
+                .chain(nodes.iter().map(|(_, fd)| *fd))
+                .map(|fd| libc::pollfd {
+                    fd,
+                    events: libc::POLLIN,
+                    revents: 0,
+                }),
+        );
+        let ready = unsafe { libc::poll(fds.as_mut_ptr(), fds.len() as libc::nfds_t, -1) };
+        if ready < 0 {
+            if std::io::Error::last_os_error().kind() == std::io::ErrorKind::Interrupted {
+                continue;
+            }
+            let error = OsError::last("poll");
+            unsafe { libc::close(inotify) };
+            return Err(ListenError::EvdevError(error));
+        }
+        if fds[0].revents != 0 {
+            read_inotify(inotify, &mut devices);
+        }
+        for ((node, fd), pollfd) in nodes.iter().zip(&fds[1..]) {
+            if pollfd.revents == 0 {
+                continue;
+            }
+            let len = unsafe {
+                libc::read(
+                    *fd,
+                    events.as_mut_ptr() as *mut c_void,
+                    size_of::<[libc::input_event; 64]>(),
+                )
+            };
+            if len < 0 {
+                if std::io::Error::last_os_error().kind() != std::io::ErrorKind::WouldBlock {
+                    // Unplugged, before inotify tells us.
+                    devices.close(node);
+                }
+                continue;
+            }
+            let count = len as usize / size_of::<libc::input_event>();
+            for raw in &events[..count] {
+                let event_type = match converter.convert(raw.type_, raw.code, raw.value) {
+                    Some(event_type) => event_type,
+                    None => continue,
+                };
+                let (name, is_dead) = match (&event_type, &mut keyboard) {
+                    (EventType::KeyPress(_), Some(keyboard)) => keyboard.press(raw.code.into()),
+                    (EventType::KeyRelease(_), Some(keyboard)) => {
+                        keyboard.release(raw.code.into());
+                        (None, false)
+                    }
+                    _ => (None, false),
+                };
+                let (scan_code, numlock) = match event_type {
+                    EventType::KeyPress(_) | EventType::KeyRelease(_) => {
+                        (raw.code.into(), numlock_led(*fd))
+                    }
+                    _ => (0, None),
+                };
+                let mut event = Event {
+                    event_type,
+                    time: SystemTime::now(),
+                    unicode: UnicodeInfo::new(name.as_deref(), is_dead),
+                    name,
+                    scan_code,
+                    display: None,
+                    delta: converter.delta.take(),
+                    numlock,
+                };
+                motion.annotate(&mut event, display_rects);
+                callback(event);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::rdev::Key;
+
+    #[test]
+    fn test_convert() {
+        let mut converter = Converter::new(Some((100.0, 100.0)));
+        assert_eq!(
+            converter.convert(EV_KEY, 30, 1),
+            Some(EventType::KeyPress(Key::KeyA))
+        );
+        assert_eq!(
+            converter.convert(EV_KEY, BTN_LEFT, 0),
+            Some(EventType::ButtonRelease(Button::Left))
+        );
+        assert_eq!(converter.convert(EV_REL, REL_X, 10), None);
+        assert_eq!(converter.convert(EV_REL, REL_Y, -5), None);
+        assert_eq!(
+            converter.convert(EV_SYN, SYN_REPORT, 0),
+            Some(EventType::MouseMove { x: 10.0, y: 0.0 })
+        );
+        assert_eq!(converter.delta, Some((10.0, -5.0)));
+        // Nothing moved in this frame.
+        assert_eq!(converter.convert(EV_SYN, SYN_REPORT, 0), None);
+    }
+}
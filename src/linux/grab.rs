@@ -1,9 +1,11 @@
 use crate::linux::common::Display;
 use crate::linux::keyboard::Keyboard;
-use crate::rdev::{Button, Event, EventType, GrabError, Key, KeyboardState};
+use crate::motion::{display_rects, Motion};
+use crate::rdev::{Button, Event, EventType, GrabError, Key, KeyboardState, Pen, UnicodeInfo};
 use epoll::ControlOptions::{EPOLL_CTL_ADD, EPOLL_CTL_DEL};
 use evdev_rs::{
-    enums::{EventCode, EV_KEY, EV_REL},
+    enums::{EventCode, EV_ABS, EV_KEY, EV_REL, EV_SYN},
+    util::event_code_to_int,
     Device, InputEvent, UInputDevice,
 };
 use inotify::{Inotify, WatchMask};
@@ -74,10 +76,15 @@ macro_rules! convert_buttons {
 convert_buttons!(
     BTN_LEFT, Left,
     BTN_RIGHT, Right,
-    BTN_MIDDLE, Middle
+    BTN_MIDDLE, Middle,
+    BTN_SIDE, Back,
+    BTN_EXTRA, Forward,
+    // Some mice use the dedicated codes instead
+    BTN_BACK, Back,
+    BTN_FORWARD, Forward
 );
 
-//TODO: IntlBackslash, kpDelete
+//TODO: kpDelete
 #[rustfmt::skip]
 convert_keys!(
     KEY_ESC, Escape,
@@ -164,6 +171,18 @@ convert_keys!(
     KEY_KP0, Kp0,
     KEY_F11, F11,
     KEY_F12, F12,
+    KEY_F13, F13,
+    KEY_F14, F14,
+    KEY_F15, F15,
+    KEY_F16, F16,
+    KEY_F17, F17,
+    KEY_F18, F18,
+    KEY_F19, F19,
+    KEY_F20, F20,
+    KEY_F21, F21,
+    KEY_F22, F22,
+    KEY_F23, F23,
+    KEY_F24, F24,
     KEY_KPENTER, KpReturn,
     KEY_RIGHTCTRL, ControlRight,
     KEY_KPSLASH, KpDivide,
@@ -182,12 +201,44 @@ convert_keys!(
     KEY_LEFTMETA, MetaLeft,
     KEY_RIGHTMETA, MetaRight,
     KEY_PRINT, PrintScreen,
+    KEY_VOLUMEUP, VolumeUp,
+    KEY_VOLUMEDOWN, VolumeDown,
+    KEY_MUTE, VolumeMute,
+    KEY_PLAYPAUSE, MediaPlayPause,
+    KEY_NEXTSONG, MediaNext,
+    KEY_PREVIOUSSONG, MediaPrev,
+    KEY_BRIGHTNESSUP, BrightnessUp,
+    KEY_BRIGHTNESSDOWN, BrightnessDown,
+    KEY_COMPOSE, Menu,
+    KEY_KATAKANAHIRAGANA, Kana,
+    KEY_HANGEUL, Hangul,
+    KEY_HENKAN, Henkan,
+    KEY_MUHENKAN, Muhenkan,
+    KEY_YEN, Yen,
+    KEY_RO, Ro,
+    KEY_BACK, BrowserBack,
+    KEY_FORWARD, BrowserForward,
+    KEY_REFRESH, BrowserRefresh,
+    KEY_HOMEPAGE, BrowserHome,
+    KEY_SEARCH, BrowserSearch,
+    KEY_MAIL, LaunchMail,
+    KEY_CALC, Calculator,
+    KEY_SLEEP, Sleep,
     // KpDelete behaves like normal Delete most of the time
     KEY_DELETE, KpDelete,
-    // Linux doesn't have an IntlBackslash key
-    KEY_BACKSLASH, IntlBackslash
+    // The ISO key between left shift and Z
+    KEY_102ND, IntlBackslash
 );
 
+/// The device motion, it isn't clamped to the screen like the position.
+fn evdev_delta(event: &InputEvent) -> Option<(f64, f64)> {
+    match &event.event_code {
+        EventCode::EV_REL(EV_REL::REL_X) => Some((event.value as f64, 0.0)),
+        EventCode::EV_REL(EV_REL::REL_Y) => Some((0.0, event.value as f64)),
+        _ => None,
+    }
+}
+
 fn evdev_event_to_rdev_event(
     event: &InputEvent,
     x: &mut f64,
@@ -254,6 +305,206 @@ fn evdev_event_to_rdev_event(
     }
 }
 
+#[derive(Debug, Copy, Clone, PartialEq)]
+enum TouchChange {
+    None,
+    Down,
+    Move,
+    Up,
+}
+
+#[derive(Debug, Copy, Clone)]
+struct TouchSlot {
+    id: u64,
+    x: f64,
+    y: f64,
+    pressure: f64,
+    change: TouchChange,
+}
+
+impl Default for TouchSlot {
+    fn default() -> TouchSlot {
+        TouchSlot {
+            id: 0,
+            x: 0.0,
+            y: 0.0,
+            pressure: 1.0,
+            change: TouchChange::None,
+        }
+    }
+}
+
+/// Follows multitouch devices (protocol B): each finger gets a slot, slots
+/// are updated one axis at a time and the whole frame is valid on
+/// SYN_REPORT. Positions are scaled to the screen, which is right for
+/// touchscreens covering the whole display.
+#[derive(Default)]
+struct TouchTracker {
+    slots: Vec<TouchSlot>,
+    slot: usize,
+}
+
+/// Where `value` sits between the axis bounds, between 0.0 and 1.0.
+fn normalize(device: &Device, axis: EV_ABS, value: i32) -> Option<f64> {
+    let info = device.abs_info(&EventCode::EV_ABS(axis))?;
+    if info.maximum <= info.minimum {
+        return None;
+    }
+    let ratio = f64::from(value - info.minimum) / f64::from(info.maximum - info.minimum);
+    Some(ratio.clamp(0.0, 1.0))
+}
+
+impl TouchTracker {
+    fn current(&mut self) -> &mut TouchSlot {
+        if self.slots.len() <= self.slot {
+            self.slots.resize(self.slot + 1, TouchSlot::default());
+        }
+        &mut self.slots[self.slot]
+    }
+
+    fn moved(slot: &mut TouchSlot) {
+        if slot.change == TouchChange::None {
+            slot.change = TouchChange::Move;
+        }
+    }
+
+    /// Returns the touch events once a frame is complete.
+    fn add(&mut self, device: &Device, event: &InputEvent, w: f64, h: f64) -> Vec<EventType> {
+        match &event.event_code {
+            EventCode::EV_ABS(EV_ABS::ABS_MT_SLOT) => {
+                self.slot = event.value.max(0) as usize;
+            }
+            EventCode::EV_ABS(EV_ABS::ABS_MT_TRACKING_ID) => {
+                let slot = self.current();
+                if event.value < 0 {
+                    slot.change = TouchChange::Up;
+                } else {
+                    slot.id = event.value as u64;
+                    slot.pressure = 1.0;
+                    slot.change = TouchChange::Down;
+                }
+            }
+            EventCode::EV_ABS(EV_ABS::ABS_MT_POSITION_X) => {
+                if let Some(x) = normalize(device, EV_ABS::ABS_MT_POSITION_X, event.value) {
+                    let slot = self.current();
+                    slot.x = x * w;
+                    Self::moved(slot);
+                }
+            }
+            EventCode::EV_ABS(EV_ABS::ABS_MT_POSITION_Y) => {
+                if let Some(y) = normalize(device, EV_ABS::ABS_MT_POSITION_Y, event.value) {
+                    let slot = self.current();
+                    slot.y = y * h;
+                    Self::moved(slot);
+                }
+            }
+            EventCode::EV_ABS(EV_ABS::ABS_MT_PRESSURE) => {
+                if let Some(pressure) = normalize(device, EV_ABS::ABS_MT_PRESSURE, event.value) {
+                    let slot = self.current();
+                    slot.pressure = pressure;
+                    Self::moved(slot);
+                }
+            }
+            EventCode::EV_SYN(EV_SYN::SYN_REPORT) => {
+                let mut events = vec![];
+                for slot in self.slots.iter_mut() {
+                    let TouchSlot {
+                        id, x, y, pressure, ..
+                    } = *slot;
+                    match std::mem::replace(&mut slot.change, TouchChange::None) {
+                        TouchChange::None => {}
+                        TouchChange::Down => {
+                            events.push(EventType::TouchDown { id, x, y, pressure })
+                        }
+                        TouchChange::Move => {
+                            events.push(EventType::TouchMove { id, x, y, pressure })
+                        }
+                        TouchChange::Up => events.push(EventType::TouchUp { id, x, y, pressure }),
+                    }
+                }
+                return events;
+            }
+            _ => {}
+        }
+        vec![]
+    }
+}
+
+/// Follows tablets: the tool keys tell when the pen (or its eraser) is near
+/// the surface, BTN_TOUCH when it touches it. Axes can come before the tool
+/// key in a frame so we always record them and only report in proximity.
+struct PenTracker {
+    pen: Pen,
+    in_proximity: bool,
+    change: TouchChange,
+}
+
+impl Default for PenTracker {
+    fn default() -> PenTracker {
+        PenTracker {
+            pen: Pen::default(),
+            in_proximity: false,
+            change: TouchChange::None,
+        }
+    }
+}
+
+impl PenTracker {
+    fn moved(&mut self) {
+        if self.change == TouchChange::None {
+            self.change = TouchChange::Move;
+        }
+    }
+
+    fn add(&mut self, device: &Device, event: &InputEvent, w: f64, h: f64) -> Option<EventType> {
+        match &event.event_code {
+            EventCode::EV_KEY(EV_KEY::BTN_TOOL_PEN) => {
+                self.in_proximity = event.value != 0;
+                self.pen.eraser = false;
+                self.moved();
+            }
+            EventCode::EV_KEY(EV_KEY::BTN_TOOL_RUBBER) => {
+                self.in_proximity = event.value != 0;
+                self.pen.eraser = event.value != 0;
+                self.moved();
+            }
+            EventCode::EV_KEY(EV_KEY::BTN_TOUCH) if self.in_proximity => {
+                self.change = if event.value != 0 {
+                    TouchChange::Down
+                } else {
+                    TouchChange::Up
+                };
+            }
+            EventCode::EV_ABS(axis) => {
+                let value = normalize(device, axis.clone(), event.value)?;
+                match axis {
+                    EV_ABS::ABS_X => self.pen.x = value * w,
+                    EV_ABS::ABS_Y => self.pen.y = value * h,
+                    EV_ABS::ABS_PRESSURE => self.pen.pressure = value,
+                    EV_ABS::ABS_TILT_X => self.pen.tilt_x = value * 2.0 - 1.0,
+                    EV_ABS::ABS_TILT_Y => self.pen.tilt_y = value * 2.0 - 1.0,
+                    _ => return None,
+                }
+                self.moved();
+            }
+            EventCode::EV_SYN(EV_SYN::SYN_REPORT) => {
+                let change = std::mem::replace(&mut self.change, TouchChange::None);
+                if !self.in_proximity && change != TouchChange::Up {
+                    return None;
+                }
+                return match change {
+                    TouchChange::None => None,
+                    TouchChange::Down => Some(EventType::PenDown(self.pen)),
+                    TouchChange::Move => Some(EventType::PenMove(self.pen)),
+                    TouchChange::Up => Some(EventType::PenUp(self.pen)),
+                };
+            }
+            _ => {}
+        }
+        None
+    }
+}
+
 // fn rdev_event_to_evdev_event(event: &EventType, time: &TimeVal) -> Option<InputEvent> {
 //     match event {
 //         EventType::KeyPress(key) => {
@@ -315,18 +566,70 @@ where
     let mut y = current_y as f64;
     let w = width as f64;
     let h = height as f64;
-    filter_map_events(|event| {
+    let mut touches = TouchTracker::default();
+    let mut pen = PenTracker::default();
+    let mut motion = Motion::new();
+    filter_map_events(|device, event| {
+        // Touch and pen frames span several raw events, they are reported but
+        // the raw events always go through.
+        let pen_event = pen.add(device, &event, w, h);
+        for event_type in touches
+            .add(device, &event, w, h)
+            .into_iter()
+            .chain(pen_event)
+        {
+            callback(Event {
+                time: SystemTime::now(),
+                name: None,
+                event_type,
+                unicode: None,
+                scan_code: 0,
+                display: None,
+                delta: None,
+                numlock: None,
+            });
+        }
         let event_type = match evdev_event_to_rdev_event(&event, &mut x, &mut y, w, h) {
             Some(rdev_event) => rdev_event,
             // If we can't convert event, simulate it
             None => return (Some(event), GrabStatus::Continue),
         };
         let name = kb.add(&event_type);
-        let rdev_event = Event {
+        if kb.take_layout_changed() {
+            // Purely informative, there is no OS event to swallow here.
+            callback(Event {
+                time: SystemTime::now(),
+                name: None,
+                event_type: EventType::LayoutChanged,
+                unicode: None,
+                scan_code: 0,
+                display: None,
+                delta: None,
+                numlock: None,
+            });
+        }
+        let (is_dead, scan_code, numlock) = match event_type {
+            EventType::KeyPress(_) => (
+                kb.is_dead(),
+                event_code_to_int(&event.event_code).1,
+                kb.numlock(),
+            ),
+            EventType::KeyRelease(_) => {
+                (false, event_code_to_int(&event.event_code).1, kb.numlock())
+            }
+            _ => (false, 0, None),
+        };
+        let mut rdev_event = Event {
             time: SystemTime::now(),
+            unicode: UnicodeInfo::new(name.as_deref(), is_dead),
             name,
             event_type,
+            scan_code,
+            display: None,
+            delta: evdev_delta(&event),
+            numlock,
         };
+        motion.annotate(&mut rdev_event, display_rects);
         if callback(rdev_event).is_some() {
             (Some(event), GrabStatus::Continue)
         } else {
@@ -339,7 +642,7 @@ where
 
 pub fn filter_map_events<F>(mut func: F) -> io::Result<()>
 where
-    F: FnMut(InputEvent) -> (Option<InputEvent>, GrabStatus),
+    F: FnMut(&Device, InputEvent) -> (Option<InputEvent>, GrabStatus),
 {
     let (epoll_fd, mut devices, output_devices) = setup_devices()?;
     let mut inotify = setup_inotify(epoll_fd, &devices)?;
@@ -381,7 +684,7 @@ where
                             continue 'events;
                         }
                     };
-                    let (event, grab_status) = func(event);
+                    let (event, grab_status) = func(device, event);
 
                     if let (Some(event), Some(out_device)) = (event, output_devices.get(device_idx))
                     {
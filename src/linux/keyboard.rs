@@ -1,14 +1,21 @@
 extern crate x11;
+use crate::layout::NameTable;
+use crate::linux::common::{named_indicator, open_display, xkb_layouts, Display, FALSE, TRUE};
 use crate::linux::keycodes::code_from_key;
-use crate::rdev::{EventType, Key, KeyboardState};
+use crate::rdev::{DisplayError, EventType, Key, KeyboardState};
 use std::ffi::CString;
 use std::os::raw::{c_char, c_int, c_uint, c_ulong, c_void};
 use std::ptr::{null, null_mut, NonNull};
+use std::sync::Once;
 use x11::xlib;
 
+// Not exported by the x11 crate.
+const XKB_USE_CORE_KBD: c_uint = 0x0100;
+
 #[derive(Debug)]
 struct State {
     alt: bool,
+    alt_gr: bool,
     ctrl: bool,
     caps_lock: bool,
     shift: bool,
@@ -24,6 +31,7 @@ impl State {
     fn new() -> State {
         State {
             alt: false,
+            alt_gr: false,
             ctrl: false,
             caps_lock: false,
             meta: false,
@@ -36,6 +44,10 @@ impl State {
         if self.alt {
             res += xlib::Mod1Mask;
         }
+        if self.alt_gr {
+            // ISO_Level3_Shift lives on Mod5 on virtually every layout.
+            res += xlib::Mod5Mask;
+        }
         if self.ctrl {
             res += xlib::ControlMask;
         }
@@ -62,7 +74,20 @@ pub struct Keyboard {
     status: Box<i32>,
     state: State,
     serial: c_ulong,
+    group: c_uint,
+    // Set by with_layout, the live group is ignored then.
+    fixed_group: Option<c_uint>,
+    layout_changed: bool,
+    dead_pending: bool,
+    names: NameTable,
 }
+// The keyboard owns its display connection and nothing else refers to it,
+// so moving it to another thread is fine (Xlib is put in thread safe mode
+// before any keyboard is created).
+unsafe impl Send for Keyboard {}
+
+static XLIB_THREADS: Once = Once::new();
+
 impl Drop for Keyboard {
     fn drop(&mut self) {
         unsafe {
@@ -73,13 +98,16 @@ impl Drop for Keyboard {
 
 impl Keyboard {
     pub fn new() -> Option<Keyboard> {
+        XLIB_THREADS.call_once(|| unsafe {
+            xlib::XInitThreads();
+        });
         unsafe {
             // https://stackoverflow.com/questions/18246848/get-utf-8-input-with-x11-display#
             let string = CString::new("@im=none").expect("Can't creat CString");
             let ret = xlib::XSetLocaleModifiers(string.as_ptr());
             NonNull::new(ret)?;
 
-            let dpy = xlib::XOpenDisplay(null());
+            let dpy = open_display();
             if dpy.is_null() {
                 return None;
             }
@@ -142,10 +170,81 @@ impl Keyboard {
                 status: Box::new(0),
                 state: State::new(),
                 serial: 0,
+                group: 0,
+                fixed_group: None,
+                layout_changed: false,
+                dead_pending: false,
+                names: NameTable::default(),
             })
         }
     }
 
+    /// Keyboard using one of the layouts configured in X (see
+    /// `available_layouts`) instead of the current one, e.g. "fr" or
+    /// "us(intl)". Returns None if that layout isn't configured: X only knows
+    /// about the layouts of its groups.
+    pub fn with_layout(layout_id: &str) -> Option<Keyboard> {
+        let mut keyboard = Keyboard::new()?;
+        let layouts = unsafe { xkb_layouts(*keyboard.display)? };
+        let group = layouts.iter().position(|layout| layout == layout_id)?;
+        keyboard.fixed_group = Some(group as c_uint);
+        Some(keyboard)
+    }
+
+    /// Picks up keyboard mapping and group (layout) changes made since the
+    /// last call. Returns true if the layout changed.
+    pub(crate) fn refresh_layout(&mut self) -> bool {
+        if self.display.is_null() {
+            return false;
+        }
+        let mut changed = false;
+        unsafe {
+            // MappingNotify events land in our own queue, going through
+            // XNextEvent lets xlib reload its keymap tables.
+            while xlib::XPending(*self.display) > 0 {
+                let mut event: xlib::XEvent = std::mem::zeroed();
+                xlib::XNextEvent(*self.display, &mut event);
+                if event.get_type() == xlib::MappingNotify {
+                    xlib::XRefreshKeyboardMapping(&mut event.mapping);
+                    changed = true;
+                }
+            }
+            let mut xkb_state: xlib::XkbStateRec = std::mem::zeroed();
+            if self.fixed_group.is_none()
+                && xlib::XkbGetState(*self.display, XKB_USE_CORE_KBD, &mut xkb_state) == 0
+            {
+                let group = xkb_state.group as c_uint;
+                if group != self.group {
+                    self.group = group;
+                    changed = true;
+                }
+            }
+        }
+        if changed {
+            self.layout_changed = true;
+            self.names.clear();
+        }
+        changed
+    }
+
+    /// Returns true once after the keyboard picked up a layout change.
+    pub(crate) fn take_layout_changed(&mut self) -> bool {
+        std::mem::replace(&mut self.layout_changed, false)
+    }
+
+    /// Whether NumLock is on now, from the X server.
+    pub(crate) fn numlock(&self) -> Option<bool> {
+        if self.display.is_null() {
+            return None;
+        }
+        unsafe { named_indicator(*self.display, "Num Lock") }
+    }
+
+    /// True while a dead key waits for the next key.
+    pub(crate) fn is_dead(&self) -> bool {
+        self.dead_pending
+    }
+
     pub(crate) unsafe fn name_from_code(
         &mut self,
         keycode: c_uint,
@@ -155,6 +254,12 @@ impl Keyboard {
             println!("We don't seem to have a display or a xic");
             return None;
         }
+        let dead_pending = self.dead_pending;
+        if !dead_pending {
+            if let Some(name) = self.names.get(keycode, state) {
+                return name;
+            }
+        }
         const BUF_LEN: usize = 4;
         let mut buf = [0_u8; BUF_LEN];
         let key = xlib::XKeyEvent {
@@ -183,7 +288,18 @@ impl Keyboard {
         // the correct keyvalue from the utf8LookupString !!
         // https://stackoverflow.com/questions/18246848/get-utf-8-input-with-x11-display#
         // -----------------------------------------------------------------
-        xlib::XFilterEvent(&mut event, 0);
+        if xlib::XFilterEvent(&mut event, 0) == TRUE {
+            // The input method swallowed the key: it's either the start of a
+            // dead key sequence, or its end in which case the composed key
+            // is put back in our queue (with keycode 0).
+            if xlib::XCheckTypedWindowEvent(*self.display, *self.window, xlib::KeyPress, &mut event)
+                == FALSE
+            {
+                self.dead_pending = true;
+                return None;
+            }
+        }
+        self.dead_pending = false;
 
         let ret = xlib::Xutf8LookupString(
             *self.xic,
@@ -193,15 +309,25 @@ impl Keyboard {
             &mut *self.keysym,
             &mut *self.status,
         );
-        if ret == xlib::NoSymbol {
-            return None;
+        let name = if ret == xlib::NoSymbol {
+            None
+        } else {
+            let len = buf.iter().position(|ch| ch == &0).unwrap_or(BUF_LEN);
+            String::from_utf8(buf[..len].to_vec()).ok()
+        };
+        if !dead_pending {
+            self.names.insert(keycode, state, name.clone());
         }
-
-        let len = buf.iter().position(|ch| ch == &0).unwrap_or(BUF_LEN);
-        String::from_utf8(buf[..len].to_vec()).ok()
+        name
     }
 }
 
+/// Layouts that can be given to `Keyboard::with_layout`.
+pub fn available_layouts() -> Result<Vec<String>, DisplayError> {
+    let display = Display::new().ok_or(DisplayError::NoDisplay)?;
+    display.get_layouts().ok_or(DisplayError::NoDisplay)
+}
+
 impl KeyboardState for Keyboard {
     fn add(&mut self, event_type: &EventType) -> Option<String> {
         match event_type {
@@ -210,13 +336,20 @@ impl KeyboardState for Keyboard {
                     self.state.shift = true;
                     None
                 }
+                Key::AltGr => {
+                    self.state.alt_gr = true;
+                    None
+                }
                 Key::CapsLock => {
                     self.state.caps_lock = !self.state.caps_lock;
                     None
                 }
                 key => {
                     let keycode = code_from_key(*key)?;
-                    let state = self.state.value();
+                    self.refresh_layout();
+                    // The xkb group is stored in bits 13-14 of the state.
+                    let group = self.fixed_group.unwrap_or(self.group);
+                    let state = self.state.value() | (group << 13);
                     unsafe { self.name_from_code(keycode, state) }
                 }
             },
@@ -225,6 +358,10 @@ impl KeyboardState for Keyboard {
                     self.state.shift = false;
                     None
                 }
+                Key::AltGr => {
+                    self.state.alt_gr = false;
+                    None
+                }
                 _ => None,
             },
             _ => None,
@@ -232,6 +369,14 @@ impl KeyboardState for Keyboard {
     }
     fn reset(&mut self) {
         self.state = State::new();
+        self.dead_pending = false;
+        unsafe {
+            // Drop any pending dead key.
+            let pending = xlib::Xutf8ResetIC(*self.xic);
+            if !pending.is_null() {
+                xlib::XFree(pending as *mut c_void);
+            }
+        }
     }
 }
 
@@ -255,6 +400,24 @@ mod tests {
         );
     }
 
+    #[test]
+    #[ignore]
+    /// Needs the US international layout, where the quote is a dead acute:
+    /// `setxkbmap us intl`.
+    fn test_dead_keys() {
+        let mut keyboard = Keyboard::new().unwrap();
+        assert_eq!(keyboard.add(&EventType::KeyPress(Key::Quote)), None);
+        keyboard.add(&EventType::KeyRelease(Key::Quote));
+        let char_e = keyboard.add(&EventType::KeyPress(Key::KeyE));
+        assert_eq!(char_e, Some("é".to_string()));
+
+        // A reset forgets the pending dead key.
+        assert_eq!(keyboard.add(&EventType::KeyPress(Key::Quote)), None);
+        keyboard.reset();
+        let char_e = keyboard.add(&EventType::KeyPress(Key::KeyE));
+        assert_eq!(char_e, Some("e".to_string()));
+    }
+
     #[test]
     #[ignore]
     fn test_thread_safety_2() {
@@ -42,6 +42,18 @@ decl_keycodes!(
     F10, 76,
     F11, 95,
     F12, 96,
+    F13, 191,
+    F14, 192,
+    F15, 193,
+    F16, 194,
+    F17, 195,
+    F18, 196,
+    F19, 197,
+    F20, 198,
+    F21, 199,
+    F22, 200,
+    F23, 201,
+    F24, 202,
     F2, 68,
     F3, 69,
     F4, 70,
@@ -130,7 +142,31 @@ decl_keycodes!(
     Kp7, 79,
     Kp8, 80,
     Kp9, 81,
-    KpDelete, 91
+    KpDelete, 91,
+    // XF86AudioRaiseVolume and friends
+    VolumeUp, 123,
+    VolumeDown, 122,
+    VolumeMute, 121,
+    MediaPlayPause, 172,
+    MediaNext, 171,
+    MediaPrev, 173,
+    BrightnessUp, 233,
+    BrightnessDown, 232,
+    Menu, 135,
+    Kana, 101,
+    Hangul, 130,
+    Henkan, 100,
+    Muhenkan, 102,
+    Yen, 132,
+    Ro, 97,
+    BrowserBack, 166,
+    BrowserForward, 167,
+    BrowserRefresh, 181,
+    BrowserHome, 180,
+    BrowserSearch, 225,
+    LaunchMail, 163,
+    Calculator, 148,
+    Sleep, 150
 );
 
 #[cfg(test)]
@@ -1,19 +1,65 @@
 extern crate libc;
 extern crate x11;
-use crate::linux::common::{convert, FALSE, KEYBOARD};
+use crate::linux::common::{
+    catch_x_errors, convert, last_x_error, layout_event, Display, XErrorHandler, FALSE, KEYBOARD,
+    MOTION,
+};
 use crate::linux::keyboard::Keyboard;
-use crate::rdev::{Event, ListenError};
+use crate::motion::display_rects;
+use crate::rdev::{Backend, Event, ListenError, OsError};
 use std::convert::TryInto;
 use std::ffi::CStr;
-use std::os::raw::{c_char, c_int, c_uchar, c_uint, c_ulong};
-use std::ptr::null;
+use std::os::raw::{c_char, c_int, c_uchar, c_uint, c_ulong, c_void};
+use std::ptr::addr_of_mut;
 use x11::xlib;
 use x11::xrecord;
 
 static mut RECORD_ALL_CLIENTS: c_ulong = xrecord::XRecordAllClients;
 static mut GLOBAL_CALLBACK: Option<Box<dyn FnMut(Event)>> = None;
+/// Set back once recording started, see `record_callback`.
+static mut PREVIOUS_HANDLER: XErrorHandler = None;
+
+/// Whether X has the RECORD extension, None without any X server.
+#[cfg(all(target_os = "linux", feature = "evdev"))]
+fn record_available() -> Option<bool> {
+    let display = Display::new()?;
+    let available = CStr::from_bytes_with_nul(b"RECORD\0")
+        .map(|name| unsafe { !xlib::XInitExtension(display.as_ptr(), name.as_ptr()).is_null() })
+        .unwrap_or(false);
+    Some(available)
+}
+
+/// X can't tell us about input: no X server at all (Linux console, or
+/// Wayland without XWayland), or a Wayland session without RECORD.
+#[cfg(all(target_os = "linux", feature = "evdev"))]
+fn use_evdev() -> bool {
+    match record_available() {
+        None => true,
+        Some(available) => !available && std::env::var_os("WAYLAND_DISPLAY").is_some(),
+    }
+}
+
+/// What `listen` uses here.
+pub fn default_backend() -> Backend {
+    #[cfg(all(target_os = "linux", feature = "evdev"))]
+    if use_evdev() {
+        return Backend::Evdev;
+    }
+    Backend::X11
+}
 
 pub fn listen<T>(callback: T) -> Result<(), ListenError>
+where
+    T: FnMut(Event) + 'static,
+{
+    #[cfg(all(target_os = "linux", feature = "evdev"))]
+    if use_evdev() {
+        return crate::linux::evdev::listen(callback);
+    }
+    listen_x11(callback)
+}
+
+pub fn listen_x11<T>(callback: T) -> Result<(), ListenError>
 where
     T: FnMut(Event) + 'static,
 {
@@ -23,48 +69,78 @@ where
         KEYBOARD = Some(keyboard);
         GLOBAL_CALLBACK = Some(Box::new(callback));
         // Open displays
-        let dpy_control = xlib::XOpenDisplay(null());
-        if dpy_control.is_null() {
-            return Err(ListenError::MissingDisplayError);
-        }
+        let control = Display::new().ok_or(ListenError::MissingDisplayError)?;
+        let dpy_control = control.as_ptr();
         let extension_name = CStr::from_bytes_with_nul(b"RECORD\0")
-            .map_err(|_| ListenError::XRecordExtensionError)?;
+            .map_err(|_| ListenError::XRecordExtensionError(OsError::new("XInitExtension", 0)))?;
         let extension = xlib::XInitExtension(dpy_control, extension_name.as_ptr());
         if extension.is_null() {
-            return Err(ListenError::XRecordExtensionError);
+            return Err(ListenError::XRecordExtensionError(OsError::new(
+                "XInitExtension",
+                0,
+            )));
         }
 
         // Prepare record range
-        let mut record_range: xrecord::XRecordRange = *xrecord::XRecordAllocRange();
-        record_range.device_events.first = xlib::KeyPress as c_uchar;
-        record_range.device_events.last = xlib::MotionNotify as c_uchar;
+        let mut record_range = xrecord::XRecordAllocRange();
+        (*record_range).device_events.first = xlib::KeyPress as c_uchar;
+        (*record_range).device_events.last = xlib::MotionNotify as c_uchar;
 
-        // Create context
+        // Create context, its errors only come with the sync.
+        let caught = catch_x_errors();
         let context = xrecord::XRecordCreateContext(
             dpy_control,
             0,
             &mut RECORD_ALL_CLIENTS,
             1,
-            &mut &mut record_range as *mut &mut xrecord::XRecordRange
-                as *mut *mut xrecord::XRecordRange,
+            &mut record_range,
             1,
         );
+        xlib::XFree(record_range as *mut c_void);
 
-        if context == 0 {
-            return Err(ListenError::RecordContextError);
+        xlib::XSync(dpy_control, FALSE);
+        if context == 0 || last_x_error() != 0 {
+            let error = OsError::new("XRecordCreateContext", last_x_error());
+            return Err(ListenError::RecordContextError(error));
         }
+        let context = RecordContext {
+            display: dpy_control,
+            context,
+        };
 
-        xlib::XSync(dpy_control, FALSE);
-        // Run
-        let result =
-            xrecord::XRecordEnableContext(dpy_control, context, Some(record_callback), &mut 0);
+        crate::trace::installed("RECORD context");
+        // Run, blocks while recording.
+        PREVIOUS_HANDLER = caught.0;
+        let result = xrecord::XRecordEnableContext(
+            dpy_control,
+            context.context,
+            Some(record_callback),
+            &mut 0,
+        );
         if result == 0 {
-            return Err(ListenError::RecordContextEnablingError);
+            let error = OsError::new("XRecordEnableContext", last_x_error());
+            return Err(ListenError::RecordContextEnablingError(error));
         }
     }
     Ok(())
 }
 
+/// Disabled and freed on drop, before the display it was created on is
+/// closed. Until then it holds RECORD, which other clients can't enable.
+struct RecordContext {
+    display: *mut xlib::Display,
+    context: xrecord::XRecordContext,
+}
+
+impl Drop for RecordContext {
+    fn drop(&mut self) {
+        unsafe {
+            xrecord::XRecordDisableContext(self.display, self.context);
+            xrecord::XRecordFreeContext(self.display, self.context);
+        }
+    }
+}
+
 // No idea how to do that properly relevant doc lives here:
 // https://www.x.org/releases/X11R7.7/doc/libXtst/recordlib.html#Datum_Flags
 // https://docs.rs/xproto/1.1.5/xproto/struct._xEvent__bindgen_ty_1.html
@@ -90,6 +166,9 @@ unsafe extern "C" fn record_callback(
     raw_data: *mut xrecord::XRecordInterceptData,
 ) {
     let data = raw_data.as_ref().unwrap();
+    if data.category == xrecord::XRecordStartOfData {
+        xlib::XSetErrorHandler(PREVIOUS_HANDLER);
+    }
     if data.category != xrecord::XRecordFromServer {
         return;
     }
@@ -105,8 +184,13 @@ unsafe extern "C" fn record_callback(
     let x = xdatum.root_x as f64;
     let y = xdatum.root_y as f64;
 
-    if let Some(event) = convert(&mut KEYBOARD, code, type_, x, y) {
+    let keyboard = &mut KEYBOARD;
+    if let Some(mut event) = convert(keyboard, code, type_, x, y) {
+        (*addr_of_mut!(MOTION)).annotate(&mut event, display_rects);
         if let Some(callback) = &mut GLOBAL_CALLBACK {
+            if let Some(layout) = layout_event(keyboard) {
+                callback(layout);
+            }
             callback(event);
         }
     }
@@ -2,17 +2,65 @@ extern crate libc;
 extern crate x11;
 
 mod common;
+#[cfg(target_os = "linux")]
+mod conflicts;
+#[cfg(all(target_os = "linux", feature = "evdev"))]
+mod device_grab;
+#[cfg(target_os = "linux")]
+mod devices;
 mod display;
-#[cfg(feature = "unstable_grab")]
+#[cfg(all(target_os = "linux", feature = "evdev"))]
+mod evdev;
+#[cfg(all(target_os = "linux", feature = "unstable_grab"))]
 mod grab;
 mod keyboard;
 mod keycodes;
 mod listen;
+mod permissions;
+mod priority;
 mod simulate;
+mod state;
+#[cfg(all(target_os = "linux", feature = "evdev"))]
+mod uinput;
+#[cfg(feature = "wayland")]
+mod wayland;
+mod xinput;
+#[cfg(all(target_os = "linux", feature = "evdev"))]
+mod xkb;
 
-pub use crate::linux::display::display_size;
-#[cfg(feature = "unstable_grab")]
+pub use crate::linux::common::set_display_name;
+#[cfg(target_os = "linux")]
+pub use crate::linux::conflicts::conflicts;
+#[cfg(all(target_os = "linux", feature = "evdev"))]
+pub use crate::linux::device_grab::grab_device;
+#[cfg(target_os = "linux")]
+pub use crate::linux::devices::listen_devices;
+pub use crate::linux::display::{display_size, displays, listen_display_changes};
+#[cfg(all(target_os = "linux", feature = "evdev"))]
+pub use crate::linux::evdev::listen as listen_evdev;
+#[cfg(all(target_os = "linux", feature = "unstable_grab"))]
 pub use crate::linux::grab::grab;
-pub use crate::linux::keyboard::Keyboard;
-pub use crate::linux::listen::listen;
-pub use crate::linux::simulate::simulate;
+pub use crate::linux::keyboard::{available_layouts, Keyboard};
+pub use crate::linux::keycodes::{code_from_key, key_from_code};
+pub use crate::linux::listen::{default_backend, listen, listen_x11};
+pub use crate::linux::permissions::missing;
+pub use crate::linux::priority::raise_thread_priority;
+pub use crate::linux::simulate::{simulate, simulate_unicode, simulate_x11};
+pub use crate::linux::state::{
+    click_settings, get_lock_state, get_mouse_position, idle_time, keyboard_repeat, keyboard_state,
+    mouse_state, set_lock_state,
+};
+#[cfg(all(target_os = "linux", feature = "evdev"))]
+pub use crate::linux::uinput::simulate as simulate_uinput;
+pub use crate::linux::xinput::listen as listen_xinput;
+
+/// FreeBSD has no sysfs describing input devices, nor inotify to watch them.
+#[cfg(target_os = "freebsd")]
+pub fn listen_devices<T>(_callback: T) -> Result<(), crate::rdev::ListenError>
+where
+    T: FnMut(crate::rdev::DeviceEvent) + 'static,
+{
+    Err(crate::rdev::ListenError::DeviceMonitorError(
+        crate::rdev::OsError::new("inotify", 0),
+    ))
+}
@@ -0,0 +1,101 @@
+use crate::linux::common::{Display, FALSE};
+use crate::permissions::{Missing, Need};
+use crate::rdev::Backend;
+use std::ffi::CStr;
+#[cfg(target_os = "linux")]
+use std::ffi::CString;
+#[cfg(target_os = "linux")]
+use std::fs::read_dir;
+use x11::xlib;
+
+#[cfg(target_os = "linux")]
+const DEV_INPUT: &str = "/dev/input";
+#[cfg(target_os = "linux")]
+const UINPUT: &[u8] = b"/dev/uinput\0";
+
+fn has_extension(display: &Display, name: &'static [u8]) -> bool {
+    let name = match CStr::from_bytes_with_nul(name) {
+        Ok(name) => name,
+        Err(_) => return false,
+    };
+    let (mut opcode, mut event, mut error) = (0, 0, 0);
+    unsafe {
+        xlib::XQueryExtension(
+            display.as_ptr(),
+            name.as_ptr(),
+            &mut opcode,
+            &mut event,
+            &mut error,
+        ) != FALSE
+    }
+}
+
+/// What the X server lacks for `need`, `Display` without one.
+fn x_missing(extension: &'static str, name: &'static [u8]) -> Option<Missing> {
+    match Display::new() {
+        None => Some(Missing::Display),
+        Some(display) if !has_extension(&display, name) => Some(Missing::XExtension(extension)),
+        Some(_) => None,
+    }
+}
+
+/// Whether some event node can be read, like the `input` group allows.
+#[cfg(target_os = "linux")]
+fn dev_input_readable() -> bool {
+    let entries = match read_dir(DEV_INPUT) {
+        Ok(entries) => entries,
+        Err(_) => return false,
+    };
+    entries.filter_map(Result::ok).any(|entry| {
+        let name = entry.file_name();
+        let name = name.to_string_lossy();
+        name.starts_with("event")
+            && CString::new(format!("{}/{}", DEV_INPUT, name))
+                .map(|path| unsafe { libc::access(path.as_ptr(), libc::R_OK) } == 0)
+                .unwrap_or(false)
+    })
+}
+
+#[cfg(target_os = "linux")]
+fn uinput_writable() -> bool {
+    CStr::from_bytes_with_nul(UINPUT)
+        .map(|path| unsafe { libc::access(path.as_ptr(), libc::W_OK) } == 0)
+        .unwrap_or(false)
+}
+
+pub fn missing(need: Need) -> Vec<Missing> {
+    let mut missing = vec![];
+    match need {
+        Need::Listen if crate::default_backend() == Backend::Evdev =>
+        {
+            #[cfg(target_os = "linux")]
+            if !dev_input_readable() {
+                missing.push(Missing::DevInput);
+            }
+        }
+        Need::Listen => missing.extend(x_missing("RECORD", b"RECORD\0")),
+        Need::Simulate => match x_missing("XTEST", b"XTEST\0") {
+            // Without X `simulate` goes through uinput.
+            #[cfg(all(target_os = "linux", feature = "evdev"))]
+            Some(Missing::Display) => {
+                if !uinput_writable() {
+                    missing.push(Missing::Uinput);
+                }
+            }
+            x => missing.extend(x),
+        },
+        // Grabs read the devices and send what passes through uinput.
+        Need::Grab => {
+            #[cfg(target_os = "linux")]
+            {
+                if !dev_input_readable() {
+                    missing.push(Missing::DevInput);
+                }
+                if !uinput_writable() {
+                    missing.push(Missing::Uinput);
+                }
+            }
+        }
+    }
+    missing
+}
@@ -0,0 +1,32 @@
+use std::mem;
+
+/// The scheduling the thread had before `raise_thread_priority`.
+pub struct ThreadPriority {
+    policy: libc::c_int,
+    param: libc::sched_param,
+}
+
+impl Drop for ThreadPriority {
+    fn drop(&mut self) {
+        unsafe { libc::pthread_setschedparam(libc::pthread_self(), self.policy, &self.param) };
+    }
+}
+
+/// SCHED_FIFO at its lowest priority: ahead of every regular thread,
+/// behind the real time ones of the system. None without the permission.
+pub fn raise_thread_priority() -> Option<ThreadPriority> {
+    unsafe {
+        let thread = libc::pthread_self();
+        let mut policy = 0;
+        let mut param: libc::sched_param = mem::zeroed();
+        if libc::pthread_getschedparam(thread, &mut policy, &mut param) != 0 {
+            return None;
+        }
+        let mut fifo = param;
+        fifo.sched_priority = libc::sched_get_priority_min(libc::SCHED_FIFO);
+        if libc::pthread_setschedparam(thread, libc::SCHED_FIFO, &fifo) != 0 {
+            return None;
+        }
+        Some(ThreadPriority { policy, param })
+    }
+}
@@ -1,9 +1,8 @@
-use crate::linux::common::{FALSE, TRUE};
+use crate::linux::common::{open_display, FALSE, TRUE};
 use crate::linux::keycodes::code_from_key;
 use crate::rdev::{Button, EventType, SimulateError};
-use std::convert::TryInto;
-use std::os::raw::c_int;
-use std::ptr::null;
+use std::convert::{TryFrom, TryInto};
+use std::os::raw::{c_int, c_uint, c_ulong};
 use x11::xlib;
 use x11::xtest;
 
@@ -21,6 +20,8 @@ unsafe fn send_native(event_type: &EventType, display: *mut xlib::Display) -> Op
             Button::Left => xtest::XTestFakeButtonEvent(display, 1, TRUE, 0),
             Button::Middle => xtest::XTestFakeButtonEvent(display, 2, TRUE, 0),
             Button::Right => xtest::XTestFakeButtonEvent(display, 3, TRUE, 0),
+            Button::Back => xtest::XTestFakeButtonEvent(display, 8, TRUE, 0),
+            Button::Forward => xtest::XTestFakeButtonEvent(display, 9, TRUE, 0),
             Button::Unknown(code) => {
                 xtest::XTestFakeButtonEvent(display, (*code).try_into().ok()?, TRUE, 0)
             }
@@ -29,6 +30,8 @@ unsafe fn send_native(event_type: &EventType, display: *mut xlib::Display) -> Op
             Button::Left => xtest::XTestFakeButtonEvent(display, 1, FALSE, 0),
             Button::Middle => xtest::XTestFakeButtonEvent(display, 2, FALSE, 0),
             Button::Right => xtest::XTestFakeButtonEvent(display, 3, FALSE, 0),
+            Button::Back => xtest::XTestFakeButtonEvent(display, 8, FALSE, 0),
+            Button::Forward => xtest::XTestFakeButtonEvent(display, 9, FALSE, 0),
             Button::Unknown(code) => {
                 xtest::XTestFakeButtonEvent(display, (*code).try_into().ok()?, FALSE, 0)
             }
@@ -69,6 +72,19 @@ unsafe fn send_native(event_type: &EventType, display: *mut xlib::Display) -> Op
             }
             result
         }
+        EventType::Scroll { .. }
+        | EventType::LayoutChanged
+        | EventType::ImeComposition
+        | EventType::ImeCommit
+        | EventType::SecureInput(_)
+        | EventType::TouchDown { .. }
+        | EventType::TouchMove { .. }
+        | EventType::TouchUp { .. }
+        | EventType::PenDown(_)
+        | EventType::PenMove(_)
+        | EventType::PenUp(_) => return None,
+        #[cfg(feature = "gestures")]
+        EventType::Gesture(_) => return None,
     };
     if res == 0 {
         None
@@ -79,21 +95,101 @@ unsafe fn send_native(event_type: &EventType, display: *mut xlib::Display) -> Op
 
 pub fn simulate(event_type: &EventType) -> Result<(), SimulateError> {
     unsafe {
-        let dpy = xlib::XOpenDisplay(null());
+        let dpy = open_display();
+        #[cfg(all(target_os = "linux", feature = "evdev"))]
         if dpy.is_null() {
-            return Err(SimulateError);
+            return crate::linux::uinput::simulate(event_type);
         }
-        match send_native(event_type, dpy) {
-            Some(_) => {
-                xlib::XFlush(dpy);
-                xlib::XSync(dpy, 0);
-                xlib::XCloseDisplay(dpy);
-                Ok(())
-            }
-            None => {
-                xlib::XCloseDisplay(dpy);
-                Err(SimulateError)
-            }
+        simulate_on(dpy, event_type)
+    }
+}
+
+pub fn simulate_x11(event_type: &EventType) -> Result<(), SimulateError> {
+    unsafe { simulate_on(open_display(), event_type) }
+}
+
+unsafe fn simulate_on(
+    dpy: *mut xlib::Display,
+    event_type: &EventType,
+) -> Result<(), SimulateError> {
+    if dpy.is_null() {
+        return Err(SimulateError);
+    }
+    match send_native(event_type, dpy) {
+        Some(_) => {
+            xlib::XFlush(dpy);
+            xlib::XSync(dpy, 0);
+            xlib::XCloseDisplay(dpy);
+            Ok(())
+        }
+        None => {
+            xlib::XCloseDisplay(dpy);
+            Err(SimulateError)
+        }
+    }
+}
+
+/// X11 keysyms for unicode characters, Latin-1 maps directly.
+fn keysym_from_char(c: char) -> c_ulong {
+    let codepoint = c as c_ulong;
+    if codepoint < 0x100 {
+        codepoint
+    } else {
+        0x0100_0000 | codepoint
+    }
+}
+
+/// Temporarily binds the character's keysym to an unused keycode, types it
+/// and restores the previous mapping.
+unsafe fn send_unicode(c: char, display: *mut xlib::Display) -> Option<()> {
+    let mut min_code = 0;
+    let mut max_code = 0;
+    xlib::XDisplayKeycodes(display, &mut min_code, &mut max_code);
+    let mut per_code = 0;
+    let mapping = xlib::XGetKeyboardMapping(
+        display,
+        min_code.try_into().ok()?,
+        max_code - min_code + 1,
+        &mut per_code,
+    );
+    if mapping.is_null() {
+        return None;
+    }
+    let per_code_len: usize = per_code.try_into().ok()?;
+    let count: usize = (max_code - min_code + 1).try_into().ok()?;
+    let keysyms = std::slice::from_raw_parts(mapping, count * per_code_len);
+    let free = keysyms
+        .chunks(per_code_len)
+        .position(|syms| syms.iter().all(|sym| *sym == 0));
+    xlib::XFree(mapping as *mut _);
+    let code = min_code + c_int::try_from(free?).ok()?;
+
+    let mut keysym = [keysym_from_char(c); 2];
+    xlib::XChangeKeyboardMapping(display, code, 2, keysym.as_mut_ptr(), 1);
+    xlib::XSync(display, FALSE);
+    let keycode = code as c_uint;
+    let res = xtest::XTestFakeKeyEvent(display, keycode, TRUE, 0)
+        & xtest::XTestFakeKeyEvent(display, keycode, FALSE, 0);
+    xlib::XSync(display, FALSE);
+
+    let mut no_symbol = [0; 2];
+    xlib::XChangeKeyboardMapping(display, code, 2, no_symbol.as_mut_ptr(), 1);
+    xlib::XSync(display, FALSE);
+    if res == 0 {
+        None
+    } else {
+        Some(())
+    }
+}
+
+pub fn simulate_unicode(c: char) -> Result<(), SimulateError> {
+    unsafe {
+        let dpy = open_display();
+        if dpy.is_null() {
+            return Err(SimulateError);
         }
+        let result = send_unicode(c, dpy);
+        xlib::XCloseDisplay(dpy);
+        result.ok_or(SimulateError)
     }
 }
@@ -0,0 +1,96 @@
+use crate::linux::common::Display;
+use crate::linux::keycodes::key_from_code;
+use crate::linux::simulate::simulate;
+use crate::rdev::{Button, ClickSettings, DisplayError, EventType, Key, LockKey, SimulateError};
+use std::collections::HashSet;
+use std::time::Duration;
+use x11::xlib;
+
+pub fn keyboard_state() -> Result<HashSet<Key>, DisplayError> {
+    let display = Display::new().ok_or(DisplayError::NoDisplay)?;
+    let keymap = display.get_keymap();
+    let mut keys = HashSet::new();
+    for (i, byte) in keymap.iter().enumerate() {
+        for bit in 0..8 {
+            if (*byte as u8) & (1 << bit) != 0 {
+                keys.insert(key_from_code((i * 8 + bit) as u32));
+            }
+        }
+    }
+    Ok(keys)
+}
+
+pub fn mouse_state() -> Result<HashSet<Button>, DisplayError> {
+    let display = Display::new().ok_or(DisplayError::NoDisplay)?;
+    let mask = display.get_pointer_mask().ok_or(DisplayError::NoDisplay)?;
+    let mut buttons = HashSet::new();
+    // Button4 and Button5 are the wheel, X doesn't report the other buttons.
+    if mask & xlib::Button1Mask != 0 {
+        buttons.insert(Button::Left);
+    }
+    if mask & xlib::Button2Mask != 0 {
+        buttons.insert(Button::Middle);
+    }
+    if mask & xlib::Button3Mask != 0 {
+        buttons.insert(Button::Right);
+    }
+    Ok(buttons)
+}
+
+pub fn get_mouse_position() -> Result<(f64, f64), DisplayError> {
+    let display = Display::new().ok_or(DisplayError::NoDisplay)?;
+    let (x, y) = display.get_mouse_pos().ok_or(DisplayError::NoDisplay)?;
+    Ok((x as f64, y as f64))
+}
+
+pub fn idle_time() -> Result<Duration, DisplayError> {
+    let display = Display::new().ok_or(DisplayError::NoDisplay)?;
+    display.get_idle_time().ok_or(DisplayError::NoDisplay)
+}
+
+/// X has no such settings, desktops share theirs with toolkits through
+/// XSETTINGS. The defaults for those missing, or without a desktop.
+pub fn click_settings() -> Result<ClickSettings, DisplayError> {
+    let display = Display::new().ok_or(DisplayError::NoDisplay)?;
+    let xsettings = display.get_xsettings().unwrap_or_default();
+    let setting = |name| xsettings.get(name).copied().filter(|value| *value > 0);
+    let default = ClickSettings::default();
+    Ok(ClickSettings {
+        double_click_interval: setting("Net/DoubleClickTime")
+            .map_or(default.double_click_interval, |millis| {
+                Duration::from_millis(millis as u64)
+            }),
+        double_click_distance: setting("Net/DoubleClickDistance")
+            .map_or(default.double_click_distance, f64::from),
+        drag_threshold: setting("Net/DndDragThreshold").map_or(default.drag_threshold, f64::from),
+        ..default
+    })
+}
+
+pub fn keyboard_repeat() -> Result<(Duration, f64), DisplayError> {
+    let display = Display::new().ok_or(DisplayError::NoDisplay)?;
+    let (delay, interval) = display.get_repeat_rate().ok_or(DisplayError::NoDisplay)?;
+    if !display.get_auto_repeat() || interval == Duration::from_millis(0) {
+        return Ok((delay, 0.0));
+    }
+    Ok((delay, 1.0 / interval.as_secs_f64()))
+}
+
+pub fn get_lock_state(lock: LockKey) -> Result<bool, DisplayError> {
+    let display = Display::new().ok_or(DisplayError::NoDisplay)?;
+    let name = match lock {
+        LockKey::CapsLock => "Caps Lock",
+        LockKey::NumLock => "Num Lock",
+        LockKey::ScrollLock => "Scroll Lock",
+    };
+    // Layouts without that indicator can't be locked.
+    Ok(display.get_named_indicator(name).unwrap_or(false))
+}
+
+pub fn set_lock_state(lock: LockKey, on: bool) -> Result<(), SimulateError> {
+    if get_lock_state(lock).map_err(|_| SimulateError)? == on {
+        return Ok(());
+    }
+    simulate(&EventType::KeyPress(lock.key()))?;
+    simulate(&EventType::KeyRelease(lock.key()))
+}
@@ -0,0 +1,240 @@
+use crate::linux::keycodes::code_from_key;
+use crate::rdev::{Button, EventType, SimulateError};
+use lazy_static::lazy_static;
+use std::convert::TryFrom;
+use std::ffi::CString;
+use std::mem::{size_of, zeroed};
+use std::os::raw::{c_char, c_int, c_void};
+use std::os::unix::io::RawFd;
+use std::sync::Mutex;
+use std::thread;
+use std::time::Duration;
+
+const UINPUT: &str = "/dev/uinput";
+const NAME: &[u8] = b"rdev virtual input";
+// From linux/uinput.h
+const UI_DEV_CREATE: u64 = 0x5501;
+const UI_SET_EVBIT: u64 = 0x4004_5564;
+const UI_SET_KEYBIT: u64 = 0x4004_5565;
+const UI_SET_RELBIT: u64 = 0x4004_5566;
+const UINPUT_MAX_NAME_SIZE: usize = 80;
+const ABS_CNT: usize = 64;
+// From linux/input-event-codes.h
+const EV_SYN: u16 = 0x00;
+const EV_KEY: u16 = 0x01;
+const EV_REL: u16 = 0x02;
+const SYN_REPORT: u16 = 0;
+const REL_X: u16 = 0x00;
+const REL_Y: u16 = 0x01;
+const REL_HWHEEL: u16 = 0x06;
+const REL_WHEEL: u16 = 0x08;
+const BUS_VIRTUAL: u16 = 0x06;
+const BTN_LEFT: u16 = 0x110;
+const BTN_RIGHT: u16 = 0x111;
+const BTN_MIDDLE: u16 = 0x112;
+const BTN_SIDE: u16 = 0x113;
+const BTN_EXTRA: u16 = 0x114;
+// Keyboard keys are below this.
+const BTN_MISC: u16 = 0x100;
+/// Readers (udev, libinput) need a moment to pick a new device up, events
+/// sent before are lost.
+const SETTLE: Duration = Duration::from_millis(200);
+/// Far enough to reach the top left corner from anywhere.
+const TO_CORNER: i32 = -(1 << 15);
+
+/// The legacy setup struct, understood by every kernel with uinput.
+#[repr(C)]
+struct UinputUserDev {
+    name: [c_char; UINPUT_MAX_NAME_SIZE],
+    id: libc::input_id,
+    ff_effects_max: u32,
+    absmax: [i32; ABS_CNT],
+    absmin: [i32; ABS_CNT],
+    absfuzz: [i32; ABS_CNT],
+    absflat: [i32; ABS_CNT],
+}
+
+/// A virtual keyboard and mouse. It can only move relatively, so like the
+/// evdev listener we start from the top left corner and move by the
+/// difference with the last position.
+struct UInput {
+    fd: RawFd,
+    x: f64,
+    y: f64,
+    /// Whether we moved to the corner yet, relative moves don't need to.
+    homed: bool,
+}
+
+lazy_static! {
+    /// Created on the first simulate and kept, the kernel removes it when
+    /// the process exits.
+    static ref DEVICE: Mutex<Option<UInput>> = Mutex::new(None);
+}
+
+impl UInput {
+    fn new() -> Option<UInput> {
+        let path = CString::new(UINPUT).ok()?;
+        let fd = unsafe { libc::open(path.as_ptr(), libc::O_WRONLY | libc::O_CLOEXEC) };
+        if fd < 0 {
+            return None;
+        }
+        match unsafe { Self::setup(fd) } {
+            Some(()) => {}
+            None => {
+                unsafe { libc::close(fd) };
+                return None;
+            }
+        }
+        thread::sleep(SETTLE);
+        Some(UInput {
+            fd,
+            x: 0.0,
+            y: 0.0,
+            homed: false,
+        })
+    }
+
+    unsafe fn setup(fd: RawFd) -> Option<()> {
+        let set = |request: u64, value: u16| libc::ioctl(fd, request as _, c_int::from(value)) >= 0;
+        let mut ok = set(UI_SET_EVBIT, EV_KEY) && set(UI_SET_EVBIT, EV_REL);
+        for code in (1..BTN_MISC).chain(BTN_LEFT..=BTN_EXTRA) {
+            ok = ok && set(UI_SET_KEYBIT, code);
+        }
+        for code in &[REL_X, REL_Y, REL_WHEEL, REL_HWHEEL] {
+            ok = ok && set(UI_SET_RELBIT, *code);
+        }
+        if !ok {
+            return None;
+        }
+        let mut setup: UinputUserDev = zeroed();
+        for (dst, src) in setup.name.iter_mut().zip(NAME) {
+            *dst = *src as c_char;
+        }
+        setup.id.bustype = BUS_VIRTUAL;
+        let written = libc::write(
+            fd,
+            &setup as *const UinputUserDev as *const c_void,
+            size_of::<UinputUserDev>(),
+        );
+        if written != size_of::<UinputUserDev>() as isize {
+            return None;
+        }
+        if libc::ioctl(fd, UI_DEV_CREATE as _) < 0 {
+            return None;
+        }
+        Some(())
+    }
+
+    /// Writes the events as one frame.
+    fn send(&self, events: &[(u16, u16, i32)]) -> Option<()> {
+        for (type_, code, value) in events.iter().chain(&[(EV_SYN, SYN_REPORT, 0)]) {
+            let mut event: libc::input_event = unsafe { zeroed() };
+            event.type_ = *type_;
+            event.code = *code;
+            event.value = *value;
+            let written = unsafe {
+                libc::write(
+                    self.fd,
+                    &event as *const libc::input_event as *const c_void,
+                    size_of::<libc::input_event>(),
+                )
+            };
+            if written != size_of::<libc::input_event>() as isize {
+                return None;
+            }
+        }
+        Some(())
+    }
+}
+
+fn button_code(button: &Button) -> Option<u16> {
+    match button {
+        Button::Left => Some(BTN_LEFT),
+        Button::Right => Some(BTN_RIGHT),
+        Button::Middle => Some(BTN_MIDDLE),
+        Button::Back => Some(BTN_SIDE),
+        Button::Forward => Some(BTN_EXTRA),
+        Button::Unknown(_) => None,
+    }
+}
+
+fn key_code(event_type: &EventType) -> Option<u16> {
+    match event_type {
+        // X keycodes are evdev codes shifted by 8.
+        EventType::KeyPress(key) | EventType::KeyRelease(key) => {
+            let code = code_from_key(*key)?.checked_sub(8)?;
+            u16::try_from(code).ok().filter(|code| *code < BTN_MISC)
+        }
+        _ => None,
+    }
+}
+
+fn send_native(device: &mut UInput, event_type: &EventType) -> Option<()> {
+    match event_type {
+        EventType::KeyPress(_) => device.send(&[(EV_KEY, key_code(event_type)?, 1)]),
+        EventType::KeyRelease(_) => device.send(&[(EV_KEY, key_code(event_type)?, 0)]),
+        EventType::ButtonPress(button) => device.send(&[(EV_KEY, button_code(button)?, 1)]),
+        EventType::ButtonRelease(button) => device.send(&[(EV_KEY, button_code(button)?, 0)]),
+        EventType::MouseMove { x, y, .. } => {
+            if !x.is_finite() || !y.is_finite() {
+                return None;
+            }
+            if !device.homed {
+                device.send(&[(EV_REL, REL_X, TO_CORNER), (EV_REL, REL_Y, TO_CORNER)])?;
+                device.x = 0.0;
+                device.y = 0.0;
+                device.homed = true;
+            }
+            let dx = (x - device.x).round();
+            let dy = (y - device.y).round();
+            device.send(&[(EV_REL, REL_X, dx as i32), (EV_REL, REL_Y, dy as i32)])?;
+            device.x += dx;
+            device.y += dy;
+            Some(())
+        }
+        EventType::Wheel { delta_x, delta_y } => {
+            let delta_x = i32::try_from(*delta_x).ok()?;
+            let delta_y = i32::try_from(*delta_y).ok()?;
+            device.send(&[(EV_REL, REL_HWHEEL, delta_x), (EV_REL, REL_WHEEL, delta_y)])
+        }
+        _ => None,
+    }
+}
+
+/// Sends events through a uinput device, for machines without an X server.
+/// Needs write access to /dev/uinput.
+pub fn simulate(event_type: &EventType) -> Result<(), SimulateError> {
+    with_device(|device| send_native(device, event_type))
+}
+
+fn with_device<F>(send: F) -> Result<(), SimulateError>
+where
+    F: FnOnce(&mut UInput) -> Option<()>,
+{
+    let mut device = DEVICE.lock().map_err(|_| SimulateError)?;
+    if device.is_none() {
+        *device = UInput::new();
+    }
+    let device = device.as_mut().ok_or(SimulateError)?;
+    send(device).ok_or(SimulateError)
+}
+
+/// Creates the device if needed, so we know events can be sent.
+pub fn open() -> Result<(), SimulateError> {
+    with_device(|_| Some(()))
+}
+
+/// Moves the pointer by a delta, wherever it is.
+pub fn move_by(dx: f64, dy: f64) -> Result<(), SimulateError> {
+    if !dx.is_finite() || !dy.is_finite() {
+        return Err(SimulateError);
+    }
+    with_device(|device| {
+        let dx = dx.round();
+        let dy = dy.round();
+        device.send(&[(EV_REL, REL_X, dx as i32), (EV_REL, REL_Y, dy as i32)])?;
+        device.x += dx;
+        device.y += dy;
+        Some(())
+    })
+}
@@ -0,0 +1,116 @@
+use crate::rdev::{DisplayError, DisplayInfo};
+use std::cell::RefCell;
+use std::rc::Rc;
+use wayland_client::protocol::{wl_output, wl_registry};
+use wayland_client::Display;
+
+/// What wl_output told us about one monitor.
+#[derive(Debug, Default)]
+struct Output {
+    x: i64,
+    y: i64,
+    width: u64,
+    height: u64,
+    scale: i32,
+    refresh_rate: Option<f64>,
+}
+
+/// Monitors from wl_output, used when there is no X server to ask. The
+/// position is in the compositor space and the size is the current mode in
+/// pixels. Wayland has no primary monitor, the first output is used.
+pub fn displays() -> Result<Vec<DisplayInfo>, DisplayError> {
+    let display = Display::connect_to_env().map_err(|_| DisplayError::NoDisplay)?;
+    let mut queue = display.create_event_queue();
+    let attached = (*display).clone().attach(queue.token());
+    let outputs: Rc<RefCell<Vec<Output>>> = Rc::new(RefCell::new(vec![]));
+
+    let found = outputs.clone();
+    let registry = attached.get_registry();
+    registry.quick_assign(move |registry, event, _| {
+        if let wl_registry::Event::Global {
+            name,
+            interface,
+            version,
+        } = event
+        {
+            if interface != "wl_output" {
+                return;
+            }
+            let index = {
+                let mut outputs = found.borrow_mut();
+                outputs.push(Output::default());
+                outputs.len() - 1
+            };
+            // Scale events exist since version 2.
+            let output = registry.bind::<wl_output::WlOutput>(version.min(2), name);
+            let outputs = found.clone();
+            output.quick_assign(move |_, event, _| {
+                let mut outputs = outputs.borrow_mut();
+                let output = &mut outputs[index];
+                match event {
+                    wl_output::Event::Geometry { x, y, .. } => {
+                        output.x = x.into();
+                        output.y = y.into();
+                    }
+                    wl_output::Event::Mode {
+                        flags,
+                        width,
+                        height,
+                        refresh,
+                    } if flags.contains(wl_output::Mode::Current) => {
+                        output.width = width.max(0) as u64;
+                        output.height = height.max(0) as u64;
+                        // In mHz, 0 when unknown.
+                        output.refresh_rate = Some(f64::from(refresh) / 1000.0)
+                            .filter(|refresh_rate| *refresh_rate > 0.0);
+                    }
+                    wl_output::Event::Scale { factor } => output.scale = factor,
+                    _ => {}
+                }
+            });
+        }
+    });
+    // Globals come with the first roundtrip, what the bound outputs send
+    // with the second.
+    for _ in 0..2 {
+        queue
+            .sync_roundtrip(&mut (), |_, _, _| {})
+            .map_err(|_| DisplayError::NoDisplay)?;
+    }
+
+    let outputs = outputs.borrow();
+    if outputs.is_empty() {
+        return Err(DisplayError::NoDisplay);
+    }
+    Ok(outputs
+        .iter()
+        .enumerate()
+        .map(|(index, output)| DisplayInfo {
+            x: output.x,
+            y: output.y,
+            width: output.width,
+            height: output.height,
+            scale_factor: f64::from(output.scale.max(1)),
+            refresh_rate: output.refresh_rate,
+            is_primary: index == 0,
+        })
+        .collect())
+}
+
+/// Like the X root window, the box around every monitor.
+pub fn display_size() -> Result<(u64, u64), DisplayError> {
+    let displays = displays()?;
+    let left = displays.iter().map(|display| display.x).min().unwrap_or(0);
+    let top = displays.iter().map(|display| display.y).min().unwrap_or(0);
+    let right = displays
+        .iter()
+        .map(|display| display.x + display.width as i64)
+        .max()
+        .unwrap_or(0);
+    let bottom = displays
+        .iter()
+        .map(|display| display.y + display.height as i64)
+        .max()
+        .unwrap_or(0);
+    Ok(((right - left) as u64, (bottom - top) as u64))
+}
@@ -0,0 +1,228 @@
+use crate::linux::common::{convert, layout_event, Display, FALSE};
+use crate::linux::keyboard::Keyboard;
+use crate::motion::{display_rects, Motion};
+use crate::rdev::{Event, EventType, ListenError, OsError};
+use std::collections::HashMap;
+use std::ffi::CStr;
+use std::mem::zeroed;
+use std::os::raw::{c_int, c_uint};
+use std::slice;
+use x11::{xinput2, xlib};
+
+/// Asks the server for raw key, button and motion events of every master
+/// device on the root window. Unlike RECORD they aren't tied to clients,
+/// and aren't held back by grabs. Returns the XInput opcode.
+unsafe fn select_raw_events(display: *mut xlib::Display) -> Result<c_int, ListenError> {
+    let missing = || ListenError::XInputError(OsError::new("XQueryExtension", 0));
+    let name = CStr::from_bytes_with_nul(b"XInputExtension\0").map_err(|_| missing())?;
+    let mut opcode = 0;
+    let mut event = 0;
+    let mut error = 0;
+    if xlib::XQueryExtension(display, name.as_ptr(), &mut opcode, &mut event, &mut error) == FALSE {
+        return Err(missing());
+    }
+    // Raw events reach the root window since 2.0, but only regardless of
+    // grabs since 2.1.
+    let mut major = 2;
+    let mut minor = 1;
+    let status = xinput2::XIQueryVersion(display, &mut major, &mut minor);
+    if status != xlib::Success as c_int {
+        let error = OsError::new("XIQueryVersion", status.into());
+        return Err(ListenError::XInputError(error));
+    }
+    let mut mask = [0_u8; 3];
+    for event in &[
+        xinput2::XI_RawKeyPress,
+        xinput2::XI_RawKeyRelease,
+        xinput2::XI_RawButtonPress,
+        xinput2::XI_RawButtonRelease,
+        xinput2::XI_RawMotion,
+    ] {
+        xinput2::XISetMask(&mut mask, *event);
+    }
+    let mut event_mask = xinput2::XIEventMask {
+        deviceid: xinput2::XIAllMasterDevices,
+        mask_len: mask.len() as c_int,
+        mask: mask.as_mut_ptr(),
+    };
+    xinput2::XISelectEvents(
+        display,
+        xlib::XDefaultRootWindow(display),
+        &mut event_mask,
+        1,
+    );
+    xlib::XFlush(display);
+    Ok(opcode)
+}
+
+/// Whether the first valuator of `device` is relative, absolute devices
+/// (tablets, touchscreens, virtual machines) give positions, not deltas.
+unsafe fn is_relative(display: *mut xlib::Display, device: c_int) -> bool {
+    let mut count = 0;
+    let info = xinput2::XIQueryDevice(display, device, &mut count);
+    if info.is_null() {
+        return false;
+    }
+    let mut relative = false;
+    if count > 0 {
+        let info = &*info;
+        let classes = slice::from_raw_parts(info.classes, info.num_classes as usize);
+        for class in classes {
+            if (**class)._type == xinput2::XIValuatorClass {
+                let valuator = &*(*class as *const xinput2::XIValuatorClassInfo);
+                if valuator.number == 0 {
+                    relative = valuator.mode == xinput2::XIModeRelative;
+                }
+            }
+        }
+    }
+    xinput2::XIFreeDeviceInfo(info);
+    relative
+}
+
+/// The unaccelerated value of `valuator`, values are only sent for the
+/// valuators that changed, packed in the order of the mask.
+unsafe fn raw_value(event: &xinput2::XIRawEvent, valuator: c_int) -> Option<f64> {
+    let state = &event.valuators;
+    if state.mask.is_null() || (valuator >> 3) >= state.mask_len {
+        return None;
+    }
+    let mask = slice::from_raw_parts(state.mask, state.mask_len as usize);
+    if !xinput2::XIMaskIsSet(mask, valuator) {
+        return None;
+    }
+    let index = (0..valuator)
+        .filter(|bit| xinput2::XIMaskIsSet(mask, *bit))
+        .count();
+    Some(*event.raw_values.add(index))
+}
+
+unsafe fn pointer_position(display: *mut xlib::Display) -> Option<(f64, f64)> {
+    let mut root = 0;
+    let mut child = 0;
+    let mut root_x = 0;
+    let mut root_y = 0;
+    let mut x = 0;
+    let mut y = 0;
+    let mut mask = 0;
+    let status = xlib::XQueryPointer(
+        display,
+        xlib::XDefaultRootWindow(display),
+        &mut root,
+        &mut child,
+        &mut root_x,
+        &mut root_y,
+        &mut x,
+        &mut y,
+        &mut mask,
+    );
+    if status == FALSE {
+        return None;
+    }
+    Some((root_x as f64, root_y as f64))
+}
+
+/// What converting events needs to remember. Everything happens on the
+/// thread of `listen`, unlike RECORD there's no callback needing globals.
+struct State {
+    keyboard: Option<Keyboard>,
+    motion: Motion,
+    /// Whether each device reports relative motion.
+    relative: HashMap<c_int, bool>,
+}
+
+unsafe fn convert_raw(
+    display: *mut xlib::Display,
+    raw: &xinput2::XIRawEvent,
+    state: &mut State,
+) -> Option<Event> {
+    let type_ = match raw.evtype {
+        xinput2::XI_RawKeyPress => xlib::KeyPress,
+        xinput2::XI_RawKeyRelease => xlib::KeyRelease,
+        xinput2::XI_RawButtonPress => xlib::ButtonPress,
+        xinput2::XI_RawButtonRelease => xlib::ButtonRelease,
+        xinput2::XI_RawMotion => xlib::MotionNotify,
+        _ => return None,
+    };
+    // Raw events have no position, only motion needs one.
+    let (x, y) = if type_ == xlib::MotionNotify {
+        pointer_position(display)?
+    } else {
+        (0.0, 0.0)
+    };
+    let mut event = convert(&mut state.keyboard, raw.detail as c_uint, type_, x, y)?;
+    if let EventType::MouseMove { .. } = event.event_type {
+        let relative = *state
+            .relative
+            .entry(raw.sourceid)
+            .or_insert_with(|| is_relative(display, raw.sourceid));
+        if relative {
+            let delta_x = raw_value(raw, 0).unwrap_or(0.0);
+            let delta_y = raw_value(raw, 1).unwrap_or(0.0);
+            event.delta = Some((delta_x, delta_y));
+        }
+    }
+    state.motion.annotate(&mut event, display_rects);
+    Some(event)
+}
+
+/// Listens with XInput 2 raw events instead of RECORD. Deltas of relative
+/// devices are the unaccelerated ones.
+pub fn listen<T>(mut callback: T) -> Result<(), ListenError>
+where
+    T: FnMut(Event) + 'static,
+{
+    let keyboard = Keyboard::new().ok_or(ListenError::KeyboardError)?;
+    let mut state = State {
+        keyboard: Some(keyboard),
+        motion: Motion::new(),
+        relative: HashMap::new(),
+    };
+    unsafe {
+        let connection = Display::new().ok_or(ListenError::MissingDisplayError)?;
+        let display = connection.as_ptr();
+        let opcode = select_raw_events(display)?;
+        crate::trace::installed("XInput 2 raw events");
+        let mut event: xlib::XEvent = zeroed();
+        loop {
+            xlib::XNextEvent(display, &mut event);
+            let cookie = &mut event.generic_event_cookie;
+            if cookie.type_ != xlib::GenericEvent || cookie.extension != opcode {
+                continue;
+            }
+            if xlib::XGetEventData(display, cookie) == FALSE {
+                continue;
+            }
+            let raw = &*(cookie.data as *const xinput2::XIRawEvent);
+            if let Some(event) = convert_raw(display, raw, &mut state) {
+                if let Some(layout) = layout_event(&mut state.keyboard) {
+                    callback(layout);
+                }
+                callback(event);
+            }
+            xlib::XFreeEventData(display, cookie);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_raw_value() {
+        // Valuators 1 and 3 changed.
+        let mut mask = [0b1010_u8];
+        let mut values = [4.0, 8.0];
+        let mut event = xinput2::XIRawEvent::default();
+        event.valuators.mask_len = 1;
+        event.valuators.mask = mask.as_mut_ptr();
+        event.raw_values = values.as_mut_ptr();
+        unsafe {
+            assert_eq!(raw_value(&event, 0), None);
+            assert_eq!(raw_value(&event, 1), Some(4.0));
+            assert_eq!(raw_value(&event, 3), Some(8.0));
+            assert_eq!(raw_value(&event, 9), None);
+        }
+    }
+}
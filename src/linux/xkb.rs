@@ -0,0 +1,104 @@
+use std::os::raw::{c_char, c_int, c_void};
+use std::ptr::null;
+
+// From xkbcommon.h
+const XKB_CONTEXT_NO_FLAGS: c_int = 0;
+const XKB_KEYMAP_COMPILE_NO_FLAGS: c_int = 0;
+const XKB_KEY_UP: c_int = 0;
+const XKB_KEY_DOWN: c_int = 1;
+// XKB_KEY_dead_grave to XKB_KEY_dead_longsolidusoverlay
+const DEAD_KEYSYMS: std::ops::RangeInclusive<u32> = 0xfe50..=0xfe93;
+// Like X, xkb keycodes are evdev codes shifted by 8.
+const EVDEV_OFFSET: u32 = 8;
+
+#[link(name = "xkbcommon")]
+extern "C" {
+    fn xkb_context_new(flags: c_int) -> *mut c_void;
+    fn xkb_context_unref(context: *mut c_void);
+    fn xkb_keymap_new_from_names(
+        context: *mut c_void,
+        names: *const c_void,
+        flags: c_int,
+    ) -> *mut c_void;
+    fn xkb_keymap_unref(keymap: *mut c_void);
+    fn xkb_state_new(keymap: *mut c_void) -> *mut c_void;
+    fn xkb_state_unref(state: *mut c_void);
+    fn xkb_state_update_key(state: *mut c_void, key: u32, direction: c_int) -> c_int;
+    fn xkb_state_key_get_one_sym(state: *mut c_void, key: u32) -> u32;
+    fn xkb_state_key_get_utf8(
+        state: *mut c_void,
+        key: u32,
+        buffer: *mut c_char,
+        size: usize,
+    ) -> c_int;
+}
+
+/// Names keys without a display server. The keymap is the default one of
+/// xkbcommon, which follows the XKB_DEFAULT_LAYOUT (and _VARIANT, _OPTIONS)
+/// environment variables, the compositor keymap isn't reachable from here.
+pub struct XkbKeyboard {
+    context: *mut c_void,
+    keymap: *mut c_void,
+    state: *mut c_void,
+}
+
+impl XkbKeyboard {
+    pub fn new() -> Option<XkbKeyboard> {
+        unsafe {
+            let context = xkb_context_new(XKB_CONTEXT_NO_FLAGS);
+            if context.is_null() {
+                return None;
+            }
+            let keymap = xkb_keymap_new_from_names(context, null(), XKB_KEYMAP_COMPILE_NO_FLAGS);
+            if keymap.is_null() {
+                xkb_context_unref(context);
+                return None;
+            }
+            let state = xkb_state_new(keymap);
+            if state.is_null() {
+                xkb_keymap_unref(keymap);
+                xkb_context_unref(context);
+                return None;
+            }
+            Some(XkbKeyboard {
+                context,
+                keymap,
+                state,
+            })
+        }
+    }
+
+    /// The text of the evdev key `code` and whether it's a dead key, looked
+    /// up before the press changes the modifiers.
+    pub fn press(&mut self, code: u32) -> (Option<String>, bool) {
+        let key = code + EVDEV_OFFSET;
+        unsafe {
+            let is_dead = DEAD_KEYSYMS.contains(&xkb_state_key_get_one_sym(self.state, key));
+            let mut buffer = [0 as c_char; 64];
+            let len = xkb_state_key_get_utf8(self.state, key, buffer.as_mut_ptr(), buffer.len());
+            xkb_state_update_key(self.state, key, XKB_KEY_DOWN);
+            if len <= 0 {
+                return (None, is_dead);
+            }
+            let len = (len as usize).min(buffer.len() - 1);
+            let bytes: Vec<u8> = buffer[..len].iter().map(|byte| *byte as u8).collect();
+            (String::from_utf8(bytes).ok(), is_dead)
+        }
+    }
+
+    pub fn release(&mut self, code: u32) {
+        unsafe {
+            xkb_state_update_key(self.state, code + EVDEV_OFFSET, XKB_KEY_UP);
+        }
+    }
+}
+
+impl Drop for XkbKeyboard {
+    fn drop(&mut self) {
+        unsafe {
+            xkb_state_unref(self.state);
+            xkb_keymap_unref(self.keymap);
+            xkb_context_unref(self.context);
+        }
+    }
+}
@@ -1,15 +1,24 @@
 #![allow(clippy::upper_case_acronyms)]
 use crate::macos::keyboard::Keyboard;
-use crate::rdev::{Button, Event, EventType};
-use cocoa::base::id;
+use crate::motion::{display_rects, Motion};
+use crate::rdev::{Button, Event, EventType, Pen, ScrollPhase, UnicodeInfo};
+#[cfg(feature = "gestures")]
+use crate::rdev::{Gesture, GesturePhase};
+#[cfg(feature = "gestures")]
+use cocoa::appkit::NSEvent;
+use cocoa::base::{id, nil};
+use cocoa::foundation::{NSInteger, NSPoint, NSUInteger};
+use core_foundation::base::CFRelease;
 use core_graphics::event::{CGEvent, CGEventFlags, CGEventTapLocation, CGEventType, EventField};
 use lazy_static::lazy_static;
 use std::convert::TryInto;
 use std::os::raw::c_void;
+use std::ptr::addr_of_mut;
 use std::sync::Mutex;
 use std::time::SystemTime;
 
-use crate::macos::keycodes::key_from_code;
+use crate::macos::keycodes::{key_from_code, key_from_nx_code};
+use objc::{class, msg_send, sel, sel_impl};
 
 pub type CFMachPortRef = *const c_void;
 pub type CFIndex = u64;
@@ -29,12 +38,74 @@ pub const kCGHeadInsertEventTap: u32 = 0;
 #[allow(non_upper_case_globals)]
 #[repr(u32)]
 pub enum CGEventTapOption {
-    #[cfg(feature = "unstable_grab")]
     Default = 0,
     ListenOnly = 1,
 }
 
+// The tap callback gets these instead of an event when the system turned it
+// off, because a callback took too long or for secure input.
+pub const TAP_DISABLED_BY_TIMEOUT: u32 = 0xFFFF_FFFE;
+pub const TAP_DISABLED_BY_USER_INPUT: u32 = 0xFFFF_FFFF;
+
+// NSSystemDefined, not part of CGEventType but used for media keys.
+pub const NX_SYSDEFINED: u32 = 14;
+// NSEvent subtype for system defined key events (NX_SUBTYPE_AUX_CONTROL_BUTTONS).
+pub const NX_SUBTYPE_AUX_CONTROL_BUTTONS: i16 = 8;
+pub const NX_KEYSTATE_DOWN: NSInteger = 0x0a;
+// NSEventTypeGesture, trackpad touches come with those.
+pub const NX_GESTURE: u32 = 29;
+// NSEventTypeMagnify, NSEventTypeSwipe and NSEventTypeRotate.
+pub const NX_MAGNIFY: u32 = 30;
+pub const NX_SWIPE: u32 = 31;
+pub const NX_ROTATE: u32 = 18;
+
+// https://developer.apple.com/documentation/appkit/nseventphase?language=objc
+#[cfg(feature = "gestures")]
+const NS_EVENT_PHASE_BEGAN: NSUInteger = 1 << 0;
+#[cfg(feature = "gestures")]
+const NS_EVENT_PHASE_STATIONARY: NSUInteger = 1 << 1;
+#[cfg(feature = "gestures")]
+const NS_EVENT_PHASE_CHANGED: NSUInteger = 1 << 2;
+#[cfg(feature = "gestures")]
+const NS_EVENT_PHASE_MAY_BEGIN: NSUInteger = 1 << 5;
+
+#[cfg(feature = "gestures")]
+const GESTURE_EVENTS: u64 =
+    (1 << NX_MAGNIFY as u64) + (1 << NX_SWIPE as u64) + (1 << NX_ROTATE as u64);
+#[cfg(not(feature = "gestures"))]
+const GESTURE_EVENTS: u64 = 0;
+
+// https://developer.apple.com/documentation/appkit/nstouchphase?language=objc
+const NS_TOUCH_PHASE_BEGAN: NSUInteger = 1 << 0;
+const NS_TOUCH_PHASE_MOVED: NSUInteger = 1 << 1;
+const NS_TOUCH_PHASE_ENDED: NSUInteger = 1 << 3;
+const NS_TOUCH_PHASE_CANCELLED: NSUInteger = 1 << 4;
+const NS_TOUCH_PHASE_ANY: NSUInteger = NSUInteger::MAX;
+
+// kCGScrollWheelEventScrollPhase and kCGScrollWheelEventMomentumPhase
+pub const SCROLL_WHEEL_EVENT_SCROLL_PHASE: u32 = 99;
+pub const SCROLL_WHEEL_EVENT_MOMENTUM_PHASE: u32 = 123;
+// kCGScrollWheelEventIsContinuous, set by trackpads and Magic Mice.
+pub const SCROLL_WHEEL_EVENT_IS_CONTINUOUS: u32 = 88;
+// https://developer.apple.com/documentation/coregraphics/cgscrollphase?language=objc
+const SCROLL_PHASE_BEGAN: i64 = 1;
+const SCROLL_PHASE_CHANGED: i64 = 2;
+const SCROLL_PHASE_ENDED: i64 = 4;
+const SCROLL_PHASE_MAY_BEGIN: i64 = 128;
+// https://developer.apple.com/documentation/coregraphics/cgmomentumscrollphase?language=objc
+const MOMENTUM_PHASE_BEGIN: i64 = 1;
+const MOMENTUM_PHASE_CONTINUE: i64 = 2;
+const MOMENTUM_PHASE_END: i64 = 3;
+
+// kCGEventMouseSubtypeTabletPoint, mouse events sent by a tablet pen.
+const TABLET_POINT: i64 = 1;
+// NSPointingDeviceTypeEraser
+const ERASER_POINTER: i64 = 3;
+
 pub static mut LAST_FLAGS: CGEventFlags = CGEventFlags::CGEventFlagNull;
+/// Proximity events tell which end of the pen is near the tablet.
+static mut PEN_ERASER: bool = false;
+static mut MOTION: Motion = Motion::new();
 lazy_static! {
     pub static ref KEYBOARD_STATE: Mutex<Keyboard> = Mutex::new(Keyboard::new().unwrap());
 }
@@ -46,13 +117,19 @@ pub const kCGEventMaskForAllEvents: u64 = (1 << CGEventType::LeftMouseDown as u6
     + (1 << CGEventType::LeftMouseUp as u64)
     + (1 << CGEventType::RightMouseDown as u64)
     + (1 << CGEventType::RightMouseUp as u64)
+    + (1 << CGEventType::OtherMouseDown as u64)
+    + (1 << CGEventType::OtherMouseUp as u64)
     + (1 << CGEventType::MouseMoved as u64)
     + (1 << CGEventType::LeftMouseDragged as u64)
     + (1 << CGEventType::RightMouseDragged as u64)
     + (1 << CGEventType::KeyDown as u64)
     + (1 << CGEventType::KeyUp as u64)
     + (1 << CGEventType::FlagsChanged as u64)
-    + (1 << CGEventType::ScrollWheel as u64);
+    + (1 << CGEventType::ScrollWheel as u64)
+    + (1 << CGEventType::TabletProximity as u64)
+    + (1 << NX_SYSDEFINED as u64)
+    + (1 << NX_GESTURE as u64)
+    + GESTURE_EVENTS;
 
 #[cfg(target_os = "macos")]
 #[link(name = "Cocoa", kind = "framework")]
@@ -72,35 +149,356 @@ extern "C" {
         order: CFIndex,
     ) -> CFRunLoopSourceRef;
     pub fn CFRunLoopAddSource(rl: CFRunLoopRef, source: CFRunLoopSourceRef, mode: CFRunLoopMode);
+    pub fn CFRunLoopRemoveSource(rl: CFRunLoopRef, source: CFRunLoopSourceRef, mode: CFRunLoopMode);
     pub fn CFRunLoopGetCurrent() -> CFRunLoopRef;
     pub fn CGEventTapEnable(tap: CFMachPortRef, enable: bool);
+    pub fn CFMachPortInvalidate(port: CFMachPortRef);
     pub fn CFRunLoopRun();
 
     pub static kCFRunLoopCommonModes: CFRunLoopMode;
 
 }
 
+/// An event tap and its run loop source, disabled, removed from the run
+/// loop of the thread and released on drop. Otherwise the tap stays in the
+/// event stream after `listen` or `grab` returned, and with `grab` the
+/// system waits on it for every event.
+pub struct EventTap {
+    pub tap: CFMachPortRef,
+    source: CFRunLoopSourceRef,
+}
+
+impl EventTap {
+    pub unsafe fn new(tap: CFMachPortRef) -> EventTap {
+        EventTap { tap, source: nil }
+    }
+
+    /// Adds the tap to the run loop of the thread and enables it, false if
+    /// its source couldn't be created.
+    pub unsafe fn add_to_run_loop(&mut self) -> bool {
+        self.source = CFMachPortCreateRunLoopSource(nil, self.tap, 0);
+        if self.source.is_null() {
+            return false;
+        }
+        CFRunLoopAddSource(CFRunLoopGetCurrent(), self.source, kCFRunLoopCommonModes);
+        CGEventTapEnable(self.tap, true);
+        crate::trace::installed("event tap");
+        true
+    }
+}
+
+impl Drop for EventTap {
+    fn drop(&mut self) {
+        unsafe {
+            CGEventTapEnable(self.tap, false);
+            if !self.source.is_null() {
+                CFRunLoopRemoveSource(CFRunLoopGetCurrent(), self.source, kCFRunLoopCommonModes);
+                CFRelease(self.source as *const c_void);
+            }
+            CFMachPortInvalidate(self.tap);
+            CFRelease(self.tap);
+        }
+    }
+}
+
 // TODO Remove this, this was added as the coded
 // existed and worked, but clippy is complaining.
 // There's probably a better fix.
 #[allow(improper_ctypes_definitions)]
 pub type QCallback = unsafe extern "C" fn(
     proxy: CGEventTapProxy,
-    _type: CGEventType,
+    _type: u32,
     cg_event: CGEventRef,
     user_info: *mut c_void,
 ) -> CGEventRef;
 
+/// The CGEventType of a raw type, None for those it doesn't know about.
+fn cg_event_type(raw_type: u32) -> Option<CGEventType> {
+    let _type = match raw_type {
+        0 => CGEventType::Null,
+        1 => CGEventType::LeftMouseDown,
+        2 => CGEventType::LeftMouseUp,
+        3 => CGEventType::RightMouseDown,
+        4 => CGEventType::RightMouseUp,
+        5 => CGEventType::MouseMoved,
+        6 => CGEventType::LeftMouseDragged,
+        7 => CGEventType::RightMouseDragged,
+        10 => CGEventType::KeyDown,
+        11 => CGEventType::KeyUp,
+        12 => CGEventType::FlagsChanged,
+        22 => CGEventType::ScrollWheel,
+        23 => CGEventType::TabletPointer,
+        24 => CGEventType::TabletProximity,
+        25 => CGEventType::OtherMouseDown,
+        26 => CGEventType::OtherMouseUp,
+        27 => CGEventType::OtherMouseDragged,
+        0xFFFF_FFFE => CGEventType::TapDisabledByTimeout,
+        0xFFFF_FFFF => CGEventType::TapDisabledByUserInput,
+        _ => return None,
+    };
+    Some(_type)
+}
+
+/// CGEventType doesn't know about NSSystemDefined events, so taps receive
+/// the raw type and we only turn it into a CGEventType once we know it's a
+/// regular event.
+pub unsafe fn convert_raw(
+    raw_type: u32,
+    cg_event: &CGEvent,
+    keyboard_state: &mut Keyboard,
+) -> Option<Event> {
+    if raw_type == NX_SYSDEFINED {
+        return convert_system_defined(cg_event);
+    }
+    convert(cg_event_type(raw_type)?, cg_event, keyboard_state)
+}
+
+/// Gesture events aren't CGEventTypes, they only make sense as NSEvents.
+pub fn is_gesture(raw_type: u32) -> bool {
+    matches!(raw_type, NX_GESTURE | NX_MAGNIFY | NX_SWIPE | NX_ROTATE)
+}
+
+#[cfg(feature = "gestures")]
+pub unsafe fn convert_gesture(cg_event: &CGEvent) -> Option<Event> {
+    let cg_event_ptr = &**cg_event as *const _ as id;
+    let ns_event: id = msg_send![class!(NSEvent), eventWithCGEvent: cg_event_ptr];
+    if ns_event.is_null() {
+        return None;
+    }
+    let phase: NSUInteger = msg_send![ns_event, phase];
+    let phase = match phase {
+        NS_EVENT_PHASE_BEGAN | NS_EVENT_PHASE_MAY_BEGIN => GesturePhase::Begin,
+        NS_EVENT_PHASE_CHANGED | NS_EVENT_PHASE_STATIONARY => GesturePhase::Change,
+        // Ended, cancelled or none for swipes that come as a single event.
+        _ => GesturePhase::End,
+    };
+    let gesture = match ns_event.eventType() as u32 {
+        NX_MAGNIFY => {
+            let delta: f64 = msg_send![ns_event, magnification];
+            Gesture::Magnify { phase, delta }
+        }
+        NX_ROTATE => {
+            let degrees: f32 = msg_send![ns_event, rotation];
+            Gesture::Rotate {
+                phase,
+                degrees: degrees.into(),
+            }
+        }
+        NX_SWIPE => {
+            let delta_x: f64 = msg_send![ns_event, deltaX];
+            let delta_y: f64 = msg_send![ns_event, deltaY];
+            Gesture::Swipe {
+                phase,
+                delta_x,
+                delta_y,
+            }
+        }
+        _ => return None,
+    };
+    Some(Event {
+        event_type: EventType::Gesture(gesture),
+        time: SystemTime::now(),
+        name: None,
+        unicode: None,
+        scan_code: 0,
+        display: None,
+        delta: None,
+        numlock: None,
+    })
+}
+
+/// Gesture events carry every finger on the trackpad, we emit one event per
+/// finger that moved. Trackpads aren't tied to the screen so positions are
+/// normalized, with (0, 0) at the top left like the screen. There's no
+/// pressure on NSTouch.
+pub unsafe fn convert_touches(raw_type: u32, cg_event: &CGEvent) -> Vec<Event> {
+    let mut events = vec![];
+    if raw_type != NX_GESTURE {
+        return events;
+    }
+    let cg_event_ptr = &**cg_event as *const _ as id;
+    let ns_event: id = msg_send![class!(NSEvent), eventWithCGEvent: cg_event_ptr];
+    if ns_event.is_null() {
+        return events;
+    }
+    let touches: id = msg_send![ns_event, touchesMatchingPhase: NS_TOUCH_PHASE_ANY inView: nil];
+    let touches: id = msg_send![touches, allObjects];
+    let count: NSUInteger = msg_send![touches, count];
+    for i in 0..count {
+        let touch: id = msg_send![touches, objectAtIndex: i];
+        let phase: NSUInteger = msg_send![touch, phase];
+        let identity: id = msg_send![touch, identity];
+        let id: NSUInteger = msg_send![identity, hash];
+        let position: NSPoint = msg_send![touch, normalizedPosition];
+        let (id, x, y, pressure) = (id as u64, position.x, 1.0 - position.y, 1.0);
+        let event_type = if phase & NS_TOUCH_PHASE_BEGAN != 0 {
+            EventType::TouchDown { id, x, y, pressure }
+        } else if phase & NS_TOUCH_PHASE_MOVED != 0 {
+            EventType::TouchMove { id, x, y, pressure }
+        } else if phase & (NS_TOUCH_PHASE_ENDED | NS_TOUCH_PHASE_CANCELLED) != 0 {
+            EventType::TouchUp { id, x, y, pressure }
+        } else {
+            // Stationary fingers
+            continue;
+        };
+        events.push(Event {
+            event_type,
+            time: SystemTime::now(),
+            name: None,
+            unicode: None,
+            scan_code: 0,
+            display: None,
+            delta: None,
+            numlock: None,
+        });
+    }
+    events
+}
+
+/// The event to emit before the last converted one, if looking its name up
+/// made the keyboard pick up a new layout.
+pub fn layout_event(keyboard_state: &mut Keyboard) -> Option<Event> {
+    if keyboard_state.take_layout_changed() {
+        Some(Event {
+            event_type: EventType::LayoutChanged,
+            time: SystemTime::now(),
+            name: None,
+            unicode: None,
+            scan_code: 0,
+            display: None,
+            delta: None,
+            numlock: None,
+        })
+    } else {
+        None
+    }
+}
+
+unsafe fn convert_system_defined(cg_event: &CGEvent) -> Option<Event> {
+    let cg_event_ptr = &**cg_event as *const _ as id;
+    let ns_event: id = msg_send![class!(NSEvent), eventWithCGEvent: cg_event_ptr];
+    let subtype: i16 = msg_send![ns_event, subtype];
+    if subtype != NX_SUBTYPE_AUX_CONTROL_BUTTONS {
+        return None;
+    }
+    let data1: NSInteger = msg_send![ns_event, data1];
+    let code = ((data1 & 0xFFFF_0000) >> 16) as u32;
+    let state = (data1 & 0xFF00) >> 8;
+    let key = key_from_nx_code(code)?;
+    let event_type = if state == NX_KEYSTATE_DOWN {
+        EventType::KeyPress(key)
+    } else {
+        EventType::KeyRelease(key)
+    };
+    Some(Event {
+        event_type,
+        time: SystemTime::now(),
+        name: None,
+        unicode: None,
+        scan_code: 0,
+        display: None,
+        delta: None,
+        numlock: None,
+    })
+}
+
+/// Scroll and momentum phases of a scroll event, None for plain mouse wheels.
+fn scroll_phase(cg_event: &CGEvent) -> Option<(ScrollPhase, bool)> {
+    let scroll = cg_event.get_integer_value_field(SCROLL_WHEEL_EVENT_SCROLL_PHASE);
+    let momentum = cg_event.get_integer_value_field(SCROLL_WHEEL_EVENT_MOMENTUM_PHASE);
+    match (scroll, momentum) {
+        (0, 0) => None,
+        (0, MOMENTUM_PHASE_BEGIN) => Some((ScrollPhase::Begin, true)),
+        (0, MOMENTUM_PHASE_CONTINUE) => Some((ScrollPhase::Change, true)),
+        (0, _) => Some((ScrollPhase::End, true)),
+        (SCROLL_PHASE_BEGAN, _) | (SCROLL_PHASE_MAY_BEGIN, _) => Some((ScrollPhase::Begin, false)),
+        (SCROLL_PHASE_CHANGED, _) => Some((ScrollPhase::Change, false)),
+        // Ended or cancelled
+        _ => Some((ScrollPhase::End, false)),
+    }
+}
+
+/// The values of the phase fields to simulate a scroll at `phase`.
+pub fn scroll_phase_fields(phase: &ScrollPhase, is_momentum: bool) -> (i64, i64) {
+    match (phase, is_momentum) {
+        (ScrollPhase::Begin, false) => (SCROLL_PHASE_BEGAN, 0),
+        (ScrollPhase::Change, false) => (SCROLL_PHASE_CHANGED, 0),
+        (ScrollPhase::End, false) => (SCROLL_PHASE_ENDED, 0),
+        (ScrollPhase::Begin, true) => (0, MOMENTUM_PHASE_BEGIN),
+        (ScrollPhase::Change, true) => (0, MOMENTUM_PHASE_CONTINUE),
+        (ScrollPhase::End, true) => (0, MOMENTUM_PHASE_END),
+    }
+}
+
+/// Buttons past left and right all come as "other" buttons, with their number.
+fn other_button(cg_event: &CGEvent) -> Button {
+    match cg_event.get_integer_value_field(EventField::MOUSE_EVENT_BUTTON_NUMBER) {
+        2 => Button::Middle,
+        3 => Button::Back,
+        4 => Button::Forward,
+        code => Button::Unknown(code as u8),
+    }
+}
+
+/// Tablets send regular mouse events with the tablet point subtype and
+/// their pressure and tilt, we turn those into pen events.
+unsafe fn convert_pen(_type: CGEventType, cg_event: &CGEvent) -> Option<EventType> {
+    match _type {
+        CGEventType::TabletProximity => {
+            let pointer =
+                cg_event.get_integer_value_field(EventField::TABLET_PROXIMITY_EVENT_POINTER_TYPE);
+            PEN_ERASER = pointer == ERASER_POINTER;
+            return None;
+        }
+        CGEventType::LeftMouseDown
+        | CGEventType::LeftMouseUp
+        | CGEventType::LeftMouseDragged
+        | CGEventType::MouseMoved => {}
+        _ => return None,
+    }
+    if cg_event.get_integer_value_field(EventField::MOUSE_EVENT_SUB_TYPE) != TABLET_POINT {
+        return None;
+    }
+    let point = cg_event.location();
+    let pen = Pen {
+        x: point.x,
+        y: point.y,
+        pressure: cg_event.get_double_value_field(EventField::TABLET_EVENT_POINT_PRESSURE),
+        tilt_x: cg_event.get_double_value_field(EventField::TABLET_EVENT_TILT_X),
+        tilt_y: cg_event.get_double_value_field(EventField::TABLET_EVENT_TILT_Y),
+        eraser: PEN_ERASER,
+    };
+    match _type {
+        CGEventType::LeftMouseDown => Some(EventType::PenDown(pen)),
+        CGEventType::LeftMouseUp => Some(EventType::PenUp(pen)),
+        _ => Some(EventType::PenMove(pen)),
+    }
+}
+
 pub unsafe fn convert(
     _type: CGEventType,
     cg_event: &CGEvent,
     keyboard_state: &mut Keyboard,
 ) -> Option<Event> {
+    if let Some(event_type) = convert_pen(_type, cg_event) {
+        return Some(Event {
+            event_type,
+            time: SystemTime::now(),
+            name: None,
+            unicode: None,
+            scan_code: 0,
+            display: None,
+            delta: None,
+            numlock: None,
+        });
+    }
     let option_type = match _type {
         CGEventType::LeftMouseDown => Some(EventType::ButtonPress(Button::Left)),
         CGEventType::LeftMouseUp => Some(EventType::ButtonRelease(Button::Left)),
         CGEventType::RightMouseDown => Some(EventType::ButtonPress(Button::Right)),
         CGEventType::RightMouseUp => Some(EventType::ButtonRelease(Button::Right)),
+        CGEventType::OtherMouseDown => Some(EventType::ButtonPress(other_button(cg_event))),
+        CGEventType::OtherMouseUp => Some(EventType::ButtonRelease(other_button(cg_event))),
         CGEventType::MouseMoved => {
             let point = cg_event.location();
             Some(EventType::MouseMove {
@@ -133,25 +531,49 @@ pub unsafe fn convert(
                 cg_event.get_integer_value_field(EventField::SCROLL_WHEEL_EVENT_POINT_DELTA_AXIS_1);
             let delta_x =
                 cg_event.get_integer_value_field(EventField::SCROLL_WHEEL_EVENT_POINT_DELTA_AXIS_2);
-            Some(EventType::Wheel { delta_x, delta_y })
+            match scroll_phase(cg_event) {
+                Some((phase, is_momentum)) => Some(EventType::Scroll {
+                    delta_x: delta_x as f64,
+                    delta_y: delta_y as f64,
+                    phase,
+                    is_momentum,
+                }),
+                None => Some(EventType::Wheel { delta_x, delta_y }),
+            }
         }
         _ => None,
     };
     if let Some(event_type) = option_type {
-        let name = match event_type {
-            EventType::KeyPress(_) => {
-                let code =
-                    cg_event.get_integer_value_field(EventField::KEYBOARD_EVENT_KEYCODE) as u32;
+        let code = cg_event.get_integer_value_field(EventField::KEYBOARD_EVENT_KEYCODE) as u32;
+        let (name, is_dead) = match event_type {
+            EventType::KeyPress(_) if crate::names::wanted() => {
                 let flags = cg_event.get_flags();
-                keyboard_state.create_string_for_key(code, flags)
+                let name = keyboard_state.create_string_for_key(code, flags);
+                (name, keyboard_state.is_dead())
             }
-            _ => None,
+            _ => (None, false),
         };
-        return Some(Event {
+        let scan_code = match event_type {
+            EventType::KeyPress(_) | EventType::KeyRelease(_) => code,
+            _ => 0,
+        };
+        let mut event = Event {
             event_type,
             time: SystemTime::now(),
+            unicode: UnicodeInfo::new(name.as_deref(), is_dead),
             name,
-        });
+            scan_code,
+            display: None,
+            delta: None,
+            numlock: None,
+        };
+        if let EventType::MouseMove { .. } = event.event_type {
+            let delta_x = cg_event.get_integer_value_field(EventField::MOUSE_EVENT_DELTA_X);
+            let delta_y = cg_event.get_integer_value_field(EventField::MOUSE_EVENT_DELTA_Y);
+            event.delta = Some((delta_x as f64, delta_y as f64));
+            (*addr_of_mut!(MOTION)).annotate(&mut event, display_rects);
+        }
+        return Some(event);
     }
     None
 }
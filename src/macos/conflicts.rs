@@ -0,0 +1,53 @@
+use crate::conflicts::Conflict;
+use std::ptr::null_mut;
+
+// From CGEventTypes.h, kCGHIDEventTap and kCGEventTapOptionListenOnly.
+const HID_EVENT_TAP: u32 = 0;
+const LISTEN_ONLY: u32 = 1;
+
+#[repr(C)]
+#[allow(non_snake_case, dead_code)]
+struct CGEventTapInformation {
+    eventTapID: u32,
+    tapPoint: u32,
+    options: u32,
+    eventsOfInterest: u64,
+    tappingProcess: i32,
+    processBeingTapped: i32,
+    enabled: bool,
+    minUsecLatency: f32,
+    avgUsecLatency: f32,
+    maxUsecLatency: f32,
+}
+
+#[link(name = "Cocoa", kind = "framework")]
+extern "C" {
+    fn CGGetEventTapList(max_taps: u32, taps: *mut CGEventTapInformation, count: *mut u32) -> i32;
+}
+
+/// The event taps of other processes, ours have our pid.
+pub fn conflicts() -> Option<Vec<Conflict>> {
+    unsafe {
+        let mut count = 0;
+        if CGGetEventTapList(0, null_mut(), &mut count) != 0 {
+            return None;
+        }
+        let mut taps = Vec::with_capacity(count as usize);
+        if CGGetEventTapList(count, taps.as_mut_ptr(), &mut count) != 0 {
+            return None;
+        }
+        taps.set_len(count as usize);
+        let pid = std::process::id() as i32;
+        let conflicts = taps
+            .iter()
+            .filter(|tap| tap.tappingProcess != pid)
+            .map(|tap| Conflict::EventTap {
+                pid: tap.tappingProcess,
+                listen_only: tap.options == LISTEN_ONLY,
+                enabled: tap.enabled,
+                hid: tap.tapPoint == HID_EVENT_TAP,
+            })
+            .collect();
+        Some(conflicts)
+    }
+}
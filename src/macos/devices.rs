@@ -0,0 +1,107 @@
+use crate::macos::iokit::*;
+use crate::rdev::{DeviceEvent, DeviceKind, InputDevice, ListenError, OsError};
+use core_foundation::array::CFArray;
+use core_foundation::base::{kCFAllocatorDefault, CFType, TCFType};
+use core_foundation::number::CFNumber;
+use core_foundation::runloop::{kCFRunLoopDefaultMode, CFRunLoop};
+use core_foundation::string::CFString;
+use std::os::raw::c_void;
+use std::ptr::addr_of_mut;
+
+static mut GLOBAL_CALLBACK: Option<Box<dyn FnMut(DeviceEvent)>> = None;
+
+unsafe fn property(device: IOHIDDeviceRef, key: &str) -> Option<CFType> {
+    let key = CFString::new(key);
+    let value = IOHIDDeviceGetProperty(device, key.as_concrete_TypeRef());
+    if value.is_null() {
+        None
+    } else {
+        Some(CFType::wrap_under_get_rule(value))
+    }
+}
+
+unsafe fn number_property(device: IOHIDDeviceRef, key: &str) -> Option<i64> {
+    property(device, key)?.downcast::<CFNumber>()?.to_i64()
+}
+
+unsafe fn input_device(device: IOHIDDeviceRef) -> InputDevice {
+    let name = property(device, "Product")
+        .and_then(|name| name.downcast::<CFString>())
+        .map(|name| name.to_string())
+        .unwrap_or_default();
+    let id = |key| number_property(device, key).unwrap_or(0) as u16;
+    let kind = match number_property(device, "PrimaryUsage").map(|usage| usage as i32) {
+        Some(USAGE_KEYBOARD) => DeviceKind::Keyboard,
+        Some(USAGE_MOUSE) => DeviceKind::Mouse,
+        _ => DeviceKind::Other,
+    };
+    InputDevice {
+        // The same reference comes with the removal.
+        id: format!("{:x}", device as usize),
+        name,
+        vendor_id: id("VendorID"),
+        product_id: id("ProductID"),
+        serial: property(device, "SerialNumber")
+            .and_then(|serial| serial.downcast::<CFString>())
+            .map(|serial| serial.to_string())
+            .filter(|serial| !serial.is_empty()),
+        kind,
+    }
+}
+
+unsafe extern "C" fn matched(
+    _context: *mut c_void,
+    _result: IOReturn,
+    _sender: *mut c_void,
+    device: IOHIDDeviceRef,
+) {
+    if let Some(callback) = &mut *addr_of_mut!(GLOBAL_CALLBACK) {
+        callback(DeviceEvent::Connected(input_device(device)));
+    }
+}
+
+unsafe extern "C" fn removed(
+    _context: *mut c_void,
+    _result: IOReturn,
+    _sender: *mut c_void,
+    device: IOHIDDeviceRef,
+) {
+    if let Some(callback) = &mut *addr_of_mut!(GLOBAL_CALLBACK) {
+        callback(DeviceEvent::Disconnected(input_device(device)));
+    }
+}
+
+/// The HID manager reports devices already plugged in as matching as soon
+/// as the run loop starts.
+pub fn listen_devices<T>(callback: T) -> Result<(), ListenError>
+where
+    T: FnMut(DeviceEvent) + 'static,
+{
+    unsafe {
+        GLOBAL_CALLBACK = Some(Box::new(callback));
+        let manager = IOHIDManagerCreate(kCFAllocatorDefault, kIOHIDOptionsTypeNone);
+        if manager.is_null() {
+            let error = OsError::new("IOHIDManagerCreate", 0);
+            return Err(ListenError::DeviceMonitorError(error));
+        }
+        let matches = CFArray::from_CFTypes(&[
+            matching(USAGE_PAGE_GENERIC, USAGE_KEYBOARD),
+            matching(USAGE_PAGE_GENERIC, USAGE_MOUSE),
+        ]);
+        IOHIDManagerSetDeviceMatchingMultiple(manager, matches.as_concrete_TypeRef());
+        IOHIDManagerRegisterDeviceMatchingCallback(manager, matched, std::ptr::null_mut());
+        IOHIDManagerRegisterDeviceRemovalCallback(manager, removed, std::ptr::null_mut());
+        IOHIDManagerScheduleWithRunLoop(
+            manager,
+            CFRunLoop::get_current().as_concrete_TypeRef(),
+            kCFRunLoopDefaultMode,
+        );
+        let opened = IOHIDManagerOpen(manager, kIOHIDOptionsTypeNone);
+        if opened != KERN_SUCCESS {
+            let error = OsError::new("IOHIDManagerOpen", opened.into());
+            return Err(ListenError::DeviceMonitorError(error));
+        }
+        CFRunLoop::run_current();
+    }
+    Ok(())
+}
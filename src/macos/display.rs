@@ -1,7 +1,95 @@
-use crate::rdev::DisplayError;
-use core_graphics::display::CGDisplay;
+use crate::rdev::{DisplayError, DisplayInfo, ListenError, OsError};
+use core_foundation::runloop::CFRunLoop;
+use core_graphics::base::CGError;
+use core_graphics::display::{CGDirectDisplayID, CGDisplay};
+use std::os::raw::c_void;
+use std::ptr::addr_of_mut;
+
+type CGDisplayReconfigurationCallBack =
+    unsafe extern "C" fn(display: CGDirectDisplayID, flags: u32, user_info: *mut c_void);
+// Sent before the change happens, there is nothing new to see yet.
+#[allow(non_upper_case_globals)]
+const kCGDisplayBeginConfigurationFlag: u32 = 1 << 0;
+const CG_SUCCESS: CGError = 0;
+
+#[link(name = "CoreGraphics", kind = "framework")]
+extern "C" {
+    fn CGDisplayRegisterReconfigurationCallback(
+        callback: CGDisplayReconfigurationCallBack,
+        user_info: *mut c_void,
+    ) -> CGError;
+}
+
+static mut DISPLAY_CALLBACK: Option<Box<dyn FnMut()>> = None;
 
 pub fn display_size() -> Result<(u64, u64), DisplayError> {
     let main = CGDisplay::main();
     Ok((main.pixels_wide(), main.pixels_high()))
 }
+
+/// Bounds are in points, like event locations, the scale factor gives
+/// pixels.
+pub fn displays() -> Result<Vec<DisplayInfo>, DisplayError> {
+    let ids = CGDisplay::active_displays().map_err(|_| DisplayError::NoDisplay)?;
+    Ok(ids
+        .into_iter()
+        .map(|id| {
+            let display = CGDisplay::new(id);
+            let bounds = display.bounds();
+            let mode = display.display_mode();
+            let scale_factor = match &mode {
+                Some(mode) if mode.width() > 0 => mode.pixel_width() as f64 / mode.width() as f64,
+                _ => 1.0,
+            };
+            // Built-in panels report 0.
+            let refresh_rate = mode
+                .map(|mode| mode.refresh_rate())
+                .filter(|rate| *rate > 0.0);
+            DisplayInfo {
+                x: bounds.origin.x as i64,
+                y: bounds.origin.y as i64,
+                width: bounds.size.width as u64,
+                height: bounds.size.height as u64,
+                scale_factor,
+                refresh_rate,
+                is_primary: display.is_main(),
+            }
+        })
+        .collect())
+}
+
+/// Called once per display involved in a change.
+unsafe extern "C" fn reconfigured(
+    _display: CGDirectDisplayID,
+    flags: u32,
+    _user_info: *mut c_void,
+) {
+    if flags & kCGDisplayBeginConfigurationFlag != 0 {
+        return;
+    }
+    if let Some(callback) = &mut *addr_of_mut!(DISPLAY_CALLBACK) {
+        callback();
+    }
+}
+
+/// Reconfiguration callbacks are delivered through the run loop of the
+/// thread, like the HID manager ones of `listen_devices`.
+pub fn listen_display_changes<T>(callback: T) -> Result<(), ListenError>
+where
+    T: FnMut() + 'static,
+{
+    unsafe {
+        DISPLAY_CALLBACK = Some(Box::new(callback));
+        let registered =
+            CGDisplayRegisterReconfigurationCallback(reconfigured, std::ptr::null_mut());
+        if registered != CG_SUCCESS {
+            let error = OsError::new(
+                "CGDisplayRegisterReconfigurationCallback",
+                registered.into(),
+            );
+            return Err(ListenError::DisplayMonitorError(error));
+        }
+        CFRunLoop::run_current();
+    }
+    Ok(())
+}
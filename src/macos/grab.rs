@@ -1,10 +1,12 @@
 #![allow(improper_ctypes_definitions)]
 use crate::macos::common::*;
+use crate::macos::permissions::tap_error;
 use crate::rdev::{Event, GrabError};
 use cocoa::base::nil;
 use cocoa::foundation::NSAutoreleasePool;
 use core_graphics::event::{CGEventTapLocation, CGEventType};
 use std::os::raw::c_void;
+use std::ptr::addr_of_mut;
 
 static mut GLOBAL_CALLBACK: Option<Box<dyn FnMut(Event) -> Option<Event>>> = None;
 
@@ -13,16 +15,35 @@ extern "C" {}
 
 unsafe extern "C" fn raw_callback(
     _proxy: CGEventTapProxy,
-    _type: CGEventType,
+    _type: u32,
     cg_event: CGEventRef,
     _user_info: *mut c_void,
 ) -> CGEventRef {
     // println!("Event ref {:?}", cg_event_ptr);
     // let cg_event: CGEvent = transmute_copy::<*mut c_void, CGEvent>(&cg_event_ptr);
+    if is_gesture(_type) {
+        if let Some(callback) = &mut *addr_of_mut!(GLOBAL_CALLBACK) {
+            let mut swallow = false;
+            for event in convert_touches(_type, &cg_event) {
+                swallow |= callback(event).is_none();
+            }
+            #[cfg(feature = "gestures")]
+            if let Some(event) = convert_gesture(&cg_event) {
+                swallow |= callback(event).is_none();
+            }
+            if swallow {
+                cg_event.set_type(CGEventType::Null);
+            }
+        }
+        return cg_event;
+    }
     let opt = KEYBOARD_STATE.lock();
     if let Ok(mut keyboard) = opt {
-        if let Some(event) = convert(_type, &cg_event, &mut keyboard) {
+        if let Some(event) = convert_raw(_type, &cg_event, &mut keyboard) {
             if let Some(callback) = &mut GLOBAL_CALLBACK {
+                if let Some(layout) = layout_event(&mut keyboard) {
+                    callback(layout);
+                }
                 if callback(event).is_none() {
                     cg_event.set_type(CGEventType::Null);
                 }
@@ -48,17 +69,12 @@ where
             nil,
         );
         if tap.is_null() {
-            return Err(GrabError::EventTapError);
+            return Err(GrabError::EventTapError(tap_error(false)));
         }
-        let _loop = CFMachPortCreateRunLoopSource(nil, tap, 0);
-        if _loop.is_null() {
+        let mut tap = EventTap::new(tap);
+        if !tap.add_to_run_loop() {
             return Err(GrabError::LoopSourceError);
         }
-
-        let current_loop = CFRunLoopGetCurrent();
-        CFRunLoopAddSource(current_loop, _loop, kCFRunLoopCommonModes);
-
-        CGEventTapEnable(tap, true);
         CFRunLoopRun();
     }
     Ok(())
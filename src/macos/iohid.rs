@@ -0,0 +1,206 @@
+use crate::macos::common::{layout_event, KEYBOARD_STATE};
+use crate::macos::iokit::*;
+use crate::macos::keycodes::code_from_key;
+use crate::macos::state::get_mouse_position;
+use crate::motion::{display_rects, Motion};
+use crate::rdev::{
+    Button, Event, EventType, Key, KeyboardState, ListenError, OsError, UnicodeInfo,
+};
+use core_foundation::array::CFArray;
+use core_foundation::base::{kCFAllocatorDefault, TCFType};
+use core_foundation::runloop::{kCFRunLoopDefaultMode, CFRunLoop};
+use std::os::raw::c_void;
+use std::ptr::addr_of_mut;
+use std::time::SystemTime;
+
+// HID usage pages and usages, from IOHIDUsageTables.h
+const PAGE_KEYBOARD: u32 = 0x07;
+const PAGE_BUTTON: u32 = 0x09;
+const PAGE_CONSUMER: u32 = 0x0C;
+const USAGE_CONSUMER_CONTROL: i32 = 0x01;
+const PAGE_APPLE_VENDOR_TOP_CASE: u32 = 0xFF;
+const PAGE_APPLE_VENDOR_KEYBOARD: u32 = 0xFF01;
+const USAGE_APPLE_FN: u32 = 0x03;
+const USAGE_X: u32 = 0x30;
+const USAGE_Y: u32 = 0x31;
+const USAGE_WHEEL: u32 = 0x38;
+const USAGE_AC_PAN: u32 = 0x238;
+// Below are the reserved and error usages, above the vendor ones.
+const FIRST_KEY: u32 = 0x04;
+const LAST_KEY: u32 = 0xE7;
+
+static mut GLOBAL_CALLBACK: Option<Box<dyn FnMut(Event)>> = None;
+static mut MOTION: Motion = Motion::new();
+
+fn consumer_key(usage: u32) -> Option<Key> {
+    match usage {
+        0x6F => Some(Key::BrightnessUp),
+        0x70 => Some(Key::BrightnessDown),
+        0xB5 => Some(Key::MediaNext),
+        0xB6 => Some(Key::MediaPrev),
+        0xCD => Some(Key::MediaPlayPause),
+        0xE2 => Some(Key::VolumeMute),
+        0xE9 => Some(Key::VolumeUp),
+        0xEA => Some(Key::VolumeDown),
+        _ => None,
+    }
+}
+
+fn key(page: u32, usage: u32) -> Option<Key> {
+    match page {
+        PAGE_KEYBOARD if (FIRST_KEY..=LAST_KEY).contains(&usage) => {
+            Key::from_hid_usage(usage as u16)
+        }
+        PAGE_CONSUMER => consumer_key(usage),
+        PAGE_APPLE_VENDOR_TOP_CASE | PAGE_APPLE_VENDOR_KEYBOARD if usage == USAGE_APPLE_FN => {
+            Some(Key::Function)
+        }
+        _ => None,
+    }
+}
+
+fn button(usage: u32) -> Button {
+    match usage {
+        1 => Button::Left,
+        2 => Button::Right,
+        3 => Button::Middle,
+        4 => Button::Back,
+        5 => Button::Forward,
+        usage => Button::Unknown(usage as u8),
+    }
+}
+
+/// HID values are relative for mice, the cursor position comes from the
+/// window server.
+fn mouse_move() -> Option<EventType> {
+    let (x, y) = get_mouse_position().ok()?;
+    Some(EventType::MouseMove { x, y })
+}
+
+unsafe fn convert(page: u32, usage: u32, relative: bool, value: i64) -> Option<EventType> {
+    if let Some(key) = key(page, usage) {
+        return match value {
+            0 => Some(EventType::KeyRelease(key)),
+            _ => Some(EventType::KeyPress(key)),
+        };
+    }
+    match (page, usage) {
+        (PAGE_BUTTON, usage) => match value {
+            0 => Some(EventType::ButtonRelease(button(usage))),
+            _ => Some(EventType::ButtonPress(button(usage))),
+        },
+        (_, _) if value == 0 => None,
+        (page, USAGE_X | USAGE_Y) if page == USAGE_PAGE_GENERIC as u32 && relative => mouse_move(),
+        (page, USAGE_WHEEL) if page == USAGE_PAGE_GENERIC as u32 => Some(EventType::Wheel {
+            delta_x: 0,
+            delta_y: value,
+        }),
+        (PAGE_CONSUMER, USAGE_AC_PAN) => Some(EventType::Wheel {
+            delta_x: value,
+            delta_y: 0,
+        }),
+        _ => None,
+    }
+}
+
+unsafe extern "C" fn input_value(
+    _context: *mut c_void,
+    _result: IOReturn,
+    _sender: *mut c_void,
+    value: IOHIDValueRef,
+) {
+    let element = IOHIDValueGetElement(value);
+    if element.is_null() {
+        return;
+    }
+    let page = IOHIDElementGetUsagePage(element);
+    let usage = IOHIDElementGetUsage(element);
+    let relative = IOHIDElementIsRelative(element) != 0;
+    let value = IOHIDValueGetIntegerValue(value) as i64;
+    let event_type = match convert(page, usage, relative, value) {
+        Some(event_type) => event_type,
+        None => return,
+    };
+    let (scan_code, name, is_dead, layout) = match &event_type {
+        EventType::KeyPress(key) | EventType::KeyRelease(key) => {
+            let scan_code = code_from_key(*key).map(u32::from).unwrap_or(0);
+            match KEYBOARD_STATE.lock() {
+                Ok(mut keyboard) => {
+                    let name = keyboard.add(&event_type);
+                    let is_dead = match event_type {
+                        EventType::KeyPress(_) => keyboard.is_dead(),
+                        _ => false,
+                    };
+                    (scan_code, name, is_dead, layout_event(&mut keyboard))
+                }
+                Err(_) => (scan_code, None, false, None),
+            }
+        }
+        _ => (0, None, false, None),
+    };
+    if let Some(callback) = &mut *addr_of_mut!(GLOBAL_CALLBACK) {
+        if let Some(layout) = layout {
+            callback(layout);
+        }
+        let mut event = Event {
+            event_type,
+            time: SystemTime::now(),
+            unicode: UnicodeInfo::new(name.as_deref(), is_dead),
+            name,
+            scan_code,
+            display: None,
+            delta: None,
+            numlock: None,
+        };
+        if let EventType::MouseMove { .. } = event.event_type {
+            event.delta = Some(match usage {
+                USAGE_X => (value as f64, 0.0),
+                _ => (0.0, value as f64),
+            });
+            (*addr_of_mut!(MOTION)).annotate(&mut event, display_rects);
+        }
+        callback(event);
+    }
+}
+
+/// Listens to keyboards and mice through the IOHIDManager. It sees HID
+/// values before the window server, so keys it never turns into events
+/// (Fn, some media keys) show up too. It needs the Input Monitoring
+/// permission instead of Accessibility. Touches, gestures and pens aren't
+/// HID keyboards or mice and aren't reported.
+pub fn listen<T>(callback: T) -> Result<(), ListenError>
+where
+    T: FnMut(Event) + 'static,
+{
+    unsafe {
+        GLOBAL_CALLBACK = Some(Box::new(callback));
+        let manager = IOHIDManagerCreate(kCFAllocatorDefault, kIOHIDOptionsTypeNone);
+        if manager.is_null() {
+            return Err(ListenError::IOHidError(OsError::new(
+                "IOHIDManagerCreate",
+                0,
+            )));
+        }
+        let matches = CFArray::from_CFTypes(&[
+            matching(USAGE_PAGE_GENERIC, USAGE_KEYBOARD),
+            matching(USAGE_PAGE_GENERIC, USAGE_MOUSE),
+            matching(PAGE_CONSUMER as i32, USAGE_CONSUMER_CONTROL),
+        ]);
+        IOHIDManagerSetDeviceMatchingMultiple(manager, matches.as_concrete_TypeRef());
+        IOHIDManagerRegisterInputValueCallback(manager, input_value, std::ptr::null_mut());
+        IOHIDManagerScheduleWithRunLoop(
+            manager,
+            CFRunLoop::get_current().as_concrete_TypeRef(),
+            kCFRunLoopDefaultMode,
+        );
+        // Fails without the Input Monitoring permission.
+        let opened = IOHIDManagerOpen(manager, kIOHIDOptionsTypeNone);
+        if opened != KERN_SUCCESS {
+            let error = OsError::new("IOHIDManagerOpen", opened.into());
+            return Err(ListenError::IOHidError(error));
+        }
+        crate::trace::installed("HID manager");
+        CFRunLoop::run_current();
+    }
+    Ok(())
+}
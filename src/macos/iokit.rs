@@ -0,0 +1,88 @@
+use core_foundation::array::CFArrayRef;
+use core_foundation::base::{CFAllocatorRef, CFIndex, CFType, CFTypeRef, TCFType};
+use core_foundation::dictionary::CFDictionary;
+use core_foundation::number::CFNumber;
+use core_foundation::runloop::CFRunLoopRef;
+use core_foundation::string::{CFString, CFStringRef};
+use std::os::raw::c_void;
+
+pub type IOReturn = i32;
+pub type IOHIDManagerRef = *mut c_void;
+pub type IOHIDDeviceRef = *mut c_void;
+pub type IOHIDValueRef = *mut c_void;
+pub type IOHIDElementRef = *mut c_void;
+pub type IOHIDDeviceCallback = unsafe extern "C" fn(
+    context: *mut c_void,
+    result: IOReturn,
+    sender: *mut c_void,
+    device: IOHIDDeviceRef,
+);
+pub type IOHIDValueCallback = unsafe extern "C" fn(
+    context: *mut c_void,
+    result: IOReturn,
+    sender: *mut c_void,
+    value: IOHIDValueRef,
+);
+pub const KERN_SUCCESS: IOReturn = 0;
+#[allow(non_upper_case_globals)]
+pub const kIOHIDOptionsTypeNone: u32 = 0;
+// IOHIDRequestType and IOHIDAccessType, from IOHIDLib.h
+#[allow(non_upper_case_globals)]
+pub const kIOHIDRequestTypeListenEvent: u32 = 1;
+#[allow(non_upper_case_globals)]
+pub const kIOHIDAccessTypeGranted: u32 = 0;
+#[allow(non_upper_case_globals)]
+pub const kIOHIDAccessTypeDenied: u32 = 1;
+// Generic desktop page, keyboards and mice.
+pub const USAGE_PAGE_GENERIC: i32 = 0x01;
+pub const USAGE_MOUSE: i32 = 0x02;
+pub const USAGE_KEYBOARD: i32 = 0x06;
+
+#[link(name = "IOKit", kind = "framework")]
+extern "C" {
+    pub fn IOHIDManagerCreate(allocator: CFAllocatorRef, options: u32) -> IOHIDManagerRef;
+    pub fn IOHIDManagerSetDeviceMatchingMultiple(manager: IOHIDManagerRef, multiple: CFArrayRef);
+    pub fn IOHIDManagerRegisterDeviceMatchingCallback(
+        manager: IOHIDManagerRef,
+        callback: IOHIDDeviceCallback,
+        context: *mut c_void,
+    );
+    pub fn IOHIDManagerRegisterDeviceRemovalCallback(
+        manager: IOHIDManagerRef,
+        callback: IOHIDDeviceCallback,
+        context: *mut c_void,
+    );
+    pub fn IOHIDManagerRegisterInputValueCallback(
+        manager: IOHIDManagerRef,
+        callback: IOHIDValueCallback,
+        context: *mut c_void,
+    );
+    pub fn IOHIDManagerScheduleWithRunLoop(
+        manager: IOHIDManagerRef,
+        run_loop: CFRunLoopRef,
+        mode: CFStringRef,
+    );
+    pub fn IOHIDManagerOpen(manager: IOHIDManagerRef, options: u32) -> IOReturn;
+    pub fn IOHIDDeviceGetProperty(device: IOHIDDeviceRef, key: CFStringRef) -> CFTypeRef;
+    pub fn IOHIDValueGetElement(value: IOHIDValueRef) -> IOHIDElementRef;
+    pub fn IOHIDValueGetIntegerValue(value: IOHIDValueRef) -> CFIndex;
+    pub fn IOHIDElementGetUsagePage(element: IOHIDElementRef) -> u32;
+    pub fn IOHIDElementGetUsage(element: IOHIDElementRef) -> u32;
+    pub fn IOHIDElementIsRelative(element: IOHIDElementRef) -> u8;
+    pub fn IOHIDCheckAccess(request: u32) -> u32;
+    pub fn IOHIDRequestAccess(request: u32) -> u8;
+}
+
+/// Matches the devices whose primary usage is `usage` on `page`.
+pub fn matching(page: i32, usage: i32) -> CFDictionary<CFType, CFType> {
+    CFDictionary::from_CFType_pairs(&[
+        (
+            CFString::new("DeviceUsagePage").as_CFType(),
+            CFNumber::from(page).as_CFType(),
+        ),
+        (
+            CFString::new("DeviceUsage").as_CFType(),
+            CFNumber::from(usage).as_CFType(),
+        ),
+    ])
+}
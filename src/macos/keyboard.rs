@@ -1,13 +1,18 @@
 #![allow(clippy::upper_case_acronyms)]
+use crate::layout::NameTable;
 use crate::macos::keycodes::code_from_key;
-use crate::rdev::{EventType, Key, KeyboardState};
-use core_foundation::base::{CFRelease, OSStatus};
-use core_foundation::string::UniChar;
+use crate::rdev::{DisplayError, EventType, Key, KeyboardState};
+use core_foundation::base::{CFRelease, OSStatus, TCFType};
+use core_foundation::string::{CFString, CFStringRef, UniChar};
+use core_foundation_sys::array::{CFArrayGetCount, CFArrayGetValueAtIndex, CFArrayRef};
+use core_foundation_sys::base::CFRetain;
 use core_foundation_sys::data::{CFDataGetBytePtr, CFDataRef};
+use core_foundation_sys::dictionary::CFDictionaryRef;
 use core_graphics::event::CGEventFlags;
 use std::convert::TryInto;
 use std::ffi::c_void;
 use std::os::raw::c_uint;
+use std::ptr::{null, null_mut};
 
 type TISInputSourceRef = *mut c_void;
 type ModifierState = u32;
@@ -38,6 +43,10 @@ extern "C" {
     fn TISCopyCurrentKeyboardLayoutInputSource() -> TISInputSourceRef;
     fn TISCopyCurrentKeyboardInputSource() -> TISInputSourceRef;
     fn TISGetInputSourceProperty(source: TISInputSourceRef, property: *mut c_void) -> CFDataRef;
+    fn TISCreateInputSourceList(
+        properties: CFDictionaryRef,
+        include_all_installed: bool,
+    ) -> CFArrayRef;
     fn UCKeyTranslate(
         layout: *const u8,
         code: u16,
@@ -52,6 +61,7 @@ extern "C" {
     ) -> OSStatus;
     fn LMGetKbdType() -> u32;
     static kTISPropertyUnicodeKeyLayoutData: *mut c_void;
+    static kTISPropertyInputSourceID: *mut c_void;
 
 }
 
@@ -59,6 +69,13 @@ pub struct Keyboard {
     dead_state: u32,
     shift: bool,
     caps_lock: bool,
+    alt: bool,
+    alt_gr: bool,
+    layout_id: Option<String>,
+    layout_changed: bool,
+    // Set by with_layout, the current input source is ignored then.
+    fixed_layout: Option<String>,
+    names: NameTable,
 }
 impl Keyboard {
     pub fn new() -> Option<Keyboard> {
@@ -66,15 +83,72 @@ impl Keyboard {
             dead_state: 0,
             shift: false,
             caps_lock: false,
+            alt: false,
+            alt_gr: false,
+            layout_id: None,
+            layout_changed: false,
+            fixed_layout: None,
+            names: NameTable::default(),
         })
     }
 
+    /// Keyboard using the input source `layout_id` (e.g.
+    /// "com.apple.keylayout.French", see `available_layouts`) instead of the
+    /// current one.
+    pub fn with_layout(layout_id: &str) -> Option<Keyboard> {
+        let source = unsafe { copy_input_source(layout_id) };
+        if source.is_null() {
+            return None;
+        }
+        unsafe { CFRelease(source) };
+        let mut keyboard = Keyboard::new()?;
+        keyboard.fixed_layout = Some(layout_id.to_string());
+        Some(keyboard)
+    }
+
+    /// Compares the id of the current input source (e.g.
+    /// com.apple.keylayout.French) with the one used for the last key.
+    /// Returns true if it changed, pending dead keys are dropped.
+    unsafe fn refresh_layout(&mut self, source: TISInputSourceRef) -> bool {
+        let id = match input_source_id(source) {
+            Some(id) => id,
+            None => return false,
+        };
+        if self.layout_id.as_ref() == Some(&id) {
+            return false;
+        }
+        // The very first key is not a change.
+        let changed = self.layout_id.is_some();
+        self.layout_id = Some(id);
+        self.dead_state = 0;
+        self.names.clear();
+        if changed {
+            self.layout_changed = true;
+        }
+        changed
+    }
+
+    /// Returns true once after the keyboard picked up a layout change.
+    pub(crate) fn take_layout_changed(&mut self) -> bool {
+        std::mem::replace(&mut self.layout_changed, false)
+    }
+
+    /// True while a dead key waits for the next key.
+    pub(crate) fn is_dead(&self) -> bool {
+        self.dead_state != 0
+    }
+
     fn modifier_state(&self) -> ModifierState {
+        let mut state = 0;
         if self.caps_lock || self.shift {
-            2
-        } else {
-            0
+            state += 1 << 1;
+        }
+        // Both options give access to the third level on macOS, and to the
+        // dead keys behind it (Option+E, then E gives é).
+        if self.alt || self.alt_gr {
+            state += 1 << 3;
         }
+        state
     }
 
     pub(crate) unsafe fn create_string_for_key(
@@ -91,7 +165,21 @@ impl Keyboard {
         code: u32,
         modifier_state: ModifierState,
     ) -> Option<String> {
-        let mut keyboard = TISCopyCurrentKeyboardInputSource();
+        let mut keyboard = match &self.fixed_layout {
+            Some(id) => copy_input_source(id),
+            None => TISCopyCurrentKeyboardInputSource(),
+        };
+        if keyboard.is_null() {
+            return None;
+        }
+        self.refresh_layout(keyboard);
+        let dead_pending = self.is_dead();
+        if !dead_pending {
+            if let Some(name) = self.names.get(code, modifier_state) {
+                CFRelease(keyboard);
+                return name;
+            }
+        }
         let mut layout = TISGetInputSourceProperty(keyboard, kTISPropertyUnicodeKeyLayoutData);
 
         if layout.is_null() {
@@ -122,7 +210,66 @@ impl Keyboard {
         );
         CFRelease(keyboard);
 
-        String::from_utf16(&buff[..length]).ok()
+        let name = String::from_utf16(&buff[..length]).ok();
+        if !dead_pending && !self.is_dead() {
+            self.names.insert(code, modifier_state, name.clone());
+        }
+        name
+    }
+}
+
+unsafe fn input_source_id(source: TISInputSourceRef) -> Option<String> {
+    let id = TISGetInputSourceProperty(source, kTISPropertyInputSourceID) as CFStringRef;
+    if id.is_null() {
+        return None;
+    }
+    Some(CFString::wrap_under_get_rule(id).to_string())
+}
+
+/// Enabled input sources that come with a keyboard layout, along with their ids.
+unsafe fn keyboard_input_sources(list: CFArrayRef) -> Vec<(TISInputSourceRef, String)> {
+    let mut sources = vec![];
+    for i in 0..CFArrayGetCount(list) {
+        let source = CFArrayGetValueAtIndex(list, i) as TISInputSourceRef;
+        if TISGetInputSourceProperty(source, kTISPropertyUnicodeKeyLayoutData).is_null() {
+            continue;
+        }
+        if let Some(id) = input_source_id(source) {
+            sources.push((source, id));
+        }
+    }
+    sources
+}
+
+/// Same ownership as TISCopyCurrentKeyboardInputSource, null if not found.
+unsafe fn copy_input_source(layout_id: &str) -> TISInputSourceRef {
+    let list = TISCreateInputSourceList(null(), false);
+    if list.is_null() {
+        return null_mut();
+    }
+    let source = keyboard_input_sources(list)
+        .into_iter()
+        .find(|(_, id)| id == layout_id)
+        .map(|(source, _)| CFRetain(source) as TISInputSourceRef)
+        .unwrap_or(null_mut());
+    CFRelease(list as *const c_void);
+    source
+}
+
+/// Layouts that can be given to `Keyboard::with_layout`: ids of the
+/// enabled keyboard input sources.
+pub fn available_layouts() -> Result<Vec<String>, DisplayError> {
+    unsafe {
+        let list = TISCreateInputSourceList(null(), false);
+        if list.is_null() {
+            return Err(DisplayError::NoDisplay);
+        }
+        let ids = keyboard_input_sources(list)
+            .into_iter()
+            .map(|(_, id)| id)
+            .collect();
+        CFRelease(list as *const c_void);
+        Ok(ids)
     }
 }
 
@@ -134,6 +281,14 @@ impl KeyboardState for Keyboard {
                     self.shift = true;
                     None
                 }
+                Key::Alt => {
+                    self.alt = true;
+                    None
+                }
+                Key::AltGr => {
+                    self.alt_gr = true;
+                    None
+                }
                 Key::CapsLock => {
                     self.caps_lock = !self.caps_lock;
                     None
@@ -148,6 +303,14 @@ impl KeyboardState for Keyboard {
                     self.shift = false;
                     None
                 }
+                Key::Alt => {
+                    self.alt = false;
+                    None
+                }
+                Key::AltGr => {
+                    self.alt_gr = false;
+                    None
+                }
                 _ => None,
             },
             _ => None,
@@ -158,6 +321,8 @@ impl KeyboardState for Keyboard {
         self.dead_state = 0;
         self.shift = false;
         self.caps_lock = false;
+        self.alt = false;
+        self.alt_gr = false;
     }
 }
 
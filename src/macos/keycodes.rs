@@ -16,6 +16,15 @@ const F1: CGKeyCode = 122;
 const F10: CGKeyCode = 109;
 const F11: CGKeyCode = 103;
 const F12: CGKeyCode = 111;
+const F13: CGKeyCode = 105;
+const F14: CGKeyCode = 107;
+const F15: CGKeyCode = 113;
+const F16: CGKeyCode = 106;
+const F17: CGKeyCode = 64;
+const F18: CGKeyCode = 79;
+const F19: CGKeyCode = 80;
+const F20: CGKeyCode = 90;
+// There is no F21-F24 on macOS.
 const F2: CGKeyCode = 120;
 const F3: CGKeyCode = 99;
 const F4: CGKeyCode = 118;
@@ -25,6 +34,13 @@ const F7: CGKeyCode = 98;
 const F8: CGKeyCode = 100;
 const F9: CGKeyCode = 101;
 const FUNCTION: CGKeyCode = 63;
+// kVK_ContextualMenu
+const MENU: CGKeyCode = 110;
+// kVK_JIS_*, macOS has no Hangul nor Henkan keys.
+const KANA: CGKeyCode = 104;
+const EISU: CGKeyCode = 102;
+const YEN: CGKeyCode = 93;
+const RO: CGKeyCode = 94;
 const LEFT_ARROW: CGKeyCode = 123;
 const META_LEFT: CGKeyCode = 55;
 const META_RIGHT: CGKeyCode = 54;
@@ -82,6 +98,8 @@ const KEY_M: CGKeyCode = 46;
 const COMMA: CGKeyCode = 43;
 const DOT: CGKeyCode = 47;
 const SLASH: CGKeyCode = 44;
+// kVK_ISO_Section
+const INTL_BACKSLASH: CGKeyCode = 10;
 
 pub fn code_from_key(key: Key) -> Option<CGKeyCode> {
     match key {
@@ -97,6 +115,14 @@ pub fn code_from_key(key: Key) -> Option<CGKeyCode> {
         Key::F10 => Some(F10),
         Key::F11 => Some(F11),
         Key::F12 => Some(F12),
+        Key::F13 => Some(F13),
+        Key::F14 => Some(F14),
+        Key::F15 => Some(F15),
+        Key::F16 => Some(F16),
+        Key::F17 => Some(F17),
+        Key::F18 => Some(F18),
+        Key::F19 => Some(F19),
+        Key::F20 => Some(F20),
         Key::F2 => Some(F2),
         Key::F3 => Some(F3),
         Key::F4 => Some(F4),
@@ -162,7 +188,13 @@ pub fn code_from_key(key: Key) -> Option<CGKeyCode> {
         Key::Comma => Some(COMMA),
         Key::Dot => Some(DOT),
         Key::Slash => Some(SLASH),
+        Key::IntlBackslash => Some(INTL_BACKSLASH),
         Key::Function => Some(FUNCTION),
+        Key::Menu => Some(MENU),
+        Key::Kana => Some(KANA),
+        Key::Muhenkan => Some(EISU),
+        Key::Yen => Some(YEN),
+        Key::Ro => Some(RO),
         Key::Unknown(code) => code.try_into().ok(),
         _ => None,
     }
@@ -181,6 +213,14 @@ pub fn key_from_code(code: CGKeyCode) -> Key {
         F10 => Key::F10,
         F11 => Key::F11,
         F12 => Key::F12,
+        F13 => Key::F13,
+        F14 => Key::F14,
+        F15 => Key::F15,
+        F16 => Key::F16,
+        F17 => Key::F17,
+        F18 => Key::F18,
+        F19 => Key::F19,
+        F20 => Key::F20,
         F2 => Key::F2,
         F3 => Key::F3,
         F4 => Key::F4,
@@ -246,11 +286,57 @@ pub fn key_from_code(code: CGKeyCode) -> Key {
         COMMA => Key::Comma,
         DOT => Key::Dot,
         SLASH => Key::Slash,
+        INTL_BACKSLASH => Key::IntlBackslash,
         FUNCTION => Key::Function,
+        MENU => Key::Menu,
+        KANA => Key::Kana,
+        EISU => Key::Muhenkan,
+        YEN => Key::Yen,
+        RO => Key::Ro,
         code => Key::Unknown(code.into()),
     }
 }
 
+// Media keys don't have a virtual key code, they arrive as NSSystemDefined
+// events carrying one of the NX_KEYTYPE_* values from <IOKit/hidsystem/ev_keymap.h>
+pub type NXKeyType = u32;
+const NX_KEYTYPE_SOUND_UP: NXKeyType = 0;
+const NX_KEYTYPE_SOUND_DOWN: NXKeyType = 1;
+const NX_KEYTYPE_BRIGHTNESS_UP: NXKeyType = 2;
+const NX_KEYTYPE_BRIGHTNESS_DOWN: NXKeyType = 3;
+const NX_KEYTYPE_MUTE: NXKeyType = 7;
+const NX_KEYTYPE_PLAY: NXKeyType = 16;
+const NX_KEYTYPE_NEXT: NXKeyType = 17;
+const NX_KEYTYPE_PREVIOUS: NXKeyType = 18;
+
+pub fn nx_code_from_key(key: Key) -> Option<NXKeyType> {
+    match key {
+        Key::VolumeUp => Some(NX_KEYTYPE_SOUND_UP),
+        Key::VolumeDown => Some(NX_KEYTYPE_SOUND_DOWN),
+        Key::BrightnessUp => Some(NX_KEYTYPE_BRIGHTNESS_UP),
+        Key::BrightnessDown => Some(NX_KEYTYPE_BRIGHTNESS_DOWN),
+        Key::VolumeMute => Some(NX_KEYTYPE_MUTE),
+        Key::MediaPlayPause => Some(NX_KEYTYPE_PLAY),
+        Key::MediaNext => Some(NX_KEYTYPE_NEXT),
+        Key::MediaPrev => Some(NX_KEYTYPE_PREVIOUS),
+        _ => None,
+    }
+}
+
+pub fn key_from_nx_code(code: NXKeyType) -> Option<Key> {
+    match code {
+        NX_KEYTYPE_SOUND_UP => Some(Key::VolumeUp),
+        NX_KEYTYPE_SOUND_DOWN => Some(Key::VolumeDown),
+        NX_KEYTYPE_BRIGHTNESS_UP => Some(Key::BrightnessUp),
+        NX_KEYTYPE_BRIGHTNESS_DOWN => Some(Key::BrightnessDown),
+        NX_KEYTYPE_MUTE => Some(Key::VolumeMute),
+        NX_KEYTYPE_PLAY => Some(Key::MediaPlayPause),
+        NX_KEYTYPE_NEXT => Some(Key::MediaNext),
+        NX_KEYTYPE_PREVIOUS => Some(Key::MediaPrev),
+        _ => None,
+    }
+}
+
 #[cfg(test)]
 mod test {
     use super::{code_from_key, key_from_code};
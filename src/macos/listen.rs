@@ -1,28 +1,79 @@
 #![allow(improper_ctypes_definitions)]
 use crate::macos::common::*;
-use crate::rdev::{Event, ListenError};
+use crate::macos::permissions::tap_error;
+use crate::macos::state::is_secure_input_active;
+use crate::rdev::{Event, EventType, ListenError};
 use cocoa::base::nil;
 use cocoa::foundation::NSAutoreleasePool;
-use core_graphics::event::{CGEventTapLocation, CGEventType};
+use core_graphics::event::CGEventTapLocation;
 use std::os::raw::c_void;
+use std::ptr::addr_of_mut;
+use std::time::SystemTime;
 
 static mut GLOBAL_CALLBACK: Option<Box<dyn FnMut(Event)>> = None;
+static mut TAP: Option<CFMachPortRef> = None;
+static mut SECURE_INPUT: bool = false;
+
+/// Keyboard events stop coming without a word while Secure Input is on, so
+/// we look for changes on every event we still get.
+unsafe fn check_secure_input() {
+    let active = is_secure_input_active();
+    if active == SECURE_INPUT {
+        return;
+    }
+    SECURE_INPUT = active;
+    if let Some(callback) = &mut *addr_of_mut!(GLOBAL_CALLBACK) {
+        callback(Event {
+            event_type: EventType::SecureInput(active),
+            time: SystemTime::now(),
+            name: None,
+            unicode: None,
+            scan_code: 0,
+            display: None,
+            delta: None,
+            numlock: None,
+        });
+    }
+}
 
 #[link(name = "Cocoa", kind = "framework")]
 extern "C" {}
 
 unsafe extern "C" fn raw_callback(
     _proxy: CGEventTapProxy,
-    _type: CGEventType,
+    _type: u32,
     cg_event: CGEventRef,
     _user_info: *mut c_void,
 ) -> CGEventRef {
     // println!("Event ref {:?}", cg_event_ptr);
     // let cg_event: CGEvent = transmute_copy::<*mut c_void, CGEvent>(&cg_event_ptr);
+    check_secure_input();
+    if _type == TAP_DISABLED_BY_TIMEOUT || _type == TAP_DISABLED_BY_USER_INPUT {
+        if let Some(tap) = TAP {
+            CGEventTapEnable(tap, true);
+            crate::trace::tap_reenabled(_type == TAP_DISABLED_BY_TIMEOUT);
+        }
+        return cg_event;
+    }
+    if is_gesture(_type) {
+        if let Some(callback) = &mut *addr_of_mut!(GLOBAL_CALLBACK) {
+            for event in convert_touches(_type, &cg_event) {
+                callback(event);
+            }
+            #[cfg(feature = "gestures")]
+            if let Some(event) = convert_gesture(&cg_event) {
+                callback(event);
+            }
+        }
+        return cg_event;
+    }
     let opt = KEYBOARD_STATE.lock();
     if let Ok(mut keyboard) = opt {
-        if let Some(event) = convert(_type, &cg_event, &mut keyboard) {
+        if let Some(event) = convert_raw(_type, &cg_event, &mut keyboard) {
             if let Some(callback) = &mut GLOBAL_CALLBACK {
+                if let Some(layout) = layout_event(&mut keyboard) {
+                    callback(layout);
+                }
                 callback(event);
             }
         }
@@ -36,30 +87,46 @@ pub fn listen<T>(callback: T) -> Result<(), ListenError>
 where
     T: FnMut(Event) + 'static,
 {
+    listen_tap(true, callback)
+}
+
+/// Without `listen_only` the tap is an active filter, which the system
+/// disables more readily when the callback is slow. Events are never
+/// modified either way.
+pub fn listen_tap<T>(listen_only: bool, callback: T) -> Result<(), ListenError>
+where
+    T: FnMut(Event) + 'static,
+{
+    let option = if listen_only {
+        CGEventTapOption::ListenOnly
+    } else {
+        CGEventTapOption::Default
+    };
     unsafe {
         GLOBAL_CALLBACK = Some(Box::new(callback));
+        // If it is already on, the application is told right away.
+        SECURE_INPUT = false;
+        check_secure_input();
         let _pool = NSAutoreleasePool::new(nil);
         let tap = CGEventTapCreate(
             CGEventTapLocation::HID, // HID, Session, AnnotatedSession,
             kCGHeadInsertEventTap,
-            CGEventTapOption::ListenOnly,
+            option,
             kCGEventMaskForAllEvents,
             raw_callback,
             nil,
         );
         if tap.is_null() {
-            return Err(ListenError::EventTapError);
+            return Err(ListenError::EventTapError(tap_error(listen_only)));
         }
-        let _loop = CFMachPortCreateRunLoopSource(nil, tap, 0);
-        if _loop.is_null() {
+        let mut tap = EventTap::new(tap);
+        TAP = Some(tap.tap);
+        if !tap.add_to_run_loop() {
+            TAP = None;
             return Err(ListenError::LoopSourceError);
         }
-
-        let current_loop = CFRunLoopGetCurrent();
-        CFRunLoopAddSource(current_loop, _loop, kCFRunLoopCommonModes);
-
-        CGEventTapEnable(tap, true);
         CFRunLoopRun();
+        TAP = None;
     }
     Ok(())
 }
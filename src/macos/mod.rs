@@ -1,15 +1,32 @@
 mod common;
+mod conflicts;
+mod devices;
 mod display;
 #[cfg(feature = "unstable_grab")]
 mod grab;
+mod iohid;
+mod iokit;
 mod keyboard;
 mod keycodes;
 mod listen;
+mod permissions;
+mod priority;
 mod simulate;
+mod state;
 
-pub use crate::macos::display::display_size;
+pub use crate::macos::conflicts::conflicts;
+pub use crate::macos::devices::listen_devices;
+pub use crate::macos::display::{display_size, displays, listen_display_changes};
 #[cfg(feature = "unstable_grab")]
 pub use crate::macos::grab::grab;
-pub use crate::macos::keyboard::Keyboard;
-pub use crate::macos::listen::listen;
-pub use crate::macos::simulate::simulate;
+pub use crate::macos::iohid::listen as listen_iohid;
+pub use crate::macos::keyboard::{available_layouts, Keyboard};
+pub use crate::macos::keycodes::{code_from_key, key_from_code};
+pub use crate::macos::listen::{listen, listen_tap};
+pub use crate::macos::permissions::{missing, query_permissions, request_permissions};
+pub use crate::macos::priority::raise_thread_priority;
+pub use crate::macos::simulate::{simulate, simulate_unicode};
+pub use crate::macos::state::{
+    click_settings, get_lock_state, get_mouse_position, idle_time, is_secure_input_active,
+    keyboard_repeat, keyboard_state, mouse_state, set_lock_state,
+};
@@ -0,0 +1,91 @@
+use crate::macos::iokit::*;
+use crate::permissions::{Access, Missing, Need, Permissions};
+use crate::rdev::OsError;
+use core_foundation::base::TCFType;
+use core_foundation::boolean::CFBoolean;
+use core_foundation::dictionary::{CFDictionary, CFDictionaryRef};
+use core_foundation::string::{CFString, CFStringRef};
+
+#[link(name = "ApplicationServices", kind = "framework")]
+extern "C" {
+    static kAXTrustedCheckOptionPrompt: CFStringRef;
+    fn AXIsProcessTrustedWithOptions(options: CFDictionaryRef) -> u8;
+}
+
+/// With `prompt` the system shows its dialog pointing to the settings, it
+/// doesn't wait for the answer.
+fn accessibility(prompt: bool) -> Access {
+    let trusted = unsafe {
+        let key = CFString::wrap_under_get_rule(kAXTrustedCheckOptionPrompt);
+        let options = CFDictionary::from_CFType_pairs(&[(
+            key.as_CFType(),
+            CFBoolean::from(prompt).as_CFType(),
+        )]);
+        AXIsProcessTrustedWithOptions(options.as_concrete_TypeRef())
+    };
+    if trusted != 0 {
+        Access::Granted
+    } else {
+        Access::Denied
+    }
+}
+
+#[allow(non_upper_case_globals)]
+fn input_monitoring() -> Access {
+    match unsafe { IOHIDCheckAccess(kIOHIDRequestTypeListenEvent) } {
+        kIOHIDAccessTypeGranted => Access::Granted,
+        kIOHIDAccessTypeDenied => Access::Denied,
+        _ => Access::Unknown,
+    }
+}
+
+/// Why `CGEventTapCreate` returned null, it gives no error of its own: the
+/// permission the tap needs is usually missing, listen only taps need
+/// Input Monitoring and the others Accessibility.
+pub fn tap_error(listen_only: bool) -> OsError {
+    let granted = if listen_only {
+        input_monitoring() == Access::Granted
+    } else {
+        accessibility(false) == Access::Granted
+    };
+    match (granted, listen_only) {
+        (true, _) => OsError::new("CGEventTapCreate", 0),
+        (false, true) => OsError::new("IOHIDCheckAccess", 0),
+        (false, false) => OsError::new("AXIsProcessTrustedWithOptions", 0),
+    }
+}
+
+pub fn query_permissions() -> Permissions {
+    Permissions {
+        accessibility: accessibility(false),
+        input_monitoring: input_monitoring(),
+    }
+}
+
+pub fn request_permissions() -> Permissions {
+    let accessibility = match accessibility(false) {
+        Access::Granted => Access::Granted,
+        _ => accessibility(true),
+    };
+    // Only prompts the first time, later the user has to go to the settings.
+    if input_monitoring() != Access::Granted {
+        unsafe { IOHIDRequestAccess(kIOHIDRequestTypeListenEvent) };
+    }
+    Permissions {
+        accessibility,
+        input_monitoring: input_monitoring(),
+    }
+}
+
+/// Event taps, `simulate` and grabs need Accessibility, and listening Input
+/// Monitoring too: the default tap is listen only (`Settings::listen_only`).
+pub fn missing(need: Need) -> Vec<Missing> {
+    let mut missing = vec![];
+    if accessibility(false) != Access::Granted {
+        missing.push(Missing::Accessibility);
+    }
+    if need == Need::Listen && input_monitoring() != Access::Granted {
+        missing.push(Missing::InputMonitoring);
+    }
+    missing
+}
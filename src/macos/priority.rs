@@ -0,0 +1,51 @@
+use std::ffi::c_void;
+use std::os::raw::{c_int, c_uint};
+
+#[allow(non_camel_case_types)]
+type qos_class_t = c_uint;
+const QOS_CLASS_USER_INTERACTIVE: qos_class_t = 0x21;
+const QOS_CLASS_DEFAULT: qos_class_t = 0x15;
+const QOS_CLASS_UNSPECIFIED: qos_class_t = 0x00;
+
+extern "C" {
+    fn pthread_self() -> *mut c_void;
+    fn pthread_get_qos_class_np(
+        thread: *mut c_void,
+        qos_class: *mut qos_class_t,
+        relative_priority: *mut c_int,
+    ) -> c_int;
+    fn pthread_set_qos_class_self_np(qos_class: qos_class_t, relative_priority: c_int) -> c_int;
+}
+
+/// The QoS class the thread had before `raise_thread_priority`.
+pub struct ThreadPriority {
+    qos_class: qos_class_t,
+    relative_priority: c_int,
+}
+
+impl Drop for ThreadPriority {
+    fn drop(&mut self) {
+        unsafe { pthread_set_qos_class_self_np(self.qos_class, self.relative_priority) };
+    }
+}
+
+pub fn raise_thread_priority() -> Option<ThreadPriority> {
+    unsafe {
+        let mut qos_class = QOS_CLASS_UNSPECIFIED;
+        let mut relative_priority = 0;
+        if pthread_get_qos_class_np(pthread_self(), &mut qos_class, &mut relative_priority) != 0 {
+            return None;
+        }
+        if pthread_set_qos_class_self_np(QOS_CLASS_USER_INTERACTIVE, 0) != 0 {
+            return None;
+        }
+        // Threads start without a class, which can't be set back.
+        if qos_class == QOS_CLASS_UNSPECIFIED {
+            qos_class = QOS_CLASS_DEFAULT;
+        }
+        Some(ThreadPriority {
+            qos_class,
+            relative_priority,
+        })
+    }
+}
@@ -1,12 +1,53 @@
 use crate::rdev::{Button, EventType, SimulateError};
 use core_graphics::event::{
-    CGEvent, CGEventTapLocation, CGEventType, CGMouseButton, ScrollEventUnit,
+    CGEvent, CGEventTapLocation, CGEventType, CGMouseButton, EventField, ScrollEventUnit,
 };
 use core_graphics::event_source::{CGEventSource, CGEventSourceStateID};
 use core_graphics::geometry::CGPoint;
 use std::convert::TryInto;
 
-use crate::macos::keycodes::code_from_key;
+use crate::macos::common::{
+    scroll_phase_fields, NX_KEYSTATE_DOWN, NX_SUBTYPE_AUX_CONTROL_BUTTONS, NX_SYSDEFINED,
+    SCROLL_WHEEL_EVENT_IS_CONTINUOUS, SCROLL_WHEEL_EVENT_MOMENTUM_PHASE,
+    SCROLL_WHEEL_EVENT_SCROLL_PHASE,
+};
+use crate::macos::keycodes::{code_from_key, nx_code_from_key};
+use cocoa::base::{id, nil};
+use cocoa::foundation::{NSInteger, NSPoint};
+use objc::{class, msg_send, sel, sel_impl};
+use std::os::raw::c_void;
+
+const NX_KEYSTATE_UP: NSInteger = 0x0b;
+
+/// Left and right have their own event types, every other button is an
+/// "other" button told apart by its number.
+unsafe fn button_event(source: CGEventSource, button: &Button, down: bool) -> Option<CGEvent> {
+    let point = get_current_mouse_location()?;
+    let number: i64 = match button {
+        Button::Left => 0,
+        Button::Right => 1,
+        Button::Middle => 2,
+        Button::Back => 3,
+        Button::Forward => 4,
+        Button::Unknown(code) => (*code).into(),
+    };
+    let event_type = match (number, down) {
+        (0, true) => CGEventType::LeftMouseDown,
+        (0, false) => CGEventType::LeftMouseUp,
+        (1, true) => CGEventType::RightMouseDown,
+        (1, false) => CGEventType::RightMouseUp,
+        (_, true) => CGEventType::OtherMouseDown,
+        (_, false) => CGEventType::OtherMouseUp,
+    };
+    let mouse_button = match number {
+        0 => CGMouseButton::Left,
+        1 => CGMouseButton::Right,
+        _ => CGMouseButton::Center,
+    };
+    let event = CGEvent::new_mouse_event(source, event_type, point, mouse_button).ok()?;
+    event.set_integer_value_field(EventField::MOUSE_EVENT_BUTTON_NUMBER, number);
+    Some(event)
+}
 
 unsafe fn convert_native_with_source(
     event_type: &EventType,
@@ -21,36 +62,8 @@ unsafe fn convert_native_with_source(
             let code = code_from_key(*key)?;
             CGEvent::new_keyboard_event(source, code, false).ok()
         }
-        EventType::ButtonPress(button) => {
-            let point = get_current_mouse_location()?;
-            let event = match button {
-                Button::Left => CGEventType::LeftMouseDown,
-                Button::Right => CGEventType::RightMouseDown,
-                _ => return None,
-            };
-            CGEvent::new_mouse_event(
-                source,
-                event,
-                point,
-                CGMouseButton::Left, // ignored because we don't use OtherMouse EventType
-            )
-            .ok()
-        }
-        EventType::ButtonRelease(button) => {
-            let point = get_current_mouse_location()?;
-            let event = match button {
-                Button::Left => CGEventType::LeftMouseUp,
-                Button::Right => CGEventType::RightMouseUp,
-                _ => return None,
-            };
-            CGEvent::new_mouse_event(
-                source,
-                event,
-                point,
-                CGMouseButton::Left, // ignored because we don't use OtherMouse EventType
-            )
-            .ok()
-        }
+        EventType::ButtonPress(button) => button_event(source, button, true),
+        EventType::ButtonRelease(button) => button_event(source, button, false),
         EventType::MouseMove { x, y } => {
             let point = CGPoint { x: (*x), y: (*y) };
             CGEvent::new_mouse_event(source, CGEventType::MouseMoved, point, CGMouseButton::Left)
@@ -68,6 +81,41 @@ unsafe fn convert_native_with_source(
             )
             .ok()
         }
+        EventType::Scroll {
+            delta_x,
+            delta_y,
+            phase,
+            is_momentum,
+        } => {
+            let wheel_count = 2;
+            let event = CGEvent::new_scroll_event(
+                source,
+                ScrollEventUnit::PIXEL,
+                wheel_count,
+                delta_y.round() as i32,
+                delta_x.round() as i32,
+                0,
+            )
+            .ok()?;
+            let (scroll_phase, momentum_phase) = scroll_phase_fields(phase, *is_momentum);
+            // Without it apps take pixel deltas for lines of a wheel.
+            event.set_integer_value_field(SCROLL_WHEEL_EVENT_IS_CONTINUOUS, 1);
+            event.set_integer_value_field(SCROLL_WHEEL_EVENT_SCROLL_PHASE, scroll_phase);
+            event.set_integer_value_field(SCROLL_WHEEL_EVENT_MOMENTUM_PHASE, momentum_phase);
+            Some(event)
+        }
+        EventType::LayoutChanged
+        | EventType::ImeComposition
+        | EventType::ImeCommit
+        | EventType::SecureInput(_)
+        | EventType::TouchDown { .. }
+        | EventType::TouchMove { .. }
+        | EventType::TouchUp { .. }
+        | EventType::PenDown(_)
+        | EventType::PenMove(_)
+        | EventType::PenUp(_) => None,
+        #[cfg(feature = "gestures")]
+        EventType::Gesture(_) => None,
     }
 }
 
@@ -83,10 +131,57 @@ unsafe fn get_current_mouse_location() -> Option<CGPoint> {
 }
 
 #[link(name = "Cocoa", kind = "framework")]
-extern "C" {}
+extern "C" {
+    fn CGEventPost(tap: CGEventTapLocation, event: *mut c_void);
+}
+
+/// Media keys are not regular keyboard events, we have to go through an
+/// NSSystemDefined event instead.
+unsafe fn simulate_media_key(code: u32, down: bool) -> Result<(), SimulateError> {
+    let state = if down {
+        NX_KEYSTATE_DOWN
+    } else {
+        NX_KEYSTATE_UP
+    };
+    let flags: u64 = (state as u64) << 8;
+    let data1: NSInteger = ((code as NSInteger) << 16) | (state << 8);
+    let ns_event: id = msg_send![class!(NSEvent),
+        otherEventWithType: NX_SYSDEFINED as u64
+        location: NSPoint::new(0.0, 0.0)
+        modifierFlags: flags
+        timestamp: 0.0_f64
+        windowNumber: 0 as NSInteger
+        context: nil
+        subtype: NX_SUBTYPE_AUX_CONTROL_BUTTONS
+        data1: data1
+        data2: -1 as NSInteger
+    ];
+    if ns_event == nil {
+        return Err(SimulateError);
+    }
+    let cg_event: *mut c_void = msg_send![ns_event, CGEvent];
+    if cg_event.is_null() {
+        return Err(SimulateError);
+    }
+    CGEventPost(CGEventTapLocation::HID, cg_event);
+    Ok(())
+}
 
 pub fn simulate(event_type: &EventType) -> Result<(), SimulateError> {
     unsafe {
+        match event_type {
+            EventType::KeyPress(key) => {
+                if let Some(code) = nx_code_from_key(*key) {
+                    return simulate_media_key(code, true);
+                }
+            }
+            EventType::KeyRelease(key) => {
+                if let Some(code) = nx_code_from_key(*key) {
+                    return simulate_media_key(code, false);
+                }
+            }
+            _ => (),
+        }
         if let Some(cg_event) = convert_native(event_type) {
             cg_event.post(CGEventTapLocation::HID);
             Ok(())
@@ -95,3 +190,16 @@ pub fn simulate(event_type: &EventType) -> Result<(), SimulateError> {
         }
     }
 }
+
+pub fn simulate_unicode(c: char) -> Result<(), SimulateError> {
+    let mut buffer = [0_u8; 4];
+    let string = c.encode_utf8(&mut buffer);
+    for down in [true, false].iter() {
+        let source =
+            CGEventSource::new(CGEventSourceStateID::HIDSystemState).map_err(|_| SimulateError)?;
+        let cg_event = CGEvent::new_keyboard_event(source, 0, *down).map_err(|_| SimulateError)?;
+        cg_event.set_string(string);
+        cg_event.post(CGEventTapLocation::HID);
+    }
+    Ok(())
+}
@@ -0,0 +1,196 @@
+use crate::macos::keycodes::key_from_code;
+use crate::rdev::{Button, ClickSettings, DisplayError, Key, LockKey, SimulateError};
+use core_graphics::event::{CGEvent, CGKeyCode};
+use core_graphics::event_source::CGEventSource;
+use objc::{class, msg_send, sel, sel_impl};
+use std::collections::HashSet;
+use std::ffi::c_void;
+use std::os::raw::c_char;
+use std::time::Duration;
+
+type CGEventSourceStateID = i32;
+#[allow(non_upper_case_globals)]
+const kCGEventSourceStateCombinedSessionState: CGEventSourceStateID = 0;
+// kCGAnyInputEventType, every event type.
+const ANY_INPUT_EVENT_TYPE: u32 = !0;
+
+type IOReturn = i32;
+type MachPort = u32;
+const KERN_SUCCESS: IOReturn = 0;
+#[allow(non_upper_case_globals)]
+const kIOHIDParamConnectType: u32 = 1;
+#[allow(non_upper_case_globals)]
+const kIOHIDCapsLockState: u32 = 1;
+
+#[link(name = "Cocoa", kind = "framework")]
+extern "C" {
+    fn CGEventSourceKeyState(state_id: CGEventSourceStateID, key: CGKeyCode) -> bool;
+    fn CGEventSourceButtonState(state_id: CGEventSourceStateID, button: u32) -> bool;
+    fn CGEventSourceSecondsSinceLastEventType(
+        state_id: CGEventSourceStateID,
+        event_type: u32,
+    ) -> f64;
+}
+
+#[link(name = "Carbon", kind = "framework")]
+extern "C" {
+    fn IsSecureEventInputEnabled() -> u8;
+}
+
+#[link(name = "IOKit", kind = "framework")]
+extern "C" {
+    static kIOMasterPortDefault: MachPort;
+    static mach_task_self_: MachPort;
+    fn IOServiceMatching(name: *const c_char) -> *mut c_void;
+    fn IOServiceGetMatchingService(master: MachPort, matching: *mut c_void) -> MachPort;
+    fn IOServiceOpen(
+        service: MachPort,
+        task: MachPort,
+        kind: u32,
+        connect: *mut MachPort,
+    ) -> IOReturn;
+    fn IOServiceClose(connect: MachPort) -> IOReturn;
+    fn IOObjectRelease(object: MachPort) -> IOReturn;
+    fn IOHIDGetModifierLockState(connect: MachPort, selector: u32, state: *mut bool) -> IOReturn;
+    fn IOHIDSetModifierLockState(connect: MachPort, selector: u32, state: bool) -> IOReturn;
+}
+
+/// Runs `f` with a connection to the HID system, the only place where the
+/// caps lock state can be set (a simulated key press doesn't toggle it).
+unsafe fn with_hid_system<T>(f: impl FnOnce(MachPort) -> Option<T>) -> Option<T> {
+    let matching = IOServiceMatching(b"IOHIDSystem\0".as_ptr() as *const c_char);
+    // IOServiceGetMatchingService consumes `matching`.
+    let service = IOServiceGetMatchingService(kIOMasterPortDefault, matching);
+    if service == 0 {
+        return None;
+    }
+    let mut connect = 0;
+    let status = IOServiceOpen(
+        service,
+        mach_task_self_,
+        kIOHIDParamConnectType,
+        &mut connect,
+    );
+    IOObjectRelease(service);
+    if status != KERN_SUCCESS {
+        return None;
+    }
+    let result = f(connect);
+    IOServiceClose(connect);
+    result
+}
+
+pub fn keyboard_state() -> Result<HashSet<Key>, DisplayError> {
+    let mut keys = HashSet::new();
+    // Virtual key codes all fit in 7 bits.
+    for code in 0..128 {
+        if unsafe { CGEventSourceKeyState(kCGEventSourceStateCombinedSessionState, code) } {
+            keys.insert(key_from_code(code));
+        }
+    }
+    Ok(keys)
+}
+
+pub fn mouse_state() -> Result<HashSet<Button>, DisplayError> {
+    let mut buttons = HashSet::new();
+    for code in 0..32 {
+        if unsafe { CGEventSourceButtonState(kCGEventSourceStateCombinedSessionState, code) } {
+            buttons.insert(match code {
+                0 => Button::Left,
+                1 => Button::Right,
+                2 => Button::Middle,
+                3 => Button::Back,
+                4 => Button::Forward,
+                code => Button::Unknown(code as u8),
+            });
+        }
+    }
+    Ok(buttons)
+}
+
+/// macOS keyboards have no NumLock nor ScrollLock, they are always off.
+pub fn get_mouse_position() -> Result<(f64, f64), DisplayError> {
+    let source =
+        CGEventSource::new(core_graphics::event_source::CGEventSourceStateID::CombinedSessionState)
+            .map_err(|_| DisplayError::NoDisplay)?;
+    // A blank event carries the current location.
+    let event = CGEvent::new(source).map_err(|_| DisplayError::NoDisplay)?;
+    let point = event.location();
+    Ok((point.x, point.y))
+}
+
+pub fn is_secure_input_active() -> bool {
+    unsafe { IsSecureEventInputEnabled() != 0 }
+}
+
+pub fn idle_time() -> Result<Duration, DisplayError> {
+    let seconds = unsafe {
+        CGEventSourceSecondsSinceLastEventType(
+            kCGEventSourceStateCombinedSessionState,
+            ANY_INPUT_EVENT_TYPE,
+        )
+    };
+    if !seconds.is_finite() || seconds < 0.0 {
+        return Err(DisplayError::ConversionError);
+    }
+    Ok(Duration::from_secs_f64(seconds))
+}
+
+/// AppKit has no drag threshold setting.
+pub fn click_settings() -> Result<ClickSettings, DisplayError> {
+    let interval: f64 = unsafe { msg_send![class!(NSEvent), doubleClickInterval] };
+    if !interval.is_finite() || interval < 0.0 {
+        return Err(DisplayError::ConversionError);
+    }
+    Ok(ClickSettings {
+        double_click_interval: Duration::from_secs_f64(interval),
+        ..Default::default()
+    })
+}
+
+pub fn keyboard_repeat() -> Result<(Duration, f64), DisplayError> {
+    let (delay, interval): (f64, f64) = unsafe {
+        (
+            msg_send![class!(NSEvent), keyRepeatDelay],
+            msg_send![class!(NSEvent), keyRepeatInterval],
+        )
+    };
+    if !delay.is_finite() || delay < 0.0 || !interval.is_finite() || interval <= 0.0 {
+        return Err(DisplayError::ConversionError);
+    }
+    Ok((Duration::from_secs_f64(delay), 1.0 / interval))
+}
+
+pub fn get_lock_state(lock: LockKey) -> Result<bool, DisplayError> {
+    match lock {
+        LockKey::CapsLock => unsafe {
+            with_hid_system(|connect| {
+                let mut state = false;
+                if IOHIDGetModifierLockState(connect, kIOHIDCapsLockState, &mut state)
+                    != KERN_SUCCESS
+                {
+                    return None;
+                }
+                Some(state)
+            })
+            .ok_or(DisplayError::NoDisplay)
+        },
+        LockKey::NumLock | LockKey::ScrollLock => Ok(false),
+    }
+}
+
+pub fn set_lock_state(lock: LockKey, on: bool) -> Result<(), SimulateError> {
+    match lock {
+        LockKey::CapsLock => unsafe {
+            with_hid_system(|connect| {
+                if IOHIDSetModifierLockState(connect, kIOHIDCapsLockState, on) != KERN_SUCCESS {
+                    return None;
+                }
+                Some(())
+            })
+            .ok_or(SimulateError)
+        },
+        LockKey::NumLock | LockKey::ScrollLock if !on => Ok(()),
+        LockKey::NumLock | LockKey::ScrollLock => Err(SimulateError),
+    }
+}
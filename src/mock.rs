@@ -0,0 +1,461 @@
+//! A backend living in memory, for testing input handling without a display
+//! server (CI containers). Requires the `mock` feature.
+//! `listen_with` a `Backend::Mock` setting gets the events given to
+//! `push_event`, and `simulate_with` one records what it's given for
+//! `take_simulated`. Nothing reaches or comes from the OS.
+//! `Backend::Loopback` connects the two instead: what `simulate_with` it
+//! sends goes through the `grab` callbacks of this module, then to every
+//! listener, named by a `UsKeyboard`. Tests can check that pressing Ctrl+S
+//! saves without a display. Names are always US QWERTY, not the platform
+//! `Keyboard` with the user's layout: dead keys, AltGr and other layouts
+//! aren't covered.
+//!
+//! ```no_run
+//! use rdev::mock::{push_event, take_simulated};
+//! use rdev::{listen_with, simulate_with, Backend, Event, EventType, Key, Settings};
+//! use std::thread;
+//! use std::time::SystemTime;
+//!
+//! let settings = Settings {
+//!     backend: Some(Backend::Mock),
+//!     ..Default::default()
+//! };
+//! thread::spawn(move || {
+//!     listen_with(settings, |event| println!("{:?}", event)).ok();
+//! });
+//! push_event(Event::new(
+//!     EventType::KeyPress(Key::KeyS),
+//!     SystemTime::now(),
+//!     Some("s".to_string()),
+//! ));
+//! simulate_with(settings, &EventType::KeyRelease(Key::KeyS)).unwrap();
+//! assert_eq!(take_simulated(), vec![EventType::KeyRelease(Key::KeyS)]);
+//! ```
+use crate::clock::{Clock, SystemClock};
+use crate::rdev::{Event, EventType, Key, KeyboardState, ListenError, SimulateError};
+use lazy_static::lazy_static;
+use std::collections::{HashSet, VecDeque};
+use std::sync::mpsc::{channel, Sender};
+use std::sync::{Condvar, Mutex};
+use std::thread::{self, ThreadId};
+
+lazy_static! {
+    static ref PUSHED: Mutex<VecDeque<Event>> = Mutex::new(VecDeque::new());
+    static ref PUSHED_CHANGED: Condvar = Condvar::new();
+    static ref SIMULATED: Mutex<Vec<EventType>> = Mutex::new(Vec::new());
+    static ref CLOCK: Mutex<Box<dyn Clock>> = Mutex::new(Box::new(SystemClock));
+    static ref LOOPBACK: Mutex<Loopback> = Mutex::new(Loopback::default());
+}
+
+type GrabRequest = (Event, Sender<Option<Event>>);
+
+#[derive(Default)]
+struct Loopback {
+    keyboard: UsKeyboard,
+    held: HashSet<Key>,
+    listeners: Vec<Sender<Event>>,
+    grabs: Vec<(ThreadId, Sender<GrabRequest>)>,
+}
+
+/// Queues an event for the mock listeners, each event goes to one of them.
+/// Events pushed before anyone listens are kept.
+pub fn push_event(event: Event) {
+    if let Ok(mut pushed) = PUSHED.lock() {
+        pushed.push_back(event);
+        PUSHED_CHANGED.notify_one();
+    }
+}
+
+/// Where `push_event_type` gets the time of events from, `SystemClock` by
+/// default. A `VirtualClock` makes the times deterministic.
+pub fn set_clock<C: Clock + 'static>(clock: C) {
+    if let Ok(mut current) = CLOCK.lock() {
+        *current = Box::new(clock);
+    }
+}
+
+/// `push_event` with an event at the time of the mock clock.
+pub fn push_event_type(event_type: EventType) {
+    let time = match CLOCK.lock() {
+        Ok(clock) => clock.now(),
+        Err(_) => return,
+    };
+    push_event(Event {
+        event_type,
+        time,
+        name: None,
+        unicode: None,
+        scan_code: 0,
+        display: None,
+        delta: None,
+        numlock: None,
+    });
+}
+
+/// The events simulated with the mock backend since the last call, oldest
+/// first.
+pub fn take_simulated() -> Vec<EventType> {
+    SIMULATED
+        .lock()
+        .map(|mut simulated| simulated.drain(..).collect())
+        .unwrap_or_default()
+}
+
+pub(crate) fn listen<T>(mut callback: T) -> Result<(), ListenError>
+where
+    T: FnMut(Event),
+{
+    loop {
+        let event = {
+            let mut pushed = PUSHED.lock().map_err(|_| ListenError::UnsupportedBackend)?;
+            loop {
+                match pushed.pop_front() {
+                    Some(event) => break event,
+                    None => {
+                        pushed = PUSHED_CHANGED
+                            .wait(pushed)
+                            .map_err(|_| ListenError::UnsupportedBackend)?
+                    }
+                }
+            }
+        };
+        // Not holding the lock, the callback may push.
+        callback(event);
+    }
+}
+
+pub(crate) fn simulate(event_type: &EventType) -> Result<(), SimulateError> {
+    let mut simulated = SIMULATED.lock().map_err(|_| SimulateError)?;
+    simulated.push(*event_type);
+    Ok(())
+}
+
+/// A US QWERTY layout that needs no OS, what `Backend::Loopback` names
+/// events with. Letters with Ctrl held give control characters, like X11
+/// and Windows do.
+#[derive(Debug, Default)]
+pub struct UsKeyboard {
+    shift: HashSet<Key>,
+    ctrl: HashSet<Key>,
+    caps_lock: bool,
+}
+
+impl UsKeyboard {
+    pub fn new() -> UsKeyboard {
+        UsKeyboard::default()
+    }
+}
+
+/// The characters of a key, without and with Shift.
+fn us_chars(key: Key) -> Option<(char, char)> {
+    const LETTERS: [Key; 26] = [
+        Key::KeyA,
+        Key::KeyB,
+        Key::KeyC,
+        Key::KeyD,
+        Key::KeyE,
+        Key::KeyF,
+        Key::KeyG,
+        Key::KeyH,
+        Key::KeyI,
+        Key::KeyJ,
+        Key::KeyK,
+        Key::KeyL,
+        Key::KeyM,
+        Key::KeyN,
+        Key::KeyO,
+        Key::KeyP,
+        Key::KeyQ,
+        Key::KeyR,
+        Key::KeyS,
+        Key::KeyT,
+        Key::KeyU,
+        Key::KeyV,
+        Key::KeyW,
+        Key::KeyX,
+        Key::KeyY,
+        Key::KeyZ,
+    ];
+    if let Some(index) = LETTERS.iter().position(|letter| *letter == key) {
+        let c = (b'a' + index as u8) as char;
+        return Some((c, c.to_ascii_uppercase()));
+    }
+    let chars = match key {
+        Key::BackQuote => ('`', '~'),
+        Key::Num1 => ('1', '!'),
+        Key::Num2 => ('2', '@'),
+        Key::Num3 => ('3', '#'),
+        Key::Num4 => ('4', '$'),
+        Key::Num5 => ('5', '%'),
+        Key::Num6 => ('6', '^'),
+        Key::Num7 => ('7', '&'),
+        Key::Num8 => ('8', '*'),
+        Key::Num9 => ('9', '('),
+        Key::Num0 => ('0', ')'),
+        Key::Minus => ('-', '_'),
+        Key::Equal => ('=', '+'),
+        Key::LeftBracket => ('[', '{'),
+        Key::RightBracket => (']', '}'),
+        Key::BackSlash | Key::IntlBackslash => ('\\', '|'),
+        Key::SemiColon => (';', ':'),
+        Key::Quote => ('\'', '"'),
+        Key::Comma => (',', '<'),
+        Key::Dot => ('.', '>'),
+        Key::Slash => ('/', '?'),
+        Key::Space => (' ', ' '),
+        Key::Return | Key::KpReturn => ('\r', '\r'),
+        Key::Tab => ('\t', '\t'),
+        Key::Backspace => ('\u{8}', '\u{8}'),
+        Key::Escape => ('\u{1b}', '\u{1b}'),
+        Key::Kp0 => ('0', '0'),
+        Key::Kp1 => ('1', '1'),
+        Key::Kp2 => ('2', '2'),
+        Key::Kp3 => ('3', '3'),
+        Key::Kp4 => ('4', '4'),
+        Key::Kp5 => ('5', '5'),
+        Key::Kp6 => ('6', '6'),
+        Key::Kp7 => ('7', '7'),
+        Key::Kp8 => ('8', '8'),
+        Key::Kp9 => ('9', '9'),
+        Key::KpPlus => ('+', '+'),
+        Key::KpMinus => ('-', '-'),
+        Key::KpMultiply => ('*', '*'),
+        Key::KpDivide => ('/', '/'),
+        _ => return None,
+    };
+    Some(chars)
+}
+
+impl KeyboardState for UsKeyboard {
+    fn add(&mut self, event_type: &EventType) -> Option<String> {
+        match *event_type {
+            EventType::KeyPress(key @ Key::ShiftLeft)
+            | EventType::KeyPress(key @ Key::ShiftRight) => {
+                self.shift.insert(key);
+                None
+            }
+            EventType::KeyRelease(key @ Key::ShiftLeft)
+            | EventType::KeyRelease(key @ Key::ShiftRight) => {
+                self.shift.remove(&key);
+                None
+            }
+            EventType::KeyPress(key @ Key::ControlLeft)
+            | EventType::KeyPress(key @ Key::ControlRight) => {
+                self.ctrl.insert(key);
+                None
+            }
+            EventType::KeyRelease(key @ Key::ControlLeft)
+            | EventType::KeyRelease(key @ Key::ControlRight) => {
+                self.ctrl.remove(&key);
+                None
+            }
+            EventType::KeyPress(Key::CapsLock) => {
+                self.caps_lock = !self.caps_lock;
+                None
+            }
+            EventType::KeyPress(key) => {
+                let (lower, upper) = us_chars(key)?;
+                let is_letter = lower.is_ascii_lowercase();
+                if !self.ctrl.is_empty() {
+                    // Ctrl+A is 1, Ctrl+Z is 26.
+                    return is_letter.then(|| ((lower as u8 - b'a' + 1) as char).to_string());
+                }
+                let shift = !self.shift.is_empty();
+                // Caps Lock inverts Shift for letters.
+                let shifted = shift != (self.caps_lock && is_letter);
+                Some(if shifted { upper } else { lower }.to_string())
+            }
+            _ => None,
+        }
+    }
+
+    fn reset(&mut self) {
+        *self = UsKeyboard::default();
+    }
+}
+
+/// The keys held, as simulated with `Backend::Loopback`.
+pub fn held_keys() -> HashSet<Key> {
+    LOOPBACK
+        .lock()
+        .map(|loopback| loopback.held.clone())
+        .unwrap_or_default()
+}
+
+/// Forgets the keys held and the keyboard state of `Backend::Loopback`,
+/// between tests.
+pub fn reset_loopback() {
+    if let Ok(mut loopback) = LOOPBACK.lock() {
+        loopback.held.clear();
+        loopback.keyboard.reset();
+    }
+}
+
+/// Like `crate::grab`, for events simulated with `Backend::Loopback`.
+/// Blocks. `callback` runs on this thread, events it simulates don't go
+/// through it again.
+pub fn grab<T>(callback: T) -> Result<(), ListenError>
+where
+    T: Fn(Event) -> Option<Event>,
+{
+    let (sender, receiver) = channel::<GrabRequest>();
+    LOOPBACK
+        .lock()
+        .map_err(|_| ListenError::UnsupportedBackend)?
+        .grabs
+        .push((thread::current().id(), sender));
+    for (event, reply) in receiver {
+        reply.send(callback(event)).ok();
+    }
+    Ok(())
+}
+
+pub(crate) fn listen_loopback<T>(mut callback: T) -> Result<(), ListenError>
+where
+    T: FnMut(Event),
+{
+    let (sender, receiver) = channel();
+    LOOPBACK
+        .lock()
+        .map_err(|_| ListenError::UnsupportedBackend)?
+        .listeners
+        .push(sender);
+    for event in receiver {
+        callback(event);
+    }
+    Ok(())
+}
+
+pub(crate) fn simulate_loopback(event_type: &EventType) -> Result<(), SimulateError> {
+    let time = CLOCK.lock().map_err(|_| SimulateError)?.now();
+    let (name, grabs) = {
+        let mut loopback = LOOPBACK.lock().map_err(|_| SimulateError)?;
+        let name = loopback.keyboard.add(event_type);
+        let current = thread::current().id();
+        let grabs: Vec<_> = loopback
+            .grabs
+            .iter()
+            .filter(|(thread, _)| *thread != current)
+            .cloned()
+            .collect();
+        (name, grabs)
+    };
+    let mut event = Some(Event::new(*event_type, time, name));
+    // Not holding the lock, callbacks may simulate.
+    let mut gone = vec![];
+    for (thread, grab) in grabs {
+        let grabbed = match event {
+            Some(ref grabbed) => grabbed.clone(),
+            None => break,
+        };
+        let (reply, replied) = channel();
+        if grab.send((grabbed, reply)).is_err() {
+            // The grab went away and dropped its receiver.
+            gone.push(thread);
+            continue;
+        }
+        if let Ok(passed) = replied.recv() {
+            event = passed;
+        }
+    }
+    let mut loopback = LOOPBACK.lock().map_err(|_| SimulateError)?;
+    loopback.grabs.retain(|(thread, _)| !gone.contains(thread));
+    let event = match event {
+        Some(event) => event,
+        // Suppressed, nothing got pressed.
+        None => return Ok(()),
+    };
+    match event.event_type {
+        EventType::KeyPress(key) => {
+            loopback.held.insert(key);
+        }
+        EventType::KeyRelease(key) => {
+            loopback.held.remove(&key);
+        }
+        _ => (),
+    }
+    loopback
+        .listeners
+        .retain(|listener| listener.send(event.clone()).is_ok());
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::rdev::Key;
+    use std::sync::mpsc;
+    use std::thread;
+    use std::time::{Duration, SystemTime};
+
+    #[test]
+    fn test_mock() {
+        let (sender, receiver) = mpsc::channel();
+        thread::spawn(move || {
+            listen(move |event| sender.send(event.event_type).unwrap()).ok();
+        });
+        push_event(Event::new(
+            EventType::KeyPress(Key::KeyA),
+            SystemTime::now(),
+            None,
+        ));
+        assert_eq!(
+            receiver.recv_timeout(Duration::from_secs(1)),
+            Ok(EventType::KeyPress(Key::KeyA))
+        );
+        simulate(&EventType::KeyRelease(Key::KeyA)).unwrap();
+        assert_eq!(take_simulated(), vec![EventType::KeyRelease(Key::KeyA)]);
+        assert_eq!(take_simulated(), vec![]);
+    }
+
+    #[test]
+    fn test_loopback() {
+        let (sender, receiver) = mpsc::channel();
+        thread::spawn(move || {
+            listen_loopback(move |event| sender.send((event.event_type, event.name)).unwrap()).ok();
+        });
+        thread::spawn(|| {
+            grab(|event| match event.event_type {
+                EventType::KeyPress(Key::KeyQ) => None,
+                _ => Some(event),
+            })
+            .ok();
+        });
+        while {
+            let loopback = LOOPBACK.lock().unwrap();
+            loopback.listeners.is_empty() || loopback.grabs.is_empty()
+        } {
+            thread::sleep(Duration::from_millis(1));
+        }
+
+        for event_type in [
+            EventType::KeyPress(Key::ShiftLeft),
+            EventType::KeyPress(Key::KeyQ),
+            EventType::KeyPress(Key::KeyS),
+            EventType::KeyRelease(Key::ShiftLeft),
+            EventType::KeyPress(Key::ControlLeft),
+            EventType::KeyPress(Key::KeyS),
+        ] {
+            simulate_loopback(&event_type).unwrap();
+        }
+        // Listeners get events on their own thread.
+        let received: Vec<_> = (0..5)
+            .map_while(|_| receiver.recv_timeout(Duration::from_secs(1)).ok())
+            .collect();
+        assert_eq!(
+            received,
+            vec![
+                (EventType::KeyPress(Key::ShiftLeft), None),
+                (EventType::KeyPress(Key::KeyS), Some("S".to_string())),
+                (EventType::KeyRelease(Key::ShiftLeft), None),
+                (EventType::KeyPress(Key::ControlLeft), None),
+                (EventType::KeyPress(Key::KeyS), Some("\u{13}".to_string())),
+            ]
+        );
+        // The grab suppressed KeyQ.
+        let held: HashSet<Key> = [Key::KeyS, Key::ControlLeft].iter().copied().collect();
+        assert_eq!(held_keys(), held);
+        reset_loopback();
+        assert!(held_keys().is_empty());
+    }
+}
@@ -0,0 +1,258 @@
+use crate::coordinates;
+use crate::rdev::{DisplayInfo, Event, EventType};
+use std::time::{Duration, Instant};
+
+/// Displays are queried again at most this often when the cursor is
+/// outside of all the known ones.
+const REFRESH_INTERVAL: Duration = Duration::from_secs(1);
+
+/// A monitor in the coordinates the OS gives, with its scale factor.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub(crate) struct Rect {
+    pub x: f64,
+    pub y: f64,
+    pub width: f64,
+    pub height: f64,
+    pub scale: f64,
+}
+
+impl Rect {
+    pub fn contains(&self, x: f64, y: f64) -> bool {
+        x >= self.x && x < self.x + self.width && y >= self.y && y < self.y + self.height
+    }
+}
+
+impl From<&DisplayInfo> for Rect {
+    fn from(display: &DisplayInfo) -> Rect {
+        Rect {
+            x: display.x as f64,
+            y: display.y as f64,
+            width: display.width as f64,
+            height: display.height as f64,
+            scale: display.scale_factor,
+        }
+    }
+}
+
+/// The bounds of `displays()`, so indices match.
+pub(crate) fn display_rects() -> Vec<Rect> {
+    crate::_displays()
+        .map(|displays| displays.iter().map(Rect::from).collect())
+        .unwrap_or_default()
+}
+
+/// Fills the display and delta of MouseMove events, remembering the display
+/// bounds so we don't query the OS on every event.
+#[derive(Debug)]
+pub(crate) struct Motion {
+    last: Option<(f64, f64)>,
+    displays: Vec<Rect>,
+    refreshed: Option<Instant>,
+}
+
+impl Motion {
+    pub const fn new() -> Motion {
+        Motion {
+            last: None,
+            displays: Vec::new(),
+            refreshed: None,
+        }
+    }
+
+    fn display_index<F>(&mut self, x: f64, y: f64, displays: F) -> Option<usize>
+    where
+        F: FnOnce() -> Vec<Rect>,
+    {
+        let index = self.displays.iter().position(|rect| rect.contains(x, y));
+        if index.is_some() {
+            return index;
+        }
+        let stale = self
+            .refreshed
+            .map(|refreshed| refreshed.elapsed() >= REFRESH_INTERVAL)
+            .unwrap_or(true);
+        if !stale {
+            return None;
+        }
+        self.displays = displays();
+        self.refreshed = Some(Instant::now());
+        self.displays.iter().position(|rect| rect.contains(x, y))
+    }
+
+    /// A delta already filled comes from the OS and isn't clamped to the
+    /// screen edges, it's kept. Otherwise we use the difference with the
+    /// previous position.
+    pub fn annotate<F>(&mut self, event: &mut Event, displays: F)
+    where
+        F: FnOnce() -> Vec<Rect>,
+    {
+        if let EventType::MouseMove { x, y } = &mut event.event_type {
+            event.display = self.display_index(*x, *y, displays);
+            event.delta = event
+                .delta
+                .or_else(|| self.last.map(|(last_x, last_y)| (*x - last_x, *y - last_y)));
+            self.last = Some((*x, *y));
+            let mode = coordinates::current_mode();
+            let rect = event.display.and_then(|index| self.displays.get(index));
+            if let (Some(mode), Some(rect)) = (mode, rect) {
+                let factor = coordinates::factor(mode, rect.scale);
+                *x = rect.x + (*x - rect.x) * factor;
+                *y = rect.y + (*y - rect.y) * factor;
+                event.delta = event
+                    .delta
+                    .map(|(delta_x, delta_y)| (delta_x * factor, delta_y * factor));
+            }
+        }
+    }
+}
+
+/// Lets through at most one `MouseMove` per interval, see
+/// `Settings::mouse_move_max_hz`. The moves in between are merged into the
+/// next one let through, their deltas added up, and the last one is sent
+/// once due, or before any other event, so the resting position always
+/// comes.
+#[derive(Debug)]
+pub(crate) struct MoveThrottle {
+    interval: Duration,
+    last: Option<Instant>,
+    pending: Option<Event>,
+}
+
+impl MoveThrottle {
+    pub fn new(max_hz: u32) -> MoveThrottle {
+        MoveThrottle {
+            interval: Duration::from_secs(1) / max_hz.max(1),
+            last: None,
+            pending: None,
+        }
+    }
+
+    /// With the deltas of the pending move, if any.
+    fn merge(&mut self, mut event: Event) -> Event {
+        let pending = match self.pending.take() {
+            Some(pending) => pending,
+            None => return event,
+        };
+        if let (Some((pending_x, pending_y)), Some((delta_x, delta_y))) =
+            (pending.delta, &mut event.delta)
+        {
+            *delta_x += pending_x;
+            *delta_y += pending_y;
+        }
+        event
+    }
+
+    pub fn add<F: FnMut(Event)>(&mut self, event: Event, now: Instant, mut send: F) {
+        if let EventType::MouseMove { .. } = event.event_type {
+            let event = self.merge(event);
+            let due = self
+                .last
+                .is_none_or(|last| now.duration_since(last) >= self.interval);
+            if due {
+                self.last = Some(now);
+                send(event);
+            } else {
+                self.pending = Some(event);
+            }
+        } else {
+            self.flush(now, &mut send);
+            send(event);
+        }
+    }
+
+    /// When the pending move should be sent.
+    pub fn deadline(&self) -> Option<Instant> {
+        self.pending.as_ref()?;
+        Some(self.last? + self.interval)
+    }
+
+    /// Sends the pending move if it's due by `now`.
+    pub fn poll<F: FnMut(Event)>(&mut self, now: Instant, send: F) {
+        if self.deadline().is_some_and(|deadline| deadline <= now) {
+            self.flush(now, send);
+        }
+    }
+
+    pub fn flush<F: FnMut(Event)>(&mut self, now: Instant, mut send: F) {
+        if let Some(pending) = self.pending.take() {
+            self.last = Some(now);
+            send(pending);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::SystemTime;
+
+    fn mouse_move(x: f64, y: f64, delta: Option<(f64, f64)>) -> Event {
+        let mut event = Event::new(EventType::MouseMove { x, y }, SystemTime::now(), None);
+        event.delta = delta;
+        event
+    }
+
+    #[test]
+    fn test_move_throttle() {
+        let mut throttle = MoveThrottle::new(100);
+        let start = Instant::now();
+        let mut sent = vec![];
+        let moved =
+            |x: f64, delta_x: f64| (EventType::MouseMove { x, y: 0.0 }, Some((delta_x, 0.0)));
+        // 1 kHz for 25ms.
+        for i in 0..25 {
+            let now = start + Duration::from_millis(i);
+            let x = i as f64;
+            throttle.add(mouse_move(x, 0.0, Some((1.0, 0.0))), now, |event| {
+                sent.push((event.event_type, event.delta))
+            });
+        }
+        assert_eq!(
+            sent,
+            vec![moved(0.0, 1.0), moved(10.0, 10.0), moved(20.0, 10.0)]
+        );
+        let deadline = throttle.deadline().unwrap();
+        assert_eq!(deadline, start + Duration::from_millis(30));
+        throttle.poll(deadline - Duration::from_millis(1), |_| unreachable!());
+        // The resting position comes.
+        throttle.poll(deadline, |event| sent.push((event.event_type, event.delta)));
+        assert_eq!(sent[3], moved(24.0, 4.0));
+        assert_eq!(throttle.deadline(), None);
+    }
+
+    #[test]
+    fn test_annotate() {
+        let displays = || {
+            vec![
+                Rect {
+                    x: 0.0,
+                    y: 0.0,
+                    width: 100.0,
+                    height: 100.0,
+                    scale: 1.0,
+                },
+                Rect {
+                    x: 100.0,
+                    y: 0.0,
+                    width: 100.0,
+                    height: 100.0,
+                    scale: 1.0,
+                },
+            ]
+        };
+        let mut motion = Motion::new();
+        let mut first = mouse_move(10.0, 10.0, None);
+        motion.annotate(&mut first, displays);
+        assert_eq!((first.display, first.delta), (Some(0), None));
+        let mut second = mouse_move(150.0, 20.0, None);
+        // Already known displays, no query.
+        motion.annotate(&mut second, || unreachable!());
+        assert_eq!(
+            (second.display, second.delta),
+            (Some(1), Some((140.0, 10.0)))
+        );
+        let mut raw = mouse_move(150.0, 20.0, Some((3.0, 0.0)));
+        motion.annotate(&mut raw, || unreachable!());
+        assert_eq!((raw.display, raw.delta), (Some(1), Some((3.0, 0.0))));
+    }
+}
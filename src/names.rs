@@ -0,0 +1,42 @@
+use std::cell::Cell;
+
+thread_local! {
+    static WANTED: Cell<bool> = const { Cell::new(true) };
+}
+
+/// Whether the hooks running on this thread translate keys to their
+/// `name`, see `Settings::names`. Hooks are called on the thread that
+/// installed them, so each listener has its own.
+pub(crate) fn wanted() -> bool {
+    WANTED.with(Cell::get)
+}
+
+/// Puts the previous value back when dropped.
+pub(crate) struct Wanted(bool);
+
+impl Drop for Wanted {
+    fn drop(&mut self) {
+        WANTED.with(|wanted| wanted.set(self.0));
+    }
+}
+
+pub(crate) fn set_wanted(names: bool) -> Wanted {
+    Wanted(WANTED.with(|wanted| wanted.replace(names)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_wanted() {
+        assert!(wanted());
+        {
+            let _names = set_wanted(false);
+            assert!(!wanted());
+            // Other threads still get names.
+            assert!(std::thread::spawn(wanted).join().unwrap());
+        }
+        assert!(wanted());
+    }
+}
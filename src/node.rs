@@ -0,0 +1,246 @@
+//! The Node.js addon, for Electron apps and other tools. Requires the `node`
+//! feature, build it with `cargo rustc --release --features node --crate-type
+//! cdylib` and copy the library to `rdev.node`. On macOS, also pass
+//! `-- -C link-arg=-undefined -C link-arg=dynamic_lookup`. Works with any
+//! Node.js with N-API 4, Node.js 10.16 and up.
+//!
+//! Events are objects: `eventType` is the name of the `EventType` variant,
+//! `key` and `button` are names like `"KeyA"` and `"Left"`, `time` is in
+//! milliseconds since the UNIX epoch like `Date.now()`. Callbacks are called
+//! on the main thread, with an error first like other Node.js callbacks.
+//!
+//! ```js
+//! const rdev = require("./rdev.node");
+//!
+//! rdev.listen((err, event) => {
+//!   if (err) throw err;
+//!   console.log(event.eventType, event.key);
+//! });
+//! const hotkeys = new rdev.HotkeyManager();
+//! hotkeys.register("Ctrl+Shift+P", () => console.log("palette"));
+//! hotkeys.listen();
+//! rdev.simulate({ eventType: "KeyPress", key: "KeyA" });
+//! ```
+use crate::hotkey::{HotkeyId, HotkeyManager, KeySequence};
+use crate::rdev::{Button, EventType, Key};
+use napi::threadsafe_function::{
+    ErrorStrategy, ThreadSafeCallContext, ThreadsafeFunction, ThreadsafeFunctionCallMode,
+};
+use napi::{Error, JsFunction, Result};
+use napi_derive::napi;
+use std::thread;
+use std::time::{Duration, UNIX_EPOCH};
+
+/// An `Event`, or an event to `simulate` where only `eventType` and its
+/// fields are needed.
+#[napi(object, js_name = "Event")]
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct JsEvent {
+    pub event_type: String,
+    pub time: Option<f64>,
+    pub name: Option<String>,
+    pub scan_code: Option<u32>,
+    pub numlock: Option<bool>,
+    pub key: Option<String>,
+    pub button: Option<String>,
+    pub x: Option<f64>,
+    pub y: Option<f64>,
+    pub delta_x: Option<i64>,
+    pub delta_y: Option<i64>,
+}
+
+impl From<&crate::rdev::Event> for JsEvent {
+    fn from(event: &crate::rdev::Event) -> JsEvent {
+        // `KeyPress(KeyA)` or `MouseMove { .. }` is `KeyPress` or `MouseMove`.
+        let debug = format!("{:?}", event.event_type);
+        let kind = debug
+            .split(|c: char| !c.is_alphanumeric())
+            .next()
+            .unwrap_or_default();
+        let time = event
+            .time
+            .duration_since(UNIX_EPOCH)
+            .unwrap_or(Duration::ZERO);
+        let mut js_event = JsEvent {
+            event_type: kind.to_string(),
+            time: Some(time.as_secs_f64() * 1000.0),
+            name: event.name.clone(),
+            scan_code: Some(event.scan_code),
+            numlock: event.numlock,
+            ..Default::default()
+        };
+        match event.event_type {
+            EventType::KeyPress(key) | EventType::KeyRelease(key) => {
+                js_event.key = Some(key.to_string())
+            }
+            EventType::ButtonPress(button) | EventType::ButtonRelease(button) => {
+                js_event.button = Some(format!("{:?}", button))
+            }
+            EventType::MouseMove { x, y } => {
+                js_event.x = Some(x);
+                js_event.y = Some(y);
+            }
+            EventType::Wheel { delta_x, delta_y } => {
+                js_event.delta_x = Some(delta_x);
+                js_event.delta_y = Some(delta_y);
+            }
+            _ => (),
+        }
+        js_event
+    }
+}
+
+fn field<T>(value: Option<T>, name: &str) -> Result<T> {
+    value.ok_or_else(|| Error::from_reason(format!("event without {}", name)))
+}
+
+fn parse_key(event: &JsEvent) -> Result<Key> {
+    let key = field(event.key.as_deref(), "key")?;
+    key.parse()
+        .map_err(|_| Error::from_reason(format!("unknown key {:?}", key)))
+}
+
+fn parse_button(event: &JsEvent) -> Result<Button> {
+    let button = field(event.button.as_deref(), "button")?;
+    button
+        .parse()
+        .map_err(|_| Error::from_reason(format!("unknown button {:?}", button)))
+}
+
+/// The event types `simulate` can send.
+fn event_type_from_js(event: &JsEvent) -> Result<EventType> {
+    let event_type = match event.event_type.as_str() {
+        "KeyPress" => EventType::KeyPress(parse_key(event)?),
+        "KeyRelease" => EventType::KeyRelease(parse_key(event)?),
+        "ButtonPress" => EventType::ButtonPress(parse_button(event)?),
+        "ButtonRelease" => EventType::ButtonRelease(parse_button(event)?),
+        "MouseMove" => EventType::MouseMove {
+            x: field(event.x, "x")?,
+            y: field(event.y, "y")?,
+        },
+        "Wheel" => EventType::Wheel {
+            delta_x: field(event.delta_x, "deltaX")?,
+            delta_y: field(event.delta_y, "deltaY")?,
+        },
+        kind => {
+            return Err(Error::from_reason(format!(
+                "can't simulate {:?} events",
+                kind
+            )))
+        }
+    };
+    Ok(event_type)
+}
+
+/// Calls `callback` with every event, from a thread of its own so it
+/// returns right away. Listening can't be stopped, and keeps the process
+/// alive.
+#[napi(ts_args_type = "callback: (err: Error | null, event: Event) => void")]
+pub fn listen(callback: JsFunction) -> Result<()> {
+    let callback: ThreadsafeFunction<crate::rdev::Event> = callback.create_threadsafe_function(
+        0,
+        |context: ThreadSafeCallContext<crate::rdev::Event>| {
+            Ok(vec![JsEvent::from(&context.value)])
+        },
+    )?;
+    thread::spawn(move || {
+        let listening = callback.clone();
+        let listened = crate::listen(move |event| {
+            listening.call(Ok(event), ThreadsafeFunctionCallMode::NonBlocking);
+        });
+        if let Err(error) = listened {
+            let error = Error::from_reason(error.to_string());
+            callback.call(Err(error), ThreadsafeFunctionCallMode::Blocking);
+        }
+    });
+    Ok(())
+}
+
+/// Sends an event, see `simulate`.
+#[napi]
+pub fn simulate(event: JsEvent) -> Result<()> {
+    let event_type = event_type_from_js(&event)?;
+    crate::simulate(&event_type).map_err(|error| Error::from_reason(error.to_string()))
+}
+
+#[napi(object)]
+pub struct DisplaySize {
+    pub width: u32,
+    pub height: u32,
+}
+
+/// The size of the main display, see `display_size`.
+#[napi]
+pub fn display_size() -> Result<DisplaySize> {
+    let (width, height) =
+        crate::display_size().map_err(|error| Error::from_reason(error.to_string()))?;
+    Ok(DisplaySize {
+        width: width as u32,
+        height: height as u32,
+    })
+}
+
+/// A `HotkeyManager`, registering hotkeys and sequences from their strings
+/// like `"Ctrl+Shift+P"` or `"Ctrl+K, Ctrl+C"`.
+#[napi(js_name = "HotkeyManager")]
+#[derive(Default)]
+pub struct JsHotkeyManager {
+    manager: HotkeyManager,
+}
+
+#[napi]
+impl JsHotkeyManager {
+    #[napi(constructor)]
+    pub fn new() -> JsHotkeyManager {
+        JsHotkeyManager::default()
+    }
+
+    /// Returns the id to `unregister` it with. Hotkeys aren't suppressed,
+    /// other apps see them too.
+    #[napi(ts_args_type = "hotkey: string, callback: () => void")]
+    pub fn register(&self, hotkey: String, callback: JsFunction) -> Result<i64> {
+        let sequence: KeySequence = hotkey
+            .parse()
+            .map_err(|_| Error::from_reason(format!("invalid hotkey {:?}", hotkey)))?;
+        let callback: ThreadsafeFunction<(), ErrorStrategy::Fatal> = callback
+            .create_threadsafe_function(0, |_: ThreadSafeCallContext<()>| Ok(Vec::<()>::new()))?;
+        let id = self
+            .manager
+            .register_sequence(sequence, false, move || {
+                callback.call((), ThreadsafeFunctionCallMode::NonBlocking);
+            })
+            .map_err(|error| Error::from_reason(format!("{:?}", error)))?;
+        Ok(id.0 as i64)
+    }
+
+    #[napi]
+    pub fn unregister(&self, id: i64) -> Result<()> {
+        self.manager
+            .unregister(HotkeyId(id as u64))
+            .map_err(|error| Error::from_reason(format!("{:?}", error)))
+    }
+
+    /// Listens for the registered hotkeys from a thread of its own, hotkeys
+    /// can still be registered after. `callback` gets listening errors.
+    #[napi(ts_args_type = "callback?: (err: Error) => void")]
+    pub fn listen(&self, callback: Option<JsFunction>) -> Result<()> {
+        let callback: Option<ThreadsafeFunction<String, ErrorStrategy::Fatal>> = callback
+            .map(|callback| {
+                callback.create_threadsafe_function(0, |context: ThreadSafeCallContext<String>| {
+                    Ok(vec![context
+                        .env
+                        .create_error(Error::from_reason(context.value))?])
+                })
+            })
+            .transpose()?;
+        let manager = self.manager.clone();
+        thread::spawn(move || {
+            if let Err(error) = manager.listen() {
+                if let Some(callback) = callback {
+                    callback.call(error.to_string(), ThreadsafeFunctionCallMode::Blocking);
+                }
+            }
+        });
+        Ok(())
+    }
+}
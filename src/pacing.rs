@@ -0,0 +1,109 @@
+use lazy_static::lazy_static;
+use std::sync::Mutex;
+use std::thread;
+use std::time::{Duration, Instant};
+
+/// How fast `simulate_all` and `simulate_text` are allowed to send events.
+/// Some targets (remote desktops, Electron apps) drop events that come
+/// in too fast. The default sends events as fast as possible.
+///
+/// ```no_run
+/// use rdev::{set_simulate_pacing, Pacing};
+/// use std::time::Duration;
+///
+/// set_simulate_pacing(Pacing {
+///     events_per_second: Some(100.0),
+///     min_gap: Duration::from_millis(5),
+/// });
+/// ```
+#[derive(Debug, Copy, Clone, PartialEq, Default)]
+pub struct Pacing {
+    /// Maximum number of events sent per second, None for no limit.
+    pub events_per_second: Option<f64>,
+    /// Minimum time between two consecutive events.
+    pub min_gap: Duration,
+}
+
+impl Pacing {
+    /// The actual time we wait between two events.
+    pub fn gap(&self) -> Duration {
+        let rate_gap = match self.events_per_second {
+            // Tiny rates overflow a Duration.
+            Some(rate) if rate > 0.0 && rate.is_finite() => {
+                Duration::try_from_secs_f64(1.0 / rate).unwrap_or(Duration::MAX)
+            }
+            _ => Duration::from_secs(0),
+        };
+        rate_gap.max(self.min_gap)
+    }
+}
+
+lazy_static! {
+    static ref PACING: Mutex<Pacing> = Mutex::new(Pacing::default());
+}
+
+pub(crate) fn set_pacing(pacing: Pacing) {
+    if let Ok(mut current) = PACING.lock() {
+        *current = pacing;
+    }
+}
+
+pub(crate) fn current_pacing() -> Pacing {
+    PACING.lock().map(|pacing| *pacing).unwrap_or_default()
+}
+
+/// Sleeps just enough between events to respect a `Pacing`.
+pub(crate) struct Pacer {
+    gap: Duration,
+    last: Option<Instant>,
+}
+
+impl Pacer {
+    pub(crate) fn new(pacing: Pacing) -> Pacer {
+        Pacer {
+            gap: pacing.gap(),
+            last: None,
+        }
+    }
+
+    /// Blocks until the next event is allowed to be sent.
+    pub(crate) fn wait(&mut self) {
+        if let Some(last) = self.last {
+            let elapsed = last.elapsed();
+            if elapsed < self.gap {
+                thread::sleep(self.gap - elapsed);
+            }
+        }
+        self.last = Some(Instant::now());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_gap() {
+        assert_eq!(Pacing::default().gap(), Duration::from_secs(0));
+        let pacing = Pacing {
+            events_per_second: Some(100.0),
+            min_gap: Duration::from_millis(5),
+        };
+        assert_eq!(pacing.gap(), Duration::from_millis(10));
+        let pacing = Pacing {
+            events_per_second: Some(1000.0),
+            min_gap: Duration::from_millis(5),
+        };
+        assert_eq!(pacing.gap(), Duration::from_millis(5));
+        let pacing = Pacing {
+            events_per_second: Some(0.0),
+            min_gap: Duration::from_millis(0),
+        };
+        assert_eq!(pacing.gap(), Duration::from_secs(0));
+        let pacing = Pacing {
+            events_per_second: Some(f64::MIN_POSITIVE),
+            min_gap: Duration::from_millis(0),
+        };
+        assert_eq!(pacing.gap(), Duration::MAX);
+    }
+}
@@ -0,0 +1,181 @@
+//! Whether the OS lets rdev see and send input. On macOS `listen`,
+//! `simulate` and `grab` need the Accessibility permission, and
+//! `Backend::IOHid` needs Input Monitoring. Without them events are
+//! silently dropped, so check before listening and point the user to
+//! System Settings > Privacy & Security when something is missing.
+//! Other platforms don't ask and always report `Granted`.
+//!
+//! ```no_run
+//! use rdev::permissions;
+//!
+//! let permissions = permissions::query();
+//! if !permissions.all_granted() {
+//!     // Shows the system dialogs, the answer comes later.
+//!     permissions::request();
+//!     println!("Enable rdev in System Settings > Privacy & Security, then restart");
+//! }
+//! ```
+
+/// The state of one permission.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub enum Access {
+    Granted,
+    Denied,
+    /// The user was never asked, `request` will prompt them.
+    Unknown,
+}
+
+/// The permissions input needs, see the module documentation.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub struct Permissions {
+    /// MacOS, to listen with event taps, simulate and grab.
+    pub accessibility: Access,
+    /// MacOS, to listen with `Backend::IOHid`.
+    pub input_monitoring: Access,
+}
+
+impl Permissions {
+    pub fn all_granted(&self) -> bool {
+        self.accessibility == Access::Granted && self.input_monitoring == Access::Granted
+    }
+}
+
+/// The current permissions, without prompting.
+pub fn query() -> Permissions {
+    #[cfg(target_os = "macos")]
+    {
+        crate::macos::query_permissions()
+    }
+    #[cfg(not(target_os = "macos"))]
+    {
+        Permissions {
+            accessibility: Access::Granted,
+            input_monitoring: Access::Granted,
+        }
+    }
+}
+
+/// Prompts for the missing permissions and returns them. The system dialogs
+/// don't wait for the user, so what was just asked is usually still not
+/// granted: `query` again later. MacOS only applies a new Accessibility
+/// permission to processes started after it.
+pub fn request() -> Permissions {
+    #[cfg(target_os = "macos")]
+    {
+        crate::macos::request_permissions()
+    }
+    #[cfg(not(target_os = "macos"))]
+    {
+        query()
+    }
+}
+
+/// Something `listen`, `grab` or `simulate` need that the process doesn't
+/// have, see `can_listen`.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+#[non_exhaustive]
+pub enum Missing {
+    /// MacOS, the Accessibility permission.
+    Accessibility,
+    /// MacOS, the Input Monitoring permission.
+    InputMonitoring,
+    /// Linux and FreeBSD, no X server could be opened, check $DISPLAY.
+    Display,
+    /// Linux and FreeBSD, the X server doesn't have this extension.
+    XExtension(&'static str),
+    /// Linux, no device in /dev/input can be read, the user needs to be in
+    /// the `input` group.
+    DevInput,
+    /// Linux, /dev/uinput can't be written.
+    Uinput,
+    /// Windows, another desktop takes the input (lock screen, UAC prompt):
+    /// nothing can be listened to or simulated until it's gone.
+    InputDesktop,
+    /// Windows, the process isn't elevated: input going to elevated windows
+    /// isn't hooked, and can't be simulated to them (UIPI). Only a limit,
+    /// see `is_required`.
+    Elevation,
+}
+
+impl Missing {
+    /// False for what only limits it, like `Elevation`.
+    pub fn is_required(&self) -> bool {
+        !matches!(self, Missing::Elevation)
+    }
+}
+
+/// What `can_listen`, `can_grab` and `can_simulate` found missing.
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct Report {
+    pub missing: Vec<Missing>,
+}
+
+impl Report {
+    /// Nothing required is missing, the call can work.
+    pub fn is_ok(&self) -> bool {
+        self.missing.iter().all(|missing| !missing.is_required())
+    }
+}
+
+/// What the blocking calls are checked for.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub(crate) enum Need {
+    Listen,
+    Grab,
+    Simulate,
+}
+
+fn report(need: Need) -> Report {
+    #[cfg(target_os = "macos")]
+    let missing = crate::macos::missing(need);
+    #[cfg(any(target_os = "linux", target_os = "freebsd"))]
+    let missing = crate::linux::missing(need);
+    #[cfg(target_os = "windows")]
+    let missing = crate::windows::missing(need);
+    Report { missing }
+}
+
+/// Whether `listen` can work with the default backend, without blocking or
+/// prompting. Cheap, it doesn't install anything. Unlike `query` it looks
+/// beyond macOS permissions: the X server and its extensions, the
+/// /dev/input and /dev/uinput nodes, the input desktop of Windows.
+///
+/// ```no_run
+/// use rdev::permissions;
+///
+/// let report = permissions::can_listen();
+/// if !report.is_ok() {
+///     println!("Missing {:?}", report.missing);
+/// }
+/// ```
+pub fn can_listen() -> Report {
+    report(Need::Listen)
+}
+
+/// Whether `grab` (and `grab_device` on Linux) can work.
+pub fn can_grab() -> Report {
+    report(Need::Grab)
+}
+
+/// Whether `simulate` can work with the default backend.
+pub fn can_simulate() -> Report {
+    report(Need::Simulate)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_report() {
+        assert!(Report::default().is_ok());
+        let limited = Report {
+            missing: vec![Missing::Elevation],
+        };
+        assert!(limited.is_ok());
+        let missing = Report {
+            missing: vec![Missing::Elevation, Missing::Display],
+        };
+        assert!(!missing.is_ok());
+    }
+}
@@ -0,0 +1,167 @@
+use crate::rdev::{Button, EventType, Key, SimulateError};
+use lazy_static::lazy_static;
+use std::collections::HashMap;
+use std::hash::Hash;
+use std::sync::{Mutex, Once, PoisonError};
+
+/// How a backend `simulate_with` picked sends an event.
+pub(crate) type Sender = fn(&EventType) -> Result<(), SimulateError>;
+
+/// Keys and buttons we simulated a press of, and no release yet, with the
+/// backend that pressed them. None is the one `simulate` uses.
+#[derive(Debug, Default)]
+struct Pressed {
+    keys: HashMap<Key, Option<Sender>>,
+    buttons: HashMap<Button, Option<Sender>>,
+}
+
+/// The first press keeps its backend, the release goes where it went.
+fn update<T: Eq + Hash>(
+    held: &mut HashMap<T, Option<Sender>>,
+    pressed: bool,
+    item: T,
+    sender: Option<Sender>,
+) -> bool {
+    if !pressed {
+        return held.remove(&item).is_some();
+    }
+    if held.contains_key(&item) {
+        return false;
+    }
+    held.insert(item, sender);
+    true
+}
+
+impl Pressed {
+    /// False for what doesn't change the set.
+    fn sent(&mut self, event_type: &EventType, sender: Option<Sender>) -> bool {
+        match *event_type {
+            EventType::KeyPress(key) => update(&mut self.keys, true, key, sender),
+            EventType::KeyRelease(key) => update(&mut self.keys, false, key, sender),
+            EventType::ButtonPress(button) => update(&mut self.buttons, true, button, sender),
+            EventType::ButtonRelease(button) => update(&mut self.buttons, false, button, sender),
+            _ => false,
+        }
+    }
+
+    fn releases(&mut self) -> Vec<(EventType, Option<Sender>)> {
+        let keys = self
+            .keys
+            .drain()
+            .map(|(key, sender)| (EventType::KeyRelease(key), sender));
+        let buttons = self
+            .buttons
+            .drain()
+            .map(|(button, sender)| (EventType::ButtonRelease(button), sender));
+        keys.chain(buttons).collect()
+    }
+}
+
+lazy_static! {
+    static ref PRESSED: Mutex<Pressed> = Mutex::new(Pressed::default());
+}
+static AT_EXIT: Once = Once::new();
+
+extern "C" {
+    fn atexit(callback: extern "C" fn()) -> std::os::raw::c_int;
+}
+
+extern "C" fn release_at_exit() {
+    release_all_pressed().ok();
+}
+
+/// `event_type` was simulated through `sender`, None for the default
+/// backend. The first press registers an `atexit` handler releasing what is
+/// still held when the process exits.
+pub(crate) fn sent(event_type: &EventType, sender: Option<Sender>) {
+    let changed = PRESSED
+        .lock()
+        .unwrap_or_else(PoisonError::into_inner)
+        .sent(event_type, sender);
+    // Nothing can be released before a press.
+    if changed {
+        AT_EXIT.call_once(|| unsafe {
+            atexit(release_at_exit);
+        });
+    }
+}
+
+/// Releases every key and button rdev simulated a press of and not the
+/// release, so a remapper stopping between the two doesn't leave Ctrl or
+/// Shift stuck down for the whole system. Each release goes through the
+/// backend that sent the press, `Backend::Mock` and `Backend::Loopback`
+/// presses aren't tracked as they never reach the system. Done on `exit`
+/// and when `grab` returns, and by `ReleasePressed` on drop, which also
+/// runs when a panic unwinds. A process killed outright (SIGKILL, a crash)
+/// can't do it.
+///
+/// ```no_run
+/// use rdev::{release_all_pressed, simulate, EventType, Key};
+///
+/// simulate(&EventType::KeyPress(Key::ControlLeft)).unwrap();
+/// release_all_pressed().unwrap();
+/// ```
+pub fn release_all_pressed() -> Result<(), SimulateError> {
+    let releases = PRESSED
+        .lock()
+        .unwrap_or_else(PoisonError::into_inner)
+        .releases();
+    let mut default = vec![];
+    let mut result = Ok(());
+    for (release, sender) in releases {
+        match sender {
+            Some(send) => result = result.and(send(&release)),
+            None => default.push(release),
+        }
+    }
+    crate::simulate_batch(&default).and(result)
+}
+
+/// Calls `release_all_pressed` when dropped.
+///
+/// ```no_run
+/// use rdev::ReleasePressed;
+///
+/// let _release = ReleasePressed;
+/// // Simulating, panics included, until the end of the scope.
+/// ```
+#[derive(Debug, Default)]
+pub struct ReleasePressed;
+
+impl Drop for ReleasePressed {
+    fn drop(&mut self) {
+        release_all_pressed().ok();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn other(_: &EventType) -> Result<(), SimulateError> {
+        Ok(())
+    }
+
+    #[test]
+    fn test_pressed() {
+        let mut pressed = Pressed::default();
+        assert!(pressed.sent(&EventType::KeyPress(Key::ShiftLeft), None));
+        // Autorepeat, even from another backend.
+        assert!(!pressed.sent(&EventType::KeyPress(Key::ShiftLeft), Some(other)));
+        assert!(pressed.sent(&EventType::ButtonPress(Button::Left), Some(other)));
+        assert!(pressed.sent(&EventType::KeyPress(Key::KeyA), None));
+        assert!(pressed.sent(&EventType::KeyRelease(Key::KeyA), None));
+        assert!(!pressed.sent(&EventType::KeyRelease(Key::KeyB), None));
+        let releases = pressed.releases();
+        assert_eq!(releases.len(), 2);
+        assert!(matches!(
+            releases[0],
+            (EventType::KeyRelease(Key::ShiftLeft), None)
+        ));
+        assert!(matches!(
+            releases[1],
+            (EventType::ButtonRelease(Button::Left), Some(_))
+        ));
+        assert!(pressed.releases().is_empty());
+    }
+}
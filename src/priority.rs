@@ -0,0 +1,46 @@
+use std::sync::atomic::{AtomicBool, Ordering};
+
+/// How the threads capturing events are scheduled, see `set_hook_priority`.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Default)]
+#[non_exhaustive]
+pub enum HookPriority {
+    /// Threads keep whatever priority they have.
+    #[default]
+    Normal,
+    /// Ahead of the threads of other apps: THREAD_PRIORITY_TIME_CRITICAL on
+    /// Windows, the user interactive QoS class on macOS and SCHED_FIFO on
+    /// Linux. Linux needs CAP_SYS_NICE or an RLIMIT_RTPRIO for it, threads
+    /// keep their priority otherwise.
+    TimeCritical,
+}
+
+static TIME_CRITICAL: AtomicBool = AtomicBool::new(false);
+
+pub(crate) fn set_priority(priority: HookPriority) {
+    TIME_CRITICAL.store(priority == HookPriority::TimeCritical, Ordering::SeqCst);
+}
+
+/// Raises the priority of the current thread if asked to, it goes back to
+/// what it was when the returned value is dropped (on the same thread).
+pub(crate) fn raise() -> Option<impl Sized> {
+    if !TIME_CRITICAL.load(Ordering::SeqCst) {
+        return None;
+    }
+    crate::_raise_thread_priority()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_raise() {
+        assert!(raise().is_none());
+        set_priority(HookPriority::TimeCritical);
+        // Without the permission, the thread is left alone.
+        let raised = raise();
+        set_priority(HookPriority::Normal);
+        drop(raised);
+        assert!(raise().is_none());
+    }
+}
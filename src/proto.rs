@@ -0,0 +1,724 @@
+//! Events as protobuf messages, for endpoints in other languages reading
+//! them with the code protoc generates from `SCHEMA` (`bindings/rdev.proto`
+//! in the repository). Keys and buttons are the stable numbers of
+//! `compact`, the time a `google.protobuf.Timestamp`.
+//! `EventWriter` and `EventReader` stream them, each message prefixed by
+//! its varint length, and the reader skips events this version doesn't
+//! know, like `jsonl`.
+//!
+//! ```no_run
+//! use rdev::proto::{decode, encode, EventReader, EventWriter};
+//! use rdev::listen;
+//! use std::fs::File;
+//! use std::io::BufReader;
+//!
+//! let mut reader = EventReader::new(BufReader::new(File::open("events.pb").unwrap()));
+//! for event in &mut reader {
+//!     println!("{:?}", event.unwrap());
+//! }
+//!
+//! let mut writer = EventWriter::new(File::create("events.pb").unwrap());
+//! // This will block.
+//! listen(move |event| {
+//!     let bytes = encode(&event);
+//!     assert_eq!(decode(&bytes).unwrap(), Some(event.clone()));
+//!     writer.write(&event).unwrap();
+//! })
+//! .unwrap();
+//! ```
+use crate::ids::{button_from_id, button_to_id, key_from_id, key_to_id};
+use crate::rdev::{Event, EventType, Pen, ScrollPhase, UnicodeInfo};
+#[cfg(feature = "gestures")]
+use crate::rdev::{Gesture, GesturePhase};
+use std::io::{self, Read, Write};
+use std::time::{Duration, UNIX_EPOCH};
+
+/// The protobuf schema of the messages.
+pub const SCHEMA: &str = include_str!("../bindings/rdev.proto");
+
+/// Longer messages are garbage, not events.
+const MAX_LEN: u64 = 1 << 20;
+
+const VARINT: u32 = 0;
+const FIXED64: u32 = 1;
+const LEN: u32 = 2;
+const FIXED32: u32 = 5;
+
+// The fields of the `event_type` oneof.
+const KEY_PRESS: u32 = 10;
+const KEY_RELEASE: u32 = 11;
+const BUTTON_PRESS: u32 = 12;
+const BUTTON_RELEASE: u32 = 13;
+const MOUSE_MOVE: u32 = 14;
+const WHEEL: u32 = 15;
+const SCROLL: u32 = 16;
+const LAYOUT_CHANGED: u32 = 17;
+const IME_COMPOSITION: u32 = 18;
+const IME_COMMIT: u32 = 19;
+const SECURE_INPUT: u32 = 20;
+const TOUCH_DOWN: u32 = 21;
+const TOUCH_MOVE: u32 = 22;
+const TOUCH_UP: u32 = 23;
+const PEN_DOWN: u32 = 24;
+const PEN_MOVE: u32 = 25;
+const PEN_UP: u32 = 26;
+#[cfg(feature = "gestures")]
+const MAGNIFY: u32 = 27;
+#[cfg(feature = "gestures")]
+const ROTATE: u32 = 28;
+#[cfg(feature = "gestures")]
+const SWIPE: u32 = 29;
+
+fn invalid(message: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, message.to_string())
+}
+
+fn put_varint(buf: &mut Vec<u8>, mut value: u64) {
+    while value >= 0x80 {
+        buf.push(value as u8 | 0x80);
+        value >>= 7;
+    }
+    buf.push(value as u8);
+}
+
+fn put_tag(buf: &mut Vec<u8>, field: u32, wire: u32) {
+    put_varint(buf, u64::from(field << 3 | wire));
+}
+
+// Proto3 leaves out fields holding their default, except `optional` ones
+// and the oneof.
+fn put_uint(buf: &mut Vec<u8>, field: u32, value: u64) {
+    if value != 0 {
+        put_tag(buf, field, VARINT);
+        put_varint(buf, value);
+    }
+}
+
+fn put_sint(buf: &mut Vec<u8>, field: u32, value: i64) {
+    put_uint(buf, field, ((value << 1) ^ (value >> 63)) as u64);
+}
+
+fn put_double(buf: &mut Vec<u8>, field: u32, value: f64) {
+    if value.to_bits() != 0 {
+        put_tag(buf, field, FIXED64);
+        buf.extend_from_slice(&value.to_le_bytes());
+    }
+}
+
+fn put_bytes(buf: &mut Vec<u8>, field: u32, bytes: &[u8]) {
+    put_tag(buf, field, LEN);
+    put_varint(buf, bytes.len() as u64);
+    buf.extend_from_slice(bytes);
+}
+
+fn put_message(buf: &mut Vec<u8>, field: u32, fill: impl FnOnce(&mut Vec<u8>)) {
+    let mut message = vec![];
+    fill(&mut message);
+    put_bytes(buf, field, &message);
+}
+
+fn scroll_phase_to_id(phase: &ScrollPhase) -> u64 {
+    match phase {
+        ScrollPhase::Begin => 1,
+        ScrollPhase::Change => 2,
+        ScrollPhase::End => 3,
+    }
+}
+
+fn scroll_phase_from_id(id: u64) -> Option<ScrollPhase> {
+    match id {
+        1 => Some(ScrollPhase::Begin),
+        2 => Some(ScrollPhase::Change),
+        3 => Some(ScrollPhase::End),
+        _ => None,
+    }
+}
+
+#[cfg(feature = "gestures")]
+fn phase_to_id(phase: &GesturePhase) -> u64 {
+    match phase {
+        GesturePhase::Begin => 1,
+        GesturePhase::Change => 2,
+        GesturePhase::End => 3,
+    }
+}
+
+#[cfg(feature = "gestures")]
+fn phase_from_id(id: u64) -> Option<GesturePhase> {
+    match id {
+        1 => Some(GesturePhase::Begin),
+        2 => Some(GesturePhase::Change),
+        3 => Some(GesturePhase::End),
+        _ => None,
+    }
+}
+
+fn put_touch(buf: &mut Vec<u8>, id: u64, x: f64, y: f64, pressure: f64) {
+    put_uint(buf, 1, id);
+    put_double(buf, 2, x);
+    put_double(buf, 3, y);
+    put_double(buf, 4, pressure);
+}
+
+fn put_pen(buf: &mut Vec<u8>, pen: &Pen) {
+    put_double(buf, 1, pen.x);
+    put_double(buf, 2, pen.y);
+    put_double(buf, 3, pen.pressure);
+    put_double(buf, 4, pen.tilt_x);
+    put_double(buf, 5, pen.tilt_y);
+    put_uint(buf, 6, pen.eraser as u64);
+}
+
+/// The `rdev.Event` message of the event.
+pub fn encode(event: &Event) -> Vec<u8> {
+    let mut buf = vec![];
+    let since = event.time.duration_since(UNIX_EPOCH).unwrap_or_default();
+    put_message(&mut buf, 1, |time| {
+        put_uint(time, 1, since.as_secs());
+        put_uint(time, 2, u64::from(since.subsec_nanos()));
+    });
+    if let Some(name) = &event.name {
+        put_bytes(&mut buf, 2, name.as_bytes());
+    }
+    put_uint(&mut buf, 3, u64::from(event.scan_code));
+    let is_dead = event
+        .unicode
+        .as_ref()
+        .map(|unicode| unicode.is_dead)
+        .unwrap_or(false);
+    put_uint(&mut buf, 4, is_dead as u64);
+    put_event_type(&mut buf, event);
+    buf
+}
+
+fn put_event_type(buf: &mut Vec<u8>, event: &Event) {
+    let put_id = |buf: &mut Vec<u8>, field, id| {
+        put_tag(buf, field, VARINT);
+        put_varint(buf, id);
+    };
+    match &event.event_type {
+        EventType::KeyPress(key) => put_id(buf, KEY_PRESS, key_to_id(key)),
+        EventType::KeyRelease(key) => put_id(buf, KEY_RELEASE, key_to_id(key)),
+        EventType::ButtonPress(button) => put_id(buf, BUTTON_PRESS, button_to_id(button)),
+        EventType::ButtonRelease(button) => put_id(buf, BUTTON_RELEASE, button_to_id(button)),
+        EventType::MouseMove { x, y } => put_message(buf, MOUSE_MOVE, |message| {
+            put_double(message, 1, *x);
+            put_double(message, 2, *y);
+            if let Some(display) = event.display {
+                put_tag(message, 3, VARINT);
+                put_varint(message, display as u64);
+            }
+            if let Some((delta_x, delta_y)) = event.delta {
+                for (field, delta) in [(4, delta_x), (5, delta_y)].iter() {
+                    put_tag(message, *field, FIXED64);
+                    message.extend_from_slice(&delta.to_le_bytes());
+                }
+            }
+        }),
+        EventType::Wheel { delta_x, delta_y } => put_message(buf, WHEEL, |message| {
+            put_sint(message, 1, *delta_x);
+            put_sint(message, 2, *delta_y);
+        }),
+        EventType::Scroll {
+            delta_x,
+            delta_y,
+            phase,
+            is_momentum,
+        } => put_message(buf, SCROLL, |message| {
+            put_double(message, 1, *delta_x);
+            put_double(message, 2, *delta_y);
+            put_uint(message, 3, scroll_phase_to_id(phase));
+            put_uint(message, 4, *is_momentum as u64);
+        }),
+        EventType::LayoutChanged => put_bytes(buf, LAYOUT_CHANGED, &[]),
+        EventType::ImeComposition => put_bytes(buf, IME_COMPOSITION, &[]),
+        EventType::ImeCommit => put_bytes(buf, IME_COMMIT, &[]),
+        EventType::SecureInput(active) => put_id(buf, SECURE_INPUT, *active as u64),
+        EventType::TouchDown { id, x, y, pressure } => put_message(buf, TOUCH_DOWN, |message| {
+            put_touch(message, *id, *x, *y, *pressure)
+        }),
+        EventType::TouchMove { id, x, y, pressure } => put_message(buf, TOUCH_MOVE, |message| {
+            put_touch(message, *id, *x, *y, *pressure)
+        }),
+        EventType::TouchUp { id, x, y, pressure } => put_message(buf, TOUCH_UP, |message| {
+            put_touch(message, *id, *x, *y, *pressure)
+        }),
+        EventType::PenDown(pen) => put_message(buf, PEN_DOWN, |message| put_pen(message, pen)),
+        EventType::PenMove(pen) => put_message(buf, PEN_MOVE, |message| put_pen(message, pen)),
+        EventType::PenUp(pen) => put_message(buf, PEN_UP, |message| put_pen(message, pen)),
+        #[cfg(feature = "gestures")]
+        EventType::Gesture(Gesture::Magnify { phase, delta }) => {
+            put_message(buf, MAGNIFY, |message| {
+                put_uint(message, 1, phase_to_id(phase));
+                put_double(message, 2, *delta);
+            })
+        }
+        #[cfg(feature = "gestures")]
+        EventType::Gesture(Gesture::Rotate { phase, degrees }) => {
+            put_message(buf, ROTATE, |message| {
+                put_uint(message, 1, phase_to_id(phase));
+                put_double(message, 2, *degrees);
+            })
+        }
+        #[cfg(feature = "gestures")]
+        EventType::Gesture(Gesture::Swipe {
+            phase,
+            delta_x,
+            delta_y,
+        }) => put_message(buf, SWIPE, |message| {
+            put_uint(message, 1, phase_to_id(phase));
+            put_double(message, 2, *delta_x);
+            put_double(message, 3, *delta_y);
+        }),
+    }
+}
+
+fn read_varint(bytes: &mut &[u8]) -> io::Result<u64> {
+    let mut value = 0;
+    for shift in (0..64).step_by(7) {
+        let (&byte, rest) = bytes
+            .split_first()
+            .ok_or_else(|| invalid("message cut short"))?;
+        *bytes = rest;
+        value |= u64::from(byte & 0x7f) << shift;
+        if byte & 0x80 == 0 {
+            return Ok(value);
+        }
+    }
+    Err(invalid("varint longer than 10 bytes"))
+}
+
+fn take<'a>(bytes: &mut &'a [u8], len: u64) -> io::Result<&'a [u8]> {
+    if len > bytes.len() as u64 {
+        return Err(invalid("message cut short"));
+    }
+    let (taken, rest) = bytes.split_at(len as usize);
+    *bytes = rest;
+    Ok(taken)
+}
+
+#[derive(Clone, Copy)]
+enum Value<'a> {
+    Varint(u64),
+    Fixed64(u64),
+    Bytes(&'a [u8]),
+    Fixed32,
+}
+
+impl<'a> Value<'a> {
+    fn uint(self) -> io::Result<u64> {
+        match self {
+            Value::Varint(value) => Ok(value),
+            _ => Err(invalid("expected a varint")),
+        }
+    }
+
+    fn sint(self) -> io::Result<i64> {
+        let value = self.uint()?;
+        Ok((value >> 1) as i64 ^ -((value & 1) as i64))
+    }
+
+    fn double(self) -> io::Result<f64> {
+        match self {
+            Value::Fixed64(bits) => Ok(f64::from_bits(bits)),
+            _ => Err(invalid("expected a double")),
+        }
+    }
+
+    fn bytes(self) -> io::Result<&'a [u8]> {
+        match self {
+            Value::Bytes(bytes) => Ok(bytes),
+            _ => Err(invalid("expected a length delimited field")),
+        }
+    }
+}
+
+/// The fields of a message, in order. Fields added by later versions are
+/// read like the others, callers skip them.
+fn fields(mut bytes: &[u8]) -> io::Result<Vec<(u32, Value<'_>)>> {
+    let mut fields = vec![];
+    while !bytes.is_empty() {
+        let tag = read_varint(&mut bytes)?;
+        let field = (tag >> 3) as u32;
+        let value = match (tag & 7) as u32 {
+            VARINT => Value::Varint(read_varint(&mut bytes)?),
+            FIXED64 => {
+                let mut le = [0; 8];
+                le.copy_from_slice(take(&mut bytes, 8)?);
+                Value::Fixed64(u64::from_le_bytes(le))
+            }
+            LEN => {
+                let len = read_varint(&mut bytes)?;
+                Value::Bytes(take(&mut bytes, len)?)
+            }
+            FIXED32 => {
+                take(&mut bytes, 4)?;
+                Value::Fixed32
+            }
+            _ => return Err(invalid("unsupported wire type")),
+        };
+        fields.push((field, value));
+    }
+    Ok(fields)
+}
+
+fn touch(value: Value) -> io::Result<(u64, f64, f64, f64)> {
+    let mut touch = (0, 0.0, 0.0, 0.0);
+    for (field, value) in fields(value.bytes()?)? {
+        match field {
+            1 => touch.0 = value.uint()?,
+            2 => touch.1 = value.double()?,
+            3 => touch.2 = value.double()?,
+            4 => touch.3 = value.double()?,
+            _ => (),
+        }
+    }
+    Ok(touch)
+}
+
+fn pen(value: Value) -> io::Result<Pen> {
+    let mut pen = Pen::default();
+    for (field, value) in fields(value.bytes()?)? {
+        match field {
+            1 => pen.x = value.double()?,
+            2 => pen.y = value.double()?,
+            3 => pen.pressure = value.double()?,
+            4 => pen.tilt_x = value.double()?,
+            5 => pen.tilt_y = value.double()?,
+            6 => pen.eraser = value.uint()? != 0,
+            _ => (),
+        }
+    }
+    Ok(pen)
+}
+
+#[cfg(feature = "gestures")]
+fn gesture(field: u32, value: Value) -> io::Result<Option<Gesture>> {
+    let (mut phase, mut first, mut second) = (0, 0.0, 0.0);
+    for (field, value) in fields(value.bytes()?)? {
+        match field {
+            1 => phase = value.uint()?,
+            2 => first = value.double()?,
+            3 => second = value.double()?,
+            _ => (),
+        }
+    }
+    let phase = match phase_from_id(phase) {
+        Some(phase) => phase,
+        None => return Ok(None),
+    };
+    Ok(Some(match field {
+        MAGNIFY => Gesture::Magnify {
+            phase,
+            delta: first,
+        },
+        ROTATE => Gesture::Rotate {
+            phase,
+            degrees: first,
+        },
+        _ => Gesture::Swipe {
+            phase,
+            delta_x: first,
+            delta_y: second,
+        },
+    }))
+}
+
+/// None for kinds, keys and phases this version doesn't know. Only the
+/// event type, and the display and delta of mouse moves, are set.
+fn event(field: u32, value: Value) -> io::Result<Option<Event>> {
+    let (mut display, mut delta) = (None, None);
+    let event_type = match field {
+        KEY_PRESS => key_from_id(value.uint()?).map(EventType::KeyPress),
+        KEY_RELEASE => key_from_id(value.uint()?).map(EventType::KeyRelease),
+        BUTTON_PRESS => button_from_id(value.uint()?).map(EventType::ButtonPress),
+        BUTTON_RELEASE => button_from_id(value.uint()?).map(EventType::ButtonRelease),
+        MOUSE_MOVE => {
+            let (mut x, mut y) = (0.0, 0.0);
+            let (mut delta_x, mut delta_y) = (None, None);
+            for (field, value) in fields(value.bytes()?)? {
+                match field {
+                    1 => x = value.double()?,
+                    2 => y = value.double()?,
+                    3 => display = Some(value.uint()? as usize),
+                    4 => delta_x = Some(value.double()?),
+                    5 => delta_y = Some(value.double()?),
+                    _ => (),
+                }
+            }
+            delta = delta_x.zip(delta_y);
+            Some(EventType::MouseMove { x, y })
+        }
+        WHEEL => {
+            let (mut delta_x, mut delta_y) = (0, 0);
+            for (field, value) in fields(value.bytes()?)? {
+                match field {
+                    1 => delta_x = value.sint()?,
+                    2 => delta_y = value.sint()?,
+                    _ => (),
+                }
+            }
+            Some(EventType::Wheel { delta_x, delta_y })
+        }
+        SCROLL => {
+            let (mut delta_x, mut delta_y, mut phase, mut is_momentum) = (0.0, 0.0, 0, false);
+            for (field, value) in fields(value.bytes()?)? {
+                match field {
+                    1 => delta_x = value.double()?,
+                    2 => delta_y = value.double()?,
+                    3 => phase = value.uint()?,
+                    4 => is_momentum = value.uint()? != 0,
+                    _ => (),
+                }
+            }
+            scroll_phase_from_id(phase).map(|phase| EventType::Scroll {
+                delta_x,
+                delta_y,
+                phase,
+                is_momentum,
+            })
+        }
+        LAYOUT_CHANGED => Some(EventType::LayoutChanged),
+        IME_COMPOSITION => Some(EventType::ImeComposition),
+        IME_COMMIT => Some(EventType::ImeCommit),
+        SECURE_INPUT => Some(EventType::SecureInput(value.uint()? != 0)),
+        TOUCH_DOWN | TOUCH_MOVE | TOUCH_UP => {
+            let (id, x, y, pressure) = touch(value)?;
+            Some(match field {
+                TOUCH_DOWN => EventType::TouchDown { id, x, y, pressure },
+                TOUCH_MOVE => EventType::TouchMove { id, x, y, pressure },
+                _ => EventType::TouchUp { id, x, y, pressure },
+            })
+        }
+        PEN_DOWN => Some(EventType::PenDown(pen(value)?)),
+        PEN_MOVE => Some(EventType::PenMove(pen(value)?)),
+        PEN_UP => Some(EventType::PenUp(pen(value)?)),
+        #[cfg(feature = "gestures")]
+        MAGNIFY | ROTATE | SWIPE => gesture(field, value)?.map(EventType::Gesture),
+        _ => None,
+    };
+    Ok(event_type.map(|event_type| Event {
+        display,
+        delta,
+        ..Event::new(event_type, UNIX_EPOCH, None)
+    }))
+}
+
+/// Reads an `rdev.Event` message, None when this version of rdev doesn't
+/// know its `event_type`, `InvalidData` when it isn't one. Gestures are
+/// only read with the `gestures` feature.
+pub fn decode(bytes: &[u8]) -> io::Result<Option<Event>> {
+    let mut time = UNIX_EPOCH;
+    let mut name = None;
+    let mut scan_code = 0;
+    let mut is_dead = false;
+    let mut kind = None;
+    for (field, value) in fields(bytes)? {
+        match field {
+            1 => {
+                let (mut secs, mut nanos) = (0, 0);
+                for (field, value) in fields(value.bytes()?)? {
+                    match field {
+                        1 => secs = value.uint()?,
+                        2 => nanos = value.uint()?,
+                        _ => (),
+                    }
+                }
+                time = UNIX_EPOCH + Duration::from_secs(secs) + Duration::from_nanos(nanos);
+            }
+            2 => {
+                let bytes = value.bytes()?.to_vec();
+                name = Some(String::from_utf8(bytes).map_err(|_| invalid("name isn't UTF-8"))?);
+            }
+            3 => scan_code = value.uint()? as u32,
+            4 => is_dead = value.uint()? != 0,
+            // The last field of a oneof wins.
+            field if field >= KEY_PRESS => kind = Some((field, value)),
+            _ => (),
+        }
+    }
+    let event = match kind {
+        Some((field, value)) => event(field, value)?,
+        None => None,
+    };
+    Ok(event.map(|event| Event {
+        time,
+        unicode: UnicodeInfo::new(name.as_deref(), is_dead),
+        name,
+        scan_code,
+        numlock: None,
+        ..event
+    }))
+}
+
+/// Writes events prefixed by their length.
+pub struct EventWriter<W: Write> {
+    writer: W,
+}
+
+impl<W: Write> EventWriter<W> {
+    pub fn new(writer: W) -> EventWriter<W> {
+        EventWriter { writer }
+    }
+
+    pub fn write(&mut self, event: &Event) -> io::Result<()> {
+        let message = encode(event);
+        let mut len = vec![];
+        put_varint(&mut len, message.len() as u64);
+        self.writer.write_all(&len)?;
+        self.writer.write_all(&message)
+    }
+
+    pub fn flush(&mut self) -> io::Result<()> {
+        self.writer.flush()
+    }
+
+    pub fn into_inner(self) -> W {
+        self.writer
+    }
+}
+
+/// Iterates over the events of a stream written by `EventWriter`.
+pub struct EventReader<R> {
+    reader: R,
+    skipped: usize,
+}
+
+impl<R: Read> EventReader<R> {
+    pub fn new(reader: R) -> EventReader<R> {
+        EventReader { reader, skipped: 0 }
+    }
+
+    /// How many events this version didn't know so far.
+    pub fn skipped(&self) -> usize {
+        self.skipped
+    }
+
+    /// The next message, None at the end of the stream.
+    fn message(&mut self) -> io::Result<Option<Vec<u8>>> {
+        let mut len = 0;
+        for shift in (0..64).step_by(7) {
+            let mut byte = [0];
+            if self.reader.read(&mut byte)? == 0 {
+                if shift == 0 {
+                    return Ok(None);
+                }
+                return Err(io::ErrorKind::UnexpectedEof.into());
+            }
+            len |= u64::from(byte[0] & 0x7f) << shift;
+            if byte[0] & 0x80 == 0 {
+                break;
+            }
+        }
+        if len > MAX_LEN {
+            return Err(invalid("message longer than 1 MiB"));
+        }
+        let mut message = vec![0; len as usize];
+        self.reader.read_exact(&mut message)?;
+        Ok(Some(message))
+    }
+}
+
+impl<R: Read> Iterator for EventReader<R> {
+    type Item = io::Result<Event>;
+
+    fn next(&mut self) -> Option<io::Result<Event>> {
+        loop {
+            let message = match self.message() {
+                Ok(message) => message?,
+                Err(error) => return Some(Err(error)),
+            };
+            match decode(&message) {
+                Ok(Some(event)) => return Some(Ok(event)),
+                Ok(None) => self.skipped += 1,
+                Err(error) => return Some(Err(error)),
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ids::UNKNOWN_OFFSET;
+    use crate::rdev::{Button, Key};
+
+    fn screaming_snake(name: &str) -> String {
+        let mut snake = String::new();
+        for (i, c) in name.chars().enumerate() {
+            if i > 0 && c.is_ascii_uppercase() {
+                snake.push('_');
+            }
+            snake.push(c.to_ascii_uppercase());
+        }
+        snake
+    }
+
+    #[test]
+    fn test_proto() {
+        // The enums of the schema are the stable numbers.
+        let mut keys = 0;
+        for line in SCHEMA.lines() {
+            let line = line.trim().trim_end_matches(';');
+            let (name, id) = match line.split_once(" = ") {
+                Some((name, id)) if name.starts_with("KEY_") => (name, id.parse().unwrap()),
+                _ => continue,
+            };
+            if let Some(key) = key_from_id(id) {
+                assert_eq!(
+                    name,
+                    format!("KEY_{}", screaming_snake(&format!("{:?}", key)))
+                );
+                keys += 1;
+            }
+        }
+        assert_eq!(keys, (1..UNKNOWN_OFFSET).filter_map(key_from_id).count());
+
+        let time = UNIX_EPOCH + Duration::from_nanos(1_700_000_000_123_456_789);
+        let event = |event_type| Event::new(event_type, time, None);
+        let events = vec![
+            Event {
+                name: Some("a".into()),
+                unicode: UnicodeInfo::new(Some("a"), false),
+                scan_code: 30,
+                ..event(EventType::KeyPress(Key::KeyA))
+            },
+            event(EventType::ButtonRelease(Button::Unknown(9))),
+            Event {
+                display: Some(0),
+                delta: Some((0.0, -2.0)),
+                ..event(EventType::MouseMove { x: 1.5, y: 0.0 })
+            },
+            event(EventType::Wheel {
+                delta_x: 0,
+                delta_y: -3,
+            }),
+            event(EventType::ImeCommit),
+            event(EventType::SecureInput(false)),
+            event(EventType::TouchUp {
+                id: 2,
+                x: 10.0,
+                y: 20.0,
+                pressure: 0.5,
+            }),
+        ];
+        let mut writer = EventWriter::new(vec![]);
+        for event in &events {
+            writer.write(event).unwrap();
+        }
+        let mut bytes = writer.into_inner();
+        // An event kind from the future, field 99.
+        bytes.extend_from_slice(&[3, 0x9a, 0x06, 0]);
+        let mut reader = EventReader::new(&bytes[..]);
+        let read: Vec<Event> = (&mut reader).map(Result::unwrap).collect();
+        assert_eq!(read, events);
+        assert_eq!(reader.skipped(), 1);
+
+        // Key A pressed at 1s, as `protoc --encode` writes it.
+        let message = [0x0a, 0x02, 0x08, 0x01, 0x50, 0x4d];
+        let read = decode(&message).unwrap().unwrap();
+        assert_eq!(read.event_type, EventType::KeyPress(Key::KeyA));
+        assert_eq!(read.time, UNIX_EPOCH + Duration::from_secs(1));
+        assert!(decode(&[0x52, 0x05]).is_err());
+    }
+}
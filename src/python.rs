@@ -0,0 +1,311 @@
+//! The `rdev` Python extension module. Requires the `python` feature, build
+//! and install it with `maturin develop --release`, or wheels with
+//! `maturin build --release`, see `pyproject.toml`.
+//!
+//! Events are dicts: `event_type` is the name of the `EventType` variant,
+//! `key` and `button` are names like `"KeyA"` and `"Left"`, `time` is in
+//! seconds since the UNIX epoch like `time.time()`.
+//!
+//! ```python
+//! import rdev
+//!
+//! def callback(event):
+//!     if event["event_type"] == "KeyPress" and event["key"] == "Escape":
+//!         raise SystemExit
+//!     print(event)
+//!
+//! # Blocks until the callback raises, or Ctrl+C.
+//! rdev.listen(callback)
+//! ```
+use crate::rdev::{Button, Event, EventType, Key};
+use pyo3::exceptions::{PyOSError, PyValueError};
+use pyo3::prelude::*;
+use pyo3::types::PyDict;
+use std::fmt::Debug;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::mpsc::{self, Receiver, TryRecvError};
+use std::sync::{Arc, Mutex, PoisonError};
+use std::thread;
+use std::time::{Duration, UNIX_EPOCH};
+
+/// How often `listen` and `grab` look for Ctrl+C.
+const SIGNAL_POLL: Duration = Duration::from_millis(50);
+
+fn event_to_py(py: Python<'_>, event: &Event) -> PyResult<PyObject> {
+    let dict = PyDict::new(py);
+    // `KeyPress(KeyA)` or `MouseMove { .. }` is `KeyPress` or `MouseMove`.
+    let debug = format!("{:?}", event.event_type);
+    let kind = debug
+        .split(|c: char| !c.is_alphanumeric())
+        .next()
+        .unwrap_or_default();
+    dict.set_item("event_type", kind)?;
+    let time = event
+        .time
+        .duration_since(UNIX_EPOCH)
+        .map(|time| time.as_secs_f64())
+        .unwrap_or(0.0);
+    dict.set_item("time", time)?;
+    dict.set_item("name", event.name.as_deref())?;
+    dict.set_item("scan_code", event.scan_code)?;
+    dict.set_item("numlock", event.numlock)?;
+    match event.event_type {
+        EventType::KeyPress(key) | EventType::KeyRelease(key) => {
+            dict.set_item("key", key.to_string())?
+        }
+        EventType::ButtonPress(button) | EventType::ButtonRelease(button) => {
+            dict.set_item("button", format!("{:?}", button))?
+        }
+        EventType::MouseMove { x, y } => {
+            dict.set_item("x", x)?;
+            dict.set_item("y", y)?;
+        }
+        EventType::Wheel { delta_x, delta_y } => {
+            dict.set_item("delta_x", delta_x)?;
+            dict.set_item("delta_y", delta_y)?;
+        }
+        _ => (),
+    }
+    Ok(dict.into())
+}
+
+fn get<'py, T: FromPyObject<'py>>(event: &'py PyDict, name: &str) -> PyResult<T> {
+    event
+        .get_item(name)?
+        .ok_or_else(|| PyValueError::new_err(format!("event without {:?}", name)))?
+        .extract()
+}
+
+fn parse_key(event: &PyDict) -> PyResult<Key> {
+    let key: String = get(event, "key")?;
+    key.parse()
+        .map_err(|_| PyValueError::new_err(format!("unknown key {:?}", key)))
+}
+
+fn parse_button(event: &PyDict) -> PyResult<Button> {
+    let button: String = get(event, "button")?;
+    button
+        .parse()
+        .map_err(|_| PyValueError::new_err(format!("unknown button {:?}", button)))
+}
+
+/// The event types `simulate` can send, from dicts like those of `listen`.
+fn event_type_from_py(event: &PyDict) -> PyResult<EventType> {
+    let kind: String = get(event, "event_type")?;
+    let event_type = match kind.as_str() {
+        "KeyPress" => EventType::KeyPress(parse_key(event)?),
+        "KeyRelease" => EventType::KeyRelease(parse_key(event)?),
+        "ButtonPress" => EventType::ButtonPress(parse_button(event)?),
+        "ButtonRelease" => EventType::ButtonRelease(parse_button(event)?),
+        "MouseMove" => EventType::MouseMove {
+            x: get(event, "x")?,
+            y: get(event, "y")?,
+        },
+        "Wheel" => EventType::Wheel {
+            delta_x: get(event, "delta_x")?,
+            delta_y: get(event, "delta_y")?,
+        },
+        _ => {
+            return Err(PyValueError::new_err(format!(
+                "can't simulate {:?} events",
+                kind
+            )))
+        }
+    };
+    Ok(event_type)
+}
+
+/// Calls a Python callback from the thread of `listen` or `grab`, taking
+/// the GIL for each event, and hands its first exception to the Python
+/// thread waiting in `wait`.
+struct Dispatch {
+    callback: PyObject,
+    error: Mutex<Option<PyErr>>,
+    stopped: AtomicBool,
+}
+
+impl Dispatch {
+    fn new(callback: PyObject) -> Arc<Dispatch> {
+        Arc::new(Dispatch {
+            callback,
+            error: Mutex::new(None),
+            stopped: AtomicBool::new(false),
+        })
+    }
+
+    /// Calls the callback with `event` and converts what it returned, `None`
+    /// once stopped or when the callback raised.
+    fn call<T, F>(&self, event: &Event, convert: F) -> Option<T>
+    where
+        F: FnOnce(Python<'_>, PyObject) -> PyResult<T>,
+    {
+        if self.stopped.load(Ordering::SeqCst) {
+            return None;
+        }
+        Python::with_gil(|py| {
+            let result = event_to_py(py, event)
+                .and_then(|event| self.callback.call1(py, (event,)))
+                .and_then(|result| convert(py, result));
+            match result {
+                Ok(result) => Some(result),
+                Err(error) => {
+                    self.stop(Some(error));
+                    None
+                }
+            }
+        })
+    }
+
+    fn stop(&self, error: Option<PyErr>) {
+        self.stopped.store(true, Ordering::SeqCst);
+        let mut slot = self.error.lock().unwrap_or_else(PoisonError::into_inner);
+        if slot.is_none() {
+            *slot = error;
+        }
+    }
+
+    /// Waits with the GIL released until the callback raised, the OS hook
+    /// `finished` with an error, or Ctrl+C. The hook itself can't be
+    /// removed, after that it stays in the background and no longer calls
+    /// Python.
+    fn wait<E: Debug>(&self, py: Python<'_>, finished: Receiver<Result<(), E>>) -> PyResult<()> {
+        loop {
+            if let Err(error) = py.check_signals() {
+                self.stop(None);
+                return Err(error);
+            }
+            if let Some(error) = self
+                .error
+                .lock()
+                .unwrap_or_else(PoisonError::into_inner)
+                .take()
+            {
+                return Err(error);
+            }
+            match finished.try_recv() {
+                Ok(Ok(())) => return Ok(()),
+                Ok(Err(error)) => return Err(PyOSError::new_err(format!("{:?}", error))),
+                Err(TryRecvError::Disconnected) => {
+                    return Err(PyOSError::new_err("the OS hook panicked"))
+                }
+                Err(TryRecvError::Empty) => (),
+            }
+            py.allow_threads(|| thread::sleep(SIGNAL_POLL));
+        }
+    }
+}
+
+/// listen(callback, /)
+/// --
+///
+/// Calls `callback` with every event, from another thread. Blocks until
+/// the callback raises, which is raised again here, or Ctrl+C.
+#[pyfunction]
+fn listen(py: Python<'_>, callback: PyObject) -> PyResult<()> {
+    let dispatch = Dispatch::new(callback);
+    let (sender, receiver) = mpsc::channel();
+    let listening = dispatch.clone();
+    thread::spawn(move || {
+        let listened = crate::listen(move |event| {
+            listening.call(&event, |_, _| Ok(()));
+        });
+        let _ = sender.send(listened);
+    });
+    dispatch.wait(py, receiver)
+}
+
+/// grab(callback, /)
+/// --
+///
+/// Like `listen`, but the event only goes through if `callback` returns it,
+/// or another event dict to send instead, and is blocked if it returns
+/// `None`. Events go through untouched once the callback raised.
+#[cfg(feature = "unstable_grab")]
+#[cfg(not(target_os = "freebsd"))]
+#[pyfunction]
+fn grab(py: Python<'_>, callback: PyObject) -> PyResult<()> {
+    let dispatch = Dispatch::new(callback);
+    let (sender, receiver) = mpsc::channel();
+    let grabbing = dispatch.clone();
+    thread::spawn(move || {
+        let grabbed = crate::grab(move |event| {
+            let returned = grabbing.call(&event, |py, returned| {
+                if returned.is_none(py) {
+                    return Ok(None);
+                }
+                let event_type = event_type_from_py(returned.as_ref(py).downcast()?)?;
+                Ok(Some(Event {
+                    event_type,
+                    ..event.clone()
+                }))
+            });
+            returned.unwrap_or(Some(event))
+        });
+        let _ = sender.send(grabbed);
+    });
+    dispatch.wait(py, receiver)
+}
+
+/// simulate(event, /)
+/// --
+///
+/// Sends a key, button, mouse move or wheel event, a dict like those of
+/// `listen` where only `event_type` and its fields are needed.
+#[pyfunction]
+fn simulate(py: Python<'_>, event: &PyDict) -> PyResult<()> {
+    let event_type = event_type_from_py(event)?;
+    py.allow_threads(|| crate::simulate(&event_type))
+        .map_err(|error| PyOSError::new_err(error.to_string()))
+}
+
+/// display_size()
+/// --
+///
+/// The size of the main display, `(width, height)`.
+#[pyfunction]
+fn display_size() -> PyResult<(u64, u64)> {
+    crate::display_size().map_err(|error| PyOSError::new_err(error.to_string()))
+}
+
+#[pymodule]
+fn rdev(_py: Python<'_>, module: &PyModule) -> PyResult<()> {
+    module.add_function(wrap_pyfunction!(listen, module)?)?;
+    #[cfg(feature = "unstable_grab")]
+    #[cfg(not(target_os = "freebsd"))]
+    module.add_function(wrap_pyfunction!(grab, module)?)?;
+    module.add_function(wrap_pyfunction!(simulate, module)?)?;
+    module.add_function(wrap_pyfunction!(display_size, module)?)?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::SystemTime;
+
+    #[test]
+    fn test_python_events() {
+        pyo3::prepare_freethreaded_python();
+        Python::with_gil(|py| {
+            for event_type in [
+                EventType::KeyPress(Key::KeyA),
+                EventType::KeyRelease(Key::Unknown(500)),
+                EventType::ButtonPress(Button::Unknown(8)),
+                EventType::MouseMove { x: 10.5, y: 20.0 },
+                EventType::Wheel {
+                    delta_x: 0,
+                    delta_y: -1,
+                },
+            ] {
+                let event = Event::new(event_type, SystemTime::now(), None);
+                let dict = event_to_py(py, &event).unwrap();
+                let dict = dict.as_ref(py).downcast().unwrap();
+                assert_eq!(event_type_from_py(dict).unwrap(), event_type);
+            }
+            let dict = PyDict::new(py);
+            dict.set_item("event_type", "KeyPress").unwrap();
+            dict.set_item("key", "NotAKey").unwrap();
+            assert!(event_type_from_py(dict).is_err());
+        });
+    }
+}
@@ -1,21 +1,56 @@
 #[cfg(feature = "serialize")]
 use serde::{Deserialize, Serialize};
 use std::str::FromStr;
-use std::time::SystemTime;
+use std::time::{Duration, SystemTime};
 use std::{fmt, fmt::Display};
 
+#[cfg(feature = "schemars")]
+use schemars::JsonSchema;
 #[cfg(feature = "typescript")]
 use specta::Type;
 
-#[cfg(feature = "python")]
-use pyo3::prelude::*;
-
 // /// Callback type to send to listen function.
 // pub type Callback = dyn FnMut(Event) -> ();
 
 /// Callback type to send to grab function.
 pub type GrabCallback = fn(event: Event) -> Option<Event>;
 
+/// The OS call an error comes from and the code it failed with.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+#[non_exhaustive]
+pub struct OsError {
+    /// The call that failed, like `"inotify_add_watch"` or
+    /// `"XRecordCreateContext"`.
+    pub operation: &'static str,
+    /// `GetLastError` on Windows, errno or the X error code on Linux, the
+    /// `CGError` or `IOReturn` on macOS. 0 when the call doesn't give one.
+    pub code: i64,
+}
+
+impl OsError {
+    pub(crate) fn new(operation: &'static str, code: i64) -> OsError {
+        OsError { operation, code }
+    }
+
+    /// With errno, `GetLastError` on Windows.
+    #[cfg(not(target_os = "macos"))]
+    pub(crate) fn last(operation: &'static str) -> OsError {
+        let code = std::io::Error::last_os_error().raw_os_error().unwrap_or(0);
+        OsError::new(operation, code.into())
+    }
+}
+
+impl Display for OsError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self.code {
+            0 => write!(f, "{} failed", self.operation),
+            code => write!(f, "{} failed with error {}", self.operation, code),
+        }
+    }
+}
+
+impl std::error::Error for OsError {}
+
 /// Errors that occur when trying to capture OS events.
 /// Be careful on Mac, not setting accessibility does not cause an error
 /// it justs ignores events.
@@ -23,7 +58,7 @@ pub type GrabCallback = fn(event: Event) -> Option<Event>;
 #[non_exhaustive]
 pub enum ListenError {
     /// MacOS
-    EventTapError,
+    EventTapError(OsError),
     /// MacOS
     LoopSourceError,
     /// Linux
@@ -31,15 +66,143 @@ pub enum ListenError {
     /// Linux
     KeyboardError,
     /// Linux
-    RecordContextEnablingError,
+    RecordContextEnablingError(OsError),
     /// Linux
-    RecordContextError,
+    RecordContextError(OsError),
     /// Linux
-    XRecordExtensionError,
+    XRecordExtensionError(OsError),
     /// Windows
     KeyHookError(u32),
     /// Windows
     MouseHookError(u32),
+    /// All, `listen_devices` couldn't watch for devices
+    DeviceMonitorError(OsError),
+    /// All, `listen_displays` couldn't watch for monitor changes
+    DisplayMonitorError(OsError),
+    /// Linux, no device in /dev/input could be read, the user usually needs
+    /// to be in the `input` group
+    EvdevError(OsError),
+    /// Linux and FreeBSD, the X server doesn't have XInput 2.1
+    XInputError(OsError),
+    /// Windows, the Raw Input window couldn't be created or registered
+    RawInputError(OsError),
+    /// MacOS, the HID manager couldn't be opened, the Input Monitoring
+    /// permission is needed
+    IOHidError(OsError),
+    /// All, `listen_with` was given a backend this platform doesn't have
+    UnsupportedBackend,
+}
+
+/// How `listen_with` captures events and `simulate_with` sends them, each
+/// platform only has some of them.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+#[non_exhaustive]
+pub enum Backend {
+    /// Linux and FreeBSD, the RECORD extension to listen and XTest to
+    /// simulate.
+    X11,
+    /// Linux and FreeBSD, XInput 2 raw events to listen, XTest to simulate. RECORD is
+    /// deprecated, missing from some servers and only one client can use
+    /// it at a time; raw events have none of these problems, and give
+    /// unaccelerated mouse deltas.
+    XInput2,
+    /// Linux, the compositor. Wayland doesn't give clients global input
+    /// and no protocol for it is supported yet, so this is always
+    /// `UnsupportedBackend`; `Evdev` works in Wayland sessions.
+    Wayland,
+    /// Linux, /dev/input to listen and /dev/uinput to simulate, with the
+    /// `evdev` feature. Works without any display server but needs to be in
+    /// the `input` group.
+    Evdev,
+    /// Windows, low level keyboard and mouse hooks, what `listen` uses.
+    Hooks,
+    /// Windows, Raw Input (WM_INPUT) sent to a hidden window. Nothing is
+    /// hooked, so it adds no latency to other applications and can't be
+    /// removed under load, but touch, pen and IME input aren't recognized.
+    RawInput,
+    /// MacOS, a CGEventTap, what `listen` uses. Needs the Accessibility
+    /// permission.
+    EventTap,
+    /// MacOS, the IOHIDManager. It sees keys the window server never turns
+    /// into events, like Fn and some media keys, and needs Input Monitoring
+    /// instead of Accessibility. Touches, gestures and pens aren't reported.
+    /// Listening only.
+    IOHid,
+    /// All, with the `mock` feature. Events come from `mock::push_event`
+    /// and simulated ones go to `mock::take_simulated`, see `mock`.
+    #[cfg(feature = "mock")]
+    Mock,
+    /// All, with the `mock` feature. Simulated events go to the listeners
+    /// of this backend and `mock::grab`, see `mock`.
+    #[cfg(feature = "mock")]
+    Loopback,
+}
+
+/// Options of `listen_with` and `simulate_with`, `Default` gives what
+/// `listen` and `simulate` use.
+///
+/// ```no_run
+/// use rdev::{Backend, Settings};
+///
+/// let settings = Settings {
+///     backend: Some(Backend::RawInput),
+///     ..Default::default()
+/// };
+/// ```
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub struct Settings {
+    /// None picks the same backend as `listen` and `simulate`, see
+    /// `default_backend`.
+    pub backend: Option<Backend>,
+    /// MacOS, create the event tap with `kCGEventTapOptionListenOnly`, true
+    /// by default. The system doesn't treat the process as an event filter
+    /// then, and is less likely to disable the tap under load. False makes
+    /// it an active tap, events are still passed through unchanged.
+    pub listen_only: bool,
+    /// Windows, install the hooks on a thread rdev owns and pumps messages
+    /// for, the callback still runs on the calling thread. Hooks only work
+    /// while their thread pumps messages, so this keeps `listen_with`
+    /// working when the callback blocks. `grab` has to answer from the hook
+    /// and can't do this.
+    pub hook_thread: bool,
+    /// Some, hooks push events to a lock-free queue of this many events on a
+    /// thread of their own, and the callback takes them from it on the
+    /// calling thread. Hooks then return right away whatever the callback
+    /// does, Windows doesn't time them out and high mouse report rates don't
+    /// slow down the system. Events are dropped while the queue is full.
+    pub buffer: Option<usize>,
+    /// Some, at most this many `MouseMove` per second reach the callback,
+    /// for apps that don't need every report of a 8 kHz mouse. The moves
+    /// skipped add their delta to the next one, and the last one still
+    /// comes when the mouse stops. Listens through a queue like `buffer`,
+    /// of 1024 events unless `buffer` says otherwise.
+    pub mouse_move_max_hz: Option<u32>,
+    /// True by default. False, keys aren't translated with the keyboard
+    /// layout: events come without `name` and `unicode`, which saves the
+    /// most expensive part of handling a key for apps that only look at
+    /// `EventType`. Windows and X11 notice layout changes while
+    /// translating, so no `LayoutChanged` comes either.
+    pub names: bool,
+    /// Windows, report keypad keys as `Kp*` whatever NumLock says, like
+    /// Linux and macOS do. Windows otherwise gives the keys they act as
+    /// with NumLock off (`Home` for `Kp7`), which a recording then replays
+    /// differently. `Event::numlock` tells what the key did. False by
+    /// default.
+    pub physical_keypad: bool,
+}
+
+impl Default for Settings {
+    fn default() -> Settings {
+        Settings {
+            backend: None,
+            listen_only: true,
+            hook_thread: false,
+            buffer: None,
+            mouse_move_max_hz: None,
+            names: true,
+            physical_keypad: false,
+        }
+    }
 }
 
 /// Errors that occur when trying to grab OS events.
@@ -49,7 +212,7 @@ pub enum ListenError {
 #[non_exhaustive]
 pub enum GrabError {
     /// MacOS
-    EventTapError,
+    EventTapError(OsError),
     /// MacOS
     LoopSourceError,
     /// Linux
@@ -63,7 +226,105 @@ pub enum GrabError {
     /// All
     SimulateError,
     IoError(std::io::Error),
+    /// Linux, no device in /dev/input matched (None), or none could be
+    /// grabbed
+    EvdevError(Option<OsError>),
+}
+/// What an input device is used for, as far as the OS can tell.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "typescript", derive(Type))]
+#[cfg_attr(feature = "serialize", derive(Serialize, Deserialize))]
+pub enum DeviceKind {
+    Keyboard,
+    Mouse,
+    Other,
 }
+
+/// An input device reported by `listen_devices`. `id` is the OS handle of
+/// the device (its path on Linux and Windows), it's only stable while the
+/// device stays connected. Vendor and product ids are 0 when unknown.
+/// `serial` is the serial number the device reports, which tells apart two
+/// devices of the same model. Many keyboards and mice don't have one.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "typescript", derive(Type))]
+#[cfg_attr(feature = "serialize", derive(Serialize, Deserialize))]
+pub struct InputDevice {
+    pub id: String,
+    pub name: String,
+    pub vendor_id: u16,
+    pub product_id: u16,
+    pub serial: Option<String>,
+    pub kind: DeviceKind,
+}
+
+/// An input device was plugged in or removed.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "typescript", derive(Type))]
+#[cfg_attr(feature = "serialize", derive(Serialize, Deserialize))]
+pub enum DeviceEvent {
+    Connected(InputDevice),
+    Disconnected(InputDevice),
+}
+
+/// A monitor returned by `displays()`. The position and size are in the
+/// coordinates of `MouseMove` events (points on macOS, multiply by
+/// `scale_factor` for pixels). `refresh_rate` is in Hz, None when the OS
+/// doesn't know it.
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "typescript", derive(Type))]
+#[cfg_attr(feature = "serialize", derive(Serialize, Deserialize))]
+pub struct DisplayInfo {
+    pub x: i64,
+    pub y: i64,
+    pub width: u64,
+    pub height: u64,
+    pub scale_factor: f64,
+    pub refresh_rate: Option<f64>,
+    pub is_primary: bool,
+}
+
+/// The monitor configuration changed, see `listen_displays`. Monitors are
+/// told apart by their position, one that moved is `Removed` then `Added`.
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "typescript", derive(Type))]
+#[cfg_attr(feature = "serialize", derive(Serialize, Deserialize))]
+pub enum DisplayEvent {
+    Added(DisplayInfo),
+    Removed(DisplayInfo),
+    /// Same position, but the size, scale factor or refresh rate changed.
+    Changed(DisplayInfo),
+    /// The new primary monitor.
+    PrimaryChanged(DisplayInfo),
+}
+
+/// How clicks become double clicks and drags, see `click_settings`.
+/// Distances are in the coordinates of `MouseMove` events.
+#[derive(Debug, Copy, Clone, PartialEq)]
+#[cfg_attr(feature = "serialize", derive(Serialize, Deserialize))]
+pub struct ClickSettings {
+    /// Longest time between the presses of a double click.
+    pub double_click_interval: Duration,
+    /// Farthest the second press can be from the first one.
+    pub double_click_distance: f64,
+    /// How far the pointer moves with a button down before it's a drag.
+    pub drag_threshold: f64,
+    /// How long a button is held still for a long press, no OS has a
+    /// setting for it.
+    pub long_press: Duration,
+}
+
+/// The GTK defaults.
+impl Default for ClickSettings {
+    fn default() -> ClickSettings {
+        ClickSettings {
+            double_click_interval: Duration::from_millis(400),
+            double_click_distance: 5.0,
+            drag_threshold: 8.0,
+            long_press: Duration::from_millis(500),
+        }
+    }
+}
+
 /// Errors that occur when trying to get display size.
 #[non_exhaustive]
 #[derive(Debug)]
@@ -96,6 +357,150 @@ impl Display for SimulateError {
 
 impl std::error::Error for SimulateError {}
 
+const ACCESSIBILITY: &str =
+    "grant the Accessibility permission in System Settings > Privacy & Security > Accessibility";
+const INPUT_GROUP: &str = "add the user to the `input` group and log in again";
+
+impl Display for ListenError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ListenError::EventTapError(error) => write!(
+                f,
+                "Could not create the event tap ({}), {}",
+                error, ACCESSIBILITY
+            ),
+            ListenError::LoopSourceError => {
+                write!(f, "Could not add the event tap to the run loop")
+            }
+            ListenError::MissingDisplayError => write!(
+                f,
+                "Could not open the X display, check $DISPLAY (without X, use Backend::Evdev)"
+            ),
+            ListenError::KeyboardError => write!(f, "Could not set up the X keyboard"),
+            ListenError::RecordContextEnablingError(error) => write!(
+                f,
+                "Could not enable the RECORD context ({}), another client may be using it, try \
+                 Backend::XInput2",
+                error
+            ),
+            ListenError::RecordContextError(error) => {
+                write!(f, "Could not create the RECORD context ({})", error)
+            }
+            ListenError::XRecordExtensionError(error) => write!(
+                f,
+                "The X server doesn't have the RECORD extension ({}), try Backend::XInput2",
+                error
+            ),
+            ListenError::KeyHookError(code) => {
+                write!(f, "Could not install the keyboard hook (error {})", code)
+            }
+            ListenError::MouseHookError(code) => {
+                write!(f, "Could not install the mouse hook (error {})", code)
+            }
+            ListenError::DeviceMonitorError(error) => {
+                write!(f, "Could not watch for input devices ({})", error)
+            }
+            ListenError::DisplayMonitorError(error) => {
+                write!(f, "Could not watch for monitor changes ({})", error)
+            }
+            ListenError::EvdevError(error) => write!(
+                f,
+                "Could not read any device in /dev/input ({}), {}",
+                error, INPUT_GROUP
+            ),
+            ListenError::XInputError(error) => write!(
+                f,
+                "The X server doesn't have XInput 2.1 ({}), try Backend::X11",
+                error
+            ),
+            ListenError::RawInputError(error) => {
+                write!(f, "Could not register for Raw Input ({})", error)
+            }
+            ListenError::IOHidError(error) => write!(
+                f,
+                "Could not open the HID manager ({}), grant the Input Monitoring permission in \
+                 System Settings > Privacy & Security > Input Monitoring",
+                error
+            ),
+            ListenError::UnsupportedBackend => {
+                write!(f, "This platform doesn't have the backend asked for")
+            }
+        }
+    }
+}
+
+impl std::error::Error for ListenError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            ListenError::EventTapError(error)
+            | ListenError::RecordContextEnablingError(error)
+            | ListenError::RecordContextError(error)
+            | ListenError::XRecordExtensionError(error)
+            | ListenError::DeviceMonitorError(error)
+            | ListenError::DisplayMonitorError(error)
+            | ListenError::EvdevError(error)
+            | ListenError::XInputError(error)
+            | ListenError::RawInputError(error)
+            | ListenError::IOHidError(error) => Some(error),
+            _ => None,
+        }
+    }
+}
+
+impl Display for GrabError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            GrabError::EventTapError(error) => write!(
+                f,
+                "Could not create the event tap ({}), {}",
+                error, ACCESSIBILITY
+            ),
+            GrabError::LoopSourceError => write!(f, "Could not add the event tap to the run loop"),
+            GrabError::MissingDisplayError => {
+                write!(f, "Could not open the X display, check $DISPLAY")
+            }
+            GrabError::KeyboardError => write!(f, "Could not set up the X keyboard"),
+            GrabError::KeyHookError(code) => {
+                write!(f, "Could not install the keyboard hook (error {})", code)
+            }
+            GrabError::MouseHookError(code) => {
+                write!(f, "Could not install the mouse hook (error {})", code)
+            }
+            GrabError::SimulateError => {
+                write!(f, "Could not simulate the events of the callback")
+            }
+            GrabError::IoError(_) => write!(f, "Could not grab the input devices"),
+            GrabError::EvdevError(None) => write!(f, "No device in /dev/input matched"),
+            GrabError::EvdevError(Some(error)) => write!(
+                f,
+                "No device in /dev/input could be grabbed ({}), {}",
+                error, INPUT_GROUP
+            ),
+        }
+    }
+}
+
+impl std::error::Error for GrabError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            GrabError::IoError(error) => Some(error),
+            GrabError::EventTapError(error) | GrabError::EvdevError(Some(error)) => Some(error),
+            _ => None,
+        }
+    }
+}
+
+impl Display for DisplayError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            DisplayError::NoDisplay => write!(f, "No display found"),
+            DisplayError::ConversionError => write!(f, "Could not convert the display size"),
+        }
+    }
+}
+
+impl std::error::Error for DisplayError {}
+
 /// Key names based on physical location on the device
 /// Merge Option(MacOS) and Alt(Windows, Linux) into Alt
 /// Merge Windows (Windows), Meta(Linux), Command(MacOS) into Meta
@@ -107,9 +512,11 @@ impl std::error::Error for SimulateError {}
 #[derive(Debug, Copy, Clone, PartialEq, Eq, Hash, PartialOrd, Ord)]
 #[cfg_attr(feature = "typescript", derive(Type))]
 #[cfg_attr(feature = "serialize", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "schemars", derive(JsonSchema))]
 pub enum Key {
-    /// Alt key on Linux and Windows (option key on macOS)
+    /// Alt key on Linux and Windows (option key on macOS), also `Key::AltLeft`
     Alt,
+    /// Right Alt key, also `Key::AltRight`
     AltGr,
     Backspace,
     CapsLock,
@@ -123,6 +530,18 @@ pub enum Key {
     F10,
     F11,
     F12,
+    F13,
+    F14,
+    F15,
+    F16,
+    F17,
+    F18,
+    F19,
+    F20,
+    F21,
+    F22,
+    F23,
+    F24,
     F2,
     F3,
     F4,
@@ -216,9 +635,46 @@ pub enum Key {
     Kp9,
     KpDelete,
     Function,
+    VolumeUp,
+    VolumeDown,
+    VolumeMute,
+    MediaPlayPause,
+    MediaNext,
+    MediaPrev,
+    BrightnessUp,
+    BrightnessDown,
+    /// Context menu key (Apps key on Windows)
+    Menu,
+    /// Katakana/Hiragana key of Japanese keyboards (Kana on macOS)
+    Kana,
+    /// Hangul/English toggle of Korean keyboards
+    Hangul,
+    /// Japanese conversion key
+    Henkan,
+    /// Japanese non-conversion key (Eisu on macOS)
+    Muhenkan,
+    Yen,
+    Ro,
+    BrowserBack,
+    BrowserForward,
+    BrowserRefresh,
+    BrowserHome,
+    BrowserSearch,
+    LaunchMail,
+    Calculator,
+    Sleep,
     Unknown(u32),
 }
 
+#[allow(non_upper_case_globals)]
+impl Key {
+    /// Left Alt (left Option on macOS), same key as `Key::Alt`.
+    pub const AltLeft: Key = Key::Alt;
+    /// Right Alt (right Option on macOS), same key as `Key::AltGr`: we report
+    /// the physical key whether the layout makes it an AltGr or not.
+    pub const AltRight: Key = Key::AltGr;
+}
+
 #[derive(Debug, PartialEq, Eq)]
 pub struct ParseKeyError;
 
@@ -229,6 +685,8 @@ impl FromStr for Key {
         Ok(match s {
             "Alt" => Key::Alt,
             "AltGr" => Key::AltGr,
+            "AltLeft" => Key::AltLeft,
+            "AltRight" => Key::AltRight,
             "Backspace" => Key::Backspace,
             "CapsLock" => Key::CapsLock,
             "ControlLeft" => Key::ControlLeft,
@@ -241,6 +699,18 @@ impl FromStr for Key {
             "F10" => Key::F10,
             "F11" => Key::F11,
             "F12" => Key::F12,
+            "F13" => Key::F13,
+            "F14" => Key::F14,
+            "F15" => Key::F15,
+            "F16" => Key::F16,
+            "F17" => Key::F17,
+            "F18" => Key::F18,
+            "F19" => Key::F19,
+            "F20" => Key::F20,
+            "F21" => Key::F21,
+            "F22" => Key::F22,
+            "F23" => Key::F23,
+            "F24" => Key::F24,
             "F2" => Key::F2,
             "F3" => Key::F3,
             "F4" => Key::F4,
@@ -332,6 +802,29 @@ impl FromStr for Key {
             "Kp9" => Key::Kp9,
             "KpDelete" => Key::KpDelete,
             "Function" => Key::Function,
+            "VolumeUp" => Key::VolumeUp,
+            "VolumeDown" => Key::VolumeDown,
+            "VolumeMute" => Key::VolumeMute,
+            "MediaPlayPause" => Key::MediaPlayPause,
+            "MediaNext" => Key::MediaNext,
+            "MediaPrev" => Key::MediaPrev,
+            "BrightnessUp" => Key::BrightnessUp,
+            "BrightnessDown" => Key::BrightnessDown,
+            "Menu" => Key::Menu,
+            "Kana" => Key::Kana,
+            "Hangul" => Key::Hangul,
+            "Henkan" => Key::Henkan,
+            "Muhenkan" => Key::Muhenkan,
+            "Yen" => Key::Yen,
+            "Ro" => Key::Ro,
+            "BrowserBack" => Key::BrowserBack,
+            "BrowserForward" => Key::BrowserForward,
+            "BrowserRefresh" => Key::BrowserRefresh,
+            "BrowserHome" => Key::BrowserHome,
+            "BrowserSearch" => Key::BrowserSearch,
+            "LaunchMail" => Key::LaunchMail,
+            "Calculator" => Key::Calculator,
+            "Sleep" => Key::Sleep,
             unknown => {
                 if let Some(id) = unknown.strip_prefix("Unknown(") {
                     if let Some(id) = id.strip_suffix(')') {
@@ -364,6 +857,18 @@ impl ToString for Key {
             Key::F10 => "F10".into(),
             Key::F11 => "F11".into(),
             Key::F12 => "F12".into(),
+            Key::F13 => "F13".into(),
+            Key::F14 => "F14".into(),
+            Key::F15 => "F15".into(),
+            Key::F16 => "F16".into(),
+            Key::F17 => "F17".into(),
+            Key::F18 => "F18".into(),
+            Key::F19 => "F19".into(),
+            Key::F20 => "F20".into(),
+            Key::F21 => "F21".into(),
+            Key::F22 => "F22".into(),
+            Key::F23 => "F23".into(),
+            Key::F24 => "F24".into(),
             Key::F2 => "F2".into(),
             Key::F3 => "F3".into(),
             Key::F4 => "F4".into(),
@@ -455,29 +960,140 @@ impl ToString for Key {
             Key::Kp9 => "Kp9".into(),
             Key::KpDelete => "KpDelete".into(),
             Key::Function => "Function".into(),
+            Key::VolumeUp => "VolumeUp".into(),
+            Key::VolumeDown => "VolumeDown".into(),
+            Key::VolumeMute => "VolumeMute".into(),
+            Key::MediaPlayPause => "MediaPlayPause".into(),
+            Key::MediaNext => "MediaNext".into(),
+            Key::MediaPrev => "MediaPrev".into(),
+            Key::BrightnessUp => "BrightnessUp".into(),
+            Key::BrightnessDown => "BrightnessDown".into(),
+            Key::Menu => "Menu".into(),
+            Key::Kana => "Kana".into(),
+            Key::Hangul => "Hangul".into(),
+            Key::Henkan => "Henkan".into(),
+            Key::Muhenkan => "Muhenkan".into(),
+            Key::Yen => "Yen".into(),
+            Key::Ro => "Ro".into(),
+            Key::BrowserBack => "BrowserBack".into(),
+            Key::BrowserForward => "BrowserForward".into(),
+            Key::BrowserRefresh => "BrowserRefresh".into(),
+            Key::BrowserHome => "BrowserHome".into(),
+            Key::BrowserSearch => "BrowserSearch".into(),
+            Key::LaunchMail => "LaunchMail".into(),
+            Key::Calculator => "Calculator".into(),
+            Key::Sleep => "Sleep".into(),
             Key::Unknown(id) => format!("Unknown({})", id),
         }
     }
 }
 
+/// Keys with an on/off state, see `get_lock_state` and `set_lock_state`.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "typescript", derive(Type))]
+#[cfg_attr(feature = "serialize", derive(Serialize, Deserialize))]
+pub enum LockKey {
+    CapsLock,
+    NumLock,
+    ScrollLock,
+}
+
+impl LockKey {
+    /// The key toggling this lock.
+    pub fn key(&self) -> Key {
+        match self {
+            LockKey::CapsLock => Key::CapsLock,
+            LockKey::NumLock => Key::NumLock,
+            LockKey::ScrollLock => Key::ScrollLock,
+        }
+    }
+}
+
+/// Modifiers held along with a key. Only `shift` and `alt_gr` change the
+/// character a key types on usual layouts.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash, Default)]
+#[cfg_attr(feature = "typescript", derive(Type))]
+#[cfg_attr(feature = "serialize", derive(Serialize, Deserialize))]
+pub struct Modifiers {
+    pub shift: bool,
+    pub ctrl: bool,
+    pub alt: bool,
+    pub meta: bool,
+    pub alt_gr: bool,
+}
+
 /// Standard mouse buttons
-/// Some mice have more than 3 buttons. These are not defined, and different
+/// `Back` and `Forward` are the thumb buttons (XButton1 and XButton2 on Windows).
+/// Some mice have even more buttons. These are not defined, and different
 /// OSs will give different `Button::Unknown` values.
 #[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
 #[cfg_attr(feature = "typescript", derive(Type))]
 #[cfg_attr(feature = "serialize", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "schemars", derive(JsonSchema))]
 pub enum Button {
     Left,
     Right,
     Middle,
+    Back,
+    Forward,
     Unknown(u8),
 }
 
+/// Like the `Debug` name, `"Left"` or `"Unknown(8)"`.
+impl Display for Button {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        fmt::Debug::fmt(self, f)
+    }
+}
+
+impl FromStr for Button {
+    type Err = ParseKeyError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Ok(match s {
+            "Left" => Button::Left,
+            "Right" => Button::Right,
+            "Middle" => Button::Middle,
+            "Back" => Button::Back,
+            "Forward" => Button::Forward,
+            _ => match call(s)? {
+                ("Unknown", args) => match args.as_slice() {
+                    [id] => Button::Unknown(arg(id)?),
+                    _ => return Err(ParseKeyError),
+                },
+                _ => return Err(ParseKeyError),
+            },
+        })
+    }
+}
+
+/// `"Name(a,b)"` as the name and its arguments, `"Name"` without any.
+fn call(s: &str) -> Result<(&str, Vec<&str>), ParseKeyError> {
+    match s.split_once('(') {
+        Some((name, args)) => {
+            let args = args.strip_suffix(')').ok_or(ParseKeyError)?;
+            // The button of `ButtonPress(Unknown(8))` is one argument.
+            let args = if args.contains('(') {
+                vec![args]
+            } else {
+                args.split(',').map(str::trim).collect()
+            };
+            Ok((name, args))
+        }
+        None => Ok((s, vec![])),
+    }
+}
+
+fn arg<T: FromStr>(arg: &str) -> Result<T, ParseKeyError> {
+    arg.parse().map_err(|_| ParseKeyError)
+}
+
 /// In order to manage different OSs, the current EventType choices are a mix and
 /// match to account for all possible events.
 #[derive(Debug, Copy, Clone, PartialEq)]
 #[cfg_attr(feature = "typescript", derive(Type))]
 #[cfg_attr(feature = "serialize", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "schemars", derive(JsonSchema))]
 pub enum EventType {
     /// The keys correspond to a standard qwerty layout, they don't correspond
     /// To the actual letter a user would use, that requires some layout logic to be added.
@@ -499,31 +1115,457 @@ pub enum EventType {
         delta_x: i64,
         delta_y: i64,
     },
+    /// Scrolling from a trackpad or other continuous device, with the same
+    /// directions as `Wheel` and deltas in pixels. `is_momentum` is true for the
+    /// inertia the OS adds after a flick, `phase` then follows the momentum.
+    /// Only macOS reports phases, scrolling without them still comes as `Wheel`.
+    /// Simulated on macOS, and on Windows as wheel deltas in fractions of a
+    /// notch. `simulate_smooth_scroll` sends a whole scroll.
+    Scroll {
+        delta_x: f64,
+        delta_y: f64,
+        phase: ScrollPhase,
+        is_momentum: bool,
+    },
+    /// The keyboard layout changed (the user switched layouts), names of the
+    /// following events use the new one. Only emitted by `listen` and `grab`,
+    /// it can't be simulated.
+    LayoutChanged,
+    /// An input method (IME) is composing text, the key that was just pressed
+    /// went to the IME instead of the application. `name` holds the
+    /// composition string when the OS tells us.
+    ImeComposition,
+    /// An input method (IME) committed text, it's in `name`.
+    /// Only emitted on Windows for now, other OSs don't expose the IME state
+    /// of other applications. Like `LayoutChanged` it can't be simulated.
+    ImeCommit,
+    /// An application turned Secure Input on (true) or off (false), password
+    /// fields and Terminal's Secure Keyboard Entry do. While it's on, macOS
+    /// doesn't send keyboard events to `listen`. Only emitted on macOS, the
+    /// change is noticed with the next mouse event; it can't be simulated.
+    SecureInput(bool),
+    /// A finger touched a touchscreen or trackpad. `id` stays the same for
+    /// the whole contact, until the matching `TouchUp`. `x` and `y` are in
+    /// pixels like `MouseMove`, except on macOS trackpads which aren't tied to
+    /// the screen and give positions between 0.0 and 1.0. `pressure` goes from
+    /// 0.0 to 1.0, devices that don't report it always give 1.0.
+    ///
+    /// Windows only sees the primary contact (as the OS promotes it to mouse
+    /// events, which this replaces), Linux gets touches through `grab` on
+    /// multitouch devices and macOS from trackpad gestures. Touches can't be
+    /// simulated.
+    TouchDown {
+        id: u64,
+        x: f64,
+        y: f64,
+        pressure: f64,
+    },
+    TouchMove {
+        id: u64,
+        x: f64,
+        y: f64,
+        pressure: f64,
+    },
+    TouchUp {
+        id: u64,
+        x: f64,
+        y: f64,
+        pressure: f64,
+    },
+    /// A pen or stylus touched the surface.
+    ///
+    /// Windows only gets the position from its hooks, with a pressure of
+    /// 1.0 while touching. macOS gets tablet pressure and tilt, Linux gets
+    /// everything through `grab` on tablet devices. Pens can't be simulated.
+    PenDown(Pen),
+    /// The pen moved, touching the surface or hovering over it (pressure 0.0).
+    PenMove(Pen),
+    PenUp(Pen),
+    /// A trackpad gesture, requires the `gestures` feature. Only emitted on
+    /// macOS, Windows doesn't share precision touchpad gestures with other
+    /// applications (pinches arrive as Ctrl + `Wheel`). Can't be simulated.
+    #[cfg(feature = "gestures")]
+    Gesture(Gesture),
+}
+
+/// Round trips with `FromStr`: `"KeyPress(KeyA)"`, `"ButtonPress(Unknown(8))"`,
+/// `"MouseMove(100.5,200.0)"`, `"Wheel(0,-1)"`, `"LayoutChanged"`. Arguments
+/// follow the fields, floats keep their `.0`. Pens are
+/// `"PenDown(x,y,pressure,tilt_x,tilt_y,eraser)"` and gestures
+/// `"Magnify(Begin,0.1)"`, `"Rotate(phase,degrees)"` or
+/// `"Swipe(phase,delta_x,delta_y)"`.
+impl Display for EventType {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            EventType::KeyPress(key) => write!(f, "KeyPress({})", key.to_string()),
+            EventType::KeyRelease(key) => write!(f, "KeyRelease({})", key.to_string()),
+            EventType::ButtonPress(button) => write!(f, "ButtonPress({})", button),
+            EventType::ButtonRelease(button) => write!(f, "ButtonRelease({})", button),
+            EventType::MouseMove { x, y } => write!(f, "MouseMove({:?},{:?})", x, y),
+            EventType::Wheel { delta_x, delta_y } => write!(f, "Wheel({},{})", delta_x, delta_y),
+            EventType::Scroll {
+                delta_x,
+                delta_y,
+                phase,
+                is_momentum,
+            } => write!(
+                f,
+                "Scroll({:?},{:?},{:?},{})",
+                delta_x, delta_y, phase, is_momentum
+            ),
+            EventType::LayoutChanged => f.write_str("LayoutChanged"),
+            EventType::ImeComposition => f.write_str("ImeComposition"),
+            EventType::ImeCommit => f.write_str("ImeCommit"),
+            EventType::SecureInput(enabled) => write!(f, "SecureInput({})", enabled),
+            EventType::TouchDown { id, x, y, pressure } => {
+                write!(f, "TouchDown({},{:?},{:?},{:?})", id, x, y, pressure)
+            }
+            EventType::TouchMove { id, x, y, pressure } => {
+                write!(f, "TouchMove({},{:?},{:?},{:?})", id, x, y, pressure)
+            }
+            EventType::TouchUp { id, x, y, pressure } => {
+                write!(f, "TouchUp({},{:?},{:?},{:?})", id, x, y, pressure)
+            }
+            EventType::PenDown(pen) => write!(f, "PenDown({})", PenArgs(pen)),
+            EventType::PenMove(pen) => write!(f, "PenMove({})", PenArgs(pen)),
+            EventType::PenUp(pen) => write!(f, "PenUp({})", PenArgs(pen)),
+            #[cfg(feature = "gestures")]
+            EventType::Gesture(Gesture::Magnify { phase, delta }) => {
+                write!(f, "Magnify({:?},{:?})", phase, delta)
+            }
+            #[cfg(feature = "gestures")]
+            EventType::Gesture(Gesture::Rotate { phase, degrees }) => {
+                write!(f, "Rotate({:?},{:?})", phase, degrees)
+            }
+            #[cfg(feature = "gestures")]
+            EventType::Gesture(Gesture::Swipe {
+                phase,
+                delta_x,
+                delta_y,
+            }) => write!(f, "Swipe({:?},{:?},{:?})", phase, delta_x, delta_y),
+        }
+    }
+}
+
+struct PenArgs<'a>(&'a Pen);
+
+impl Display for PenArgs<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let pen = self.0;
+        write!(
+            f,
+            "{:?},{:?},{:?},{:?},{:?},{}",
+            pen.x, pen.y, pen.pressure, pen.tilt_x, pen.tilt_y, pen.eraser
+        )
+    }
+}
+
+fn pen(args: &[&str]) -> Result<Pen, ParseKeyError> {
+    match args {
+        [x, y, pressure, tilt_x, tilt_y, eraser] => Ok(Pen {
+            x: arg(x)?,
+            y: arg(y)?,
+            pressure: arg(pressure)?,
+            tilt_x: arg(tilt_x)?,
+            tilt_y: arg(tilt_y)?,
+            eraser: arg(eraser)?,
+        }),
+        _ => Err(ParseKeyError),
+    }
+}
+
+fn scroll_phase(phase: &str) -> Result<ScrollPhase, ParseKeyError> {
+    match phase {
+        "Begin" => Ok(ScrollPhase::Begin),
+        "Change" => Ok(ScrollPhase::Change),
+        "End" => Ok(ScrollPhase::End),
+        _ => Err(ParseKeyError),
+    }
+}
+
+#[cfg(feature = "gestures")]
+fn gesture_phase(phase: &str) -> Result<GesturePhase, ParseKeyError> {
+    match phase {
+        "Begin" => Ok(GesturePhase::Begin),
+        "Change" => Ok(GesturePhase::Change),
+        "End" => Ok(GesturePhase::End),
+        _ => Err(ParseKeyError),
+    }
+}
+
+impl FromStr for EventType {
+    type Err = ParseKeyError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (name, args) = call(s.trim())?;
+        Ok(match (name, args.as_slice()) {
+            ("KeyPress", [key]) => EventType::KeyPress(key.parse()?),
+            ("KeyRelease", [key]) => EventType::KeyRelease(key.parse()?),
+            ("ButtonPress", [button]) => EventType::ButtonPress(button.parse()?),
+            ("ButtonRelease", [button]) => EventType::ButtonRelease(button.parse()?),
+            ("MouseMove", [x, y]) => EventType::MouseMove {
+                x: arg(x)?,
+                y: arg(y)?,
+            },
+            ("Wheel", [delta_x, delta_y]) => EventType::Wheel {
+                delta_x: arg(delta_x)?,
+                delta_y: arg(delta_y)?,
+            },
+            ("Scroll", [delta_x, delta_y, phase, is_momentum]) => EventType::Scroll {
+                delta_x: arg(delta_x)?,
+                delta_y: arg(delta_y)?,
+                phase: scroll_phase(phase)?,
+                is_momentum: arg(is_momentum)?,
+            },
+            ("LayoutChanged", []) => EventType::LayoutChanged,
+            ("ImeComposition", []) => EventType::ImeComposition,
+            ("ImeCommit", []) => EventType::ImeCommit,
+            ("SecureInput", [enabled]) => EventType::SecureInput(arg(enabled)?),
+            ("TouchDown", [id, x, y, pressure]) => EventType::TouchDown {
+                id: arg(id)?,
+                x: arg(x)?,
+                y: arg(y)?,
+                pressure: arg(pressure)?,
+            },
+            ("TouchMove", [id, x, y, pressure]) => EventType::TouchMove {
+                id: arg(id)?,
+                x: arg(x)?,
+                y: arg(y)?,
+                pressure: arg(pressure)?,
+            },
+            ("TouchUp", [id, x, y, pressure]) => EventType::TouchUp {
+                id: arg(id)?,
+                x: arg(x)?,
+                y: arg(y)?,
+                pressure: arg(pressure)?,
+            },
+            ("PenDown", args) => EventType::PenDown(pen(args)?),
+            ("PenMove", args) => EventType::PenMove(pen(args)?),
+            ("PenUp", args) => EventType::PenUp(pen(args)?),
+            #[cfg(feature = "gestures")]
+            ("Magnify", [phase, delta]) => EventType::Gesture(Gesture::Magnify {
+                phase: gesture_phase(phase)?,
+                delta: arg(delta)?,
+            }),
+            #[cfg(feature = "gestures")]
+            ("Rotate", [phase, degrees]) => EventType::Gesture(Gesture::Rotate {
+                phase: gesture_phase(phase)?,
+                degrees: arg(degrees)?,
+            }),
+            #[cfg(feature = "gestures")]
+            ("Swipe", [phase, delta_x, delta_y]) => EventType::Gesture(Gesture::Swipe {
+                phase: gesture_phase(phase)?,
+                delta_x: arg(delta_x)?,
+                delta_y: arg(delta_y)?,
+            }),
+            _ => return Err(ParseKeyError),
+        })
+    }
+}
+
+/// Where a continuous scroll is at.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "typescript", derive(Type))]
+#[cfg_attr(feature = "serialize", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "schemars", derive(JsonSchema))]
+pub enum ScrollPhase {
+    Begin,
+    Change,
+    End,
+}
+
+/// State of a pen or stylus. `x` and `y` are in pixels like `MouseMove`,
+/// `pressure` goes from 0.0 to 1.0 and `tilt_x`/`tilt_y` from -1.0 to 1.0,
+/// 0.0 being upright. `eraser` is true when the tablet reports the eraser
+/// end is used.
+#[derive(Debug, Copy, Clone, PartialEq, Default)]
+#[cfg_attr(feature = "typescript", derive(Type))]
+#[cfg_attr(feature = "serialize", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "schemars", derive(JsonSchema))]
+pub struct Pen {
+    pub x: f64,
+    pub y: f64,
+    pub pressure: f64,
+    pub tilt_x: f64,
+    pub tilt_y: f64,
+    pub eraser: bool,
+}
+
+/// Where a gesture is at. Some gestures (legacy swipes) only come as `End`.
+#[cfg(feature = "gestures")]
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "typescript", derive(Type))]
+#[cfg_attr(feature = "serialize", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "schemars", derive(JsonSchema))]
+pub enum GesturePhase {
+    Begin,
+    Change,
+    End,
+}
+
+/// Trackpad gestures, values are the change since the previous event.
+#[cfg(feature = "gestures")]
+#[derive(Debug, Copy, Clone, PartialEq)]
+#[cfg_attr(feature = "typescript", derive(Type))]
+#[cfg_attr(feature = "serialize", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "schemars", derive(JsonSchema))]
+pub enum Gesture {
+    /// Pinch, `delta` is added to the scale (0.1 means 10% bigger).
+    Magnify { phase: GesturePhase, delta: f64 },
+    /// Two finger rotation in degrees, positive is counterclockwise.
+    Rotate { phase: GesturePhase, degrees: f64 },
+    /// Three finger swipe, deltas are -1.0, 0.0 or 1.0 with positive
+    /// values going left and up.
+    Swipe {
+        phase: GesturePhase,
+        delta_x: f64,
+        delta_y: f64,
+    },
+}
+
+/// The characters of a key event, as code points, and whether a dead key is
+/// pending, waiting for the next key to compose with.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "typescript", derive(Type))]
+#[cfg_attr(feature = "serialize", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "schemars", derive(JsonSchema))]
+pub struct UnicodeInfo {
+    pub code_points: Vec<u32>,
+    pub is_dead: bool,
+}
+
+impl UnicodeInfo {
+    /// None when the key gave no character and no dead key is pending.
+    pub(crate) fn new(name: Option<&str>, is_dead: bool) -> Option<UnicodeInfo> {
+        if name.is_none() && !is_dead {
+            return None;
+        }
+        Some(UnicodeInfo {
+            code_points: name.unwrap_or_default().chars().map(|c| c as u32).collect(),
+            is_dead,
+        })
+    }
 }
 
 /// When events arrive from the OS they get some additional information added from
 /// EventType, which is the time when this event was received, and the name Option
 /// which contains what characters should be emmitted from that event. This relies
 /// on the OS layout and keyboard state machinery.
-/// Caveat: Dead keys don't function on Linux(X11) yet. You will receive None for
-/// a dead key, and the raw letter instead of accentuated letter.
+/// Dead keys yield None (or an empty string), the accentuated letter comes
+/// with the key that follows.
+/// `unicode` holds the same characters along with the dead key state.
+/// `scan_code` is the platform code of the physical key, the evdev code on
+/// Linux, the scan code on Windows and the virtual key code on macOS (which
+/// names positions, not characters). It's 0 for other events.
+/// Only `name` and `unicode` allocate, events without text like mouse moves
+/// are delivered without any allocation (`benches/allocations.rs`).
+/// Mouse moves also get `display`, the index in `displays()` of the monitor
+/// under the cursor, and `delta`, the movement since the last
+/// event. On macOS and with the linux grab the delta comes from the device
+/// and keeps going at the screen edges, elsewhere it's the difference between
+/// positions. Both are None for other events and ignored when simulating.
+/// `numlock` is whether NumLock was on when a key event happened, sampled
+/// by the backend that listened to it, so a keypad key tells if it typed a
+/// digit or moved the caret. It's None for other events, on macOS which
+/// has no NumLock, and for events made up or read from formats not keeping
+/// it.
+/// More fields may be added, build events with `Event::new`.
 #[derive(Debug, Clone, PartialEq)]
 #[cfg_attr(feature = "typescript", derive(Type))]
 #[cfg_attr(feature = "serialize", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "schemars", derive(JsonSchema))]
+#[non_exhaustive]
 pub struct Event {
+    #[cfg_attr(feature = "serialize", serde(with = "epoch_millis"))]
+    #[cfg_attr(feature = "typescript", specta(type = f64))]
+    #[cfg_attr(feature = "schemars", schemars(with = "f64"))]
     pub time: SystemTime,
     pub name: Option<String>,
     pub event_type: EventType,
+    #[cfg_attr(feature = "serialize", serde(default))]
+    pub unicode: Option<UnicodeInfo>,
+    #[cfg_attr(feature = "serialize", serde(default))]
+    pub scan_code: u32,
+    #[cfg_attr(
+        feature = "serialize",
+        serde(default, skip_serializing_if = "Option::is_none")
+    )]
+    pub display: Option<usize>,
+    #[cfg_attr(
+        feature = "serialize",
+        serde(default, skip_serializing_if = "Option::is_none")
+    )]
+    pub delta: Option<(f64, f64)>,
+    #[cfg_attr(
+        feature = "serialize",
+        serde(default, skip_serializing_if = "Option::is_none")
+    )]
+    pub numlock: Option<bool>,
+}
+
+impl Event {
+    /// An event with the other fields empty.
+    pub fn new(event_type: EventType, time: SystemTime, name: Option<String>) -> Event {
+        Event {
+            time,
+            name,
+            event_type,
+            unicode: None,
+            scan_code: 0,
+            display: None,
+            delta: None,
+            numlock: None,
+        }
+    }
+}
+
+/// `Event::time` in the default serde representation and TypeScript, as
+/// milliseconds since the UNIX epoch with a fraction, the way JavaScript
+/// counts them. The `{secs_since_epoch, nanos_since_epoch}` serde gives
+/// `SystemTime`, which older versions wrote, is read too.
+#[cfg(feature = "serialize")]
+mod epoch_millis {
+    use serde::{de, Deserialize, Deserializer, Serializer};
+    use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+    pub fn serialize<S: Serializer>(time: &SystemTime, serializer: S) -> Result<S::Ok, S::Error> {
+        let millis = time
+            .duration_since(UNIX_EPOCH)
+            .map(|d| d.as_millis() as f64 + f64::from(d.subsec_nanos() % 1_000_000) / 1e6)
+            .unwrap_or(0.0);
+        serializer.serialize_f64(millis)
+    }
+
+    #[derive(Deserialize)]
+    #[serde(untagged)]
+    enum Time {
+        Millis(f64),
+        SystemTime(SystemTime),
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<SystemTime, D::Error> {
+        match Time::deserialize(deserializer)? {
+            // In two parts, a double of the whole time can't hold nanoseconds.
+            Time::Millis(millis) if millis >= 0.0 && millis.is_finite() => {
+                let whole = millis.trunc();
+                let nanos = ((millis - whole) * 1e6).round() as u64;
+                Ok(UNIX_EPOCH + Duration::from_millis(whole as u64) + Duration::from_nanos(nanos))
+            }
+            Time::Millis(millis) => Err(de::Error::invalid_value(
+                de::Unexpected::Float(millis),
+                &"milliseconds since the UNIX epoch",
+            )),
+            Time::SystemTime(time) => Ok(time),
+        }
+    }
 }
 
 /// We can define a dummy Keyboard, that we will use to detect
 /// what kind of EventType trigger some String. We get the currently used
 /// layout for now !
-/// Caveat : This is layout dependent. If your app needs to support
-/// layout switching don't use this !
-/// Caveat: On Linux, the dead keys mechanism is not implemented.
-/// Caveat: Only shift and dead keys are implemented, Alt+unicode code on windows
-/// won't work.
+/// The layout is refreshed automatically when the user switches layouts.
+/// Caveat: Only shift, AltGr and dead keys are implemented.
+/// On Windows, Alt+numpad codes give their character when Alt is released.
 ///
 /// ```no_run
 /// use rdev::{Keyboard, EventType, Key, KeyboardState};
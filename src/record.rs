@@ -0,0 +1,672 @@
+//! Recording events to a file, with the time elapsed between them, and
+//! playing them back, for macros and tests. Requires the `record` feature.
+//! JSONL recordings have one `{"delay_ms":12,"event":[...]}` line per event,
+//! with the `compact` representation of events, stable across versions. Binary recordings start
+//! with `BINARY_MAGIC`, then each event is its delay in milliseconds and its
+//! `compact` representation, as varints and little endian floats. They are
+//! about 10x smaller.
+//!
+//! ```no_run
+//! use rdev::record::{RecordFormat, Recorder};
+//! use rdev::EventType;
+//! use std::thread;
+//! use std::time::Duration;
+//!
+//! let recorder = Recorder::create("macro.rec", RecordFormat::Binary).unwrap();
+//! recorder.set_filter(|event| !matches!(event.event_type, EventType::MouseMove { .. }));
+//! let listener = recorder.clone();
+//! thread::spawn(move || listener.listen());
+//! recorder.start();
+//! thread::sleep(Duration::from_secs(10));
+//! recorder.stop().unwrap();
+//! ```
+use crate::binary;
+use crate::clock::{Clock, SystemClock};
+use crate::compact::CompactEvent;
+use crate::hotkey::{Hotkey, HotkeyError, HotkeyManager};
+use crate::rdev::{Button, Event, EventType, Key, ListenError, SimulateError};
+use lazy_static::lazy_static;
+use serde::{Deserialize, Serialize};
+use std::fs::File;
+use std::io::{self, BufRead, BufReader, BufWriter, Write};
+use std::path::Path;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex, Once, PoisonError};
+use std::thread;
+use std::time::{Duration, SystemTime};
+
+type Filter = Box<dyn Fn(&Event) -> bool + Send>;
+
+/// The first bytes of binary recordings, the last one is the version.
+pub const BINARY_MAGIC: &[u8; 8] = b"RDEVREC\x01";
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub enum RecordFormat {
+    /// A JSON object per line.
+    Jsonl,
+    /// Varints, see the module documentation.
+    Binary,
+}
+
+/// An event and the time since the previous one in the recording (since
+/// `start` for the first one).
+#[derive(Debug, Clone, PartialEq)]
+pub struct RecordedEvent {
+    pub delay: Duration,
+    pub event: Event,
+}
+
+#[derive(Serialize, Deserialize)]
+struct Line {
+    delay_ms: u64,
+    event: CompactEvent,
+}
+
+fn write_event<W: Write>(
+    writer: &mut W,
+    format: RecordFormat,
+    recorded: &RecordedEvent,
+) -> io::Result<()> {
+    let delay_ms = recorded.delay.as_millis() as u64;
+    match format {
+        RecordFormat::Jsonl => {
+            let line = Line {
+                delay_ms,
+                event: CompactEvent(recorded.event.clone()),
+            };
+            serde_json::to_writer(&mut *writer, &line)?;
+            writer.write_all(b"\n")
+        }
+        RecordFormat::Binary => {
+            binary::write_varint(writer, delay_ms)?;
+            binary::to_writer(writer, &CompactEvent(recorded.event.clone()))
+        }
+    }
+}
+
+/// Reads a recording of either format.
+pub fn read_recording<R: io::Read>(reader: R) -> io::Result<Vec<RecordedEvent>> {
+    let mut reader = BufReader::new(reader);
+    let mut recorded = vec![];
+    if reader.fill_buf()?.starts_with(BINARY_MAGIC) {
+        reader.consume(BINARY_MAGIC.len());
+        while !reader.fill_buf()?.is_empty() {
+            let delay_ms = binary::read_varint(&mut reader)?;
+            let CompactEvent(event) = binary::from_reader(&mut reader)?;
+            recorded.push(RecordedEvent {
+                delay: Duration::from_millis(delay_ms),
+                event,
+            });
+        }
+    } else {
+        for line in reader.lines() {
+            let line = line?;
+            if line.trim().is_empty() {
+                continue;
+            }
+            let Line {
+                delay_ms,
+                event: CompactEvent(event),
+            } = serde_json::from_str(&line)?;
+            recorded.push(RecordedEvent {
+                delay: Duration::from_millis(delay_ms),
+                event,
+            });
+        }
+    }
+    Ok(recorded)
+}
+
+pub fn read_recording_file<P: AsRef<Path>>(path: P) -> io::Result<Vec<RecordedEvent>> {
+    read_recording(File::open(path)?)
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+enum Status {
+    Paused,
+    Recording,
+    Stopped,
+}
+
+struct State {
+    writer: Option<Box<dyn Write + Send>>,
+    format: RecordFormat,
+    status: Status,
+    filter: Option<Filter>,
+    clock: Box<dyn Clock>,
+    /// When the previous event was recorded, or recording (re)started.
+    last: SystemTime,
+    error: Option<io::Error>,
+}
+
+/// Writes the events it's given to a file while started. Clones share the
+/// same recording, so one can run `listen` while another starts, pauses
+/// and stops it. Use `record` to feed it events from your own `listen` or
+/// `grab` callback instead.
+///
+/// Recorders begin paused. The time spent paused isn't recorded: the delay
+/// of the first event after `start` is counted from `start`. Events are
+/// written as they come, buffered, `stop` flushes them and closes the file.
+#[derive(Clone)]
+pub struct Recorder {
+    state: Arc<Mutex<State>>,
+}
+
+impl Recorder {
+    pub fn new<W: Write + Send + 'static>(
+        mut writer: W,
+        format: RecordFormat,
+    ) -> io::Result<Recorder> {
+        if format == RecordFormat::Binary {
+            writer.write_all(BINARY_MAGIC)?;
+        }
+        Ok(Recorder {
+            state: Arc::new(Mutex::new(State {
+                writer: Some(Box::new(writer)),
+                format,
+                status: Status::Paused,
+                filter: None,
+                clock: Box::new(SystemClock),
+                last: SystemTime::now(),
+                error: None,
+            })),
+        })
+    }
+
+    /// Creates the file, replacing an existing one.
+    pub fn create<P: AsRef<Path>>(path: P, format: RecordFormat) -> io::Result<Recorder> {
+        Recorder::new(BufWriter::new(File::create(path)?), format)
+    }
+
+    fn state(&self) -> std::sync::MutexGuard<'_, State> {
+        self.state.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+
+    /// Only the events for which `filter` returns true are recorded.
+    pub fn set_filter<F>(&self, filter: F)
+    where
+        F: Fn(&Event) -> bool + Send + 'static,
+    {
+        self.state().filter = Some(Box::new(filter));
+    }
+
+    /// Where `start` gets the time from, the delay of the first event is
+    /// counted from it. Other delays are between the times of events.
+    pub fn set_clock<C: Clock + 'static>(&self, clock: C) {
+        self.state().clock = Box::new(clock);
+    }
+
+    /// Starts or resumes recording, does nothing once stopped.
+    pub fn start(&self) {
+        let mut state = self.state();
+        if state.status == Status::Paused {
+            state.status = Status::Recording;
+            state.last = state.clock.now();
+        }
+    }
+
+    pub fn pause(&self) {
+        let mut state = self.state();
+        if state.status == Status::Recording {
+            state.status = Status::Paused;
+        }
+    }
+
+    pub fn is_recording(&self) -> bool {
+        self.state().status == Status::Recording
+    }
+
+    /// Flushes and closes the recording. Returns the first error writing
+    /// it, recording stopped then.
+    pub fn stop(&self) -> io::Result<()> {
+        let mut state = self.state();
+        state.status = Status::Stopped;
+        let flushed = match state.writer.take() {
+            Some(mut writer) => writer.flush(),
+            None => Ok(()),
+        };
+        match state.error.take() {
+            Some(error) => Err(error),
+            None => flushed,
+        }
+    }
+
+    /// Records `event` if recording and it passes the filter.
+    pub fn record(&self, event: &Event) {
+        let mut state = self.state();
+        if state.status != Status::Recording {
+            return;
+        }
+        if let Some(filter) = &state.filter {
+            if !filter(event) {
+                return;
+            }
+        }
+        // Events can come from another clock than ours, or slightly out of
+        // order, never go back in time.
+        let delay = event.time.duration_since(state.last).unwrap_or_default();
+        if delay > Duration::from_millis(0) {
+            state.last = event.time;
+        }
+        let recorded = RecordedEvent {
+            delay,
+            event: event.clone(),
+        };
+        let format = state.format;
+        let written = match state.writer.as_mut() {
+            Some(writer) => write_event(writer, format, &recorded),
+            None => Ok(()),
+        };
+        if let Err(error) = written {
+            state.error = Some(error);
+            state.status = Status::Stopped;
+        }
+    }
+
+    /// Records what `listen` gets. Blocks, even once stopped.
+    pub fn listen(&self) -> Result<(), ListenError> {
+        let recorder = self.clone();
+        crate::listen(move |event| recorder.record(&event))
+    }
+}
+
+/// How `Player::play` ended.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum PlaybackEnd {
+    Finished,
+    /// The abort hotkey was pressed.
+    Aborted,
+}
+
+#[derive(Debug)]
+#[non_exhaustive]
+pub enum PlaybackError {
+    /// Speed wasn't a positive number.
+    InvalidSpeed,
+    /// A key or mouse event couldn't be simulated.
+    SimulateError,
+    /// The abort hotkey is used by another playback.
+    HotkeyError(HotkeyError),
+    IoError(io::Error),
+}
+
+impl From<SimulateError> for PlaybackError {
+    fn from(_: SimulateError) -> PlaybackError {
+        PlaybackError::SimulateError
+    }
+}
+
+impl From<HotkeyError> for PlaybackError {
+    fn from(error: HotkeyError) -> PlaybackError {
+        PlaybackError::HotkeyError(error)
+    }
+}
+
+impl From<io::Error> for PlaybackError {
+    fn from(error: io::Error) -> PlaybackError {
+        PlaybackError::IoError(error)
+    }
+}
+
+/// We check for the abort hotkey at least this often while waiting.
+const ABORT_POLL: Duration = Duration::from_millis(10);
+
+lazy_static! {
+    /// `listen` can't be stopped, abort hotkeys of every playback share one
+    /// listener started with the first of them.
+    static ref ABORT_HOTKEYS: HotkeyManager = HotkeyManager::new();
+}
+static ABORT_LISTENER: Once = Once::new();
+
+/// Replays recorded events with `simulate`. Events are sent when they are
+/// due since the playback started, so time spent simulating doesn't add up.
+/// Keys and buttons still held when playback ends, is aborted or fails are
+/// released. Events that aren't input, like `LayoutChanged`, are skipped.
+///
+/// ```no_run
+/// use rdev::record::{read_recording_file, Player};
+/// use std::time::Duration;
+///
+/// let player = Player {
+///     speed: 2.0,
+///     loops: 3,
+///     abort: Some("Ctrl+Escape".parse().unwrap()),
+///     max_gap: Some(Duration::from_secs(1)),
+/// };
+/// let events = read_recording_file("macro.rec").unwrap();
+/// println!("{:?}", player.play(&events));
+/// ```
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct Player {
+    /// 2.0 plays twice as fast, `f64::INFINITY` doesn't wait at all.
+    pub speed: f64,
+    /// How many times the events are played, 0 repeats until aborted.
+    pub loops: u32,
+    /// Stops the playback when pressed. The events played are listened to
+    /// as well, so the recording shouldn't contain it.
+    pub abort: Option<Hotkey>,
+    /// Longer delays of the recording are shortened to this, before speeding
+    /// up.
+    pub max_gap: Option<Duration>,
+}
+
+impl Default for Player {
+    fn default() -> Player {
+        Player {
+            speed: 1.0,
+            loops: 1,
+            abort: None,
+            max_gap: None,
+        }
+    }
+}
+
+fn is_input(event_type: &EventType) -> bool {
+    matches!(
+        event_type,
+        EventType::KeyPress(_)
+            | EventType::KeyRelease(_)
+            | EventType::ButtonPress(_)
+            | EventType::ButtonRelease(_)
+            | EventType::MouseMove { .. }
+            | EventType::Wheel { .. }
+            | EventType::Scroll { .. }
+    )
+}
+
+/// Keys and buttons pressed by the playback, to release them at the end.
+#[derive(Default)]
+struct Held {
+    keys: Vec<Key>,
+    buttons: Vec<Button>,
+}
+
+impl Held {
+    fn update(&mut self, event_type: &EventType) {
+        match event_type {
+            EventType::KeyPress(key) if !self.keys.contains(key) => self.keys.push(*key),
+            EventType::KeyRelease(key) => self.keys.retain(|held| held != key),
+            EventType::ButtonPress(button) if !self.buttons.contains(button) => {
+                self.buttons.push(*button)
+            }
+            EventType::ButtonRelease(button) => self.buttons.retain(|held| held != button),
+            _ => (),
+        }
+    }
+
+    fn releases(&self) -> Vec<EventType> {
+        let keys = self
+            .keys
+            .iter()
+            .rev()
+            .map(|key| EventType::KeyRelease(*key));
+        let buttons = self
+            .buttons
+            .iter()
+            .rev()
+            .map(|button| EventType::ButtonRelease(*button));
+        keys.chain(buttons).collect()
+    }
+}
+
+impl Player {
+    /// When each event is due, from the start of a loop.
+    fn schedule(&self, events: &[RecordedEvent]) -> Vec<Duration> {
+        let mut due = Duration::from_millis(0);
+        events
+            .iter()
+            .map(|recorded| {
+                let delay = match self.max_gap {
+                    Some(max_gap) => recorded.delay.min(max_gap),
+                    None => recorded.delay,
+                };
+                // Capped so absurdly slow speeds don't overflow.
+                let secs = delay.as_secs_f64() / self.speed;
+                due += Duration::from_secs_f64(secs.min(u32::MAX as f64));
+                due
+            })
+            .collect()
+    }
+
+    /// Plays `events`, blocking until done. See `Player`.
+    pub fn play(&self, events: &[RecordedEvent]) -> Result<PlaybackEnd, PlaybackError> {
+        let aborted = Arc::new(AtomicBool::new(false));
+        let hotkey = match self.abort {
+            Some(hotkey) => {
+                let flag = aborted.clone();
+                let id = ABORT_HOTKEYS
+                    .register(hotkey, false, move || flag.store(true, Ordering::SeqCst))?;
+                ABORT_LISTENER.call_once(|| {
+                    thread::spawn(|| ABORT_HOTKEYS.listen());
+                });
+                Some(id)
+            }
+            None => None,
+        };
+        let played = self.play_inner(events, &aborted, &SystemClock, crate::simulate);
+        if let Some(id) = hotkey {
+            ABORT_HOTKEYS.unregister(id).ok();
+        }
+        played
+    }
+
+    /// Reads the recording at `path` and plays it.
+    pub fn play_file<P: AsRef<Path>>(&self, path: P) -> Result<PlaybackEnd, PlaybackError> {
+        self.play(&read_recording_file(path)?)
+    }
+
+    /// Plays `events` with `send` instead of `simulate`, waiting on `clock`.
+    /// With a `VirtualClock` and the mock backend, a recording is replayed
+    /// instantly in tests. `abort` isn't listened to.
+    pub fn play_with<C, F>(
+        &self,
+        events: &[RecordedEvent],
+        clock: &C,
+        send: F,
+    ) -> Result<PlaybackEnd, PlaybackError>
+    where
+        C: Clock,
+        F: FnMut(&EventType) -> Result<(), SimulateError>,
+    {
+        self.play_inner(events, &AtomicBool::new(false), clock, send)
+    }
+
+    fn play_inner<C, F>(
+        &self,
+        events: &[RecordedEvent],
+        aborted: &AtomicBool,
+        clock: &C,
+        mut send: F,
+    ) -> Result<PlaybackEnd, PlaybackError>
+    where
+        C: Clock,
+        F: FnMut(&EventType) -> Result<(), SimulateError>,
+    {
+        if self.speed.is_nan() || self.speed <= 0.0 {
+            return Err(PlaybackError::InvalidSpeed);
+        }
+        let schedule = self.schedule(events);
+        let mut held = Held::default();
+        let mut played = Ok(PlaybackEnd::Finished);
+        let mut remaining = self.loops;
+        'playing: while self.loops == 0 || remaining > 0 {
+            remaining = remaining.saturating_sub(1);
+            let start = clock.now();
+            for (recorded, due) in events.iter().zip(&schedule) {
+                let event_type = &recorded.event.event_type;
+                if !is_input(event_type) {
+                    continue;
+                }
+                if !wait_until(start + *due, aborted, clock) {
+                    played = Ok(PlaybackEnd::Aborted);
+                    break 'playing;
+                }
+                if let Err(error) = send(event_type) {
+                    played = Err(error.into());
+                    break 'playing;
+                }
+                held.update(event_type);
+            }
+        }
+        for release in held.releases() {
+            send(&release).ok();
+        }
+        played
+    }
+}
+
+/// False if aborted before `deadline`.
+fn wait_until<C: Clock>(deadline: SystemTime, aborted: &AtomicBool, clock: &C) -> bool {
+    loop {
+        if aborted.load(Ordering::SeqCst) {
+            return false;
+        }
+        match deadline.duration_since(clock.now()) {
+            Ok(left) if left > Duration::from_millis(0) => clock.sleep(left.min(ABORT_POLL)),
+            _ => return true,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::clock::VirtualClock;
+    use std::time::UNIX_EPOCH;
+
+    /// A writer we can read back after the recorder is done with it.
+    #[derive(Clone, Default)]
+    struct Shared(Arc<Mutex<Vec<u8>>>);
+
+    impl Write for Shared {
+        fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+            self.0.lock().unwrap().write(buf)
+        }
+
+        fn flush(&mut self) -> io::Result<()> {
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn test_record() {
+        let start = UNIX_EPOCH + Duration::from_secs(1_700_000_000);
+        let event = |event_type, millis| Event {
+            event_type,
+            time: start + Duration::from_millis(millis),
+            name: Some("a".to_string()),
+            unicode: None,
+            scan_code: 30,
+            display: None,
+            delta: None,
+            numlock: None,
+        };
+        for format in [RecordFormat::Jsonl, RecordFormat::Binary] {
+            let buffer = Shared::default();
+            let recorder = Recorder::new(buffer.clone(), format).unwrap();
+            recorder.set_clock(VirtualClock::new(start));
+            recorder.set_filter(|event| event.event_type != EventType::KeyPress(Key::KeyB));
+            recorder.record(&event(EventType::KeyPress(Key::KeyA), 0));
+            recorder.start();
+            recorder.record(&event(EventType::KeyPress(Key::KeyA), 0));
+            recorder.record(&event(EventType::KeyPress(Key::KeyB), 10));
+            recorder.record(&event(EventType::KeyRelease(Key::KeyA), 50));
+            recorder.pause();
+            recorder.record(&event(EventType::KeyPress(Key::KeyA), 60));
+            recorder.stop().unwrap();
+            recorder.start();
+            recorder.record(&event(EventType::KeyPress(Key::KeyA), 70));
+
+            let bytes = buffer.0.lock().unwrap().clone();
+            let recorded = read_recording(&bytes[..]).unwrap();
+            let types: Vec<EventType> = recorded.iter().map(|r| r.event.event_type).collect();
+            assert_eq!(
+                types,
+                vec![
+                    EventType::KeyPress(Key::KeyA),
+                    EventType::KeyRelease(Key::KeyA)
+                ]
+            );
+            assert_eq!(recorded[1].delay, Duration::from_millis(50));
+            assert_eq!(recorded[1].event.name, Some("a".to_string()));
+        }
+    }
+
+    #[test]
+    fn test_player() {
+        let recorded = |event_type, millis| RecordedEvent {
+            delay: Duration::from_millis(millis),
+            event: Event {
+                event_type,
+                time: SystemTime::now(),
+                name: None,
+                unicode: None,
+                scan_code: 0,
+                display: None,
+                delta: None,
+                numlock: None,
+            },
+        };
+        let events = vec![
+            recorded(EventType::KeyPress(Key::ShiftLeft), 100),
+            recorded(EventType::LayoutChanged, 0),
+            recorded(EventType::KeyPress(Key::KeyA), 5000),
+            recorded(EventType::ButtonPress(Button::Left), 20),
+        ];
+        let player = Player {
+            speed: 2.0,
+            max_gap: Some(Duration::from_millis(200)),
+            ..Default::default()
+        };
+        let millis = |millis| Duration::from_millis(millis);
+        assert_eq!(
+            player.schedule(&events),
+            vec![millis(50), millis(50), millis(150), millis(160)]
+        );
+
+        let fast = Player {
+            speed: f64::INFINITY,
+            loops: 2,
+            ..Default::default()
+        };
+        let mut sent = vec![];
+        let clock = VirtualClock::default();
+        let end = fast.play_with(&events, &clock, |event_type| {
+            sent.push(*event_type);
+            Ok(())
+        });
+        assert_eq!(end.unwrap(), PlaybackEnd::Finished);
+        assert_eq!(clock.now(), UNIX_EPOCH);
+        assert_eq!(sent.len(), 2 * 3 + 3);
+        assert_eq!(
+            sent[6..],
+            [
+                EventType::KeyRelease(Key::KeyA),
+                EventType::KeyRelease(Key::ShiftLeft),
+                EventType::ButtonRelease(Button::Left),
+            ]
+        );
+
+        // The schedule is kept without waiting.
+        let end = player.play_with(&events, &clock, |_| Ok(()));
+        assert_eq!(end.unwrap(), PlaybackEnd::Finished);
+        assert_eq!(clock.now(), UNIX_EPOCH + millis(160));
+
+        let mut sent = vec![];
+        let aborted = AtomicBool::new(true);
+        let end = fast.play_inner(&events, &aborted, &clock, |event_type| {
+            sent.push(*event_type);
+            Ok(())
+        });
+        assert_eq!(end.unwrap(), PlaybackEnd::Aborted);
+        assert!(sent.is_empty());
+        let invalid = Player {
+            speed: 0.0,
+            ..Default::default()
+        };
+        assert!(matches!(
+            invalid.play_with(&events, &clock, |_| Ok(())),
+            Err(PlaybackError::InvalidSpeed)
+        ));
+    }
+}
@@ -0,0 +1,541 @@
+use crate::hotkey::Hotkey;
+use crate::rdev::{Event, EventType, Key, Modifiers, ParseKeyError};
+use std::collections::{HashMap, HashSet, VecDeque};
+use std::panic::{self, AssertUnwindSafe};
+use std::str::FromStr;
+use std::sync::{Arc, Mutex, MutexGuard, PoisonError};
+use std::time::{Duration, Instant};
+
+/// Events we simulated come back to `grab` on most platforms, we let them
+/// through if they do within this delay.
+const INJECTED_TIMEOUT: Duration = Duration::from_millis(250);
+
+/// What a `Remapper` changes. Parses from `"CapsLock -> Escape"` (a `Key`)
+/// or `"Ctrl+H -> Backspace"` (a `Chord`, as soon as either side has
+/// modifiers), both sides parse like `Hotkey`.
+///
+/// ```
+/// use rdev::{Key, Remap};
+///
+/// let remap: Remap = "CapsLock -> Escape".parse().unwrap();
+/// assert_eq!(remap, Remap::Key(Key::CapsLock, Key::Escape));
+/// ```
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub enum Remap {
+    /// The first key behaves as the second one, with any modifiers: it's
+    /// held, repeated and released with it.
+    Key(Key, Key),
+    /// The first hotkey, pressed with exactly its modifiers, sends the
+    /// second one instead. Modifiers held that the second one doesn't have
+    /// are released while it's held, those it has are pressed.
+    Chord(Hotkey, Hotkey),
+}
+
+impl FromStr for Remap {
+    type Err = ParseKeyError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut sides = s.split("->");
+        let (from, to) = match (sides.next(), sides.next(), sides.next()) {
+            (Some(from), Some(to), None) => (from.parse::<Hotkey>()?, to.parse::<Hotkey>()?),
+            _ => return Err(ParseKeyError),
+        };
+        let none = Modifiers::default();
+        if from.modifiers == none && to.modifiers == none {
+            Ok(Remap::Key(from.key, to.key))
+        } else {
+            Ok(Remap::Chord(from, to))
+        }
+    }
+}
+
+/// A `Remap` applying only while `condition` returns true, like when a given
+/// application is focused. Conditions are called on every key press they
+/// could change, they need to be fast.
+#[derive(Clone)]
+pub struct Mapping {
+    pub remap: Remap,
+    pub condition: Option<Arc<dyn Fn() -> bool + Send + Sync>>,
+}
+
+impl Mapping {
+    pub fn when<F>(remap: Remap, condition: F) -> Mapping
+    where
+        F: Fn() -> bool + Send + Sync + 'static,
+    {
+        Mapping {
+            remap,
+            condition: Some(Arc::new(condition)),
+        }
+    }
+
+    fn applies(&self) -> bool {
+        self.condition
+            .as_ref()
+            .map(|applies| applies())
+            .unwrap_or(true)
+    }
+}
+
+impl From<Remap> for Mapping {
+    fn from(remap: Remap) -> Mapping {
+        Mapping {
+            remap,
+            condition: None,
+        }
+    }
+}
+
+/// The key pressed for each modifier a chord adds.
+fn modifier_keys(modifiers: &Modifiers) -> Vec<Key> {
+    let mut keys = vec![];
+    if modifiers.ctrl {
+        keys.push(Key::ControlLeft);
+    }
+    if modifiers.shift {
+        keys.push(Key::ShiftLeft);
+    }
+    if modifiers.alt {
+        keys.push(Key::Alt);
+    }
+    if modifiers.alt_gr {
+        keys.push(Key::AltGr);
+    }
+    if modifiers.meta {
+        keys.push(Key::MetaLeft);
+    }
+    keys
+}
+
+/// Whether `modifiers` has the modifier of `key`, None if it isn't one.
+fn has_modifier(modifiers: &Modifiers, key: Key) -> Option<bool> {
+    match key {
+        Key::ShiftLeft | Key::ShiftRight => Some(modifiers.shift),
+        Key::ControlLeft | Key::ControlRight => Some(modifiers.ctrl),
+        Key::Alt => Some(modifiers.alt),
+        Key::AltGr => Some(modifiers.alt_gr),
+        Key::MetaLeft | Key::MetaRight => Some(modifiers.meta),
+        _ => None,
+    }
+}
+
+fn modifiers_of(keys: &[Key]) -> Modifiers {
+    let mut modifiers = Modifiers::default();
+    for key in keys {
+        match key {
+            Key::ShiftLeft | Key::ShiftRight => modifiers.shift = true,
+            Key::ControlLeft | Key::ControlRight => modifiers.ctrl = true,
+            Key::Alt => modifiers.alt = true,
+            Key::AltGr => modifiers.alt_gr = true,
+            Key::MetaLeft | Key::MetaRight => modifiers.meta = true,
+            _ => (),
+        }
+    }
+    modifiers
+}
+
+/// A chord held down.
+struct Chord {
+    key: Key,
+    /// Modifiers the user holds that we released.
+    lifted: Vec<Key>,
+    /// Modifiers we pressed.
+    added: Vec<Key>,
+}
+
+#[derive(Default)]
+struct State {
+    mappings: Vec<Mapping>,
+    /// Keys held by the user, as they pressed them.
+    held: Vec<Key>,
+    /// Keys held with a `Remap::Key`, to the key we pressed instead.
+    keys: HashMap<Key, Key>,
+    /// Keys held with a `Remap::Chord`.
+    chords: HashMap<Key, Chord>,
+    /// Keys we pressed and didn't release yet.
+    down: HashSet<Key>,
+    injected: VecDeque<(EventType, Instant)>,
+}
+
+impl State {
+    /// The keys held as the OS sees them, without those of chords.
+    fn virtual_held(&self, except: Key) -> Vec<Key> {
+        let lifted: Vec<Key> = self
+            .chords
+            .values()
+            .flat_map(|chord| chord.lifted.iter().copied())
+            .collect();
+        self.held
+            .iter()
+            .filter(|key| **key != except && !self.chords.contains_key(key))
+            .map(|key| *self.keys.get(key).unwrap_or(key))
+            .filter(|key| !lifted.contains(key))
+            .collect()
+    }
+
+    /// Stops suppressing the release of a lifted modifier, true if it was.
+    fn unlift(&mut self, key: Key) -> bool {
+        for chord in self.chords.values_mut() {
+            if let Some(index) = chord.lifted.iter().position(|lifted| *lifted == key) {
+                chord.lifted.remove(index);
+                return true;
+            }
+        }
+        false
+    }
+
+    fn press(&mut self, key: Key) -> (bool, Vec<EventType>) {
+        let repeat = self.held.contains(&key);
+        if !repeat {
+            self.held.push(key);
+        }
+        if let Some(target) = self.keys.get(&key) {
+            return (false, vec![EventType::KeyPress(*target)]);
+        }
+        if let Some(chord) = self.chords.get(&key) {
+            return (false, vec![EventType::KeyPress(chord.key)]);
+        }
+        if repeat {
+            return (true, vec![]);
+        }
+        let held = self.virtual_held(key);
+        let modifiers = modifiers_of(&held);
+        let remap = self
+            .mappings
+            .iter()
+            .find(|mapping| match mapping.remap {
+                Remap::Key(from, _) => from == key && mapping.applies(),
+                Remap::Chord(from, _) => {
+                    from.key == key && from.modifiers == modifiers && mapping.applies()
+                }
+            })
+            .map(|mapping| mapping.remap);
+        match remap {
+            Some(Remap::Key(_, to)) => {
+                self.keys.insert(key, to);
+                (false, vec![EventType::KeyPress(to)])
+            }
+            Some(Remap::Chord(_, to)) => {
+                let lifted: Vec<Key> = held
+                    .iter()
+                    .copied()
+                    .filter(|held| has_modifier(&to.modifiers, *held) == Some(false))
+                    .collect();
+                let added: Vec<Key> = modifier_keys(&to.modifiers)
+                    .into_iter()
+                    .filter(|added| {
+                        !held
+                            .iter()
+                            .any(|held| modifiers_of(&[*held]) == modifiers_of(&[*added]))
+                    })
+                    .collect();
+                let mut sends: Vec<EventType> = lifted
+                    .iter()
+                    .map(|key| EventType::KeyRelease(*key))
+                    .collect();
+                sends.extend(added.iter().map(|key| EventType::KeyPress(*key)));
+                sends.push(EventType::KeyPress(to.key));
+                self.chords.insert(
+                    key,
+                    Chord {
+                        key: to.key,
+                        lifted,
+                        added,
+                    },
+                );
+                (false, sends)
+            }
+            None => (true, vec![]),
+        }
+    }
+
+    fn release(&mut self, key: Key) -> (bool, Vec<EventType>) {
+        self.held.retain(|held| *held != key);
+        if let Some(chord) = self.chords.remove(&key) {
+            let mut sends = vec![EventType::KeyRelease(chord.key)];
+            sends.extend(
+                chord
+                    .added
+                    .iter()
+                    .rev()
+                    .map(|key| EventType::KeyRelease(*key)),
+            );
+            // Modifiers still held are pressed back.
+            sends.extend(chord.lifted.iter().map(|key| EventType::KeyPress(*key)));
+            return (false, sends);
+        }
+        if let Some(target) = self.keys.remove(&key) {
+            if self.unlift(target) {
+                return (false, vec![]);
+            }
+            return (false, vec![EventType::KeyRelease(target)]);
+        }
+        if self.unlift(key) {
+            return (false, vec![]);
+        }
+        (true, vec![])
+    }
+
+    fn handle(&mut self, event_type: &EventType, now: Instant) -> (bool, Vec<EventType>) {
+        self.injected
+            .retain(|(_, sent)| now.duration_since(*sent) < INJECTED_TIMEOUT);
+        if let Some(index) = self
+            .injected
+            .iter()
+            .position(|(injected, _)| injected == event_type)
+        {
+            self.injected.remove(index);
+            return (true, vec![]);
+        }
+        let (pass, sends) = match event_type {
+            EventType::KeyPress(key) => self.press(*key),
+            EventType::KeyRelease(key) => self.release(*key),
+            _ => (true, vec![]),
+        };
+        self.sent(&sends, now);
+        (pass, sends)
+    }
+
+    fn sent(&mut self, sends: &[EventType], now: Instant) {
+        for send in sends {
+            match send {
+                EventType::KeyPress(key) => self.down.insert(*key),
+                EventType::KeyRelease(key) => self.down.remove(key),
+                _ => false,
+            };
+            self.injected.push_back((*send, now));
+        }
+    }
+
+    /// Releases what we pressed and forgets about the keys held.
+    fn reset(&mut self) -> Vec<EventType> {
+        let releases: Vec<EventType> = self.down.drain().map(EventType::KeyRelease).collect();
+        self.held.clear();
+        self.keys.clear();
+        self.chords.clear();
+        self.sent(&releases, Instant::now());
+        releases
+    }
+}
+
+/// Remaps keys and chords, on top of `grab` or from your own `grab`
+/// callback with `handle`. The first mapping of the table applying to a key
+/// press wins, the release always matches the press even if the table or
+/// conditions changed in between. Autorepeat of remapped keys repeats their
+/// target.
+///
+/// Our events reach `grab` again on most platforms, we let through those
+/// looking like what we just simulated. If anything panics while remapping,
+/// including a condition, the keys we pressed are released and the event
+/// goes through. `release_all` does the same on demand.
+///
+/// ```no_run
+/// use rdev::{Mapping, Remap, Remapper};
+///
+/// let remapper = Remapper::new(vec![
+///     Mapping::from("CapsLock -> ControlLeft".parse::<Remap>().unwrap()),
+///     Mapping::when("Ctrl+H -> Backspace".parse().unwrap(), || true),
+///     Mapping::from("Alt+KeyJ -> Ctrl+Shift+Tab".parse::<Remap>().unwrap()),
+/// ]);
+/// ```
+#[derive(Clone, Default)]
+pub struct Remapper {
+    state: Arc<Mutex<State>>,
+}
+
+impl Remapper {
+    pub fn new<I>(mappings: I) -> Remapper
+    where
+        I: IntoIterator<Item = Mapping>,
+    {
+        let remapper = Remapper::default();
+        remapper.set_mappings(mappings);
+        remapper
+    }
+
+    fn state(&self) -> MutexGuard<'_, State> {
+        self.state.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+
+    /// Replaces the table, keys held keep their current mapping until
+    /// released.
+    pub fn set_mappings<I>(&self, mappings: I)
+    where
+        I: IntoIterator<Item = Mapping>,
+    {
+        self.state().mappings = mappings.into_iter().collect();
+    }
+
+    /// Calls `send` with the events to simulate instead of `event`, returns
+    /// whether `event` should go through.
+    pub fn handle<F>(&self, event: &Event, mut send: F) -> bool
+    where
+        F: FnMut(&EventType),
+    {
+        let now = Instant::now();
+        let handled = panic::catch_unwind(AssertUnwindSafe(|| {
+            self.state().handle(&event.event_type, now)
+        }));
+        let (pass, sends) = handled.unwrap_or_else(|_| (true, self.state().reset()));
+        for event_type in &sends {
+            send(event_type);
+        }
+        pass
+    }
+
+    /// Releases the keys we pressed, they could be stuck otherwise if the
+    /// remapping stops while they are held.
+    pub fn release_all(&self) {
+        let releases = self.state().reset();
+        crate::simulate_batch(&releases).ok();
+    }
+
+    /// Remaps with `grab`, simulating the events.
+    ///
+    /// ```no_run
+    /// use rdev::{Mapping, Remapper};
+    ///
+    /// let remapper = Remapper::new(vec![Mapping::from("CapsLock -> Escape".parse().unwrap())]);
+    /// // This will block.
+    /// if let Err(error) = remapper.grab() {
+    ///     println!("Error: {:?}", error)
+    /// }
+    /// ```
+    #[cfg(feature = "unstable_grab")]
+    #[cfg(not(target_os = "freebsd"))]
+    pub fn grab(&self) -> Result<(), crate::rdev::GrabError> {
+        /// Releases our keys when grabbing stops, even by a panic.
+        struct ReleaseAll(Remapper);
+
+        impl Drop for ReleaseAll {
+            fn drop(&mut self) {
+                self.0.release_all();
+            }
+        }
+
+        let _release = ReleaseAll(self.clone());
+        let remapper = self.clone();
+        crate::grab(move |event| {
+            // A chord goes out in one go where the OS allows it, see
+            // `simulate_all`.
+            let mut sends = vec![];
+            let pass = remapper.handle(&event, |event_type| sends.push(*event_type));
+            crate::simulate_batch(&sends).ok();
+            if pass {
+                Some(event)
+            } else {
+                None
+            }
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn press(key: Key) -> EventType {
+        EventType::KeyPress(key)
+    }
+
+    fn release(key: Key) -> EventType {
+        EventType::KeyRelease(key)
+    }
+
+    #[test]
+    fn test_remap() {
+        let mut state = State {
+            mappings: vec![
+                "CapsLock -> ControlLeft".parse::<Remap>().unwrap().into(),
+                "Ctrl+H -> Backspace".parse::<Remap>().unwrap().into(),
+                Mapping::when("KeyQ -> KeyW".parse().unwrap(), || false),
+            ],
+            ..Default::default()
+        };
+        let start = Instant::now();
+        let mut at = 0;
+        let mut handle = |event_type| {
+            // Far enough apart that nothing looks injected.
+            at += 1;
+            state.handle(&event_type, start + at * INJECTED_TIMEOUT)
+        };
+        assert_eq!(
+            handle(press(Key::CapsLock)),
+            (false, vec![press(Key::ControlLeft)])
+        );
+        assert_eq!(
+            handle(press(Key::CapsLock)),
+            (false, vec![press(Key::ControlLeft)])
+        );
+        // Ctrl comes from CapsLock, Backspace has no modifier.
+        assert_eq!(
+            handle(press(Key::KeyH)),
+            (
+                false,
+                vec![release(Key::ControlLeft), press(Key::Backspace)]
+            )
+        );
+        assert_eq!(
+            handle(press(Key::KeyH)),
+            (false, vec![press(Key::Backspace)])
+        );
+        // CapsLock released while the chord is held: Control already is.
+        assert_eq!(handle(release(Key::CapsLock)), (false, vec![]));
+        assert_eq!(
+            handle(release(Key::KeyH)),
+            (false, vec![release(Key::Backspace)])
+        );
+        assert_eq!(handle(press(Key::KeyH)), (true, vec![]));
+        assert_eq!(handle(release(Key::KeyH)), (true, vec![]));
+        // Condition false
+        assert_eq!(handle(press(Key::KeyQ)), (true, vec![]));
+        assert_eq!(handle(release(Key::KeyQ)), (true, vec![]));
+        assert!(state.down.is_empty());
+
+        // Our own events come back.
+        let now = start + 100 * INJECTED_TIMEOUT;
+        assert_eq!(
+            state.handle(&press(Key::CapsLock), now),
+            (false, vec![press(Key::ControlLeft)])
+        );
+        assert_eq!(state.handle(&press(Key::ControlLeft), now), (true, vec![]));
+        assert_eq!(state.reset(), vec![release(Key::ControlLeft)]);
+    }
+
+    #[test]
+    fn test_chord_modifiers() {
+        let mut state = State {
+            mappings: vec!["Alt+KeyJ -> Ctrl+Shift+Tab"
+                .parse::<Remap>()
+                .unwrap()
+                .into()],
+            ..Default::default()
+        };
+        let now = Instant::now();
+        assert_eq!(state.handle(&press(Key::Alt), now), (true, vec![]));
+        assert_eq!(
+            state.handle(&press(Key::KeyJ), now),
+            (
+                false,
+                vec![
+                    release(Key::Alt),
+                    press(Key::ControlLeft),
+                    press(Key::ShiftLeft),
+                    press(Key::Tab)
+                ]
+            )
+        );
+        assert_eq!(
+            state.handle(&release(Key::KeyJ), now + INJECTED_TIMEOUT),
+            (
+                false,
+                vec![
+                    release(Key::Tab),
+                    release(Key::ShiftLeft),
+                    release(Key::ControlLeft),
+                    press(Key::Alt)
+                ]
+            )
+        );
+    }
+}
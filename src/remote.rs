@@ -0,0 +1,245 @@
+//! Forwarding events to other machines over TCP, a building block for
+//! software KVMs. Requires the `remote` feature.
+//! `serve` listens to the local events and sends them to every client it
+//! accepts, `receive` gets them on the other end and `simulate_from`
+//! simulates them there. Clients first send a line
+//! `{"rdev":"remote","token":"..."}`, given to the `authenticate` hook of
+//! the server with the address of the client. The server answers
+//! `{"rdev":"accepted"}` or `{"rdev":"rejected"}`, then sends events as a
+//! `jsonl` stream. Any stream carrying lines, like a WebSocket, can be
+//! bridged with the same protocol. Nothing is encrypted, use a VPN or an SSH
+//! tunnel across untrusted networks.
+//!
+//! ```no_run
+//! use rdev::remote::{serve, simulate_from};
+//! use std::time::Duration;
+//!
+//! // On the machine with the keyboard and mouse, this will block.
+//! serve("0.0.0.0:7999", |token, _| token == "secret").unwrap();
+//! // On the other one, retrying every second when disconnected.
+//! simulate_from("192.168.1.10:7999", "secret", Some(Duration::from_secs(1))).unwrap();
+//! ```
+use crate::jsonl::{EventReader, EventWriter};
+use crate::rdev::{Event, ListenError};
+use serde::{Deserialize, Serialize};
+use std::io::{self, BufRead, BufReader, BufWriter, Write};
+use std::net::{SocketAddr, TcpListener, TcpStream, ToSocketAddrs};
+use std::sync::mpsc::{channel, Sender};
+use std::sync::{Arc, Mutex, PoisonError};
+use std::thread;
+use std::time::Duration;
+
+#[derive(Debug)]
+#[non_exhaustive]
+pub enum RemoteError {
+    IoError(io::Error),
+    ListenError(ListenError),
+    /// The server refused our token.
+    Rejected,
+}
+
+impl From<io::Error> for RemoteError {
+    fn from(error: io::Error) -> RemoteError {
+        RemoteError::IoError(error)
+    }
+}
+
+#[derive(Serialize, Deserialize)]
+struct Handshake {
+    rdev: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    token: Option<String>,
+}
+
+fn write_line<W: Write>(writer: &mut W, rdev: &str, token: Option<&str>) -> io::Result<()> {
+    let handshake = Handshake {
+        rdev: rdev.to_string(),
+        token: token.map(str::to_string),
+    };
+    serde_json::to_writer(&mut *writer, &handshake)?;
+    writer.write_all(b"\n")?;
+    writer.flush()
+}
+
+fn read_line<R: BufRead>(reader: &mut R) -> io::Result<Handshake> {
+    let mut line = String::new();
+    if reader.read_line(&mut line)? == 0 {
+        return Err(io::ErrorKind::UnexpectedEof.into());
+    }
+    Ok(serde_json::from_str(&line)?)
+}
+
+type Clients = Arc<Mutex<Vec<Sender<Event>>>>;
+
+/// Checks the token of a client, then sends it the events until it
+/// disconnects.
+fn serve_client<F>(stream: TcpStream, authenticate: &F, clients: &Clients) -> io::Result<()>
+where
+    F: Fn(&str, SocketAddr) -> bool,
+{
+    let peer = stream.peer_addr()?;
+    stream.set_nodelay(true)?;
+    let handshake = read_line(&mut BufReader::new(stream.try_clone()?))?;
+    let mut writer = BufWriter::new(stream);
+    let token = handshake.token.unwrap_or_default();
+    if handshake.rdev != "remote" || !authenticate(&token, peer) {
+        return write_line(&mut writer, "rejected", None);
+    }
+    write_line(&mut writer, "accepted", None)?;
+    let mut events = EventWriter::new(writer)?;
+    events.flush()?;
+    let (sender, receiver) = channel();
+    clients
+        .lock()
+        .unwrap_or_else(PoisonError::into_inner)
+        .push(sender);
+    for event in receiver {
+        events.write(&event)?;
+        events.flush()?;
+    }
+    Ok(())
+}
+
+fn accept<F>(listener: TcpListener, authenticate: F, clients: Clients)
+where
+    F: Fn(&str, SocketAddr) -> bool + Send + Sync + 'static,
+{
+    let authenticate = Arc::new(authenticate);
+    for stream in listener.incoming().flatten() {
+        let authenticate = authenticate.clone();
+        let clients = clients.clone();
+        thread::spawn(move || serve_client(stream, &*authenticate, &clients));
+    }
+}
+
+/// Sends the events of `listen` to every client accepted on `addr`,
+/// `authenticate` gets their token and address. Runs `listen` on this
+/// thread, so it blocks like it.
+pub fn serve<A, F>(addr: A, authenticate: F) -> Result<(), RemoteError>
+where
+    A: ToSocketAddrs,
+    F: Fn(&str, SocketAddr) -> bool + Send + Sync + 'static,
+{
+    let listener = TcpListener::bind(addr)?;
+    let clients: Clients = Arc::new(Mutex::new(vec![]));
+    let accepted = clients.clone();
+    thread::spawn(move || accept(listener, authenticate, accepted));
+    crate::listen(move |event| {
+        let mut clients = clients.lock().unwrap_or_else(PoisonError::into_inner);
+        // Clients that disconnected dropped their receiver.
+        clients.retain(|client| client.send(event.clone()).is_ok());
+    })
+    .map_err(RemoteError::ListenError)
+}
+
+/// One connection, until the server closes it.
+fn receive_once<A, F>(addr: &A, token: &str, callback: &mut F) -> Result<(), RemoteError>
+where
+    A: ToSocketAddrs,
+    F: FnMut(Event),
+{
+    let stream = TcpStream::connect(addr)?;
+    stream.set_nodelay(true)?;
+    write_line(&mut stream.try_clone()?, "remote", Some(token))?;
+    let mut reader = BufReader::new(stream);
+    if read_line(&mut reader)?.rdev != "accepted" {
+        return Err(RemoteError::Rejected);
+    }
+    for event in EventReader::new(reader)? {
+        callback(event?);
+    }
+    Ok(())
+}
+
+/// Calls `callback` with the events a `serve` server sends. Blocks. With
+/// `reconnect`, connection errors and disconnections are retried after that
+/// delay, forever. A rejected token is never retried.
+pub fn receive<A, F>(
+    addr: A,
+    token: &str,
+    reconnect: Option<Duration>,
+    mut callback: F,
+) -> Result<(), RemoteError>
+where
+    A: ToSocketAddrs,
+    F: FnMut(Event),
+{
+    loop {
+        let received = receive_once(&addr, token, &mut callback);
+        match (received, reconnect) {
+            (Err(RemoteError::Rejected), _) => return Err(RemoteError::Rejected),
+            (received, None) => return received,
+            (_, Some(delay)) => thread::sleep(delay),
+        }
+    }
+}
+
+/// `receive` with the events simulated, those this platform can't simulate
+/// are ignored.
+pub fn simulate_from<A>(
+    addr: A,
+    token: &str,
+    reconnect: Option<Duration>,
+) -> Result<(), RemoteError>
+where
+    A: ToSocketAddrs,
+{
+    receive(addr, token, reconnect, |event| {
+        crate::simulate(&event.event_type).ok();
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::rdev::{EventType, Key};
+    use std::time::SystemTime;
+
+    #[test]
+    fn test_remote() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap();
+        let clients: Clients = Arc::new(Mutex::new(vec![]));
+        let server = clients.clone();
+        thread::spawn(move || {
+            for stream in listener.incoming().take(2) {
+                let authenticate = |token: &str, _: SocketAddr| token == "secret";
+                serve_client(stream.unwrap(), &authenticate, &server).ok();
+            }
+        });
+
+        assert!(matches!(
+            receive(addr, "wrong", Some(Duration::from_millis(10)), |_| ()),
+            Err(RemoteError::Rejected)
+        ));
+        let sender = thread::spawn(move || {
+            let event = Event {
+                event_type: EventType::KeyPress(Key::KeyA),
+                time: SystemTime::now(),
+                name: None,
+                unicode: None,
+                scan_code: 0,
+                display: None,
+                delta: None,
+                numlock: None,
+            };
+            loop {
+                let mut clients = clients.lock().unwrap();
+                if let Some(client) = clients.pop() {
+                    client.send(event).unwrap();
+                    // Dropping the sender closes the connection.
+                    return;
+                }
+                drop(clients);
+                thread::sleep(Duration::from_millis(10));
+            }
+        });
+        let mut received = vec![];
+        receive(addr, "secret", None, |event| {
+            received.push(event.event_type)
+        })
+        .unwrap();
+        sender.join().unwrap();
+        assert_eq!(received, vec![EventType::KeyPress(Key::KeyA)]);
+    }
+}
@@ -0,0 +1,234 @@
+use crate::motion::MoveThrottle;
+use crate::rdev::{Event, ListenError, Settings};
+use std::cell::UnsafeCell;
+use std::mem::MaybeUninit;
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex, PoisonError};
+use std::thread::{self, Thread};
+use std::time::Instant;
+
+/// A fixed size queue for one thread pushing and one popping, without
+/// locks: `head` is only written by the consumer and `tail` by the
+/// producer, the slots between them hold values.
+struct Ring<T> {
+    slots: Box<[UnsafeCell<MaybeUninit<T>>]>,
+    head: AtomicUsize,
+    tail: AtomicUsize,
+}
+
+// Slots are only touched by the side owning them at the time, see `push`
+// and `pop`.
+unsafe impl<T: Send> Sync for Ring<T> {}
+
+impl<T> Drop for Ring<T> {
+    fn drop(&mut self) {
+        let (head, tail) = (*self.head.get_mut(), *self.tail.get_mut());
+        for offset in 0..tail.wrapping_sub(head) {
+            let index = head.wrapping_add(offset) % self.slots.len();
+            let slot = &mut self.slots[index];
+            unsafe { slot.get_mut().as_mut_ptr().drop_in_place() };
+        }
+    }
+}
+
+pub(crate) struct Producer<T> {
+    ring: Arc<Ring<T>>,
+}
+
+pub(crate) struct Consumer<T> {
+    ring: Arc<Ring<T>>,
+}
+
+/// A ring of `capacity` values, at least 1.
+pub(crate) fn ring<T>(capacity: usize) -> (Producer<T>, Consumer<T>) {
+    let slots = (0..capacity.max(1))
+        .map(|_| UnsafeCell::new(MaybeUninit::uninit()))
+        .collect();
+    let ring = Arc::new(Ring {
+        slots,
+        head: AtomicUsize::new(0),
+        tail: AtomicUsize::new(0),
+    });
+    (Producer { ring: ring.clone() }, Consumer { ring })
+}
+
+impl<T> Producer<T> {
+    /// The values waiting to be popped.
+    pub(crate) fn len(&self) -> usize {
+        let ring = &*self.ring;
+        let tail = ring.tail.load(Ordering::Relaxed);
+        tail.wrapping_sub(ring.head.load(Ordering::Acquire))
+    }
+
+    pub(crate) fn capacity(&self) -> usize {
+        self.ring.slots.len()
+    }
+
+    /// Gives `value` back when the ring is full.
+    pub(crate) fn push(&mut self, value: T) -> Result<(), T> {
+        let ring = &*self.ring;
+        let tail = ring.tail.load(Ordering::Relaxed);
+        let head = ring.head.load(Ordering::Acquire);
+        if tail.wrapping_sub(head) == ring.slots.len() {
+            return Err(value);
+        }
+        // The consumer is done with this slot since it moved `head` past it.
+        unsafe {
+            (*ring.slots[tail % ring.slots.len()].get())
+                .as_mut_ptr()
+                .write(value)
+        };
+        ring.tail.store(tail.wrapping_add(1), Ordering::Release);
+        Ok(())
+    }
+}
+
+impl<T> Consumer<T> {
+    pub(crate) fn pop(&mut self) -> Option<T> {
+        let ring = &*self.ring;
+        let head = ring.head.load(Ordering::Relaxed);
+        let tail = ring.tail.load(Ordering::Acquire);
+        if head == tail {
+            return None;
+        }
+        // The producer wrote this slot before moving `tail` past it.
+        let value = unsafe { (*ring.slots[head % ring.slots.len()].get()).as_ptr().read() };
+        ring.head.store(head.wrapping_add(1), Ordering::Release);
+        Some(value)
+    }
+}
+
+type Finished = Arc<(AtomicBool, Mutex<Option<Result<(), ListenError>>>)>;
+
+/// The hook side of `listen_buffered`. Not generic over the callback, a
+/// closure calling `listen_with` from `listen_buffered` would instantiate it
+/// again for every callback type, endlessly.
+fn run_hooks(
+    settings: Settings,
+    mut producer: Producer<Event>,
+    consumer: Thread,
+    finished: Finished,
+) {
+    let woken = consumer.clone();
+    let mut dropped = 0;
+    let listened = crate::listen_with(settings, move |event| {
+        // Full, the callback is behind: the hook can't wait for it.
+        match producer.push(event) {
+            Ok(()) => crate::trace::queued(producer.len()),
+            Err(_) => {
+                dropped += 1;
+                crate::trace::dropped(dropped, producer.capacity());
+            }
+        }
+        // Only wakes the thread up if it's parked.
+        woken.unpark();
+    });
+    *finished.1.lock().unwrap_or_else(PoisonError::into_inner) = Some(listened);
+    finished.0.store(true, Ordering::Release);
+    consumer.unpark();
+}
+
+fn deliver<T: FnMut(Event)>(event: Event, throttle: &mut Option<MoveThrottle>, callback: &mut T) {
+    match throttle {
+        Some(throttle) => throttle.add(event, Instant::now(), callback),
+        None => callback(event),
+    }
+}
+
+/// The size of the ring when only `Settings::mouse_move_max_hz` asks for
+/// one.
+pub(crate) const DEFAULT_CAPACITY: usize = 1024;
+
+/// `listen_with` with `Settings::buffer`: the hooks run on a thread of
+/// their own which only pushes events to the ring, `callback` gets them on
+/// the calling thread. The hook thread never waits on the callback, events
+/// are dropped when the ring is full. Mouse moves go through a
+/// `MoveThrottle` with `max_hz`, waking up for the pending one.
+pub(crate) fn listen_buffered<T>(
+    settings: Settings,
+    capacity: usize,
+    max_hz: Option<u32>,
+    mut callback: T,
+) -> Result<(), ListenError>
+where
+    T: FnMut(Event) + 'static,
+{
+    let (producer, mut consumer) = ring(capacity);
+    let finished: Finished = Arc::new((AtomicBool::new(false), Mutex::new(None)));
+    let (hook_finished, consumer_thread) = (finished.clone(), thread::current());
+    thread::spawn(move || run_hooks(settings, producer, consumer_thread, hook_finished));
+    let mut throttle = max_hz.map(MoveThrottle::new);
+    loop {
+        while let Some(event) = consumer.pop() {
+            deliver(event, &mut throttle, &mut callback);
+        }
+        if finished.0.load(Ordering::Acquire) {
+            while let Some(event) = consumer.pop() {
+                deliver(event, &mut throttle, &mut callback);
+            }
+            if let Some(throttle) = &mut throttle {
+                throttle.flush(Instant::now(), &mut callback);
+            }
+            let listened = finished
+                .1
+                .lock()
+                .unwrap_or_else(PoisonError::into_inner)
+                .take();
+            return listened.unwrap_or(Ok(()));
+        }
+        match throttle
+            .as_mut()
+            .and_then(|throttle| Some((throttle.deadline()?, throttle)))
+        {
+            Some((deadline, throttle)) => {
+                thread::park_timeout(deadline.saturating_duration_since(Instant::now()));
+                throttle.poll(Instant::now(), &mut callback);
+            }
+            None => thread::park(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_ring() {
+        let (mut producer, mut consumer) = ring(2);
+        assert_eq!(consumer.pop(), None);
+        // Wraps around a few times.
+        for i in 0..10 {
+            producer.push(i).unwrap();
+            producer.push(i + 100).unwrap();
+            assert_eq!(producer.push(i + 200), Err(i + 200));
+            assert_eq!(consumer.pop(), Some(i));
+            assert_eq!(consumer.pop(), Some(i + 100));
+            assert_eq!(consumer.pop(), None);
+        }
+        // Values left in the ring are dropped with it.
+        let (mut producer, consumer) = ring(2);
+        let value = Arc::new(());
+        producer.push(value.clone()).ok();
+        assert_eq!(Arc::strong_count(&value), 2);
+        drop((producer, consumer));
+        assert_eq!(Arc::strong_count(&value), 1);
+
+        let (mut producer, mut consumer) = ring(16);
+        let pushing = thread::spawn(move || {
+            for i in 0..10_000 {
+                while producer.push(i).is_err() {
+                    thread::yield_now();
+                }
+            }
+        });
+        let mut expected = 0;
+        while expected < 10_000 {
+            if let Some(i) = consumer.pop() {
+                assert_eq!(i, expected);
+                expected += 1;
+            }
+        }
+        pushing.join().unwrap();
+    }
+}
@@ -0,0 +1,507 @@
+//! A stable serde representation of events, which every later version of
+//! rdev reads the same on every OS. The default representation follows the
+//! structs: `time` is whatever `SystemTime` serializes to on the platform,
+//! and a new field or variant breaks older readers. Here an event is a map:
+//!
+//! ```json
+//! {"version":1,"time":1700000000123,"event_type":{"type":"KeyPress","key":"KeyS"},"name":"s","scan_code":31}
+//! ```
+//!
+//! - `version` is the `VERSION` that wrote it.
+//! - `time` is in milliseconds since the UNIX epoch.
+//! - `event_type` is tagged by `type`, with the `EventType` variant names.
+//!   Keys are named like `Key::from_str`, buttons like their `Display` name
+//!   (`"Left"`, `"Unknown(8)"`), phases `"Begin"`, `"Change"` or `"End"`.
+//!   Gestures are tagged `"Magnify"`, `"Rotate"` and `"Swipe"`.
+//! - `name`, `is_dead` (the dead key state of `unicode`), `scan_code`,
+//!   `display` and `delta` are left out when unset, `unicode` is rebuilt
+//!   from `name` and `is_dead`.
+//!
+//! Fields ever added are optional, readers skip the ones they don't know.
+//! Events of a type, key or phase added later read as a `StableEvent`
+//! without `event`, instead of failing.
+//!
+//! ```no_run
+//! use rdev::schema::StableEvent;
+//! use rdev::{Event, EventType, Key};
+//! use std::time::SystemTime;
+//!
+//! let mut event = Event::new(
+//!     EventType::KeyPress(Key::KeyS),
+//!     SystemTime::now(),
+//!     Some("s".to_string()),
+//! );
+//! event.scan_code = 31;
+//! let serialized = serde_json::to_string(&StableEvent::from(event)).unwrap();
+//! let stable: StableEvent = serde_json::from_str(&serialized).unwrap();
+//! if let Some(event) = stable.event {
+//!     println!("{:?}", event);
+//! }
+//! ```
+//!
+//! The `serialize` and `deserialize` functions work with `#[serde(with = "rdev::schema")]`,
+//! `deserialize` fails on events it doesn't know.
+use crate::rdev::{Event, EventType, Key, Pen, ScrollPhase, UnicodeInfo};
+#[cfg(feature = "gestures")]
+use crate::rdev::{Gesture, GesturePhase};
+use serde::{de, Deserialize, Deserializer, Serialize, Serializer};
+use std::time::{Duration, UNIX_EPOCH};
+
+/// Written in every event, only changes if old readers can't skip what's
+/// new.
+pub const VERSION: u32 = 1;
+
+/// An event read or written with the stable representation.
+#[derive(Debug, Clone, PartialEq)]
+pub struct StableEvent {
+    /// The `VERSION` that wrote it.
+    pub version: u32,
+    /// None for events this version of rdev doesn't know.
+    pub event: Option<Event>,
+}
+
+impl From<Event> for StableEvent {
+    fn from(event: Event) -> StableEvent {
+        StableEvent {
+            version: VERSION,
+            event: Some(event),
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize)]
+struct Wire {
+    version: u32,
+    time: u64,
+    event_type: WireType,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    name: Option<String>,
+    #[serde(default, skip_serializing_if = "is_false")]
+    is_dead: bool,
+    #[serde(default, skip_serializing_if = "is_zero")]
+    scan_code: u32,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    display: Option<usize>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    delta: Option<(f64, f64)>,
+}
+
+fn is_false(value: &bool) -> bool {
+    !value
+}
+
+fn is_zero(value: &u32) -> bool {
+    *value == 0
+}
+
+#[derive(Serialize, Deserialize)]
+#[serde(tag = "type")]
+enum WireType {
+    KeyPress {
+        key: String,
+    },
+    KeyRelease {
+        key: String,
+    },
+    ButtonPress {
+        button: String,
+    },
+    ButtonRelease {
+        button: String,
+    },
+    MouseMove {
+        x: f64,
+        y: f64,
+    },
+    Wheel {
+        delta_x: i64,
+        delta_y: i64,
+    },
+    Scroll {
+        delta_x: f64,
+        delta_y: f64,
+        phase: Phase,
+        #[serde(default)]
+        is_momentum: bool,
+    },
+    LayoutChanged,
+    ImeComposition,
+    ImeCommit,
+    SecureInput {
+        enabled: bool,
+    },
+    TouchDown(Touch),
+    TouchMove(Touch),
+    TouchUp(Touch),
+    PenDown(WirePen),
+    PenMove(WirePen),
+    PenUp(WirePen),
+    Magnify {
+        phase: Phase,
+        delta: f64,
+    },
+    Rotate {
+        phase: Phase,
+        degrees: f64,
+    },
+    Swipe {
+        phase: Phase,
+        delta_x: f64,
+        delta_y: f64,
+    },
+    #[serde(other)]
+    Unknown,
+}
+
+#[derive(Serialize, Deserialize)]
+enum Phase {
+    Begin,
+    Change,
+    End,
+    #[serde(other)]
+    Unknown,
+}
+
+#[derive(Serialize, Deserialize)]
+struct Touch {
+    id: u64,
+    x: f64,
+    y: f64,
+    #[serde(default = "full_pressure")]
+    pressure: f64,
+}
+
+fn full_pressure() -> f64 {
+    1.0
+}
+
+#[derive(Serialize, Deserialize)]
+struct WirePen {
+    x: f64,
+    y: f64,
+    #[serde(default)]
+    pressure: f64,
+    #[serde(default)]
+    tilt_x: f64,
+    #[serde(default)]
+    tilt_y: f64,
+    #[serde(default)]
+    eraser: bool,
+}
+
+impl From<&ScrollPhase> for Phase {
+    fn from(phase: &ScrollPhase) -> Phase {
+        match phase {
+            ScrollPhase::Begin => Phase::Begin,
+            ScrollPhase::Change => Phase::Change,
+            ScrollPhase::End => Phase::End,
+        }
+    }
+}
+
+impl Phase {
+    fn scroll(&self) -> Option<ScrollPhase> {
+        match self {
+            Phase::Begin => Some(ScrollPhase::Begin),
+            Phase::Change => Some(ScrollPhase::Change),
+            Phase::End => Some(ScrollPhase::End),
+            Phase::Unknown => None,
+        }
+    }
+
+    #[cfg(feature = "gestures")]
+    fn gesture(&self) -> Option<GesturePhase> {
+        match self {
+            Phase::Begin => Some(GesturePhase::Begin),
+            Phase::Change => Some(GesturePhase::Change),
+            Phase::End => Some(GesturePhase::End),
+            Phase::Unknown => None,
+        }
+    }
+}
+
+#[cfg(feature = "gestures")]
+impl From<&GesturePhase> for Phase {
+    fn from(phase: &GesturePhase) -> Phase {
+        match phase {
+            GesturePhase::Begin => Phase::Begin,
+            GesturePhase::Change => Phase::Change,
+            GesturePhase::End => Phase::End,
+        }
+    }
+}
+
+impl From<&Pen> for WirePen {
+    fn from(pen: &Pen) -> WirePen {
+        WirePen {
+            x: pen.x,
+            y: pen.y,
+            pressure: pen.pressure,
+            tilt_x: pen.tilt_x,
+            tilt_y: pen.tilt_y,
+            eraser: pen.eraser,
+        }
+    }
+}
+
+impl From<WirePen> for Pen {
+    fn from(pen: WirePen) -> Pen {
+        Pen {
+            x: pen.x,
+            y: pen.y,
+            pressure: pen.pressure,
+            tilt_x: pen.tilt_x,
+            tilt_y: pen.tilt_y,
+            eraser: pen.eraser,
+        }
+    }
+}
+
+fn touch(id: u64, x: f64, y: f64, pressure: f64) -> Touch {
+    Touch { id, x, y, pressure }
+}
+
+impl From<&EventType> for WireType {
+    fn from(event_type: &EventType) -> WireType {
+        match *event_type {
+            EventType::KeyPress(key) => WireType::KeyPress {
+                key: key.to_string(),
+            },
+            EventType::KeyRelease(key) => WireType::KeyRelease {
+                key: key.to_string(),
+            },
+            EventType::ButtonPress(button) => WireType::ButtonPress {
+                button: button.to_string(),
+            },
+            EventType::ButtonRelease(button) => WireType::ButtonRelease {
+                button: button.to_string(),
+            },
+            EventType::MouseMove { x, y } => WireType::MouseMove { x, y },
+            EventType::Wheel { delta_x, delta_y } => WireType::Wheel { delta_x, delta_y },
+            EventType::Scroll {
+                delta_x,
+                delta_y,
+                ref phase,
+                is_momentum,
+            } => WireType::Scroll {
+                delta_x,
+                delta_y,
+                phase: phase.into(),
+                is_momentum,
+            },
+            EventType::LayoutChanged => WireType::LayoutChanged,
+            EventType::ImeComposition => WireType::ImeComposition,
+            EventType::ImeCommit => WireType::ImeCommit,
+            EventType::SecureInput(enabled) => WireType::SecureInput { enabled },
+            EventType::TouchDown { id, x, y, pressure } => {
+                WireType::TouchDown(touch(id, x, y, pressure))
+            }
+            EventType::TouchMove { id, x, y, pressure } => {
+                WireType::TouchMove(touch(id, x, y, pressure))
+            }
+            EventType::TouchUp { id, x, y, pressure } => {
+                WireType::TouchUp(touch(id, x, y, pressure))
+            }
+            EventType::PenDown(ref pen) => WireType::PenDown(pen.into()),
+            EventType::PenMove(ref pen) => WireType::PenMove(pen.into()),
+            EventType::PenUp(ref pen) => WireType::PenUp(pen.into()),
+            #[cfg(feature = "gestures")]
+            EventType::Gesture(Gesture::Magnify { ref phase, delta }) => WireType::Magnify {
+                phase: phase.into(),
+                delta,
+            },
+            #[cfg(feature = "gestures")]
+            EventType::Gesture(Gesture::Rotate { ref phase, degrees }) => WireType::Rotate {
+                phase: phase.into(),
+                degrees,
+            },
+            #[cfg(feature = "gestures")]
+            EventType::Gesture(Gesture::Swipe {
+                ref phase,
+                delta_x,
+                delta_y,
+            }) => WireType::Swipe {
+                phase: phase.into(),
+                delta_x,
+                delta_y,
+            },
+        }
+    }
+}
+
+impl WireType {
+    /// None for what this version doesn't know.
+    fn event_type(self) -> Option<EventType> {
+        let key = |key: &str| key.parse::<Key>().ok();
+        Some(match self {
+            WireType::KeyPress { key: name } => EventType::KeyPress(key(&name)?),
+            WireType::KeyRelease { key: name } => EventType::KeyRelease(key(&name)?),
+            WireType::ButtonPress { button } => EventType::ButtonPress(button.parse().ok()?),
+            WireType::ButtonRelease { button } => EventType::ButtonRelease(button.parse().ok()?),
+            WireType::MouseMove { x, y } => EventType::MouseMove { x, y },
+            WireType::Wheel { delta_x, delta_y } => EventType::Wheel { delta_x, delta_y },
+            WireType::Scroll {
+                delta_x,
+                delta_y,
+                phase,
+                is_momentum,
+            } => EventType::Scroll {
+                delta_x,
+                delta_y,
+                phase: phase.scroll()?,
+                is_momentum,
+            },
+            WireType::LayoutChanged => EventType::LayoutChanged,
+            WireType::ImeComposition => EventType::ImeComposition,
+            WireType::ImeCommit => EventType::ImeCommit,
+            WireType::SecureInput { enabled } => EventType::SecureInput(enabled),
+            WireType::TouchDown(Touch { id, x, y, pressure }) => {
+                EventType::TouchDown { id, x, y, pressure }
+            }
+            WireType::TouchMove(Touch { id, x, y, pressure }) => {
+                EventType::TouchMove { id, x, y, pressure }
+            }
+            WireType::TouchUp(Touch { id, x, y, pressure }) => {
+                EventType::TouchUp { id, x, y, pressure }
+            }
+            WireType::PenDown(pen) => EventType::PenDown(pen.into()),
+            WireType::PenMove(pen) => EventType::PenMove(pen.into()),
+            WireType::PenUp(pen) => EventType::PenUp(pen.into()),
+            #[cfg(feature = "gestures")]
+            WireType::Magnify { phase, delta } => EventType::Gesture(Gesture::Magnify {
+                phase: phase.gesture()?,
+                delta,
+            }),
+            #[cfg(feature = "gestures")]
+            WireType::Rotate { phase, degrees } => EventType::Gesture(Gesture::Rotate {
+                phase: phase.gesture()?,
+                degrees,
+            }),
+            #[cfg(feature = "gestures")]
+            WireType::Swipe {
+                phase,
+                delta_x,
+                delta_y,
+            } => EventType::Gesture(Gesture::Swipe {
+                phase: phase.gesture()?,
+                delta_x,
+                delta_y,
+            }),
+            // `Unknown`, and gestures without the `gestures` feature.
+            _ => return None,
+        })
+    }
+}
+
+fn wire(version: u32, event: &Event) -> Wire {
+    Wire {
+        version,
+        time: event
+            .time
+            .duration_since(UNIX_EPOCH)
+            .map(|d| d.as_millis() as u64)
+            .unwrap_or(0),
+        event_type: (&event.event_type).into(),
+        name: event.name.clone(),
+        is_dead: matches!(event.unicode, Some(UnicodeInfo { is_dead: true, .. })),
+        scan_code: event.scan_code,
+        display: event.display,
+        delta: event.delta,
+    }
+}
+
+impl From<Wire> for StableEvent {
+    fn from(wire: Wire) -> StableEvent {
+        let Wire {
+            version,
+            time,
+            event_type,
+            name,
+            is_dead,
+            scan_code,
+            display,
+            delta,
+        } = wire;
+        let event = event_type.event_type().map(|event_type| Event {
+            time: UNIX_EPOCH + Duration::from_millis(time),
+            unicode: UnicodeInfo::new(name.as_deref(), is_dead),
+            name,
+            event_type,
+            scan_code,
+            display,
+            delta,
+            numlock: None,
+        });
+        StableEvent { version, event }
+    }
+}
+
+/// Events without `event` can't be written, there's nothing to write.
+impl Serialize for StableEvent {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        match &self.event {
+            Some(event) => wire(self.version, event).serialize(serializer),
+            None => Err(serde::ser::Error::custom("unknown event")),
+        }
+    }
+}
+
+impl<'de> Deserialize<'de> for StableEvent {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<StableEvent, D::Error> {
+        Wire::deserialize(deserializer).map(StableEvent::from)
+    }
+}
+
+pub fn serialize<S: Serializer>(event: &Event, serializer: S) -> Result<S::Ok, S::Error> {
+    wire(VERSION, event).serialize(serializer)
+}
+
+pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Event, D::Error> {
+    let StableEvent { version, event } = StableEvent::deserialize(deserializer)?;
+    event.ok_or_else(|| {
+        de::Error::custom(format!(
+            "event of schema version {} unknown to version {}",
+            version, VERSION
+        ))
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_schema() {
+        let mut event = Event::new(
+            EventType::KeyPress(Key::KeyS),
+            UNIX_EPOCH + Duration::from_millis(1_700_000_000_123),
+            Some("s".to_string()),
+        );
+        event.unicode = UnicodeInfo::new(Some("s"), false);
+        event.scan_code = 31;
+        let serialized = serde_json::to_string(&StableEvent::from(event.clone())).unwrap();
+        assert_eq!(
+            serialized,
+            r#"{"version":1,"time":1700000000123,"event_type":{"type":"KeyPress","key":"KeyS"},"name":"s","scan_code":31}"#
+        );
+        let stable: StableEvent = serde_json::from_str(&serialized).unwrap();
+        assert_eq!(stable.event, Some(event));
+        // What a later version could write.
+        let newer = r#"{"version":2,"time":5,"event_type":{"type":"Hover","x":1.0},"new":true}"#;
+        let stable: StableEvent = serde_json::from_str(newer).unwrap();
+        assert_eq!(stable.version, 2);
+        assert_eq!(stable.event, None);
+        let newer = r#"{"version":2,"time":5,"event_type":{"type":"KeyPress","key":"KeyNew"}}"#;
+        assert_eq!(
+            serde_json::from_str::<StableEvent>(newer).unwrap().event,
+            None
+        );
+        let moved = r#"{"version":1,"time":5,"event_type":{"type":"MouseMove","x":1.0,"y":2.0,"extra":0},"delta":[3.0,0.0]}"#;
+        let event = serde_json::from_str::<StableEvent>(moved)
+            .unwrap()
+            .event
+            .unwrap();
+        assert_eq!(event.event_type, EventType::MouseMove { x: 1.0, y: 2.0 });
+        assert_eq!(event.delta, Some((3.0, 0.0)));
+    }
+}
@@ -0,0 +1,144 @@
+use crate::rdev::{EventType, ScrollPhase, SimulateError};
+use std::thread;
+use std::time::Duration;
+
+/// Each momentum event scrolls this much of the one before.
+const MOMENTUM_DECAY: f64 = 0.7;
+
+/// How `simulate_smooth_scroll` spreads a scroll over time, like a
+/// trackpad does: the gesture in `steps` events over `duration`, then as
+/// many decaying momentum events.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct SmoothScroll {
+    pub duration: Duration,
+    pub steps: u32,
+    pub momentum: bool,
+}
+
+impl Default for SmoothScroll {
+    fn default() -> SmoothScroll {
+        SmoothScroll {
+            duration: Duration::from_millis(150),
+            steps: 10,
+            momentum: true,
+        }
+    }
+}
+
+fn scroll_event(delta: (f64, f64), phase: ScrollPhase, is_momentum: bool) -> EventType {
+    EventType::Scroll {
+        delta_x: delta.0,
+        delta_y: delta.1,
+        phase,
+        is_momentum,
+    }
+}
+
+/// The `Scroll` events of a smooth scroll by `delta_x` and `delta_y`
+/// pixels: a Begin and Changes, an End without delta, then the same for
+/// the momentum. Deltas are whole pixels adding up to the rounded totals.
+pub fn smooth_scroll_events(delta_x: f64, delta_y: f64, scroll: &SmoothScroll) -> Vec<EventType> {
+    let steps = scroll.steps.max(1) as usize;
+    let mut weights = vec![1.0; steps];
+    if scroll.momentum {
+        weights.extend((1..=steps).map(|i| MOMENTUM_DECAY.powi(i as i32)));
+    }
+    let total: f64 = weights.iter().sum();
+    let (mut sent_x, mut sent_y, mut sum) = (0.0, 0.0, 0.0);
+    let deltas: Vec<(f64, f64)> = weights
+        .iter()
+        .map(|weight| {
+            sum += weight;
+            let x = (delta_x * sum / total).round() - sent_x;
+            let y = (delta_y * sum / total).round() - sent_y;
+            sent_x += x;
+            sent_y += y;
+            (x, y)
+        })
+        .collect();
+
+    let mut events = vec![];
+    for (is_momentum, part) in deltas.chunks(steps).enumerate() {
+        let is_momentum = is_momentum > 0;
+        for (i, delta) in part.iter().enumerate() {
+            let phase = if i == 0 {
+                ScrollPhase::Begin
+            } else {
+                ScrollPhase::Change
+            };
+            events.push(scroll_event(*delta, phase, is_momentum));
+        }
+        events.push(scroll_event((0.0, 0.0), ScrollPhase::End, is_momentum));
+    }
+    events
+}
+
+/// Scrolls by `delta_x` and `delta_y` pixels the way a trackpad does,
+/// blocking for about twice `scroll.duration` with momentum. MacOS apps
+/// get the phases and momentum, and scroll as smoothly as with a trackpad
+/// instead of by lines, Windows gets wheel deltas in fractions of a notch
+/// like precision touchpads send. Linux can't simulate scrolls.
+///
+/// ```no_run
+/// use rdev::{simulate_smooth_scroll, SmoothScroll};
+///
+/// // 300 pixels down.
+/// simulate_smooth_scroll(0.0, -300.0, SmoothScroll::default()).unwrap();
+/// ```
+pub fn simulate_smooth_scroll(
+    delta_x: f64,
+    delta_y: f64,
+    scroll: SmoothScroll,
+) -> Result<(), SimulateError> {
+    let gap = scroll.duration / scroll.steps.max(1);
+    for event_type in smooth_scroll_events(delta_x, delta_y, &scroll) {
+        crate::simulate(&event_type)?;
+        thread::sleep(gap);
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_smooth_scroll_events() {
+        let scroll = SmoothScroll {
+            steps: 4,
+            ..Default::default()
+        };
+        let events = smooth_scroll_events(10.0, -301.0, &scroll);
+        assert_eq!(events.len(), 10);
+        let (mut x, mut y) = (0.0, 0.0);
+        for event in &events {
+            if let EventType::Scroll {
+                delta_x, delta_y, ..
+            } = event
+            {
+                assert_eq!(delta_y.fract(), 0.0);
+                x += delta_x;
+                y += delta_y;
+            }
+        }
+        assert_eq!((x, y), (10.0, -301.0));
+        assert_eq!(
+            events[0],
+            scroll_event((2.0, -52.0), ScrollPhase::Begin, false)
+        );
+        assert_eq!(events[4], scroll_event((0.0, 0.0), ScrollPhase::End, false));
+        assert!(matches!(
+            events[5],
+            EventType::Scroll {
+                phase: ScrollPhase::Begin,
+                is_momentum: true,
+                ..
+            }
+        ));
+        let without = SmoothScroll {
+            momentum: false,
+            ..scroll
+        };
+        assert_eq!(smooth_scroll_events(0.0, 1.0, &without).len(), 5);
+    }
+}
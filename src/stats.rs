@@ -0,0 +1,224 @@
+//! Aggregate metrics of the input, for typing analytics and the like without
+//! storing keystrokes. Requires the `stats` feature.
+//! `StatsCollector` counts what it's given, `listen` feeds it from
+//! `crate::listen` and hands out a snapshot of every period.
+//!
+//! ```no_run
+//! use rdev::stats::listen;
+//! use std::time::Duration;
+//!
+//! // Every minute, idle after 30 seconds without input. This will block.
+//! listen(Duration::from_secs(60), Duration::from_secs(30), |stats| {
+//!     println!("{} keys, {:.0} pixels", stats.key_presses(), stats.distance);
+//! })
+//! .unwrap();
+//! ```
+use crate::rdev::{Button, Event, EventType, Key, ListenError};
+use std::collections::{HashMap, HashSet};
+use std::sync::{Arc, Mutex, PoisonError};
+use std::thread;
+use std::time::{Duration, SystemTime};
+
+/// Metrics over a period.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Stats {
+    pub start: SystemTime,
+    pub end: SystemTime,
+    /// Presses of each key, without autorepeat.
+    pub keys: HashMap<Key, u64>,
+    pub clicks: HashMap<Button, u64>,
+    /// In the coordinates of `MouseMove` events.
+    pub distance: f64,
+    /// Wheel events.
+    pub scrolls: u64,
+    /// Time within the idle threshold of some input, the rest is `idle`.
+    pub active: Duration,
+    pub idle: Duration,
+}
+
+impl Stats {
+    fn new(start: SystemTime) -> Stats {
+        Stats {
+            start,
+            end: start,
+            keys: HashMap::new(),
+            clicks: HashMap::new(),
+            distance: 0.0,
+            scrolls: 0,
+            active: Duration::from_secs(0),
+            idle: Duration::from_secs(0),
+        }
+    }
+
+    pub fn key_presses(&self) -> u64 {
+        self.keys.values().sum()
+    }
+
+    pub fn total_clicks(&self) -> u64 {
+        self.clicks.values().sum()
+    }
+}
+
+fn since(later: SystemTime, earlier: SystemTime) -> Duration {
+    later.duration_since(earlier).unwrap_or_default()
+}
+
+/// Counts events into `Stats`, the time of events is theirs so recorded
+/// streams can be fed too.
+pub struct StatsCollector {
+    idle_threshold: Duration,
+    stats: Stats,
+    last_input: Option<SystemTime>,
+    position: Option<(f64, f64)>,
+    held: HashSet<Key>,
+}
+
+impl StatsCollector {
+    /// Time more than `idle_threshold` after the last input is idle.
+    pub fn new(idle_threshold: Duration, start: SystemTime) -> StatsCollector {
+        StatsCollector {
+            idle_threshold,
+            stats: Stats::new(start),
+            last_input: None,
+            position: None,
+            held: HashSet::new(),
+        }
+    }
+
+    /// Counts the time until `time` as active or idle.
+    fn advance(&mut self, time: SystemTime) {
+        let from = self.stats.end;
+        if time <= from {
+            return;
+        }
+        let active_until = self
+            .last_input
+            .map(|last| last + self.idle_threshold)
+            .unwrap_or(from);
+        let active = since(active_until.min(time), from);
+        self.stats.active += active;
+        self.stats.idle += since(time, from) - active;
+        self.stats.end = time;
+    }
+
+    pub fn add(&mut self, event: &Event) {
+        self.advance(event.time);
+        match event.event_type {
+            EventType::KeyPress(key) => {
+                if self.held.insert(key) {
+                    *self.stats.keys.entry(key).or_insert(0) += 1;
+                }
+            }
+            EventType::KeyRelease(key) => {
+                self.held.remove(&key);
+            }
+            EventType::ButtonPress(button) => {
+                *self.stats.clicks.entry(button).or_insert(0) += 1;
+            }
+            EventType::ButtonRelease(_) => (),
+            EventType::MouseMove { x, y, .. } => {
+                if let Some((last_x, last_y)) = self.position {
+                    self.stats.distance += (x - last_x).hypot(y - last_y);
+                }
+                self.position = Some((x, y));
+            }
+            EventType::Wheel { .. } => self.stats.scrolls += 1,
+            // Not input.
+            _ => return,
+        }
+        self.last_input = Some(
+            self.last_input
+                .map_or(event.time, |last| last.max(event.time)),
+        );
+    }
+
+    /// The metrics so far.
+    pub fn stats(&self) -> &Stats {
+        &self.stats
+    }
+
+    /// The metrics until `time`, and starts a new period there.
+    pub fn take(&mut self, time: SystemTime) -> Stats {
+        self.advance(time);
+        let end = self.stats.end;
+        std::mem::replace(&mut self.stats, Stats::new(end))
+    }
+}
+
+/// Collects the events of `listen`, calling `callback` from another thread
+/// with the stats of every `period`. Blocks.
+pub fn listen<F>(
+    period: Duration,
+    idle_threshold: Duration,
+    mut callback: F,
+) -> Result<(), ListenError>
+where
+    F: FnMut(Stats) + Send + 'static,
+{
+    let collector = Arc::new(Mutex::new(StatsCollector::new(
+        idle_threshold,
+        SystemTime::now(),
+    )));
+    let periodic = collector.clone();
+    thread::spawn(move || loop {
+        thread::sleep(period);
+        let stats = periodic
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .take(SystemTime::now());
+        callback(stats);
+    });
+    crate::listen(move |event| {
+        collector
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .add(&event)
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::UNIX_EPOCH;
+
+    #[test]
+    fn test_stats() {
+        let second = Duration::from_secs(1);
+        let start = UNIX_EPOCH + 1000 * second;
+        let mut collector = StatsCollector::new(10 * second, start);
+        let event = |event_type, at: u32| Event {
+            event_type,
+            time: start + at * second,
+            name: None,
+            unicode: None,
+            scan_code: 0,
+            display: None,
+            delta: None,
+            numlock: None,
+        };
+        let events = [
+            event(EventType::KeyPress(Key::KeyA), 2),
+            event(EventType::KeyPress(Key::KeyA), 3),
+            event(EventType::KeyRelease(Key::KeyA), 4),
+            event(EventType::KeyPress(Key::KeyA), 5),
+            event(EventType::ButtonPress(Button::Left), 6),
+            event(EventType::MouseMove { x: 0.0, y: 0.0 }, 7),
+            event(EventType::MouseMove { x: 3.0, y: 4.0 }, 8),
+        ];
+        for event in &events {
+            collector.add(event);
+        }
+        let stats = collector.take(start + 30 * second);
+        assert_eq!(stats.keys.get(&Key::KeyA), Some(&2));
+        assert_eq!(stats.total_clicks(), 1);
+        assert_eq!(stats.distance, 5.0);
+        // Idle until the first key, and from 8 + 10 seconds.
+        assert_eq!(stats.active, 16 * second);
+        assert_eq!(stats.idle, 14 * second);
+        assert_eq!(stats.start, start);
+
+        let stats = collector.take(start + 40 * second);
+        assert_eq!(stats.key_presses(), 0);
+        assert_eq!(stats.idle, 10 * second);
+    }
+}
@@ -0,0 +1,215 @@
+use crate::rdev::{Button, Event, EventType, ListenError};
+use std::f64::consts::PI;
+
+/// A straight part of a stroke, y grows downwards like `MouseMove`.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub enum Direction {
+    Up,
+    Down,
+    Left,
+    Right,
+    /// Only with `StrokeSettings::diagonals`.
+    UpLeft,
+    UpRight,
+    DownLeft,
+    DownRight,
+}
+
+impl Direction {
+    /// The direction of a move, in 4 sectors of the circle or 8 with
+    /// diagonals.
+    fn of(dx: f64, dy: f64, diagonals: bool) -> Direction {
+        // Counterclockwise from Right.
+        let angle = (-dy).atan2(dx);
+        let sectors = if diagonals { 8 } else { 4 };
+        let sector = ((angle / (2.0 * PI / sectors as f64)).round() as i64).rem_euclid(sectors);
+        let eighth = sector * (8 / sectors);
+        match eighth {
+            0 => Direction::Right,
+            1 => Direction::UpRight,
+            2 => Direction::Up,
+            3 => Direction::UpLeft,
+            4 => Direction::Left,
+            5 => Direction::DownLeft,
+            6 => Direction::Down,
+            _ => Direction::DownRight,
+        }
+    }
+}
+
+/// How strokes are cut into directions.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct StrokeSettings {
+    /// Strokes are drawn with this button held.
+    pub button: Button,
+    /// The grid size, moves shorter than this are ignored, in the
+    /// coordinates of `MouseMove` events.
+    pub threshold: f64,
+    /// Recognizes diagonal directions too.
+    pub diagonals: bool,
+}
+
+impl Default for StrokeSettings {
+    fn default() -> StrokeSettings {
+        StrokeSettings {
+            button: Button::Right,
+            threshold: 30.0,
+            diagonals: false,
+        }
+    }
+}
+
+/// A stroke matching a registered gesture.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct StrokeGesture {
+    pub name: String,
+    pub directions: Vec<Direction>,
+}
+
+/// Recognizes mouse gestures: directions drawn with a button held, like
+/// "right then down". Strokes matching a gesture added with `add_gesture`
+/// are returned by `add` when the button is released. Suppressing the
+/// button, like the context menu of a right click, is left to a `grab`
+/// callback.
+///
+/// ```no_run
+/// use rdev::{Direction, StrokeRecognizer, StrokeSettings};
+///
+/// let mut recognizer = StrokeRecognizer::new(StrokeSettings::default());
+/// recognizer.add_gesture("close tab", &[Direction::Down, Direction::Right]);
+/// // This will block.
+/// recognizer
+///     .listen(|gesture| println!("{}", gesture.name))
+///     .unwrap();
+/// ```
+pub struct StrokeRecognizer {
+    settings: StrokeSettings,
+    gestures: Vec<StrokeGesture>,
+    position: (f64, f64),
+    /// Where the current direction started, while the button is held.
+    anchor: Option<(f64, f64)>,
+    directions: Vec<Direction>,
+}
+
+impl StrokeRecognizer {
+    pub fn new(settings: StrokeSettings) -> StrokeRecognizer {
+        StrokeRecognizer {
+            settings,
+            gestures: vec![],
+            position: (0.0, 0.0),
+            anchor: None,
+            directions: vec![],
+        }
+    }
+
+    /// Replaces a gesture with the same directions.
+    pub fn add_gesture(&mut self, name: &str, directions: &[Direction]) {
+        self.gestures
+            .retain(|gesture| gesture.directions != directions);
+        self.gestures.push(StrokeGesture {
+            name: name.to_string(),
+            directions: directions.to_vec(),
+        });
+    }
+
+    pub fn remove_gesture(&mut self, name: &str) {
+        self.gestures.retain(|gesture| gesture.name != name);
+    }
+
+    /// The directions of the stroke being drawn.
+    pub fn directions(&self) -> &[Direction] {
+        &self.directions
+    }
+
+    pub fn add(&mut self, event: &Event) -> Option<StrokeGesture> {
+        match event.event_type {
+            EventType::ButtonPress(button) if button == self.settings.button => {
+                self.anchor = Some(self.position);
+                self.directions.clear();
+            }
+            EventType::ButtonRelease(button) if button == self.settings.button => {
+                self.anchor.take()?;
+                let directions = std::mem::take(&mut self.directions);
+                return self
+                    .gestures
+                    .iter()
+                    .find(|gesture| gesture.directions == directions)
+                    .cloned();
+            }
+            EventType::MouseMove { x, y, .. } => {
+                self.position = (x, y);
+                let anchor = self.anchor?;
+                let (dx, dy) = (x - anchor.0, y - anchor.1);
+                if dx.hypot(dy) < self.settings.threshold {
+                    return None;
+                }
+                let direction = Direction::of(dx, dy, self.settings.diagonals);
+                if self.directions.last() != Some(&direction) {
+                    self.directions.push(direction);
+                }
+                self.anchor = Some(self.position);
+            }
+            _ => (),
+        }
+        None
+    }
+
+    /// Recognizes gestures in the events of `listen`. Blocks.
+    pub fn listen<F>(mut self, mut callback: F) -> Result<(), ListenError>
+    where
+        F: FnMut(StrokeGesture) + 'static,
+    {
+        crate::listen(move |event| {
+            if let Some(gesture) = self.add(&event) {
+                callback(gesture);
+            }
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::SystemTime;
+
+    #[test]
+    fn test_strokes() {
+        let mut recognizer = StrokeRecognizer::new(StrokeSettings::default());
+        recognizer.add_gesture("close tab", &[Direction::Down, Direction::Right]);
+        let mut add = |event_type| {
+            recognizer.add(&Event {
+                event_type,
+                time: SystemTime::now(),
+                name: None,
+                unicode: None,
+                scan_code: 0,
+                display: None,
+                delta: None,
+                numlock: None,
+            })
+        };
+        let mut draw = |points: &[(f64, f64)]| {
+            add(EventType::ButtonPress(Button::Right));
+            for (x, y) in points {
+                add(EventType::MouseMove { x: *x, y: *y });
+            }
+            add(EventType::ButtonRelease(Button::Right))
+        };
+        // Wobbly, with moves below the threshold.
+        let gesture = draw(&[
+            (0.0, 0.0),
+            (3.0, 20.0),
+            (5.0, 40.0),
+            (5.0, 80.0),
+            (60.0, 85.0),
+        ]);
+        assert_eq!(
+            gesture.map(|gesture| gesture.name),
+            Some("close tab".to_string())
+        );
+        assert_eq!(draw(&[(60.0, 85.0), (60.0, 20.0)]), None);
+
+        assert_eq!(Direction::of(10.0, -10.0, true), Direction::UpRight);
+        assert_eq!(Direction::of(-10.0, 2.0, false), Direction::Left);
+    }
+}
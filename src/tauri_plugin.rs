@@ -0,0 +1,116 @@
+//! A Tauri plugin, requires the `tauri` feature. Events of `listen` are
+//! emitted to every window as `rdev://event`, serialized like with the
+//! `serialize` feature, and registered hotkeys as `rdev://hotkey` with their
+//! id. The commands are `plugin:rdev|simulate`, `plugin:rdev|display_size`,
+//! `plugin:rdev|register_hotkey` and `plugin:rdev|unregister_hotkey`.
+//!
+//! ```ignore
+//! tauri::Builder::default()
+//!     .plugin(rdev::tauri_plugin::init())
+//!     .run(tauri::generate_context!())
+//!     .unwrap();
+//! ```
+//!
+//! ```js
+//! import { invoke } from "@tauri-apps/api/tauri";
+//! import { listen } from "@tauri-apps/api/event";
+//!
+//! await listen("rdev://event", (event) => console.log(event.payload.event_type));
+//! const id = await invoke("plugin:rdev|register_hotkey", { hotkey: "Ctrl+Shift+P" });
+//! await listen("rdev://hotkey", (event) => event.payload === id && openPalette());
+//! await invoke("plugin:rdev|simulate", { eventType: { KeyPress: "KeyA" } });
+//! ```
+//!
+//! On macOS the main thread belongs to the webview's event loop, so events
+//! are listened to on a thread of their own, with its own run loop, started
+//! when the plugin is set up. Listening fails without the Accessibility
+//! permission, see `permissions`.
+use crate::hotkey::{HotkeyId, HotkeyManager, KeySequence};
+use crate::rdev::EventType;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+use std::thread;
+use tauri::plugin::{Builder, TauriPlugin};
+use tauri::{AppHandle, Manager, Runtime, State};
+
+pub const EVENT: &str = "rdev://event";
+pub const HOTKEY: &str = "rdev://hotkey";
+/// Emitted once, with the error, when listening fails.
+pub const LISTEN_ERROR: &str = "rdev://listen-error";
+
+struct Hotkeys(HotkeyManager);
+
+#[tauri::command]
+fn simulate(event_type: EventType) -> Result<(), String> {
+    crate::simulate(&event_type).map_err(|error| error.to_string())
+}
+
+#[tauri::command]
+fn display_size() -> Result<(u64, u64), String> {
+    crate::display_size().map_err(|error| error.to_string())
+}
+
+/// Unsuppressed, other apps see the hotkey too.
+#[tauri::command]
+fn register_hotkey<R: Runtime>(
+    app: AppHandle<R>,
+    hotkeys: State<'_, Hotkeys>,
+    hotkey: String,
+) -> Result<u64, String> {
+    let sequence: KeySequence = hotkey
+        .parse()
+        .map_err(|_| format!("invalid hotkey {:?}", hotkey))?;
+    // Known once registered, before that the hotkey is 0.
+    let fired = Arc::new(AtomicU64::new(0));
+    let id = fired.clone();
+    let registered = hotkeys
+        .0
+        .register_sequence(sequence, false, move || {
+            let _ = app.emit_all(HOTKEY, id.load(Ordering::SeqCst));
+        })
+        .map_err(|error| format!("{:?}", error))?;
+    fired.store(registered.0, Ordering::SeqCst);
+    Ok(registered.0)
+}
+
+#[tauri::command]
+fn unregister_hotkey(hotkeys: State<'_, Hotkeys>, id: u64) -> Result<(), String> {
+    hotkeys
+        .0
+        .unregister(HotkeyId(id))
+        .map_err(|error| format!("{:?}", error))
+}
+
+pub fn init<R: Runtime>() -> TauriPlugin<R> {
+    Builder::new("rdev")
+        .invoke_handler(tauri::generate_handler![
+            simulate,
+            display_size,
+            register_hotkey,
+            unregister_hotkey
+        ])
+        .setup(|app| {
+            let manager = HotkeyManager::new();
+            app.manage(Hotkeys(manager.clone()));
+            let app = app.clone();
+            thread::spawn(move || {
+                let emitter = app.clone();
+                let listened = crate::listen(move |event| {
+                    manager.handle(&event);
+                    let _ = emitter.emit_all(EVENT, event);
+                });
+                if let Err(error) = listened {
+                    let _ = app.emit_all(LISTEN_ERROR, error.to_string());
+                }
+            });
+            Ok(())
+        })
+        .build()
+}
+
+/// TypeScript declarations of the payloads and arguments, for `bindings.ts`
+/// files, with the `typescript` feature. Same as `typescript_bindings`.
+#[cfg(feature = "typescript")]
+pub fn typescript() -> Result<String, specta::ts::TsExportError> {
+    crate::typescript_bindings()
+}
@@ -0,0 +1,92 @@
+use crate::rdev::{DisplayEvent, DisplayInfo};
+
+/// Turns successive monitor lists into `DisplayEvent`s, since the OS only
+/// tells us something changed.
+pub(crate) struct Topology {
+    displays: Vec<DisplayInfo>,
+}
+
+fn same_position(a: &DisplayInfo, b: &DisplayInfo) -> bool {
+    a.x == b.x && a.y == b.y
+}
+
+impl Topology {
+    pub fn new(displays: Vec<DisplayInfo>) -> Topology {
+        Topology { displays }
+    }
+
+    pub fn update(&mut self, displays: Vec<DisplayInfo>) -> Vec<DisplayEvent> {
+        let mut events = vec![];
+        for old in &self.displays {
+            if !displays.iter().any(|new| same_position(old, new)) {
+                events.push(DisplayEvent::Removed(old.clone()));
+            }
+        }
+        for new in &displays {
+            match self.displays.iter().find(|old| same_position(old, new)) {
+                None => events.push(DisplayEvent::Added(new.clone())),
+                Some(old)
+                    if old.width != new.width
+                        || old.height != new.height
+                        || old.scale_factor != new.scale_factor
+                        || old.refresh_rate != new.refresh_rate =>
+                {
+                    events.push(DisplayEvent::Changed(new.clone()))
+                }
+                Some(_) => {}
+            }
+        }
+        let old_primary = self.displays.iter().find(|display| display.is_primary);
+        if let Some(primary) = displays.iter().find(|display| display.is_primary) {
+            let moved = old_primary
+                .map(|old| !same_position(old, primary))
+                .unwrap_or(true);
+            if moved {
+                events.push(DisplayEvent::PrimaryChanged(primary.clone()));
+            }
+        }
+        self.displays = displays;
+        events
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn display(x: i64, width: u64, is_primary: bool) -> DisplayInfo {
+        DisplayInfo {
+            x,
+            y: 0,
+            width,
+            height: 1080,
+            scale_factor: 1.0,
+            refresh_rate: Some(60.0),
+            is_primary,
+        }
+    }
+
+    #[test]
+    fn test_topology_update() {
+        let mut topology = Topology::new(vec![display(0, 1920, true)]);
+        assert_eq!(topology.update(vec![display(0, 1920, true)]), vec![]);
+        assert_eq!(
+            topology.update(vec![display(0, 1920, true), display(1920, 1280, false)]),
+            vec![DisplayEvent::Added(display(1920, 1280, false))]
+        );
+        assert_eq!(
+            topology.update(vec![display(0, 1920, false), display(1920, 2560, true)]),
+            vec![
+                DisplayEvent::Changed(display(1920, 2560, true)),
+                DisplayEvent::PrimaryChanged(display(1920, 2560, true)),
+            ]
+        );
+        assert_eq!(
+            topology.update(vec![display(0, 1920, true)]),
+            vec![
+                DisplayEvent::Removed(display(1920, 2560, true)),
+                DisplayEvent::PrimaryChanged(display(0, 1920, true)),
+            ]
+        );
+    }
+}
@@ -0,0 +1,104 @@
+//! `tracing` spans and events with the `tracing` feature, under the `rdev`
+//! target. Without it these do nothing and compile away.
+#![cfg_attr(not(feature = "tracing"), allow(unused_variables))]
+use crate::rdev::Backend;
+#[cfg(feature = "tracing")]
+use crate::rdev::Event;
+use std::fmt::Display;
+
+#[cfg(feature = "tracing")]
+pub(crate) type Entered = tracing::span::EnteredSpan;
+#[cfg(not(feature = "tracing"))]
+pub(crate) struct Entered;
+
+/// The span `listen_with` runs in, with the backend it picked.
+pub(crate) fn listening(backend: Option<Backend>) -> Entered {
+    #[cfg(feature = "tracing")]
+    {
+        let backend = backend.unwrap_or_else(crate::default_backend);
+        tracing::debug!(target: "rdev", ?backend, "backend selected");
+        tracing::info_span!(target: "rdev", "listen", ?backend).entered()
+    }
+    #[cfg(not(feature = "tracing"))]
+    {
+        Entered
+    }
+}
+
+#[cfg(feature = "unstable_grab")]
+pub(crate) fn grabbing() -> Entered {
+    #[cfg(feature = "tracing")]
+    {
+        tracing::info_span!(target: "rdev", "grab").entered()
+    }
+    #[cfg(not(feature = "tracing"))]
+    {
+        Entered
+    }
+}
+
+/// The hook, tap or context events come from is in place.
+pub(crate) fn installed(what: &'static str) {
+    #[cfg(feature = "tracing")]
+    tracing::debug!(target: "rdev", what, "installed");
+}
+
+/// Listening or grabbing returned, a listener that "silently stopped"
+/// shows up here.
+pub(crate) fn finished<E: Display>(result: &Result<(), E>) {
+    #[cfg(feature = "tracing")]
+    match result {
+        Ok(()) => tracing::info!(target: "rdev", "stopped"),
+        Err(error) => tracing::error!(target: "rdev", %error, "failed"),
+    }
+}
+
+/// `callback`, with how long events took to reach it since they were
+/// stamped and how long it took with them. The hook waits for the callback,
+/// slow ones are what gets hooks removed and taps disabled.
+#[cfg(feature = "tracing")]
+pub(crate) fn timed<T>(mut callback: T) -> impl FnMut(Event) + 'static
+where
+    T: FnMut(Event) + 'static,
+{
+    move |event: Event| {
+        let latency = event.time.elapsed().unwrap_or_default();
+        let event_type = event.event_type;
+        let started = std::time::Instant::now();
+        callback(event);
+        tracing::trace!(
+            target: "rdev",
+            ?event_type,
+            latency_us = latency.as_micros() as u64,
+            callback_us = started.elapsed().as_micros() as u64,
+            "dispatched"
+        );
+    }
+}
+
+/// The ring of `Settings::buffer` after a push.
+pub(crate) fn queued(depth: usize) {
+    #[cfg(feature = "tracing")]
+    tracing::trace!(target: "rdev", depth, "queued");
+}
+
+/// The ring of `Settings::buffer` was full, `dropped` events were lost
+/// since listening started.
+pub(crate) fn dropped(dropped: u64, capacity: usize) {
+    #[cfg(feature = "tracing")]
+    tracing::warn!(target: "rdev", dropped, capacity, "buffer full, event dropped");
+}
+
+/// A `Watchdog` saw input of the system the hook didn't get.
+pub(crate) fn stalled() {
+    #[cfg(feature = "tracing")]
+    tracing::warn!(target: "rdev", "hook stalled");
+}
+
+/// MacOS disabled the event tap, `by_timeout` when the callback was too
+/// slow rather than for user input, and it was enabled again.
+#[cfg(target_os = "macos")]
+pub(crate) fn tap_reenabled(by_timeout: bool) {
+    #[cfg(feature = "tracing")]
+    tracing::warn!(target: "rdev", by_timeout, "event tap disabled, enabled again");
+}
@@ -0,0 +1,67 @@
+//! TypeScript declarations of the types events are made of, for the
+//! `bindings.ts` of apps getting them as JSON (the Tauri plugin, or your
+//! own with the `serialize` feature). They follow the default serde
+//! representation: `Event::time` is a number of milliseconds since the
+//! UNIX epoch, keys and buttons are strings, `{ Unknown: number }` for
+//! those without a name, 64 bit integers are numbers. Requires the
+//! `typescript` feature, which turns on `serialize` so that optional
+//! fields are declared the way serde skips them.
+//!
+//! ```no_run
+//! std::fs::write("src/bindings.ts", rdev::typescript_bindings().unwrap()).unwrap();
+//! ```
+use crate::rdev::{
+    Button, DeviceEvent, DeviceKind, DisplayEvent, DisplayInfo, Event, EventType, InputDevice, Key,
+    LockKey, Modifiers, Pen, ScrollPhase, UnicodeInfo,
+};
+use specta::ts::{export, BigIntExportBehavior, ExportConfiguration, TsExportError};
+
+/// Every declaration, dependencies first.
+pub fn typescript_bindings() -> Result<String, TsExportError> {
+    // serde_json writes 64 bit integers as numbers, ids and coordinates
+    // stay well within what a double holds.
+    let config = ExportConfiguration::default().bigint(BigIntExportBehavior::Number);
+    let declarations = [
+        export::<Key>(&config)?,
+        export::<Button>(&config)?,
+        export::<ScrollPhase>(&config)?,
+        export::<Pen>(&config)?,
+        #[cfg(feature = "gestures")]
+        export::<crate::rdev::GesturePhase>(&config)?,
+        #[cfg(feature = "gestures")]
+        export::<crate::rdev::Gesture>(&config)?,
+        export::<EventType>(&config)?,
+        export::<UnicodeInfo>(&config)?,
+        export::<Event>(&config)?,
+        export::<LockKey>(&config)?,
+        export::<Modifiers>(&config)?,
+        export::<DeviceKind>(&config)?,
+        export::<InputDevice>(&config)?,
+        export::<DeviceEvent>(&config)?,
+        export::<DisplayInfo>(&config)?,
+        export::<DisplayEvent>(&config)?,
+    ];
+    Ok(declarations.join("\n\n") + "\n")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// `bindings/rdev.ts` is what apps get, changes to it should be on
+    /// purpose: `RDEV_UPDATE_BINDINGS=1 cargo test --features typescript`
+    /// writes it again. The snapshot is without `gestures`, which adds
+    /// `Gesture` declarations and an `EventType` variant.
+    #[test]
+    #[cfg_attr(feature = "gestures", ignore)]
+    fn test_bindings_snapshot() {
+        let path = concat!(env!("CARGO_MANIFEST_DIR"), "/bindings/rdev.ts");
+        let bindings = typescript_bindings().unwrap();
+        if std::env::var_os("RDEV_UPDATE_BINDINGS").is_some() {
+            std::fs::write(path, &bindings).unwrap();
+        }
+        let snapshot = std::fs::read_to_string(path).unwrap();
+        assert_eq!(bindings, snapshot);
+        assert!(bindings.contains("time: number"));
+    }
+}
@@ -0,0 +1,186 @@
+use crate::rdev::Key;
+
+macro_rules! decl_codes {
+    ($($key:ident, $code:literal),*) => {
+        impl Key {
+            /// The W3C UIEvents `code` of the key ("KeyA", "Numpad7", "AltLeft"...),
+            /// as used by browsers and the devtools protocol.
+            /// https://www.w3.org/TR/uievents-code/
+            /// Keys without a code in the spec (brightness) give None.
+            pub fn to_w3c_code(&self) -> Option<&'static str> {
+                match self {
+                    $(
+                        Key::$key => Some($code),
+                    )*
+                    _ => None,
+                }
+            }
+
+            /// Key with the W3C UIEvents `code` `code`.
+            pub fn from_w3c_code(code: &str) -> Option<Key> {
+                match code {
+                    $(
+                        $code => Some(Key::$key),
+                    )*
+                    _ => None,
+                }
+            }
+        }
+    };
+}
+
+#[rustfmt::skip]
+decl_codes!(
+    Alt, "AltLeft",
+    AltGr, "AltRight",
+    Backspace, "Backspace",
+    CapsLock, "CapsLock",
+    ControlLeft, "ControlLeft",
+    ControlRight, "ControlRight",
+    Delete, "Delete",
+    DownArrow, "ArrowDown",
+    End, "End",
+    Escape, "Escape",
+    F1, "F1",
+    F2, "F2",
+    F3, "F3",
+    F4, "F4",
+    F5, "F5",
+    F6, "F6",
+    F7, "F7",
+    F8, "F8",
+    F9, "F9",
+    F10, "F10",
+    F11, "F11",
+    F12, "F12",
+    F13, "F13",
+    F14, "F14",
+    F15, "F15",
+    F16, "F16",
+    F17, "F17",
+    F18, "F18",
+    F19, "F19",
+    F20, "F20",
+    F21, "F21",
+    F22, "F22",
+    F23, "F23",
+    F24, "F24",
+    Home, "Home",
+    LeftArrow, "ArrowLeft",
+    MetaLeft, "MetaLeft",
+    MetaRight, "MetaRight",
+    PageDown, "PageDown",
+    PageUp, "PageUp",
+    Return, "Enter",
+    RightArrow, "ArrowRight",
+    ShiftLeft, "ShiftLeft",
+    ShiftRight, "ShiftRight",
+    Space, "Space",
+    Tab, "Tab",
+    UpArrow, "ArrowUp",
+    PrintScreen, "PrintScreen",
+    ScrollLock, "ScrollLock",
+    Pause, "Pause",
+    NumLock, "NumLock",
+    BackQuote, "Backquote",
+    Num0, "Digit0",
+    Num1, "Digit1",
+    Num2, "Digit2",
+    Num3, "Digit3",
+    Num4, "Digit4",
+    Num5, "Digit5",
+    Num6, "Digit6",
+    Num7, "Digit7",
+    Num8, "Digit8",
+    Num9, "Digit9",
+    Minus, "Minus",
+    Equal, "Equal",
+    KeyA, "KeyA",
+    KeyB, "KeyB",
+    KeyC, "KeyC",
+    KeyD, "KeyD",
+    KeyE, "KeyE",
+    KeyF, "KeyF",
+    KeyG, "KeyG",
+    KeyH, "KeyH",
+    KeyI, "KeyI",
+    KeyJ, "KeyJ",
+    KeyK, "KeyK",
+    KeyL, "KeyL",
+    KeyM, "KeyM",
+    KeyN, "KeyN",
+    KeyO, "KeyO",
+    KeyP, "KeyP",
+    KeyQ, "KeyQ",
+    KeyR, "KeyR",
+    KeyS, "KeyS",
+    KeyT, "KeyT",
+    KeyU, "KeyU",
+    KeyV, "KeyV",
+    KeyW, "KeyW",
+    KeyX, "KeyX",
+    KeyY, "KeyY",
+    KeyZ, "KeyZ",
+    LeftBracket, "BracketLeft",
+    RightBracket, "BracketRight",
+    SemiColon, "Semicolon",
+    Quote, "Quote",
+    BackSlash, "Backslash",
+    IntlBackslash, "IntlBackslash",
+    Comma, "Comma",
+    Dot, "Period",
+    Slash, "Slash",
+    Insert, "Insert",
+    KpReturn, "NumpadEnter",
+    KpMinus, "NumpadSubtract",
+    KpPlus, "NumpadAdd",
+    KpMultiply, "NumpadMultiply",
+    KpDivide, "NumpadDivide",
+    Kp0, "Numpad0",
+    Kp1, "Numpad1",
+    Kp2, "Numpad2",
+    Kp3, "Numpad3",
+    Kp4, "Numpad4",
+    Kp5, "Numpad5",
+    Kp6, "Numpad6",
+    Kp7, "Numpad7",
+    Kp8, "Numpad8",
+    Kp9, "Numpad9",
+    KpDelete, "NumpadDecimal",
+    Function, "Fn",
+    VolumeUp, "AudioVolumeUp",
+    VolumeDown, "AudioVolumeDown",
+    VolumeMute, "AudioVolumeMute",
+    MediaPlayPause, "MediaPlayPause",
+    MediaNext, "MediaTrackNext",
+    MediaPrev, "MediaTrackPrevious",
+    Menu, "ContextMenu",
+    Kana, "KanaMode",
+    Hangul, "Lang1",
+    Henkan, "Convert",
+    Muhenkan, "NonConvert",
+    Yen, "IntlYen",
+    Ro, "IntlRo",
+    BrowserBack, "BrowserBack",
+    BrowserForward, "BrowserForward",
+    BrowserRefresh, "BrowserRefresh",
+    BrowserHome, "BrowserHome",
+    BrowserSearch, "BrowserSearch",
+    LaunchMail, "LaunchMail",
+    Calculator, "LaunchApp2",
+    Sleep, "Sleep"
+);
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_w3c_code() {
+        assert_eq!(Key::Dot.to_w3c_code(), Some("Period"));
+        assert_eq!(Key::Alt.to_w3c_code(), Some("AltLeft"));
+        assert_eq!(Key::from_w3c_code("Numpad7"), Some(Key::Kp7));
+        assert_eq!(Key::from_w3c_code("Unidentified"), None);
+        assert_eq!(Key::BrightnessUp.to_w3c_code(), None);
+    }
+}
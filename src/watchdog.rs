@@ -0,0 +1,223 @@
+use crate::rdev::{Event, ListenError, Settings};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex, PoisonError};
+use std::thread;
+use std::time::{Duration, Instant};
+
+/// Input the system saw this much after our last event is input we missed,
+/// below it's the jitter of the system idle time (Windows counts in
+/// 15.6 ms ticks).
+const JITTER: Duration = Duration::from_millis(50);
+const MIN_POLL: Duration = Duration::from_millis(50);
+
+/// Tells from the system idle time whether the hook still gets the input of
+/// the system.
+#[derive(Debug)]
+struct Health {
+    last_event: Instant,
+    /// The first input of the system we saw after `last_event`.
+    missed: Option<Instant>,
+    stalled: bool,
+}
+
+impl Health {
+    fn new(now: Instant) -> Health {
+        Health {
+            last_event: now,
+            missed: None,
+            stalled: false,
+        }
+    }
+
+    fn event(&mut self, now: Instant) {
+        self.last_event = now;
+        self.missed = None;
+        self.stalled = false;
+    }
+
+    /// True when it just stalled: input the system got `stall_after` ago
+    /// still didn't reach us.
+    fn check(&mut self, now: Instant, idle_time: Duration, stall_after: Duration) -> bool {
+        let input = match now.checked_sub(idle_time) {
+            Some(input) => input,
+            None => return false,
+        };
+        if self.missed.is_none() && input > self.last_event + JITTER {
+            self.missed = Some(input);
+        }
+        match self.missed {
+            Some(missed)
+                if !self.stalled && now.saturating_duration_since(missed) >= stall_after =>
+            {
+                self.stalled = true;
+                true
+            }
+            _ => false,
+        }
+    }
+}
+
+/// Tells whether the `listen` of a `Watchdog` still gets events, from any
+/// thread.
+#[derive(Debug, Clone)]
+pub struct ListenHandle {
+    health: Arc<Mutex<Health>>,
+}
+
+impl ListenHandle {
+    /// False once the system got input the hook didn't deliver, true again
+    /// with the next event.
+    pub fn is_healthy(&self) -> bool {
+        !self
+            .health
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .stalled
+    }
+
+    /// When the last event reached the callback, or listening started.
+    pub fn last_event(&self) -> Instant {
+        self.health
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .last_event
+    }
+}
+
+/// Watches `listen` for hooks that silently stopped: Windows removing a
+/// hook that was too slow, macOS disabling the tap, RECORD stalling. No
+/// event is simulated for it, the system idle time (see `idle_time`) is
+/// compared with the last event instead, so a stall is only noticed when
+/// the user gives input the hook doesn't get. Anything resetting the idle
+/// time counts as input, like video players on X.
+///
+/// ```no_run
+/// use rdev::Watchdog;
+/// use std::time::Duration;
+///
+/// let watchdog = Watchdog::new(Duration::from_secs(2))
+///     .on_stall(|| eprintln!("The hook stopped getting events"));
+/// let handle = watchdog.handle();
+/// // This will block.
+/// if let Err(error) = watchdog.listen(move |event| {
+///     println!("{:?} {}", event.event_type, handle.is_healthy())
+/// }) {
+///     println!("Error: {:?}", error)
+/// }
+/// ```
+pub struct Watchdog {
+    stall_after: Duration,
+    handle: ListenHandle,
+    on_stall: Option<Box<dyn FnMut() + Send>>,
+}
+
+impl Watchdog {
+    /// Input the system got `stall_after` ago and the hook still didn't is
+    /// a stall.
+    pub fn new(stall_after: Duration) -> Watchdog {
+        Watchdog {
+            stall_after,
+            handle: ListenHandle {
+                health: Arc::new(Mutex::new(Health::new(Instant::now()))),
+            },
+            on_stall: None,
+        }
+    }
+
+    /// Called from the watchdog thread, once per stall.
+    pub fn on_stall<F>(mut self, callback: F) -> Watchdog
+    where
+        F: FnMut() + Send + 'static,
+    {
+        self.on_stall = Some(Box::new(callback));
+        self
+    }
+
+    pub fn handle(&self) -> ListenHandle {
+        self.handle.clone()
+    }
+
+    /// `listen`, watched. Blocks.
+    pub fn listen<T>(self, callback: T) -> Result<(), ListenError>
+    where
+        T: FnMut(Event) + 'static,
+    {
+        self.listen_with(Settings::default(), callback)
+    }
+
+    /// `listen_with`, watched. Blocks.
+    pub fn listen_with<T>(self, settings: Settings, mut callback: T) -> Result<(), ListenError>
+    where
+        T: FnMut(Event) + 'static,
+    {
+        let Watchdog {
+            stall_after,
+            handle,
+            mut on_stall,
+        } = self;
+        handle
+            .health
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .event(Instant::now());
+        let done = Arc::new(AtomicBool::new(false));
+        let (watched, watching) = (handle.health.clone(), done.clone());
+        thread::spawn(move || {
+            let poll = (stall_after / 4).max(MIN_POLL);
+            while !watching.load(Ordering::SeqCst) {
+                thread::sleep(poll);
+                // Without a display (evdev) there's nothing to compare with.
+                let idle_time = match crate::idle_time() {
+                    Ok(idle_time) => idle_time,
+                    Err(_) => continue,
+                };
+                let stalled = watched
+                    .lock()
+                    .unwrap_or_else(PoisonError::into_inner)
+                    .check(Instant::now(), idle_time, stall_after);
+                if stalled {
+                    crate::trace::stalled();
+                    if let Some(on_stall) = &mut on_stall {
+                        on_stall();
+                    }
+                }
+            }
+        });
+        let health = handle.health;
+        let listened = crate::listen_with(settings, move |event| {
+            health
+                .lock()
+                .unwrap_or_else(PoisonError::into_inner)
+                .event(Instant::now());
+            callback(event);
+        });
+        done.store(true, Ordering::SeqCst);
+        listened
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_health() {
+        let ms = Duration::from_millis;
+        let stall_after = ms(1000);
+        let start = Instant::now();
+        let mut health = Health::new(start);
+        // Input we got.
+        health.event(start + ms(100));
+        assert!(!health.check(start + ms(2000), ms(1900), stall_after));
+        // Input we didn't get, only a stall once it's old enough.
+        assert!(!health.check(start + ms(3000), ms(500), stall_after));
+        assert!(!health.check(start + ms(3400), ms(100), stall_after));
+        assert!(health.check(start + ms(3600), ms(50), stall_after));
+        assert!(health.stalled);
+        // Once per stall.
+        assert!(!health.check(start + ms(5000), ms(10), stall_after));
+        health.event(start + ms(6000));
+        assert!(!health.stalled);
+        assert!(!health.check(start + ms(9000), ms(3000), stall_after));
+    }
+}
@@ -1,25 +1,40 @@
-use crate::rdev::{Button, EventType};
+use crate::motion::{display_rects, Motion};
+use crate::rdev::{Button, Event, EventType, Pen};
 use crate::windows::keyboard::Keyboard;
-use crate::windows::keycodes::key_from_code;
+use crate::windows::keycodes::key_from_event_code;
 use lazy_static::lazy_static;
 use std::convert::TryInto;
+use std::mem::zeroed;
 use std::os::raw::{c_int, c_short};
-use std::ptr::null_mut;
+use std::ptr::{addr_of_mut, null, null_mut};
 use std::sync::Mutex;
-use winapi::shared::minwindef::{DWORD, HIWORD, LPARAM, LRESULT, WORD, WPARAM};
+use winapi::shared::minwindef::{DWORD, HIWORD, LPARAM, LRESULT, UINT, WORD, WPARAM};
 use winapi::shared::ntdef::LONG;
-use winapi::shared::windef::HHOOK;
+use winapi::shared::windef::{HHOOK, HWND};
 use winapi::um::errhandlingapi::GetLastError;
+use winapi::um::libloaderapi::GetModuleHandleW;
 use winapi::um::winuser::{
-    SetWindowsHookExA, KBDLLHOOKSTRUCT, MSLLHOOKSTRUCT, WHEEL_DELTA, WH_KEYBOARD_LL, WH_MOUSE_LL,
-    WM_KEYDOWN, WM_KEYUP, WM_LBUTTONDOWN, WM_LBUTTONUP, WM_MBUTTONDOWN, WM_MBUTTONUP,
-    WM_MOUSEHWHEEL, WM_MOUSEMOVE, WM_MOUSEWHEEL, WM_RBUTTONDOWN, WM_RBUTTONUP, WM_SYSKEYDOWN,
-    WM_SYSKEYUP, WM_XBUTTONDOWN, WM_XBUTTONUP,
+    CreateWindowExW, DispatchMessageW, GetMessageW, RegisterClassW, SetWindowsHookExA,
+    TranslateMessage, UnhookWindowsHookEx, KBDLLHOOKSTRUCT, LLKHF_EXTENDED, MSG, MSLLHOOKSTRUCT,
+    VK_PACKET, VK_PROCESSKEY, WHEEL_DELTA, WH_KEYBOARD_LL, WH_MOUSE_LL, WM_KEYDOWN, WM_KEYUP,
+    WM_LBUTTONDOWN, WM_LBUTTONUP, WM_MBUTTONDOWN, WM_MBUTTONUP, WM_MOUSEHWHEEL, WM_MOUSEMOVE,
+    WM_MOUSEWHEEL, WM_RBUTTONDOWN, WM_RBUTTONUP, WM_SYSKEYDOWN, WM_SYSKEYUP, WM_XBUTTONDOWN,
+    WM_XBUTTONUP, WNDCLASSW, XBUTTON1, XBUTTON2,
 };
 pub const TRUE: i32 = 1;
 pub const FALSE: i32 = 0;
 
+// Mouse events Windows promotes from touch or pen input carry this signature
+// in their extra info, the low 7 bits hold the contact id.
+// https://docs.microsoft.com/en-us/windows/win32/tablet/system-events-and-mouse-messages
+const MI_WP_SIGNATURE: usize = 0xFF51_5700;
+const SIGNATURE_MASK: usize = 0xFFFF_FF00;
+const TOUCH_FLAG: usize = 0x80;
+const TOUCH_ID_MASK: usize = 0x7F;
+
 pub static mut HOOK: HHOOK = null_mut();
+static mut PEN_DOWN: bool = false;
+static mut MOTION: Motion = Motion::new();
 lazy_static! {
     pub(crate) static ref KEYBOARD: Mutex<Keyboard> = Mutex::new(Keyboard::new().unwrap());
 }
@@ -28,6 +43,10 @@ pub unsafe fn get_code(lpdata: LPARAM) -> DWORD {
     let kb = *(lpdata as *const KBDLLHOOKSTRUCT);
     kb.vkCode
 }
+unsafe fn is_extended(lpdata: LPARAM) -> bool {
+    let kb = *(lpdata as *const KBDLLHOOKSTRUCT);
+    kb.flags & LLKHF_EXTENDED != 0
+}
 pub unsafe fn get_scan_code(lpdata: LPARAM) -> DWORD {
     let kb = *(lpdata as *const KBDLLHOOKSTRUCT);
     kb.scanCode
@@ -43,22 +62,107 @@ pub unsafe fn get_delta(lpdata: LPARAM) -> WORD {
     let mouse = *(lpdata as *const MSLLHOOKSTRUCT);
     HIWORD(mouse.mouseData)
 }
+/// Where a mouse event promoted from pointer input came from.
+pub enum Promoted {
+    Touch(u64),
+    Pen,
+}
+pub unsafe fn get_promoted(lpdata: LPARAM) -> Option<Promoted> {
+    let mouse = *(lpdata as *const MSLLHOOKSTRUCT);
+    let extra = mouse.dwExtraInfo;
+    if extra & SIGNATURE_MASK != MI_WP_SIGNATURE {
+        None
+    } else if extra & TOUCH_FLAG != 0 {
+        Some(Promoted::Touch((extra & TOUCH_ID_MASK) as u64))
+    } else {
+        Some(Promoted::Pen)
+    }
+}
 pub unsafe fn get_button_code(lpdata: LPARAM) -> WORD {
     let mouse = *(lpdata as *const MSLLHOOKSTRUCT);
     HIWORD(mouse.mouseData)
 }
 
+fn x_button(code: WORD) -> Button {
+    match code {
+        XBUTTON1 => Button::Back,
+        XBUTTON2 => Button::Forward,
+        code => Button::Unknown(code as u8),
+    }
+}
+
+/// Low level hooks never see WM_POINTER messages, touches and pens only show
+/// up as the mouse events they get promoted to, so we turn those back.
+/// Promoted events carry no pressure or tilt, a pen touching the screen gets
+/// a pressure of 1.0 and 0.0 while hovering.
+unsafe fn convert_promoted(param: u32, lpdata: LPARAM) -> Option<EventType> {
+    if param != WM_LBUTTONDOWN && param != WM_LBUTTONUP && param != WM_MOUSEMOVE {
+        return None;
+    }
+    let promoted = get_promoted(lpdata)?;
+    let (x, y) = get_point(lpdata);
+    let (x, y) = (x as f64, y as f64);
+    match promoted {
+        Promoted::Touch(id) => {
+            let pressure = 1.0;
+            match param {
+                WM_LBUTTONDOWN => Some(EventType::TouchDown { id, x, y, pressure }),
+                WM_LBUTTONUP => Some(EventType::TouchUp { id, x, y, pressure }),
+                _ => Some(EventType::TouchMove { id, x, y, pressure }),
+            }
+        }
+        Promoted::Pen => {
+            match param {
+                WM_LBUTTONDOWN => PEN_DOWN = true,
+                WM_LBUTTONUP => PEN_DOWN = false,
+                _ => {}
+            }
+            let pen = Pen {
+                x,
+                y,
+                pressure: if PEN_DOWN { 1.0 } else { 0.0 },
+                ..Default::default()
+            };
+            match param {
+                WM_LBUTTONDOWN => Some(EventType::PenDown(pen)),
+                WM_LBUTTONUP => Some(EventType::PenUp(pen)),
+                _ => Some(EventType::PenMove(pen)),
+            }
+        }
+    }
+}
+
 pub unsafe fn convert(param: WPARAM, lpdata: LPARAM) -> Option<EventType> {
+    if let Some(event_type) = param
+        .try_into()
+        .ok()
+        .and_then(|param| convert_promoted(param, lpdata))
+    {
+        return Some(event_type);
+    }
     match param.try_into() {
         Ok(WM_KEYDOWN) | Ok(WM_SYSKEYDOWN) => {
             let code = get_code(lpdata);
-            let key = key_from_code(code as u16);
-            Some(EventType::KeyPress(key))
+            match code.try_into() {
+                // IMEs commit their text as unicode packets.
+                Ok(VK_PACKET) => Some(EventType::ImeCommit),
+                // The key was eaten by the IME.
+                Ok(VK_PROCESSKEY) => Some(EventType::ImeComposition),
+                _ => Some(EventType::KeyPress(key_from_event_code(
+                    code as u16,
+                    is_extended(lpdata),
+                ))),
+            }
         }
         Ok(WM_KEYUP) | Ok(WM_SYSKEYUP) => {
             let code = get_code(lpdata);
-            let key = key_from_code(code as u16);
-            Some(EventType::KeyRelease(key))
+            match code.try_into() {
+                Ok(VK_PACKET) | Ok(VK_PROCESSKEY) => None,
+                _ => Some(EventType::KeyRelease(key_from_event_code(
+                    code as u16,
+                    is_extended(lpdata),
+                ))),
+            }
         }
         Ok(WM_LBUTTONDOWN) => Some(EventType::ButtonPress(Button::Left)),
         Ok(WM_LBUTTONUP) => Some(EventType::ButtonRelease(Button::Left)),
@@ -67,12 +171,12 @@ pub unsafe fn convert(param: WPARAM, lpdata: LPARAM) -> Option<EventType> {
         Ok(WM_RBUTTONDOWN) => Some(EventType::ButtonPress(Button::Right)),
         Ok(WM_RBUTTONUP) => Some(EventType::ButtonRelease(Button::Right)),
         Ok(WM_XBUTTONDOWN) => {
-            let code = get_button_code(lpdata) as u8;
-            Some(EventType::ButtonPress(Button::Unknown(code)))
+            let code = get_button_code(lpdata);
+            Some(EventType::ButtonPress(x_button(code)))
         }
         Ok(WM_XBUTTONUP) => {
-            let code = get_button_code(lpdata) as u8;
-            Some(EventType::ButtonRelease(Button::Unknown(code)))
+            let code = get_button_code(lpdata);
+            Some(EventType::ButtonRelease(x_button(code)))
         }
         Ok(WM_MOUSEMOVE) => {
             let (x, y) = get_point(lpdata);
@@ -105,23 +209,90 @@ pub enum HookError {
     Key(DWORD),
 }
 
-pub unsafe fn set_key_hook(callback: RawCallback) -> Result<(), HookError> {
+/// Fills the display and delta of mouse moves. The hook only sees positions,
+/// already clamped to the screens.
+pub unsafe fn annotate_motion(event: &mut Event) {
+    (*addr_of_mut!(MOTION)).annotate(event, display_rects);
+}
+
+/// Removed on drop, however the thread stops listening: Windows only
+/// removes low level hooks when their thread exits, and until then it
+/// waits on them for every input of the system.
+pub struct Hook(HHOOK);
+
+impl Drop for Hook {
+    fn drop(&mut self) {
+        unsafe { UnhookWindowsHookEx(self.0) };
+    }
+}
+
+pub unsafe fn set_key_hook(callback: RawCallback) -> Result<Hook, HookError> {
     let hook = SetWindowsHookExA(WH_KEYBOARD_LL, Some(callback), null_mut(), 0);
 
     if hook.is_null() {
         let error = GetLastError();
         return Err(HookError::Key(error));
     }
+    crate::trace::installed("keyboard hook");
     HOOK = hook;
-    Ok(())
+    Ok(Hook(hook))
 }
 
-pub unsafe fn set_mouse_hook(callback: RawCallback) -> Result<(), HookError> {
+pub unsafe fn set_mouse_hook(callback: RawCallback) -> Result<Hook, HookError> {
     let hook = SetWindowsHookExA(WH_MOUSE_LL, Some(callback), null_mut(), 0);
     if hook.is_null() {
         let error = GetLastError();
         return Err(HookError::Mouse(error));
     }
+    crate::trace::installed("mouse hook");
     HOOK = hook;
-    Ok(())
+    Ok(Hook(hook))
+}
+
+pub type WindowProc =
+    unsafe extern "system" fn(hwnd: HWND, msg: UINT, wparam: WPARAM, lparam: LPARAM) -> LRESULT;
+
+/// A window that is never shown, to receive messages only sent to windows.
+/// `parent` is HWND_MESSAGE for a message-only window, which doesn't get
+/// broadcasts, or null for a top level one.
+pub unsafe fn create_window(class_name: &str, window_proc: WindowProc, parent: HWND) -> HWND {
+    let class_name: Vec<u16> = class_name.encode_utf16().chain(Some(0)).collect();
+    let instance = GetModuleHandleW(null());
+    let class = WNDCLASSW {
+        style: 0,
+        lpfnWndProc: Some(window_proc),
+        cbClsExtra: 0,
+        cbWndExtra: 0,
+        hInstance: instance,
+        hIcon: null_mut(),
+        hCursor: null_mut(),
+        hbrBackground: null_mut(),
+        lpszMenuName: null(),
+        lpszClassName: class_name.as_ptr(),
+    };
+    // Fails if the class already exists (listening a second time), which is fine.
+    RegisterClassW(&class);
+    CreateWindowExW(
+        0,
+        class_name.as_ptr(),
+        class_name.as_ptr(),
+        0,
+        0,
+        0,
+        0,
+        0,
+        parent,
+        null_mut(),
+        instance,
+        null_mut(),
+    )
+}
+
+/// Dispatches the messages of the thread until WM_QUIT.
+pub unsafe fn run_message_loop() {
+    let mut msg: MSG = zeroed();
+    while GetMessageW(&mut msg, null_mut(), 0, 0) > 0 {
+        TranslateMessage(&msg);
+        DispatchMessageW(&msg);
+    }
 }
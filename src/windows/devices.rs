@@ -0,0 +1,236 @@
+use crate::rdev::{DeviceEvent, DeviceKind, InputDevice, ListenError, OsError};
+use crate::windows::common::{create_window, run_message_loop, FALSE};
+use std::collections::HashMap;
+use std::mem::{size_of, zeroed};
+use std::ptr::{addr_of_mut, null_mut};
+use winapi::shared::hidsdi::{HidD_GetProductString, HidD_GetSerialNumberString};
+use winapi::shared::minwindef::{DWORD, LPARAM, LPVOID, LRESULT, UINT, ULONG, WPARAM};
+use winapi::shared::windef::HWND;
+use winapi::um::fileapi::{CreateFileW, OPEN_EXISTING};
+use winapi::um::handleapi::{CloseHandle, INVALID_HANDLE_VALUE};
+use winapi::um::winnt::{BOOLEAN, FILE_SHARE_READ, FILE_SHARE_WRITE, HANDLE};
+use winapi::um::winuser::{
+    DefWindowProcW, GetRawInputDeviceInfoW, GetRawInputDeviceList, RegisterRawInputDevices,
+    HWND_MESSAGE, RAWINPUTDEVICE, RAWINPUTDEVICELIST, RIDEV_DEVNOTIFY, RIDEV_INPUTSINK,
+    RIDI_DEVICEINFO, RIDI_DEVICENAME, RID_DEVICE_INFO, RIM_TYPEKEYBOARD, RIM_TYPEMOUSE,
+    WM_INPUT_DEVICE_CHANGE,
+};
+
+const GIDC_ARRIVAL: WPARAM = 1;
+const GIDC_REMOVAL: WPARAM = 2;
+// Generic desktop page, keyboards and mice.
+const USAGE_PAGE_GENERIC: u16 = 0x01;
+const USAGE_MOUSE: u16 = 0x02;
+const USAGE_KEYBOARD: u16 = 0x06;
+const RAW_INPUT_ERROR: UINT = UINT::MAX;
+
+static mut GLOBAL_CALLBACK: Option<Box<dyn FnMut(DeviceEvent)>> = None;
+/// Removed devices can't be queried anymore, we report what we knew.
+static mut DEVICES: Option<HashMap<usize, InputDevice>> = None;
+
+unsafe fn device_path(handle: HANDLE) -> Option<String> {
+    let mut size: UINT = 0;
+    GetRawInputDeviceInfoW(handle, RIDI_DEVICENAME, null_mut(), &mut size);
+    if size == 0 {
+        return None;
+    }
+    let mut path = vec![0_u16; size as usize];
+    let len = GetRawInputDeviceInfoW(
+        handle,
+        RIDI_DEVICENAME,
+        path.as_mut_ptr() as LPVOID,
+        &mut size,
+    );
+    if len == RAW_INPUT_ERROR {
+        return None;
+    }
+    let end = path.iter().position(|c| *c == 0).unwrap_or(path.len());
+    Some(String::from_utf16_lossy(&path[..end]))
+}
+
+unsafe fn device_kind(handle: HANDLE) -> DeviceKind {
+    let mut info: RID_DEVICE_INFO = zeroed();
+    info.cbSize = size_of::<RID_DEVICE_INFO>() as DWORD;
+    let mut size = info.cbSize as UINT;
+    let len = GetRawInputDeviceInfoW(
+        handle,
+        RIDI_DEVICEINFO,
+        &mut info as *mut _ as LPVOID,
+        &mut size,
+    );
+    if len == RAW_INPUT_ERROR {
+        return DeviceKind::Other;
+    }
+    match info.dwType {
+        RIM_TYPEKEYBOARD => DeviceKind::Keyboard,
+        RIM_TYPEMOUSE => DeviceKind::Mouse,
+        _ => DeviceKind::Other,
+    }
+}
+
+type HidString = unsafe extern "system" fn(HANDLE, LPVOID, ULONG) -> BOOLEAN;
+
+/// Opening the device without any access right is enough to ask the HID
+/// driver for its strings, even for keyboards and mice.
+unsafe fn hid_string(path: &str, get: HidString) -> Option<String> {
+    let path: Vec<u16> = path.encode_utf16().chain(Some(0)).collect();
+    let file = CreateFileW(
+        path.as_ptr(),
+        0,
+        FILE_SHARE_READ | FILE_SHARE_WRITE,
+        null_mut(),
+        OPEN_EXISTING,
+        0,
+        null_mut(),
+    );
+    if file == INVALID_HANDLE_VALUE {
+        return None;
+    }
+    // HID strings are at most 126 characters.
+    let mut name = [0_u16; 127];
+    let ok = get(file, name.as_mut_ptr() as LPVOID, (name.len() * 2) as ULONG);
+    CloseHandle(file);
+    if ok == FALSE as u8 {
+        return None;
+    }
+    let end = name.iter().position(|c| *c == 0).unwrap_or(name.len());
+    Some(String::from_utf16_lossy(&name[..end])).filter(|name| !name.is_empty())
+}
+
+/// Hardware ids are part of the device path, `\\?\HID#VID_046D&PID_C52B&...`
+fn parse_id(path: &str, prefix: &str) -> u16 {
+    let path = path.to_uppercase();
+    path.find(prefix)
+        .map(|start| start + prefix.len())
+        .and_then(|start| path.get(start..start + 4))
+        .and_then(|hex| u16::from_str_radix(hex, 16).ok())
+        .unwrap_or(0)
+}
+
+unsafe fn arrival(handle: HANDLE) {
+    let devices = match &mut *addr_of_mut!(DEVICES) {
+        Some(devices) => devices,
+        None => return,
+    };
+    // Registering sends arrivals for devices we already listed.
+    if devices.contains_key(&(handle as usize)) {
+        return;
+    }
+    let path = match device_path(handle) {
+        Some(path) => path,
+        None => return,
+    };
+    let device = InputDevice {
+        name: hid_string(&path, HidD_GetProductString).unwrap_or_else(|| path.clone()),
+        vendor_id: parse_id(&path, "VID_"),
+        product_id: parse_id(&path, "PID_"),
+        serial: hid_string(&path, HidD_GetSerialNumberString),
+        kind: device_kind(handle),
+        id: path,
+    };
+    devices.insert(handle as usize, device.clone());
+    if let Some(callback) = &mut *addr_of_mut!(GLOBAL_CALLBACK) {
+        callback(DeviceEvent::Connected(device));
+    }
+}
+
+unsafe fn removal(handle: HANDLE) {
+    if let Some(device) = (*addr_of_mut!(DEVICES))
+        .as_mut()
+        .and_then(|devices| devices.remove(&(handle as usize)))
+    {
+        if let Some(callback) = &mut *addr_of_mut!(GLOBAL_CALLBACK) {
+            callback(DeviceEvent::Disconnected(device));
+        }
+    }
+}
+
+unsafe extern "system" fn window_proc(
+    hwnd: HWND,
+    msg: UINT,
+    wparam: WPARAM,
+    lparam: LPARAM,
+) -> LRESULT {
+    if msg == WM_INPUT_DEVICE_CHANGE {
+        match wparam {
+            GIDC_ARRIVAL => arrival(lparam as HANDLE),
+            GIDC_REMOVAL => removal(lparam as HANDLE),
+            _ => {}
+        }
+        return 0;
+    }
+    DefWindowProcW(hwnd, msg, wparam, lparam)
+}
+
+pub fn listen_devices<T>(callback: T) -> Result<(), ListenError>
+where
+    T: FnMut(DeviceEvent) + 'static,
+{
+    unsafe {
+        GLOBAL_CALLBACK = Some(Box::new(callback));
+        DEVICES = Some(HashMap::new());
+
+        let item_size = size_of::<RAWINPUTDEVICELIST>() as UINT;
+        let mut count: UINT = 0;
+        if GetRawInputDeviceList(null_mut(), &mut count, item_size) == RAW_INPUT_ERROR {
+            let error = OsError::last("GetRawInputDeviceList");
+            return Err(ListenError::DeviceMonitorError(error));
+        }
+        let mut list: Vec<RAWINPUTDEVICELIST> = vec![zeroed(); count as usize];
+        let listed = GetRawInputDeviceList(list.as_mut_ptr(), &mut count, item_size);
+        if listed == RAW_INPUT_ERROR {
+            let error = OsError::last("GetRawInputDeviceList");
+            return Err(ListenError::DeviceMonitorError(error));
+        }
+        for device in &list[..listed as usize] {
+            arrival(device.hDevice);
+        }
+
+        // Device changes are only sent to windows, a message-only one is enough.
+        let hwnd = create_window("rdev_devices", window_proc, HWND_MESSAGE);
+        if hwnd.is_null() {
+            let error = OsError::last("CreateWindowExW");
+            return Err(ListenError::DeviceMonitorError(error));
+        }
+        let flags = RIDEV_DEVNOTIFY | RIDEV_INPUTSINK;
+        let devices = [
+            RAWINPUTDEVICE {
+                usUsagePage: USAGE_PAGE_GENERIC,
+                usUsage: USAGE_KEYBOARD,
+                dwFlags: flags,
+                hwndTarget: hwnd,
+            },
+            RAWINPUTDEVICE {
+                usUsagePage: USAGE_PAGE_GENERIC,
+                usUsage: USAGE_MOUSE,
+                dwFlags: flags,
+                hwndTarget: hwnd,
+            },
+        ];
+        let registered = RegisterRawInputDevices(
+            devices.as_ptr(),
+            devices.len() as UINT,
+            size_of::<RAWINPUTDEVICE>() as UINT,
+        );
+        if registered == FALSE {
+            let error = OsError::last("RegisterRawInputDevices");
+            return Err(ListenError::DeviceMonitorError(error));
+        }
+
+        run_message_loop();
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_id() {
+        let path = r"\\?\HID#VID_046D&PID_C52B&MI_00#7&1ebb799&0&0000#{884b96c3}";
+        assert_eq!(parse_id(path, "VID_"), 0x046D);
+        assert_eq!(parse_id(path, "PID_"), 0xC52B);
+        assert_eq!(parse_id(r"\\?\ACPI#PNP0303#4&1d401fb5&0", "VID_"), 0);
+    }
+}
@@ -1,6 +1,25 @@
-use crate::rdev::DisplayError;
+use crate::rdev::{DisplayError, DisplayInfo, ListenError, OsError};
+use crate::windows::common::{create_window, run_message_loop, FALSE, TRUE};
 use std::convert::TryInto;
-use winapi::um::winuser::{GetSystemMetrics, SM_CXSCREEN, SM_CYSCREEN};
+use std::mem::{size_of, transmute, zeroed};
+use std::ptr::{addr_of_mut, null_mut};
+use winapi::shared::minwindef::{BOOL, DWORD, LPARAM, LRESULT, UINT, WORD, WPARAM};
+use winapi::shared::windef::{
+    DPI_AWARENESS_CONTEXT, DPI_AWARENESS_CONTEXT_PER_MONITOR_AWARE_V2, HDC, HMONITOR, HWND, LPRECT,
+};
+use winapi::shared::winerror::S_OK;
+use winapi::um::libloaderapi::{GetModuleHandleW, GetProcAddress};
+use winapi::um::shellscalingapi::{GetDpiForMonitor, MDT_EFFECTIVE_DPI};
+use winapi::um::wingdi::DEVMODEW;
+use winapi::um::winnt::LPCSTR;
+use winapi::um::winuser::{
+    DefWindowProcW, EnumDisplayMonitors, EnumDisplaySettingsW, GetMonitorInfoW, GetSystemMetrics,
+    SetProcessDPIAware, ENUM_CURRENT_SETTINGS, LPMONITORINFO, MONITORINFOEXW, MONITORINFOF_PRIMARY,
+    SM_CXSCREEN, SM_CYSCREEN, WM_DISPLAYCHANGE, WM_SETTINGCHANGE,
+};
+
+// 100% scaling.
+const USER_DEFAULT_SCREEN_DPI: f64 = 96.0;
 
 pub fn display_size() -> Result<(u64, u64), DisplayError> {
     let w = unsafe {
@@ -15,3 +34,137 @@ pub fn display_size() -> Result<(u64, u64), DisplayError> {
     };
     Ok((w, h))
 }
+
+unsafe extern "system" fn monitor_callback(
+    monitor: HMONITOR,
+    _hdc: HDC,
+    _rect: LPRECT,
+    data: LPARAM,
+) -> BOOL {
+    let monitors = &mut *(data as *mut Vec<HMONITOR>);
+    monitors.push(monitor);
+    TRUE
+}
+
+unsafe fn display_info(monitor: HMONITOR) -> Option<DisplayInfo> {
+    let mut info: MONITORINFOEXW = zeroed();
+    info.cbSize = size_of::<MONITORINFOEXW>() as DWORD;
+    if GetMonitorInfoW(monitor, &mut info as *mut _ as LPMONITORINFO) == FALSE {
+        return None;
+    }
+    let rect = info.rcMonitor;
+    let mut mode: DEVMODEW = zeroed();
+    mode.dmSize = size_of::<DEVMODEW>() as WORD;
+    // 0 and 1 stand for the hardware default rate.
+    let refresh_rate =
+        if EnumDisplaySettingsW(info.szDevice.as_ptr(), ENUM_CURRENT_SETTINGS, &mut mode) != FALSE
+            && mode.dmDisplayFrequency > 1
+        {
+            Some(mode.dmDisplayFrequency.into())
+        } else {
+            None
+        };
+    let mut dpi_x = 0;
+    let mut dpi_y = 0;
+    let scale_factor =
+        if GetDpiForMonitor(monitor, MDT_EFFECTIVE_DPI, &mut dpi_x, &mut dpi_y) == S_OK {
+            f64::from(dpi_x) / USER_DEFAULT_SCREEN_DPI
+        } else {
+            1.0
+        };
+    Some(DisplayInfo {
+        x: rect.left.into(),
+        y: rect.top.into(),
+        width: (rect.right - rect.left).try_into().ok()?,
+        height: (rect.bottom - rect.top).try_into().ok()?,
+        scale_factor,
+        refresh_rate,
+        is_primary: info.dwFlags & MONITORINFOF_PRIMARY != 0,
+    })
+}
+
+/// Positions are in the coordinates of mouse events, which are scaled when
+/// the process isn't DPI aware.
+pub fn displays() -> Result<Vec<DisplayInfo>, DisplayError> {
+    let mut monitors: Vec<HMONITOR> = vec![];
+    unsafe {
+        let ok = EnumDisplayMonitors(
+            null_mut(),
+            null_mut(),
+            Some(monitor_callback),
+            &mut monitors as *mut _ as LPARAM,
+        );
+        if ok == FALSE {
+            return Err(DisplayError::NoDisplay);
+        }
+        Ok(monitors
+            .into_iter()
+            .filter_map(|monitor| display_info(monitor))
+            .collect())
+    }
+}
+
+type SetAwarenessContext = unsafe extern "system" fn(DPI_AWARENESS_CONTEXT) -> BOOL;
+
+/// Makes positions physical pixels for the whole process. Per monitor
+/// awareness only exists since Windows 10 1703, so it's looked up at runtime
+/// and we fall back to system awareness.
+pub fn enable_dpi_awareness() {
+    unsafe {
+        let user32: Vec<u16> = "user32.dll\0".encode_utf16().collect();
+        let module = GetModuleHandleW(user32.as_ptr());
+        let proc = if module.is_null() {
+            null_mut()
+        } else {
+            GetProcAddress(
+                module,
+                b"SetProcessDpiAwarenessContext\0".as_ptr() as LPCSTR,
+            )
+        };
+        if !proc.is_null() {
+            let set_awareness: SetAwarenessContext = transmute(proc);
+            if set_awareness(DPI_AWARENESS_CONTEXT_PER_MONITOR_AWARE_V2) != FALSE {
+                return;
+            }
+        }
+        SetProcessDPIAware();
+    }
+}
+
+static mut DISPLAY_CALLBACK: Option<Box<dyn FnMut()>> = None;
+
+/// Scale factor changes only send WM_SETTINGCHANGE, which also comes for
+/// unrelated settings, `listen_displays` drops those as nothing differs.
+unsafe extern "system" fn window_proc(
+    hwnd: HWND,
+    msg: UINT,
+    wparam: WPARAM,
+    lparam: LPARAM,
+) -> LRESULT {
+    if msg == WM_DISPLAYCHANGE || msg == WM_SETTINGCHANGE {
+        if let Some(callback) = &mut *addr_of_mut!(DISPLAY_CALLBACK) {
+            callback();
+        }
+        return 0;
+    }
+    DefWindowProcW(hwnd, msg, wparam, lparam)
+}
+
+pub fn listen_display_changes<T>(callback: T) -> Result<(), ListenError>
+where
+    T: FnMut() + 'static,
+{
+    unsafe {
+        DISPLAY_CALLBACK = Some(Box::new(callback));
+        // WM_DISPLAYCHANGE is broadcast to top level windows only, unlike
+        // device changes a message-only window doesn't get it.
+        let hwnd = create_window("rdev_displays", window_proc, null_mut());
+        if hwnd.is_null() {
+            let error = OsError::last("CreateWindowExW");
+            return Err(ListenError::DisplayMonitorError(error));
+        }
+
+        run_message_loop();
+    }
+    Ok(())
+}
@@ -1,5 +1,9 @@
-use crate::rdev::{Event, EventType, GrabError};
-use crate::windows::common::{convert, set_key_hook, set_mouse_hook, HookError, HOOK, KEYBOARD};
+use crate::rdev::{Event, EventType, GrabError, UnicodeInfo};
+use crate::windows::common::{
+    annotate_motion, convert, get_scan_code, set_key_hook, set_mouse_hook, HookError, HOOK,
+    KEYBOARD,
+};
+use crate::windows::state::event_numlock;
 use std::ptr::null_mut;
 use std::time::SystemTime;
 use winapi::um::winuser::{CallNextHookEx, GetMessageA, HC_ACTION};
@@ -10,19 +14,52 @@ unsafe extern "system" fn raw_callback(code: i32, param: usize, lpdata: isize) -
     if code == HC_ACTION {
         let opt = convert(param, lpdata);
         if let Some(event_type) = opt {
-            let name = match &event_type {
+            let (name, layout_changed, is_dead) = match &event_type {
                 EventType::KeyPress(_key) => match (*KEYBOARD).lock() {
-                    Ok(mut keyboard) => keyboard.get_name(lpdata),
-                    Err(_) => None,
+                    Ok(mut keyboard) => {
+                        let name = keyboard.get_name(lpdata);
+                        (name, keyboard.take_layout_changed(), keyboard.is_dead())
+                    }
+                    Err(_) => (None, false, false),
                 },
-                _ => None,
+                EventType::KeyRelease(_key) => match (*KEYBOARD).lock() {
+                    Ok(mut keyboard) => (keyboard.get_release_name(lpdata), false, false),
+                    Err(_) => (None, false, false),
+                },
+                EventType::ImeCommit => match (*KEYBOARD).lock() {
+                    Ok(mut keyboard) => (keyboard.get_packet_name(lpdata), false, false),
+                    Err(_) => (None, false, false),
+                },
+                _ => (None, false, false),
             };
-            let event = Event {
+            let scan_code = match &event_type {
+                EventType::KeyPress(_) | EventType::KeyRelease(_) => get_scan_code(lpdata),
+                _ => 0,
+            };
+            let mut event = Event {
                 event_type,
                 time: SystemTime::now(),
+                unicode: UnicodeInfo::new(name.as_deref(), is_dead),
                 name,
+                scan_code,
+                display: None,
+                delta: None,
+                numlock: event_numlock(&event_type),
             };
+            annotate_motion(&mut event);
             if let Some(callback) = &mut GLOBAL_CALLBACK {
+                if layout_changed {
+                    callback(Event {
+                        event_type: EventType::LayoutChanged,
+                        time: SystemTime::now(),
+                        name: None,
+                        unicode: None,
+                        scan_code: 0,
+                        display: None,
+                        delta: None,
+                        numlock: None,
+                    });
+                }
                 if callback(event).is_none() {
                     // https://stackoverflow.com/questions/42756284/blocking-windows-mouse-click-using-setwindowshookex
                     // https://android.developreference.com/article/14560004/Blocking+windows+mouse+click+using+SetWindowsHookEx()
@@ -50,8 +87,8 @@ where
 {
     unsafe {
         GLOBAL_CALLBACK = Some(Box::new(callback));
-        set_key_hook(raw_callback)?;
-        set_mouse_hook(raw_callback)?;
+        let _key_hook = set_key_hook(raw_callback)?;
+        let _mouse_hook = set_mouse_hook(raw_callback)?;
 
         GetMessageA(null_mut(), null_mut(), 0, 0);
     }
@@ -1,19 +1,27 @@
-use crate::rdev::{EventType, Key, KeyboardState};
+use crate::layout::NameTable;
+use crate::rdev::{DisplayError, EventType, Key, KeyboardState};
 use crate::windows::common::{get_code, get_scan_code, FALSE, TRUE};
 use crate::windows::keycodes::code_from_key;
 use std::ptr::null_mut;
 use winapi::shared::minwindef::{BYTE, HKL, LPARAM, UINT};
 use winapi::um::processthreadsapi::GetCurrentThreadId;
+use winapi::um::stringapiset::MultiByteToWideChar;
+use winapi::um::winnls::{CP_ACP, CP_OEMCP, MB_USEGLYPHCHARS};
 use winapi::um::winuser;
 use winapi::um::winuser::{
-    GetForegroundWindow, GetKeyState, GetKeyboardLayout, GetKeyboardState,
-    GetWindowThreadProcessId, ToUnicodeEx, VK_CAPITAL, VK_LSHIFT, VK_RSHIFT, VK_SHIFT,
+    GetForegroundWindow, GetKeyState, GetKeyboardLayout, GetKeyboardLayoutList, GetKeyboardState,
+    GetWindowThreadProcessId, ToUnicodeEx, VK_CAPITAL, VK_CONTROL, VK_LCONTROL, VK_LMENU,
+    VK_LSHIFT, VK_MENU, VK_NUMPAD0, VK_NUMPAD9, VK_RMENU, VK_RSHIFT, VK_SHIFT,
 };
 
 const VK_SHIFT_: usize = VK_SHIFT as usize;
 const VK_CAPITAL_: usize = VK_CAPITAL as usize;
 const VK_LSHIFT_: usize = VK_LSHIFT as usize;
 const VK_RSHIFT_: usize = VK_RSHIFT as usize;
+const VK_CONTROL_: usize = VK_CONTROL as usize;
+const VK_LCONTROL_: usize = VK_LCONTROL as usize;
+const VK_MENU_: usize = VK_MENU as usize;
+const VK_RMENU_: usize = VK_RMENU as usize;
 const HIGHBIT: u8 = 0x80;
 
 pub struct Keyboard {
@@ -21,6 +29,16 @@ pub struct Keyboard {
     last_scan_code: UINT,
     last_state: [BYTE; 256],
     last_is_dead: bool,
+    // HKL is a pointer, store it as usize so the keyboard can live in a static.
+    last_layout: usize,
+    layout_changed: bool,
+    // First half of a surrogate pair sent by an IME.
+    high_surrogate: Option<u16>,
+    // Numpad digits typed while Alt is held, None when not in an Alt code.
+    alt_digits: Option<Vec<u32>>,
+    // Set by with_layout, the foreground window layout is ignored then.
+    fixed_layout: Option<usize>,
+    names: NameTable,
 }
 
 impl Keyboard {
@@ -30,9 +48,73 @@ impl Keyboard {
             last_scan_code: 0,
             last_state: [0; 256],
             last_is_dead: false,
+            last_layout: 0,
+            layout_changed: false,
+            high_surrogate: None,
+            alt_digits: None,
+            fixed_layout: None,
+            names: NameTable::default(),
         })
     }
 
+    /// Keyboard using the layout `layout_id` instead of the one of the
+    /// foreground window. `layout_id` is an HKL in hexadecimal, as returned
+    /// by `available_layouts`.
+    pub fn with_layout(layout_id: &str) -> Option<Keyboard> {
+        let layout = usize::from_str_radix(layout_id.trim_start_matches("0x"), 16).ok()?;
+        let installed = available_layouts().ok()?;
+        if !installed
+            .iter()
+            .any(|id| usize::from_str_radix(id, 16).ok() == Some(layout))
+        {
+            return None;
+        }
+        let mut keyboard = Keyboard::new()?;
+        keyboard.fixed_layout = Some(layout);
+        Some(keyboard)
+    }
+
+    unsafe fn current_layout(&self) -> HKL {
+        match self.fixed_layout {
+            Some(layout) => layout as HKL,
+            None => {
+                let current_window_thread_id =
+                    GetWindowThreadProcessId(GetForegroundWindow(), null_mut());
+                GetKeyboardLayout(current_window_thread_id)
+            }
+        }
+    }
+
+    /// Checks the layout of the foreground window against the one used for
+    /// the last key. Returns true if it changed, pending dead keys are dropped
+    /// since they belonged to the previous layout.
+    pub(crate) unsafe fn refresh_layout(&mut self) -> bool {
+        let layout = self.current_layout() as usize;
+        if layout == self.last_layout {
+            return false;
+        }
+        // The very first key is not a change.
+        let changed = self.last_layout != 0;
+        self.last_layout = layout;
+        self.last_code = 0;
+        self.last_is_dead = false;
+        self.names.clear();
+        if changed {
+            self.layout_changed = true;
+        }
+        changed
+    }
+
+    /// Returns true once after the keyboard picked up a layout change.
+    pub(crate) fn take_layout_changed(&mut self) -> bool {
+        std::mem::replace(&mut self.layout_changed, false)
+    }
+
+    /// True while a dead key waits for the next key.
+    pub(crate) fn is_dead(&self) -> bool {
+        self.last_code != 0 && self.last_is_dead
+    }
+
     pub(crate) unsafe fn get_name(&mut self, lpdata: LPARAM) -> Option<String> {
         // https://gist.github.com/akimsko/2011327
         // https://www.experts-exchange.com/questions/23453780/LowLevel-Keystroke-Hook-removes-Accents-on-French-Keyboard.html
@@ -43,6 +125,20 @@ impl Keyboard {
         self.get_code_name(code, scan_code)
     }
 
+    /// Text carried by a VK_PACKET event, its scan code is a UTF-16 unit.
+    /// Characters outside the BMP come in two events, the first one gives None.
+    pub(crate) unsafe fn get_packet_name(&mut self, lpdata: LPARAM) -> Option<String> {
+        let unit = get_scan_code(lpdata) as u16;
+        if (0xD800..0xDC00).contains(&unit) {
+            self.high_surrogate = Some(unit);
+            return None;
+        }
+        match self.high_surrogate.take() {
+            Some(high) => String::from_utf16(&[high, unit]).ok(),
+            None => String::from_utf16(&[unit]).ok(),
+        }
+    }
+
     pub(crate) unsafe fn set_global_state(&mut self) -> Option<()> {
         let mut state = [0_u8; 256];
         let state_ptr = state.as_mut_ptr();
@@ -70,13 +166,88 @@ impl Keyboard {
         Some(())
     }
 
+    /// Name of a key release: the character of an Alt+numpad code when Alt
+    /// is released.
+    pub(crate) unsafe fn get_release_name(&mut self, lpdata: LPARAM) -> Option<String> {
+        self.release_code(get_code(lpdata))
+    }
+
+    unsafe fn release_code(&mut self, code: UINT) -> Option<String> {
+        if code != VK_LMENU as UINT && code != VK_MENU as UINT {
+            return None;
+        }
+        let digits = self.alt_digits.take()?;
+        let first = *digits.first()?;
+        let value = digits.iter().fold(0_u32, |acc, d| acc.wrapping_mul(10) + d);
+        // Like the system does: a leading 0 means the ANSI code page, else
+        // the OEM one (with its glyphs for control characters), modulo 256.
+        let (code_page, flags) = if first == 0 {
+            (CP_ACP, 0)
+        } else {
+            (CP_OEMCP, MB_USEGLYPHCHARS)
+        };
+        let byte = (value % 256) as u8 as i8;
+        let mut buff = [0_u16; 2];
+        let len = MultiByteToWideChar(code_page, flags, &byte, 1, buff.as_mut_ptr(), 2);
+        if len <= 0 {
+            return None;
+        }
+        String::from_utf16(&buff[..len as usize]).ok()
+    }
+
+    /// Keeps track of Alt+numpad sequences, returns true if the key is part
+    /// of one (so it doesn't type anything by itself).
+    fn track_alt_code(&mut self, code: UINT) -> bool {
+        if code == VK_LMENU as UINT || code == VK_MENU as UINT {
+            if self.alt_digits.is_none() {
+                self.alt_digits = Some(vec![]);
+            }
+            return false;
+        }
+        match self.alt_digits.as_mut() {
+            Some(digits) if (VK_NUMPAD0 as UINT..=VK_NUMPAD9 as UINT).contains(&code) => {
+                digits.push(code - VK_NUMPAD0 as UINT);
+                true
+            }
+            Some(_) => {
+                // Any other key breaks the sequence.
+                self.alt_digits = None;
+                false
+            }
+            None => false,
+        }
+    }
+
+    /// The parts of the keyboard state ToUnicodeEx looks at: Shift,
+    /// Control and Alt held, Caps Lock toggled.
+    fn modifier_state(&self) -> u32 {
+        let held = |vk: usize| (self.last_state[vk] & HIGHBIT != 0) as u32;
+        held(VK_SHIFT_)
+            | held(VK_CONTROL_) << 1
+            | held(VK_MENU_) << 2
+            | ((self.last_state[VK_CAPITAL_] & 1) as u32) << 3
+    }
+
     pub(crate) unsafe fn get_code_name(&mut self, code: UINT, scan_code: UINT) -> Option<String> {
-        let current_window_thread_id = GetWindowThreadProcessId(GetForegroundWindow(), null_mut());
+        if self.track_alt_code(code) {
+            return None;
+        }
+        self.refresh_layout();
+        let dead_pending = self.is_dead();
+        let modifier_state = self.modifier_state();
+        if !dead_pending {
+            if let Some(name) = self.names.get(code, modifier_state) {
+                self.last_code = code;
+                self.last_scan_code = scan_code;
+                self.last_is_dead = false;
+                return name;
+            }
+        }
         let state_ptr = self.last_state.as_mut_ptr();
         const BUF_LEN: i32 = 32;
         let mut buff = [0_u16; BUF_LEN as usize];
         let buff_ptr = buff.as_mut_ptr();
-        let layout = GetKeyboardLayout(current_window_thread_id);
+        let layout = self.current_layout();
         let len = ToUnicodeEx(code, scan_code, state_ptr, buff_ptr, 8 - 1, 0, layout);
 
         let mut is_dead = false;
@@ -110,6 +281,9 @@ impl Keyboard {
             self.last_scan_code = scan_code;
             self.last_is_dead = is_dead;
         }
+        if !dead_pending && !is_dead {
+            self.names.insert(code, modifier_state, result.clone());
+        }
         result
     }
 
@@ -127,6 +301,21 @@ impl Keyboard {
     }
 }
 
+/// Layouts that can be given to `Keyboard::with_layout`: the HKLs of the
+/// layouts installed for the user, in hexadecimal.
+pub fn available_layouts() -> Result<Vec<String>, DisplayError> {
+    unsafe {
+        let count = GetKeyboardLayoutList(0, null_mut());
+        let mut layouts: Vec<HKL> = vec![null_mut(); count.max(0) as usize];
+        let count = GetKeyboardLayoutList(count, layouts.as_mut_ptr());
+        layouts.truncate(count.max(0) as usize);
+        Ok(layouts
+            .iter()
+            .map(|layout| format!("{:08x}", *layout as usize))
+            .collect())
+    }
+}
+
 impl KeyboardState for Keyboard {
     fn add(&mut self, event_type: &EventType) -> Option<String> {
         match event_type {
@@ -141,6 +330,15 @@ impl KeyboardState for Keyboard {
                     self.last_state[VK_RSHIFT_] |= HIGHBIT;
                     None
                 }
+                Key::AltGr => {
+                    // Windows sees AltGr as LeftControl + RightAlt, ToUnicodeEx
+                    // needs both to give the third level.
+                    self.last_state[VK_CONTROL_] |= HIGHBIT;
+                    self.last_state[VK_LCONTROL_] |= HIGHBIT;
+                    self.last_state[VK_MENU_] |= HIGHBIT;
+                    self.last_state[VK_RMENU_] |= HIGHBIT;
+                    None
+                }
                 Key::CapsLock => {
                     self.last_state[VK_CAPITAL_] ^= 1;
                     None
@@ -161,6 +359,14 @@ impl KeyboardState for Keyboard {
                     self.last_state[VK_RSHIFT_] &= HIGHBIT;
                     None
                 }
+                Key::Alt => unsafe { self.release_code(VK_LMENU as UINT) },
+                Key::AltGr => {
+                    self.last_state[VK_CONTROL_] &= !HIGHBIT;
+                    self.last_state[VK_LCONTROL_] &= !HIGHBIT;
+                    self.last_state[VK_MENU_] &= !HIGHBIT;
+                    self.last_state[VK_RMENU_] &= !HIGHBIT;
+                    None
+                }
                 _ => None,
             },
 
@@ -171,5 +377,10 @@ impl KeyboardState for Keyboard {
     fn reset(&mut self) {
         self.last_state[16] = 0;
         self.last_state[20] = 0;
+        self.last_state[VK_CONTROL_] = 0;
+        self.last_state[VK_LCONTROL_] = 0;
+        self.last_state[VK_MENU_] = 0;
+        self.last_state[VK_RMENU_] = 0;
+        self.alt_digits = None;
     }
 }
@@ -27,6 +27,31 @@ macro_rules! decl_keycodes {
     };
 }
 
+/// The keypad keys with NumLock off come as the navigation keys they act
+/// as, without the extended flag the navigation block has. With
+/// `Settings::physical_keypad`, they're reported as the keypad keys.
+pub fn key_from_event_code(code: WORD, extended: bool) -> Key {
+    let key = key_from_code(code);
+    if extended || !crate::keypad::physical() {
+        return key;
+    }
+    match key {
+        Key::Insert => Key::Kp0,
+        Key::End => Key::Kp1,
+        Key::DownArrow => Key::Kp2,
+        Key::PageDown => Key::Kp3,
+        Key::LeftArrow => Key::Kp4,
+        // VK_CLEAR
+        Key::Unknown(12) => Key::Kp5,
+        Key::RightArrow => Key::Kp6,
+        Key::Home => Key::Kp7,
+        Key::UpArrow => Key::Kp8,
+        Key::PageUp => Key::Kp9,
+        Key::Delete => Key::KpDelete,
+        key => key,
+    }
+}
+
 // https://docs.microsoft.com/en-us/windows/win32/inputdev/virtual-key-codes
 // We redefined here for Letter and number keys which are not in winapi crate (and don't have a name either in win32)
 decl_keycodes! {
@@ -44,6 +69,18 @@ decl_keycodes! {
     F10, 121,
     F11, 122,
     F12, 123,
+    F13, 124,
+    F14, 125,
+    F15, 126,
+    F16, 127,
+    F17, 128,
+    F18, 129,
+    F19, 130,
+    F20, 131,
+    F21, 132,
+    F22, 133,
+    F23, 134,
+    F24, 135,
     F2, 113,
     F3, 114,
     F4, 115,
@@ -132,7 +169,31 @@ decl_keycodes! {
     Kp7, 103,
     Kp8, 104,
     Kp9, 105,
-    KpDelete, 110
+    KpDelete, 110,
+    VolumeUp, 0xAF,
+    VolumeDown, 0xAE,
+    VolumeMute, 0xAD,
+    MediaPlayPause, 0xB3,
+    MediaNext, 0xB0,
+    MediaPrev, 0xB1,
+    Menu, 0x5D,
+    // VK_KANA and VK_HANGUL share 0x15, the Japanese Katakana/Hiragana key
+    // comes as VK_DBE_HIRAGANA once the IME is on.
+    Kana, 0xF2,
+    Hangul, 0x15,
+    Henkan, 0x1C,
+    Muhenkan, 0x1D,
+    // VK_LAUNCH_APP2 is the calculator on virtually every keyboard
+    BrowserBack, 0xA6,
+    BrowserForward, 0xA7,
+    BrowserRefresh, 0xA8,
+    BrowserHome, 0xAC,
+    BrowserSearch, 0xAA,
+    LaunchMail, 0xB4,
+    Calculator, 0xB7,
+    Sleep, 0x5F
+    // Yen and Ro come as VK_OEM_5 and VK_OEM_102, i.e BackSlash and IntlBackslash.
+    // Brightness keys are handled by the firmware/driver, there is no virtual key.
 }
 
 #[cfg(test)]
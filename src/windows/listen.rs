@@ -1,7 +1,13 @@
-use crate::rdev::{Event, EventType, ListenError};
-use crate::windows::common::{convert, set_key_hook, set_mouse_hook, HookError, HOOK, KEYBOARD};
+use crate::rdev::{Event, EventType, ListenError, UnicodeInfo};
+use crate::windows::common::{
+    annotate_motion, convert, get_scan_code, set_key_hook, set_mouse_hook, Hook, HookError, HOOK,
+    KEYBOARD,
+};
+use crate::windows::state::event_numlock;
 use std::os::raw::c_int;
 use std::ptr::null_mut;
+use std::sync::mpsc;
+use std::thread;
 use std::time::SystemTime;
 use winapi::shared::minwindef::{LPARAM, LRESULT, WPARAM};
 use winapi::um::winuser::{CallNextHookEx, GetMessageA, HC_ACTION};
@@ -21,19 +27,53 @@ unsafe extern "system" fn raw_callback(code: c_int, param: WPARAM, lpdata: LPARA
     if code == HC_ACTION {
         let opt = convert(param, lpdata);
         if let Some(event_type) = opt {
-            let name = match &event_type {
+            let (name, layout_changed, is_dead) = match &event_type {
+                _ if !crate::names::wanted() => (None, false, false),
                 EventType::KeyPress(_key) => match (*KEYBOARD).lock() {
-                    Ok(mut keyboard) => keyboard.get_name(lpdata),
-                    Err(_) => None,
+                    Ok(mut keyboard) => {
+                        let name = keyboard.get_name(lpdata);
+                        (name, keyboard.take_layout_changed(), keyboard.is_dead())
+                    }
+                    Err(_) => (None, false, false),
                 },
-                _ => None,
+                EventType::KeyRelease(_key) => match (*KEYBOARD).lock() {
+                    Ok(mut keyboard) => (keyboard.get_release_name(lpdata), false, false),
+                    Err(_) => (None, false, false),
+                },
+                EventType::ImeCommit => match (*KEYBOARD).lock() {
+                    Ok(mut keyboard) => (keyboard.get_packet_name(lpdata), false, false),
+                    Err(_) => (None, false, false),
+                },
+                _ => (None, false, false),
+            };
+            let scan_code = match &event_type {
+                EventType::KeyPress(_) | EventType::KeyRelease(_) => get_scan_code(lpdata),
+                _ => 0,
             };
-            let event = Event {
+            let mut event = Event {
                 event_type,
                 time: SystemTime::now(),
+                unicode: UnicodeInfo::new(name.as_deref(), is_dead),
                 name,
+                scan_code,
+                display: None,
+                delta: None,
+                numlock: event_numlock(&event_type),
             };
+            annotate_motion(&mut event);
             if let Some(callback) = &mut GLOBAL_CALLBACK {
+                if layout_changed {
+                    callback(Event {
+                        event_type: EventType::LayoutChanged,
+                        time: SystemTime::now(),
+                        name: None,
+                        unicode: None,
+                        scan_code: 0,
+                        display: None,
+                        delta: None,
+                        numlock: None,
+                    });
+                }
                 callback(event);
             }
         }
@@ -41,16 +81,62 @@ unsafe extern "system" fn raw_callback(code: c_int, param: WPARAM, lpdata: LPARA
     CallNextHookEx(HOOK, code, param, lpdata)
 }
 
+unsafe fn install<T>(callback: T) -> Result<(Hook, Hook), ListenError>
+where
+    T: FnMut(Event) + 'static,
+{
+    GLOBAL_CALLBACK = Some(Box::new(callback));
+    let key_hook = set_key_hook(raw_callback)?;
+    let mouse_hook = set_mouse_hook(raw_callback)?;
+    Ok((key_hook, mouse_hook))
+}
+
 pub fn listen<T>(callback: T) -> Result<(), ListenError>
 where
     T: FnMut(Event) + 'static,
 {
     unsafe {
-        GLOBAL_CALLBACK = Some(Box::new(callback));
-        set_key_hook(raw_callback)?;
-        set_mouse_hook(raw_callback)?;
+        let _hooks = install(callback)?;
 
         GetMessageA(null_mut(), null_mut(), 0, 0);
     }
     Ok(())
 }
+
+/// The hooks live on a thread of our own pumping its messages, events are
+/// sent to `callback` on the calling thread. A callback that blocks then
+/// holds nothing back, and Windows never removes the hooks for it.
+pub fn listen_on_hook_thread<T>(mut callback: T) -> Result<(), ListenError>
+where
+    T: FnMut(Event) + 'static,
+{
+    let (sender, receiver) = mpsc::channel();
+    let (installed_sender, installed) = mpsc::channel();
+    let names = crate::names::wanted();
+    let physical_keypad = crate::keypad::physical();
+    thread::spawn(move || unsafe {
+        let _priority = crate::priority::raise();
+        let _names = crate::names::set_wanted(names);
+        let _keypad = crate::keypad::set_physical(physical_keypad);
+        let result = install(move |event| {
+            // The receiver only goes away with the calling thread.
+            sender.send(event).ok();
+        });
+        match result {
+            Ok(_hooks) => {
+                installed_sender.send(Ok(())).ok();
+                GetMessageA(null_mut(), null_mut(), 0, 0);
+            }
+            Err(error) => {
+                installed_sender.send(Err(error)).ok();
+            }
+        }
+    });
+    installed
+        .recv()
+        .unwrap_or(Err(ListenError::KeyHookError(0)))?;
+    for event in receiver {
+        callback(event);
+    }
+    Ok(())
+}
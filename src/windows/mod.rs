@@ -1,17 +1,33 @@
 extern crate winapi;
 
 mod common;
+mod devices;
 mod display;
 #[cfg(feature = "unstable_grab")]
 mod grab;
 mod keyboard;
 mod keycodes;
 mod listen;
+mod permissions;
+mod priority;
+mod raw_input;
 mod simulate;
+mod state;
 
-pub use crate::windows::display::display_size;
+pub use crate::windows::devices::listen_devices;
+pub use crate::windows::display::{
+    display_size, displays, enable_dpi_awareness, listen_display_changes,
+};
 #[cfg(feature = "unstable_grab")]
 pub use crate::windows::grab::grab;
-pub use crate::windows::keyboard::Keyboard;
-pub use crate::windows::listen::listen;
-pub use crate::windows::simulate::simulate;
+pub use crate::windows::keyboard::{available_layouts, Keyboard};
+pub use crate::windows::keycodes::{code_from_key, key_from_code};
+pub use crate::windows::listen::{listen, listen_on_hook_thread};
+pub use crate::windows::permissions::missing;
+pub use crate::windows::priority::raise_thread_priority;
+pub use crate::windows::raw_input::listen as listen_raw_input;
+pub use crate::windows::simulate::{simulate, simulate_unicode, Batch};
+pub use crate::windows::state::{
+    click_settings, get_lock_state, get_mouse_position, idle_time, keyboard_repeat, keyboard_state,
+    mouse_state, set_lock_state,
+};
@@ -0,0 +1,56 @@
+use crate::permissions::{Missing, Need};
+use std::convert::TryInto;
+use std::mem::{size_of, zeroed};
+use std::ptr::null_mut;
+use winapi::shared::minwindef::FALSE;
+use winapi::um::handleapi::CloseHandle;
+use winapi::um::processthreadsapi::{GetCurrentProcess, OpenProcessToken};
+use winapi::um::securitybaseapi::GetTokenInformation;
+use winapi::um::winnt::{TokenElevation, TOKEN_ELEVATION, TOKEN_QUERY};
+use winapi::um::winuser::{CloseDesktop, OpenInputDesktop, DESKTOP_READOBJECTS};
+
+/// Whether the desktop taking the input is ours, it isn't while the lock
+/// screen or a UAC prompt shows.
+fn on_input_desktop() -> bool {
+    unsafe {
+        let desktop = OpenInputDesktop(0, FALSE, DESKTOP_READOBJECTS);
+        if desktop.is_null() {
+            return false;
+        }
+        CloseDesktop(desktop);
+        true
+    }
+}
+
+/// Unknown counts as elevated, it's only a limit.
+fn is_elevated() -> bool {
+    unsafe {
+        let mut token = null_mut();
+        if OpenProcessToken(GetCurrentProcess(), TOKEN_QUERY, &mut token) == 0 {
+            return true;
+        }
+        let mut elevation: TOKEN_ELEVATION = zeroed();
+        let mut size = 0;
+        let queried = GetTokenInformation(
+            token,
+            TokenElevation,
+            &mut elevation as *mut TOKEN_ELEVATION as *mut _,
+            size_of::<TOKEN_ELEVATION>().try_into().unwrap_or(0),
+            &mut size,
+        );
+        CloseHandle(token);
+        queried == 0 || elevation.TokenIsElevated != 0
+    }
+}
+
+/// Hooks, `SendInput` and grabs all need the same here.
+pub fn missing(_need: Need) -> Vec<Missing> {
+    let mut missing = vec![];
+    if !on_input_desktop() {
+        missing.push(Missing::InputDesktop);
+    }
+    if !is_elevated() {
+        missing.push(Missing::Elevation);
+    }
+    missing
+}
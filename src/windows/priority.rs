@@ -0,0 +1,26 @@
+use winapi::ctypes::c_int;
+use winapi::um::processthreadsapi::{GetCurrentThread, GetThreadPriority, SetThreadPriority};
+use winapi::um::winbase::{THREAD_PRIORITY_ERROR_RETURN, THREAD_PRIORITY_TIME_CRITICAL};
+
+/// The priority the thread had before `raise_thread_priority`.
+pub struct ThreadPriority(c_int);
+
+impl Drop for ThreadPriority {
+    fn drop(&mut self) {
+        unsafe { SetThreadPriority(GetCurrentThread(), self.0) };
+    }
+}
+
+pub fn raise_thread_priority() -> Option<ThreadPriority> {
+    unsafe {
+        let thread = GetCurrentThread();
+        let previous = GetThreadPriority(thread);
+        if previous == THREAD_PRIORITY_ERROR_RETURN as c_int {
+            return None;
+        }
+        if SetThreadPriority(thread, THREAD_PRIORITY_TIME_CRITICAL as c_int) == 0 {
+            return None;
+        }
+        Some(ThreadPriority(previous))
+    }
+}
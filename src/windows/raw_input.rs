@@ -0,0 +1,246 @@
+use crate::motion::{display_rects, Motion};
+use crate::rdev::{Button, Event, EventType, ListenError, OsError, UnicodeInfo};
+use crate::windows::common::{create_window, run_message_loop, FALSE, KEYBOARD};
+use crate::windows::keycodes::key_from_event_code;
+use crate::windows::state::event_numlock;
+use std::mem::{size_of, zeroed};
+use std::os::raw::c_short;
+use std::ptr::addr_of_mut;
+use std::time::SystemTime;
+use winapi::shared::minwindef::{LPARAM, LPVOID, LRESULT, UINT, WPARAM};
+use winapi::shared::windef::{HWND, POINT};
+use winapi::um::winuser::{
+    DefWindowProcW, GetCursorPos, GetRawInputData, MapVirtualKeyW, RegisterRawInputDevices,
+    HRAWINPUT, HWND_MESSAGE, MAPVK_VSC_TO_VK_EX, MOUSE_MOVE_ABSOLUTE, RAWINPUT, RAWINPUTDEVICE,
+    RAWINPUTHEADER, RAWKEYBOARD, RAWMOUSE, RIDEV_INPUTSINK, RID_INPUT, RIM_TYPEKEYBOARD,
+    RIM_TYPEMOUSE, RI_KEY_BREAK, RI_KEY_E0, RI_MOUSE_BUTTON_4_DOWN, RI_MOUSE_BUTTON_4_UP,
+    RI_MOUSE_BUTTON_5_DOWN, RI_MOUSE_BUTTON_5_UP, RI_MOUSE_LEFT_BUTTON_DOWN,
+    RI_MOUSE_LEFT_BUTTON_UP, RI_MOUSE_MIDDLE_BUTTON_DOWN, RI_MOUSE_MIDDLE_BUTTON_UP,
+    RI_MOUSE_RIGHT_BUTTON_DOWN, RI_MOUSE_RIGHT_BUTTON_UP, RI_MOUSE_WHEEL, VK_CONTROL, VK_LCONTROL,
+    VK_LMENU, VK_MENU, VK_RCONTROL, VK_RMENU, VK_SHIFT, WHEEL_DELTA, WM_INPUT,
+};
+
+// Generic desktop page, keyboards and mice.
+const USAGE_PAGE_GENERIC: u16 = 0x01;
+const USAGE_MOUSE: u16 = 0x02;
+const USAGE_KEYBOARD: u16 = 0x06;
+const RAW_INPUT_ERROR: UINT = UINT::MAX;
+// Sent as part of escaped scan code sequences, not a key by itself.
+const VK_FAKE: u16 = 0xFF;
+// Missing from winapi.
+const RI_MOUSE_HWHEEL: u16 = 0x0800;
+
+static mut GLOBAL_CALLBACK: Option<Box<dyn FnMut(Event)>> = None;
+static mut MOTION: Motion = Motion::new();
+
+const BUTTONS: [(u16, Button, bool); 10] = [
+    (RI_MOUSE_LEFT_BUTTON_DOWN, Button::Left, true),
+    (RI_MOUSE_LEFT_BUTTON_UP, Button::Left, false),
+    (RI_MOUSE_RIGHT_BUTTON_DOWN, Button::Right, true),
+    (RI_MOUSE_RIGHT_BUTTON_UP, Button::Right, false),
+    (RI_MOUSE_MIDDLE_BUTTON_DOWN, Button::Middle, true),
+    (RI_MOUSE_MIDDLE_BUTTON_UP, Button::Middle, false),
+    (RI_MOUSE_BUTTON_4_DOWN, Button::Back, true),
+    (RI_MOUSE_BUTTON_4_UP, Button::Back, false),
+    (RI_MOUSE_BUTTON_5_DOWN, Button::Forward, true),
+    (RI_MOUSE_BUTTON_5_UP, Button::Forward, false),
+];
+
+/// Raw input only gives the generic modifier keys, the side comes from the
+/// scan code or the E0 prefix.
+fn virtual_key(keyboard: &RAWKEYBOARD) -> u16 {
+    let extended = keyboard.Flags & RI_KEY_E0 as u16 != 0;
+    match keyboard.VKey as i32 {
+        VK_SHIFT => unsafe { MapVirtualKeyW(keyboard.MakeCode.into(), MAPVK_VSC_TO_VK_EX) as u16 },
+        VK_CONTROL if extended => VK_RCONTROL as u16,
+        VK_CONTROL => VK_LCONTROL as u16,
+        VK_MENU if extended => VK_RMENU as u16,
+        VK_MENU => VK_LMENU as u16,
+        _ => keyboard.VKey,
+    }
+}
+
+unsafe fn send(event_type: EventType, name: Option<String>, is_dead: bool, scan_code: u32) {
+    send_event(Event {
+        event_type,
+        time: SystemTime::now(),
+        unicode: UnicodeInfo::new(name.as_deref(), is_dead),
+        name,
+        scan_code,
+        display: None,
+        delta: None,
+        numlock: event_numlock(&event_type),
+    });
+}
+
+unsafe fn send_event(event: Event) {
+    if let Some(callback) = &mut *addr_of_mut!(GLOBAL_CALLBACK) {
+        callback(event);
+    }
+}
+
+unsafe fn keyboard_input(keyboard: &RAWKEYBOARD) {
+    if keyboard.VKey == VK_FAKE {
+        return;
+    }
+    let code = virtual_key(keyboard);
+    let scan_code = u32::from(keyboard.MakeCode);
+    let key = key_from_event_code(code, keyboard.Flags & RI_KEY_E0 as u16 != 0);
+    if keyboard.Flags & RI_KEY_BREAK as u16 != 0 {
+        send(EventType::KeyRelease(key), None, false, scan_code);
+        return;
+    }
+    if !crate::names::wanted() {
+        send(EventType::KeyPress(key), None, false, scan_code);
+        return;
+    }
+    let (name, layout_changed, is_dead) = match (*KEYBOARD).lock() {
+        Ok(mut state) => {
+            let name = state
+                .set_global_state()
+                .and_then(|_| state.get_code_name(code.into(), scan_code));
+            (name, state.take_layout_changed(), state.is_dead())
+        }
+        Err(_) => (None, false, false),
+    };
+    if layout_changed {
+        send(EventType::LayoutChanged, None, false, 0);
+    }
+    send(EventType::KeyPress(key), name, is_dead, scan_code);
+}
+
+/// One message can carry a move, several button changes and a wheel turn.
+unsafe fn mouse_input(mouse: &RAWMOUSE) {
+    let relative = mouse.usFlags & MOUSE_MOVE_ABSOLUTE == 0;
+    if !relative || mouse.lLastX != 0 || mouse.lLastY != 0 {
+        // Raw positions are unaccelerated, the cursor position is where
+        // Windows actually put the pointer.
+        let mut point: POINT = zeroed();
+        if GetCursorPos(&mut point) != FALSE {
+            let delta = if relative {
+                Some((mouse.lLastX as f64, mouse.lLastY as f64))
+            } else {
+                None
+            };
+            let mut event = Event::new(
+                EventType::MouseMove {
+                    x: point.x as f64,
+                    y: point.y as f64,
+                },
+                SystemTime::now(),
+                None,
+            );
+            event.delta = delta;
+            (*addr_of_mut!(MOTION)).annotate(&mut event, display_rects);
+            send_event(event);
+        }
+    }
+    let flags = mouse.usButtonFlags;
+    for (flag, button, pressed) in BUTTONS.iter() {
+        if flags & flag != 0 {
+            let event_type = if *pressed {
+                EventType::ButtonPress(*button)
+            } else {
+                EventType::ButtonRelease(*button)
+            };
+            send(event_type, None, false, 0);
+        }
+    }
+    let delta = (mouse.usButtonData as c_short / WHEEL_DELTA) as i64;
+    if flags & RI_MOUSE_WHEEL != 0 {
+        send(
+            EventType::Wheel {
+                delta_x: 0,
+                delta_y: delta,
+            },
+            None,
+            false,
+            0,
+        );
+    }
+    if flags & RI_MOUSE_HWHEEL != 0 {
+        send(
+            EventType::Wheel {
+                delta_x: delta,
+                delta_y: 0,
+            },
+            None,
+            false,
+            0,
+        );
+    }
+}
+
+unsafe extern "system" fn window_proc(
+    hwnd: HWND,
+    msg: UINT,
+    wparam: WPARAM,
+    lparam: LPARAM,
+) -> LRESULT {
+    if msg == WM_INPUT {
+        // Keyboards and mice always fit, only HID reports can be bigger.
+        let mut raw: RAWINPUT = zeroed();
+        let mut size = size_of::<RAWINPUT>() as UINT;
+        let len = GetRawInputData(
+            lparam as HRAWINPUT,
+            RID_INPUT,
+            &mut raw as *mut RAWINPUT as LPVOID,
+            &mut size,
+            size_of::<RAWINPUTHEADER>() as UINT,
+        );
+        if len != RAW_INPUT_ERROR {
+            match raw.header.dwType {
+                RIM_TYPEKEYBOARD => keyboard_input(raw.data.keyboard()),
+                RIM_TYPEMOUSE => mouse_input(raw.data.mouse()),
+                _ => {}
+            }
+        }
+    }
+    // WM_INPUT needs DefWindowProc too, to clean up.
+    DefWindowProcW(hwnd, msg, wparam, lparam)
+}
+
+/// Listens with Raw Input: Windows sends keyboard and mouse input to a
+/// hidden window (RIDEV_INPUTSINK, so even when another one has focus).
+/// Nothing is hooked, so other applications see no added latency and the
+/// system can't silently remove us, but touch, pen and IME events aren't
+/// told apart. Raw Input registration is per process and overrides the one
+/// of `listen_devices`.
+pub fn listen<T>(callback: T) -> Result<(), ListenError>
+where
+    T: FnMut(Event) + 'static,
+{
+    unsafe {
+        GLOBAL_CALLBACK = Some(Box::new(callback));
+        let hwnd = create_window("rdev_raw_input", window_proc, HWND_MESSAGE);
+        if hwnd.is_null() {
+            return Err(ListenError::RawInputError(OsError::last("CreateWindowExW")));
+        }
+        let devices = [
+            RAWINPUTDEVICE {
+                usUsagePage: USAGE_PAGE_GENERIC,
+                usUsage: USAGE_KEYBOARD,
+                dwFlags: RIDEV_INPUTSINK,
+                hwndTarget: hwnd,
+            },
+            RAWINPUTDEVICE {
+                usUsagePage: USAGE_PAGE_GENERIC,
+                usUsage: USAGE_MOUSE,
+                dwFlags: RIDEV_INPUTSINK,
+                hwndTarget: hwnd,
+            },
+        ];
+        let registered = RegisterRawInputDevices(
+            devices.as_ptr(),
+            devices.len() as UINT,
+            size_of::<RAWINPUTDEVICE>() as UINT,
+        );
+        if registered == FALSE {
+            let error = OsError::last("RegisterRawInputDevices");
+            return Err(ListenError::RawInputError(error));
+        }
+        crate::trace::installed("raw input");
+        run_message_loop();
+    }
+    Ok(())
+}
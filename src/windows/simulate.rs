@@ -7,16 +7,20 @@ use winapi::shared::minwindef::{DWORD, UINT, WORD};
 use winapi::shared::ntdef::LONG;
 use winapi::um::winuser::{
     GetSystemMetrics, INPUT_u, SendInput, INPUT, INPUT_KEYBOARD, INPUT_MOUSE, KEYBDINPUT,
-    KEYEVENTF_KEYUP, MOUSEEVENTF_ABSOLUTE, MOUSEEVENTF_HWHEEL, MOUSEEVENTF_LEFTDOWN,
-    MOUSEEVENTF_LEFTUP, MOUSEEVENTF_MIDDLEDOWN, MOUSEEVENTF_MIDDLEUP, MOUSEEVENTF_MOVE,
-    MOUSEEVENTF_RIGHTDOWN, MOUSEEVENTF_RIGHTUP, MOUSEEVENTF_VIRTUALDESK, MOUSEEVENTF_WHEEL,
-    MOUSEEVENTF_XDOWN, MOUSEEVENTF_XUP, MOUSEINPUT, SM_CXVIRTUALSCREEN, SM_CYVIRTUALSCREEN,
-    WHEEL_DELTA,
+    KEYEVENTF_EXTENDEDKEY, KEYEVENTF_KEYUP, KEYEVENTF_UNICODE, MOUSEEVENTF_ABSOLUTE,
+    MOUSEEVENTF_HWHEEL, MOUSEEVENTF_LEFTDOWN, MOUSEEVENTF_LEFTUP, MOUSEEVENTF_MIDDLEDOWN,
+    MOUSEEVENTF_MIDDLEUP, MOUSEEVENTF_MOVE, MOUSEEVENTF_RIGHTDOWN, MOUSEEVENTF_RIGHTUP,
+    MOUSEEVENTF_VIRTUALDESK, MOUSEEVENTF_WHEEL, MOUSEEVENTF_XDOWN, MOUSEEVENTF_XUP, MOUSEINPUT,
+    SM_CXVIRTUALSCREEN, SM_CYVIRTUALSCREEN, VK_APPS, VK_DELETE, VK_DIVIDE, VK_DOWN, VK_END,
+    VK_HOME, VK_INSERT, VK_LEFT, VK_LWIN, VK_MEDIA_NEXT_TRACK, VK_MEDIA_PLAY_PAUSE,
+    VK_MEDIA_PREV_TRACK, VK_NEXT, VK_NUMLOCK, VK_PRIOR, VK_RCONTROL, VK_RIGHT, VK_RMENU, VK_RWIN,
+    VK_SNAPSHOT, VK_UP, VK_VOLUME_DOWN, VK_VOLUME_MUTE, VK_VOLUME_UP, WHEEL_DELTA, XBUTTON1,
+    XBUTTON2,
 };
 /// Not defined in win32 but define here for clarity
 static KEYEVENTF_KEYDOWN: DWORD = 0;
 
-fn sim_mouse_event(flags: DWORD, data: DWORD, dx: LONG, dy: LONG) -> Result<(), SimulateError> {
+fn mouse_input(flags: DWORD, data: DWORD, dx: LONG, dy: LONG) -> INPUT {
     let mut union: INPUT_u = unsafe { std::mem::zeroed() };
     let inner_union = unsafe { union.mi_mut() };
     *inner_union = MOUSEINPUT {
@@ -27,25 +31,13 @@ fn sim_mouse_event(flags: DWORD, data: DWORD, dx: LONG, dy: LONG) -> Result<(),
         time: 0,
         dwExtraInfo: 0,
     };
-    let mut input = [INPUT {
+    INPUT {
         type_: INPUT_MOUSE,
         u: union,
-    }; 1];
-    let value = unsafe {
-        SendInput(
-            input.len() as UINT,
-            input.as_mut_ptr(),
-            size_of::<INPUT>() as c_int,
-        )
-    };
-    if value != 1 {
-        Err(SimulateError)
-    } else {
-        Ok(())
     }
 }
 
-fn sim_keyboard_event(flags: DWORD, vk: WORD, scan: WORD) -> Result<(), SimulateError> {
+fn keyboard_input(flags: DWORD, vk: WORD, scan: WORD) -> INPUT {
     let mut union: INPUT_u = unsafe { std::mem::zeroed() };
     let inner_union = unsafe { union.ki_mut() };
     *inner_union = KEYBDINPUT {
@@ -55,65 +47,114 @@ fn sim_keyboard_event(flags: DWORD, vk: WORD, scan: WORD) -> Result<(), Simulate
         time: 0,
         dwExtraInfo: 0,
     };
-    let mut input = [INPUT {
+    INPUT {
         type_: INPUT_KEYBOARD,
         u: union,
-    }; 1];
+    }
+}
+
+/// Sends `inputs` with a single SendInput, input from devices or other
+/// processes can't come in between.
+fn send_inputs(inputs: &mut [INPUT]) -> Result<(), SimulateError> {
+    if inputs.is_empty() {
+        return Ok(());
+    }
     let value = unsafe {
         SendInput(
-            input.len() as UINT,
-            input.as_mut_ptr(),
+            inputs.len() as UINT,
+            inputs.as_mut_ptr(),
             size_of::<INPUT>() as c_int,
         )
     };
-    if value != 1 {
+    if value as usize != inputs.len() {
         Err(SimulateError)
     } else {
         Ok(())
     }
 }
 
-pub fn simulate(event_type: &EventType) -> Result<(), SimulateError> {
-    match event_type {
+/// Keys that need KEYEVENTF_EXTENDEDKEY, without it right Alt/Control
+/// are sent as the left ones and navigation keys as their keypad twin.
+fn is_extended(vk: WORD) -> bool {
+    [
+        VK_RMENU,
+        VK_RCONTROL,
+        VK_INSERT,
+        VK_DELETE,
+        VK_HOME,
+        VK_END,
+        VK_PRIOR,
+        VK_NEXT,
+        VK_LEFT,
+        VK_RIGHT,
+        VK_UP,
+        VK_DOWN,
+        VK_NUMLOCK,
+        VK_DIVIDE,
+        VK_SNAPSHOT,
+        VK_LWIN,
+        VK_RWIN,
+        VK_APPS,
+        VK_VOLUME_MUTE,
+        VK_VOLUME_DOWN,
+        VK_VOLUME_UP,
+        VK_MEDIA_NEXT_TRACK,
+        VK_MEDIA_PREV_TRACK,
+        VK_MEDIA_PLAY_PAUSE,
+    ]
+    .contains(&(vk as i32))
+}
+
+fn key_input(flags: DWORD, vk: WORD) -> INPUT {
+    if is_extended(vk) {
+        keyboard_input(flags | KEYEVENTF_EXTENDEDKEY, vk, 0)
+    } else {
+        keyboard_input(flags, vk, 0)
+    }
+}
+
+/// Browsers scroll about 100 pixels for each WHEEL_DELTA (120).
+const WHEEL_UNITS_PER_PIXEL: f64 = 1.2;
+
+fn wheel_data(delta: i64) -> Result<DWORD, SimulateError> {
+    Ok((c_short::try_from(delta).map_err(|_| SimulateError)? * WHEEL_DELTA) as u32)
+}
+
+/// The inputs sending `event_type`, added to `inputs`.
+fn push_inputs(event_type: &EventType, inputs: &mut Vec<INPUT>) -> Result<(), SimulateError> {
+    let input = match event_type {
         EventType::KeyPress(key) => {
             let code = code_from_key(*key).ok_or(SimulateError)?;
-            sim_keyboard_event(KEYEVENTF_KEYDOWN, code, 0)
+            key_input(KEYEVENTF_KEYDOWN, code)
         }
         EventType::KeyRelease(key) => {
             let code = code_from_key(*key).ok_or(SimulateError)?;
-            sim_keyboard_event(KEYEVENTF_KEYUP, code, 0)
+            key_input(KEYEVENTF_KEYUP, code)
         }
         EventType::ButtonPress(button) => match button {
-            Button::Left => sim_mouse_event(MOUSEEVENTF_LEFTDOWN, 0, 0, 0),
-            Button::Middle => sim_mouse_event(MOUSEEVENTF_MIDDLEDOWN, 0, 0, 0),
-            Button::Right => sim_mouse_event(MOUSEEVENTF_RIGHTDOWN, 0, 0, 0),
-            Button::Unknown(code) => sim_mouse_event(MOUSEEVENTF_XDOWN, (*code).into(), 0, 0),
+            Button::Left => mouse_input(MOUSEEVENTF_LEFTDOWN, 0, 0, 0),
+            Button::Middle => mouse_input(MOUSEEVENTF_MIDDLEDOWN, 0, 0, 0),
+            Button::Right => mouse_input(MOUSEEVENTF_RIGHTDOWN, 0, 0, 0),
+            Button::Back => mouse_input(MOUSEEVENTF_XDOWN, XBUTTON1.into(), 0, 0),
+            Button::Forward => mouse_input(MOUSEEVENTF_XDOWN, XBUTTON2.into(), 0, 0),
+            Button::Unknown(code) => mouse_input(MOUSEEVENTF_XDOWN, (*code).into(), 0, 0),
         },
         EventType::ButtonRelease(button) => match button {
-            Button::Left => sim_mouse_event(MOUSEEVENTF_LEFTUP, 0, 0, 0),
-            Button::Middle => sim_mouse_event(MOUSEEVENTF_MIDDLEUP, 0, 0, 0),
-            Button::Right => sim_mouse_event(MOUSEEVENTF_RIGHTUP, 0, 0, 0),
-            Button::Unknown(code) => sim_mouse_event(MOUSEEVENTF_XUP, (*code).into(), 0, 0),
+            Button::Left => mouse_input(MOUSEEVENTF_LEFTUP, 0, 0, 0),
+            Button::Middle => mouse_input(MOUSEEVENTF_MIDDLEUP, 0, 0, 0),
+            Button::Right => mouse_input(MOUSEEVENTF_RIGHTUP, 0, 0, 0),
+            Button::Back => mouse_input(MOUSEEVENTF_XUP, XBUTTON1.into(), 0, 0),
+            Button::Forward => mouse_input(MOUSEEVENTF_XUP, XBUTTON2.into(), 0, 0),
+            Button::Unknown(code) => mouse_input(MOUSEEVENTF_XUP, (*code).into(), 0, 0),
         },
         EventType::Wheel { delta_x, delta_y } => {
             if *delta_x != 0 {
-                sim_mouse_event(
-                    MOUSEEVENTF_HWHEEL,
-                    (c_short::try_from(*delta_x).map_err(|_| SimulateError)? * WHEEL_DELTA) as u32,
-                    0,
-                    0,
-                )?;
+                inputs.push(mouse_input(MOUSEEVENTF_HWHEEL, wheel_data(*delta_x)?, 0, 0));
             }
-
             if *delta_y != 0 {
-                sim_mouse_event(
-                    MOUSEEVENTF_WHEEL,
-                    (c_short::try_from(*delta_y).map_err(|_| SimulateError)? * WHEEL_DELTA) as u32,
-                    0,
-                    0,
-                )?;
+                inputs.push(mouse_input(MOUSEEVENTF_WHEEL, wheel_data(*delta_y)?, 0, 0));
             }
-            Ok(())
+            return Ok(());
         }
         EventType::MouseMove { x, y } => {
             let width = unsafe { GetSystemMetrics(SM_CXVIRTUALSCREEN) };
@@ -122,12 +163,89 @@ pub fn simulate(event_type: &EventType) -> Result<(), SimulateError> {
                 return Err(SimulateError);
             }
 
-            sim_mouse_event(
+            mouse_input(
                 MOUSEEVENTF_MOVE | MOUSEEVENTF_ABSOLUTE | MOUSEEVENTF_VIRTUALDESK,
                 0,
                 (*x as i32 + 1) * 65535 / width,
                 (*y as i32 + 1) * 65535 / height,
             )
         }
+        EventType::Scroll {
+            delta_x, delta_y, ..
+        } => {
+            // Fractions of WHEEL_DELTA, like precision touchpads send.
+            let units = |delta: f64| -> Result<DWORD, SimulateError> {
+                let units = (delta * WHEEL_UNITS_PER_PIXEL).round();
+                Ok(c_short::try_from(units as i64).map_err(|_| SimulateError)? as u32)
+            };
+            if *delta_x != 0.0 {
+                inputs.push(mouse_input(MOUSEEVENTF_HWHEEL, units(*delta_x)?, 0, 0));
+            }
+            if *delta_y != 0.0 {
+                inputs.push(mouse_input(MOUSEEVENTF_WHEEL, units(*delta_y)?, 0, 0));
+            }
+            return Ok(());
+        }
+        EventType::LayoutChanged
+        | EventType::ImeComposition
+        | EventType::ImeCommit
+        | EventType::SecureInput(_)
+        | EventType::TouchDown { .. }
+        | EventType::TouchMove { .. }
+        | EventType::TouchUp { .. }
+        | EventType::PenDown(_)
+        | EventType::PenMove(_)
+        | EventType::PenUp(_) => return Err(SimulateError),
+        #[cfg(feature = "gestures")]
+        EventType::Gesture(_) => return Err(SimulateError),
+    };
+    inputs.push(input);
+    Ok(())
+}
+
+pub fn simulate(event_type: &EventType) -> Result<(), SimulateError> {
+    let mut inputs = Vec::with_capacity(2);
+    push_inputs(event_type, &mut inputs)?;
+    send_inputs(&mut inputs)
+}
+
+/// Events sent together with one SendInput call, so nothing gets in
+/// between a chord or a word. Nothing is sent if one of them can't be
+/// simulated.
+#[derive(Default)]
+pub struct Batch {
+    inputs: Vec<INPUT>,
+}
+
+impl Batch {
+    pub fn push(&mut self, event_type: &EventType) -> Result<(), SimulateError> {
+        push_inputs(event_type, &mut self.inputs)
+    }
+
+    /// Like `simulate_unicode`.
+    pub fn push_unicode(&mut self, c: char) {
+        push_unicode_inputs(c, &mut self.inputs)
+    }
+
+    pub fn send(mut self) -> Result<(), SimulateError> {
+        send_inputs(&mut self.inputs)
     }
 }
+
+fn push_unicode_inputs(c: char, inputs: &mut Vec<INPUT>) {
+    let mut buffer = [0_u16; 2];
+    for unit in c.encode_utf16(&mut buffer).iter() {
+        inputs.push(keyboard_input(KEYEVENTF_UNICODE, 0, *unit));
+        inputs.push(keyboard_input(
+            KEYEVENTF_UNICODE | KEYEVENTF_KEYUP,
+            0,
+            *unit,
+        ));
+    }
+}
+
+pub fn simulate_unicode(c: char) -> Result<(), SimulateError> {
+    let mut inputs = Vec::with_capacity(4);
+    push_unicode_inputs(c, &mut inputs);
+    send_inputs(&mut inputs)
+}
@@ -0,0 +1,151 @@
+use crate::rdev::{Button, ClickSettings, DisplayError, EventType, Key, LockKey, SimulateError};
+use crate::windows::keycodes::key_from_code;
+use crate::windows::simulate::simulate;
+use std::collections::HashSet;
+use std::mem::size_of;
+use std::time::Duration;
+use winapi::shared::minwindef::{DWORD, UINT};
+use winapi::shared::windef::POINT;
+use winapi::um::sysinfoapi::GetTickCount;
+use winapi::um::winuser::{
+    GetAsyncKeyState, GetCursorPos, GetDoubleClickTime, GetKeyState, GetLastInputInfo,
+    GetSystemMetrics, SystemParametersInfoW, LASTINPUTINFO, SM_CXDOUBLECLK, SM_CXDRAG,
+    SPI_GETKEYBOARDDELAY, SPI_GETKEYBOARDSPEED, VK_CAPITAL, VK_CONTROL, VK_LBUTTON, VK_MBUTTON,
+    VK_MENU, VK_NUMLOCK, VK_RBUTTON, VK_SCROLL, VK_SHIFT, VK_XBUTTON1, VK_XBUTTON2,
+};
+
+fn is_down(vk: i32) -> bool {
+    // The most significant bit is set when the key is down.
+    unsafe { (GetAsyncKeyState(vk) as u16) & 0x8000 != 0 }
+}
+
+pub fn keyboard_state() -> Result<HashSet<Key>, DisplayError> {
+    let mut keys = HashSet::new();
+    for vk in 0x08..=0xFE {
+        // Mouse buttons and the generic modifiers, the left/right ones are
+        // reported too.
+        if [
+            VK_LBUTTON,
+            VK_RBUTTON,
+            VK_MBUTTON,
+            VK_XBUTTON1,
+            VK_XBUTTON2,
+            VK_SHIFT,
+            VK_CONTROL,
+            VK_MENU,
+        ]
+        .contains(&vk)
+        {
+            continue;
+        }
+        if is_down(vk) {
+            keys.insert(key_from_code(vk as u16));
+        }
+    }
+    Ok(keys)
+}
+
+pub fn mouse_state() -> Result<HashSet<Button>, DisplayError> {
+    let mut buttons = HashSet::new();
+    if is_down(VK_LBUTTON) {
+        buttons.insert(Button::Left);
+    }
+    if is_down(VK_RBUTTON) {
+        buttons.insert(Button::Right);
+    }
+    if is_down(VK_MBUTTON) {
+        buttons.insert(Button::Middle);
+    }
+    if is_down(VK_XBUTTON1) {
+        buttons.insert(Button::Back);
+    }
+    if is_down(VK_XBUTTON2) {
+        buttons.insert(Button::Forward);
+    }
+    Ok(buttons)
+}
+
+pub fn get_mouse_position() -> Result<(f64, f64), DisplayError> {
+    let mut point = POINT { x: 0, y: 0 };
+    if unsafe { GetCursorPos(&mut point) } == 0 {
+        return Err(DisplayError::NoDisplay);
+    }
+    Ok((point.x.into(), point.y.into()))
+}
+
+/// GetLastInputInfo is session wide and doesn't need a hook.
+pub fn idle_time() -> Result<Duration, DisplayError> {
+    let mut info = LASTINPUTINFO {
+        cbSize: size_of::<LASTINPUTINFO>() as UINT,
+        dwTime: 0,
+    };
+    if unsafe { GetLastInputInfo(&mut info) } == 0 {
+        return Err(DisplayError::NoDisplay);
+    }
+    // Both are tick counts, which wrap around every 49 days.
+    let idle = unsafe { GetTickCount() }.wrapping_sub(info.dwTime);
+    Ok(Duration::from_millis(idle.into()))
+}
+
+pub fn click_settings() -> Result<ClickSettings, DisplayError> {
+    let (interval, double_click, drag) = unsafe {
+        (
+            GetDoubleClickTime(),
+            GetSystemMetrics(SM_CXDOUBLECLK),
+            GetSystemMetrics(SM_CXDRAG),
+        )
+    };
+    Ok(ClickSettings {
+        double_click_interval: Duration::from_millis(interval.into()),
+        // The width of a rectangle centered on the first click.
+        double_click_distance: f64::from(double_click) / 2.0,
+        drag_threshold: f64::from(drag),
+        ..Default::default()
+    })
+}
+
+fn system_parameter(action: UINT) -> Result<DWORD, DisplayError> {
+    let mut value: DWORD = 0;
+    let ok = unsafe { SystemParametersInfoW(action, 0, &mut value as *mut DWORD as *mut _, 0) };
+    if ok == 0 {
+        return Err(DisplayError::NoDisplay);
+    }
+    Ok(value)
+}
+
+/// The settings are steps, from 0 (250ms) to 3 (1s) for the delay and from
+/// 0 (about 2.5 repeats per second) to 31 (about 30) for the speed.
+pub fn keyboard_repeat() -> Result<(Duration, f64), DisplayError> {
+    let delay = system_parameter(SPI_GETKEYBOARDDELAY)?.min(3);
+    let speed = system_parameter(SPI_GETKEYBOARDSPEED)?.min(31);
+    Ok((
+        Duration::from_millis(250 * u64::from(delay + 1)),
+        2.5 + f64::from(speed) * 27.5 / 31.0,
+    ))
+}
+
+pub fn get_lock_state(lock: LockKey) -> Result<bool, DisplayError> {
+    let vk = match lock {
+        LockKey::CapsLock => VK_CAPITAL,
+        LockKey::NumLock => VK_NUMLOCK,
+        LockKey::ScrollLock => VK_SCROLL,
+    };
+    // The least significant bit is the toggle state.
+    Ok(unsafe { GetKeyState(vk) } & 1 != 0)
+}
+
+/// `Event::numlock` for the hooks, None for events other than keys.
+pub(crate) fn event_numlock(event_type: &EventType) -> Option<bool> {
+    match event_type {
+        EventType::KeyPress(_) | EventType::KeyRelease(_) => get_lock_state(LockKey::NumLock).ok(),
+        _ => None,
+    }
+}
+
+pub fn set_lock_state(lock: LockKey, on: bool) -> Result<(), SimulateError> {
+    if get_lock_state(lock).map_err(|_| SimulateError)? == on {
+        return Ok(());
+    }
+    simulate(&EventType::KeyPress(lock.key()))?;
+    simulate(&EventType::KeyRelease(lock.key()))
+}
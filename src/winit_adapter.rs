@@ -0,0 +1,73 @@
+//! Sends the events of `listen` to a winit event loop, requires the `winit`
+//! feature. winit only hands out events of its own windows, this brings the
+//! global ones in as user events.
+//!
+//! ```no_run
+//! use rdev::winit_adapter::{forward, GlobalEvent};
+//! use winit::event::Event;
+//! use winit::event_loop::EventLoopBuilder;
+//!
+//! let event_loop = EventLoopBuilder::<GlobalEvent>::with_user_event().build();
+//! forward(event_loop.create_proxy());
+//! event_loop.run(|event, _, _| {
+//!     if let Event::UserEvent(global) = event {
+//!         println!("{:?} at {:?}", global.event.event_type, global.position);
+//!     }
+//! });
+//! ```
+use crate::coordinates::point_to_physical;
+use crate::rdev::{DisplayInfo, Event, EventType, ListenError};
+use std::thread::{self, JoinHandle};
+use winit::dpi::PhysicalPosition;
+use winit::event_loop::EventLoopProxy;
+use winit::window::Window;
+
+/// An event of `listen`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct GlobalEvent {
+    pub event: Event,
+    /// Where mouse moves went on the screen, in the physical pixels of
+    /// winit whatever the `CoordinateMode`.
+    pub position: Option<PhysicalPosition<f64>>,
+}
+
+impl GlobalEvent {
+    fn new(event: Event, displays: &[DisplayInfo]) -> GlobalEvent {
+        let position = match event.event_type {
+            EventType::MouseMove { x, y } => {
+                let (x, y) = point_to_physical(x, y, displays);
+                Some(PhysicalPosition::new(x, y))
+            }
+            _ => None,
+        };
+        GlobalEvent { event, position }
+    }
+
+    /// `position` relative to the inside of `window`, like its
+    /// `CursorMoved` events. None for other events, or when winit can't
+    /// tell where the window is, on Wayland.
+    pub fn window_position(&self, window: &Window) -> Option<PhysicalPosition<f64>> {
+        let position = self.position?;
+        let origin = window.inner_position().ok()?;
+        Some(PhysicalPosition::new(
+            position.x - origin.x as f64,
+            position.y - origin.y as f64,
+        ))
+    }
+}
+
+/// Listens from a thread of its own and sends every event through `proxy`,
+/// the thread returns if listening fails. Events are dropped once the event
+/// loop is gone.
+pub fn forward<T>(proxy: EventLoopProxy<T>) -> JoinHandle<Result<(), ListenError>>
+where
+    T: From<GlobalEvent> + Send + 'static,
+{
+    thread::spawn(move || {
+        // Only used to scale mouse moves, monitors rarely change.
+        let displays = crate::_displays().unwrap_or_default();
+        crate::listen(move |event| {
+            let _ = proxy.send_event(GlobalEvent::new(event, &displays).into());
+        })
+    })
+}